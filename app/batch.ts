@@ -0,0 +1,65 @@
+import * as anchor from "@coral-xyz/anchor";
+
+const LOTTERY_SEED = "lottery";
+const TICKET_SEED = "ticket";
+
+const u32Seed = (value: number) =>
+  new anchor.BN(value).toArrayLike(Buffer, "le", 4);
+
+// Turn a list of accounts into remaining accounts for a batch instruction, exactly like `batch::validate_batch`
+// expects them: deduplicated and in strictly increasing address order, with at most `max` entries
+export function batchAccounts(
+  pubkeys: anchor.web3.PublicKey[],
+  max: number,
+  isWritable = false
+): anchor.web3.AccountMeta[] {
+  const sorted = [...pubkeys]
+    .sort((a, b) => Buffer.compare(a.toBuffer(), b.toBuffer()))
+    .filter((pubkey, i, all) => i === 0 || !pubkey.equals(all[i - 1]));
+  if (sorted.length > max) {
+    throw new Error(`at most ${max} accounts can be passed in one batch`);
+  }
+
+  return sorted.map((pubkey) => ({ pubkey, isSigner: false, isWritable }));
+}
+
+// Remaining accounts for a batch of lotteries, by lottery ID
+export function lotteryBatch(
+  programId: anchor.web3.PublicKey,
+  lotteryIds: number[],
+  max: number,
+  isWritable = false
+): anchor.web3.AccountMeta[] {
+  return batchAccounts(
+    lotteryIds.map(
+      (id) =>
+        anchor.web3.PublicKey.findProgramAddressSync(
+          [Buffer.from(LOTTERY_SEED), u32Seed(id)],
+          programId
+        )[0]
+    ),
+    max,
+    isWritable
+  );
+}
+
+// Remaining accounts for a batch of tickets of one lottery, by ticket ID
+export function ticketBatch(
+  programId: anchor.web3.PublicKey,
+  lottery: anchor.web3.PublicKey,
+  ticketIds: number[],
+  max: number,
+  isWritable = false
+): anchor.web3.AccountMeta[] {
+  return batchAccounts(
+    ticketIds.map(
+      (id) =>
+        anchor.web3.PublicKey.findProgramAddressSync(
+          [Buffer.from(TICKET_SEED), lottery.toBuffer(), u32Seed(id)],
+          programId
+        )[0]
+    ),
+    max,
+    isWritable
+  );
+}
@@ -0,0 +1,146 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Lottery } from "../target/types/lottery";
+
+const LOTTERY_SEED = "lottery";
+const TICKET_SEED = "ticket";
+const CONFIG_SEED = "config";
+
+// Wrapped SOL, the input mint of every swap since prizes are paid in lamports
+const NATIVE_MINT = new anchor.web3.PublicKey(
+  "So11111111111111111111111111111111111111112"
+);
+
+// Default Jupiter swap API, any deployment exposing the same /quote and /swap-instructions endpoints works
+const JUPITER_API = "https://lite-api.jup.ag/swap/v1";
+
+// Instruction as returned by Jupiter's /swap-instructions endpoint
+type JupiterInstruction = {
+  programId: string;
+  accounts: { pubkey: string; isSigner: boolean; isWritable: boolean }[];
+  data: string;
+};
+
+const toInstruction = ({
+  programId,
+  accounts,
+  data,
+}: JupiterInstruction): anchor.web3.TransactionInstruction =>
+  new anchor.web3.TransactionInstruction({
+    programId: new anchor.web3.PublicKey(programId),
+    keys: accounts.map(({ pubkey, isSigner, isWritable }) => ({
+      pubkey: new anchor.web3.PublicKey(pubkey),
+      isSigner,
+      isWritable,
+    })),
+    data: Buffer.from(data, "base64"),
+  });
+
+// Build one transaction that claims a lamport prize and swaps it into `outputMint` through Jupiter.
+// Both steps land together or not at all, so the winner never ends up holding SOL they didn't want;
+// the swap fails (and with it the claim) if it would return less than `slippageBps` below the quote.
+// The winner signs and sends the returned transaction.
+export async function buildClaimAndSwapTransaction(
+  program: Program<Lottery>,
+  winner: anchor.web3.PublicKey,
+  lotteryId: number,
+  ticketId: number,
+  outputMint: anchor.web3.PublicKey,
+  slippageBps: number,
+  jupiterApi = JUPITER_API
+): Promise<anchor.web3.VersionedTransaction> {
+  const [lottery] = anchor.web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from(LOTTERY_SEED),
+      new anchor.BN(lotteryId).toArrayLike(Buffer, "le", 4),
+    ],
+    program.programId
+  );
+  const [ticket] = anchor.web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from(TICKET_SEED),
+      lottery.toBuffer(),
+      new anchor.BN(ticketId).toArrayLike(Buffer, "le", 4),
+    ],
+    program.programId
+  );
+
+  // The winner's share of the jackpot less the protocol fee, exactly like `Lottery::split_jackpot` and the fee
+  // split in `claim_prize` on-chain
+  const {
+    ticketPrice,
+    lastTicketId,
+    carriedOver,
+    bountyPaid,
+    winnerShareBps,
+  } = await program.account.lottery.fetch(lottery);
+  const share = ticketPrice
+    .muln(lastTicketId)
+    .add(carriedOver)
+    .sub(bountyPaid)
+    .muln(winnerShareBps)
+    .divn(10_000);
+  const [config] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from(CONFIG_SEED)],
+    program.programId
+  );
+  const { feeBps } = await program.account.config.fetch(config);
+  const prize = share.sub(share.muln(feeBps).divn(10_000));
+
+  const claim = await program.methods
+    .claimPrize(lotteryId, ticketId)
+    .accountsPartial({ lottery, ticket, authority: winner, claimer: winner })
+    .instruction();
+
+  const quote = await (
+    await fetch(
+      `${jupiterApi}/quote?inputMint=${NATIVE_MINT.toBase58()}` +
+        `&outputMint=${outputMint.toBase58()}` +
+        `&amount=${prize.toString()}&slippageBps=${slippageBps}`
+    )
+  ).json();
+  if (quote.error) throw new Error(`no swap route: ${quote.error}`);
+
+  const swap = await (
+    await fetch(`${jupiterApi}/swap-instructions`, {
+      method: "POST",
+      headers: { "Content-Type": "application/json" },
+      body: JSON.stringify({
+        quoteResponse: quote,
+        userPublicKey: winner.toBase58(),
+        wrapAndUnwrapSol: true,
+      }),
+    })
+  ).json();
+  if (swap.error) throw new Error(`swap instructions failed: ${swap.error}`);
+
+  const connection = program.provider.connection;
+  const lookupTables = await Promise.all(
+    (swap.addressLookupTableAddresses as string[]).map(async (address) => {
+      const { value } = await connection.getAddressLookupTable(
+        new anchor.web3.PublicKey(address)
+      );
+      return value;
+    })
+  );
+
+  const instructions = [
+    ...(swap.computeBudgetInstructions as JupiterInstruction[]).map(
+      toInstruction
+    ),
+    claim,
+    ...(swap.setupInstructions as JupiterInstruction[]).map(toInstruction),
+    toInstruction(swap.swapInstruction),
+    ...(swap.cleanupInstruction
+      ? [toInstruction(swap.cleanupInstruction)]
+      : []),
+  ];
+
+  const { blockhash } = await connection.getLatestBlockhash();
+  const message = new anchor.web3.TransactionMessage({
+    payerKey: winner,
+    recentBlockhash: blockhash,
+    instructions,
+  }).compileToV0Message(lookupTables.filter((table) => table !== null));
+  return new anchor.web3.VersionedTransaction(message);
+}
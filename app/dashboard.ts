@@ -0,0 +1,78 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Lottery } from "../target/types/lottery";
+import { batchAccounts } from "./batch";
+
+// Must match MAX_DASHBOARD_LOTTERIES in programs/lottery/src/constants.rs
+const MAX_DASHBOARD_LOTTERIES = 20;
+
+// Offset of `authority` in a lottery account: the 8 byte discriminator and the 4 byte id come first
+const LOTTERY_AUTHORITY_OFFSET = 8 + 4;
+
+export type CreatorDashboard = {
+  lotteries: number;
+  open: number;
+  drawn: number;
+  claimed: number;
+  ticketsSold: anchor.BN;
+  volume: anchor.BN;
+};
+
+type LotteryAccount = Awaited<
+  ReturnType<Program<Lottery>["account"]["lottery"]["fetch"]>
+>;
+
+// Count a lottery into the totals, exactly like `CreatorDashboard::add` on-chain
+function add(dashboard: CreatorDashboard, lottery: LotteryAccount) {
+  dashboard.lotteries += 1;
+  if (lottery.winnerId === null) dashboard.open += 1;
+  else if (!lottery.claimed) dashboard.drawn += 1;
+  else dashboard.claimed += 1;
+  dashboard.ticketsSold = dashboard.ticketsSold.addn(lottery.lastTicketId);
+  // Volume is in lamports, so token lotteries don't count towards it
+  if (lottery.paymentMint === null) {
+    dashboard.volume = dashboard.volume.add(
+      lottery.ticketPrice.muln(lottery.lastTicketId)
+    );
+  }
+}
+
+const empty = (): CreatorDashboard => ({
+  lotteries: 0,
+  open: 0,
+  drawn: 0,
+  claimed: 0,
+  ticketsSold: new anchor.BN(0),
+  volume: new anchor.BN(0),
+});
+
+// Aggregate every lottery of a creator off-chain, without the per-call account limit
+export async function fetchCreatorDashboard(
+  program: Program<Lottery>,
+  creator: anchor.web3.PublicKey
+): Promise<CreatorDashboard> {
+  const lotteries = await program.account.lottery.all([
+    {
+      memcmp: {
+        offset: LOTTERY_AUTHORITY_OFFSET,
+        bytes: creator.toBase58(),
+      },
+    },
+  ]);
+
+  const dashboard = empty();
+  for (const { account } of lotteries) add(dashboard, account);
+  return dashboard;
+}
+
+// Aggregate up to MAX_DASHBOARD_LOTTERIES lotteries on-chain with `get_creator_dashboard`
+export async function viewCreatorDashboard(
+  program: Program<Lottery>,
+  creator: anchor.web3.PublicKey,
+  lotteries: anchor.web3.PublicKey[]
+): Promise<CreatorDashboard> {
+  return program.methods
+    .getCreatorDashboard(creator)
+    .remainingAccounts(batchAccounts(lotteries, MAX_DASHBOARD_LOTTERIES))
+    .view();
+}
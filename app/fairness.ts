@@ -0,0 +1,278 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import {
+  createHash,
+  createPrivateKey,
+  createPublicKey,
+  sign,
+  verify,
+} from "crypto";
+import { Lottery } from "../target/types/lottery";
+
+const LOTTERY_SEED = "lottery";
+
+// DER prefixes that turn a raw ed25519 key into the PKCS#8 and SPKI keys node's crypto takes
+const ED25519_PKCS8_PREFIX = Buffer.from(
+  "302e020100300506032b657004220420",
+  "hex"
+);
+const ED25519_SPKI_PREFIX = Buffer.from("302a300506032b6570032100", "hex");
+
+// How the seed was derived on-chain, see the `draw` module
+export type DrawMethod =
+  | "recentSlotHashes"
+  | "epochSlotHash"
+  | "commitReveal"
+  | "committee";
+
+// What the lottery's events say moved through it; token lotteries count in the mint's base units
+export type FlowTotals = {
+  ticketsSold: number; // Tickets in `TicketPurchased` and `TicketsReserved`
+  sales: string; // What those tickets were paid
+  graceRefunds: string; // `TicketRefunded`, tickets taken back within the refund grace period
+  bountyPaid: string; // `DrawBountyPaid`
+  prizePaid: string; // `PrizeClaimed`, including the rollover and fee taken out of the prize
+  refunded: string; // `FundsRecovered`
+};
+
+export type FairnessReport = {
+  programId: string;
+  lotteryId: number;
+  slot: number; // The slot the lottery account was read at
+  draw: {
+    method: DrawMethod;
+    seed: string; // hex, `Lottery::draw_seed`, after any redraws
+    redraws: number; // Redraws because the drawn ticket couldn't win, each seed the `draw::redraw_seed` of the last
+    ticketCount: number;
+    winnerId: number;
+    recomputedWinnerId: number;
+  };
+  flows: FlowTotals;
+  checks: {
+    winnerMatches: boolean; // The seed gives the recorded winner
+    ticketsMatch: boolean; // The events account for every ticket ID
+    salesMatch: boolean; // The events account for the whole pot, net of grace refunds
+    bountyMatches: boolean; // The bounty events match the bounty recorded on the lottery
+    payoutsWithinJackpot: boolean; // Prize and refunds together never exceeded the jackpot
+  };
+  signer: string; // base58 public key of the operator who signed the report
+  signature: string; // base64 ed25519 signature over the report without `signer` and `signature`
+};
+
+const u32Seed = (value: number) =>
+  new anchor.BN(value).toArrayLike(Buffer, "le", 4);
+
+// Mirrors `draw::uniform_index`: each 8 byte chunk of the seed is tried in turn, rejecting values in the short last
+// block of 2^64 that would favour low indexes, and the seed is rehashed once all four are rejected
+export function uniformIndex(seed: Buffer, count: number): number {
+  const n = new anchor.BN(count);
+  const max = new anchor.BN(1).shln(64).subn(1);
+  const lastAccepted = max.sub(max.mod(n).addn(1).mod(n));
+  for (;;) {
+    for (let i = 0; i < 32; i += 8) {
+      const value = new anchor.BN(seed.subarray(i, i + 8), "le");
+      if (value.lte(lastAccepted)) return value.mod(n).toNumber();
+    }
+    seed = createHash("sha256").update(seed).digest();
+  }
+}
+
+// Add up the lottery's flows from the events in its transaction history
+// Every instruction that moves funds into or out of a lottery takes the lottery account, so its history has them all
+async function flowTotals(
+  program: Program<Lottery>,
+  lottery: anchor.web3.PublicKey,
+  lotteryId: number
+): Promise<FlowTotals> {
+  const connection = program.provider.connection;
+  const parser = new anchor.EventParser(program.programId, program.coder);
+  const zero = () => new anchor.BN(0);
+  const totals = {
+    ticketsSold: 0,
+    sales: zero(),
+    graceRefunds: zero(),
+    bountyPaid: zero(),
+    prizePaid: zero(),
+    refunded: zero(),
+  };
+
+  let before: string | undefined;
+  for (;;) {
+    const page = await connection.getSignaturesForAddress(lottery, { before });
+    if (page.length === 0) break;
+    before = page[page.length - 1].signature;
+
+    for (const { signature, err } of page) {
+      if (err) continue;
+      const tx = await connection.getTransaction(signature, {
+        maxSupportedTransactionVersion: 0,
+      });
+      for (const { name, data } of parser.parseLogs(
+        tx?.meta?.logMessages ?? []
+      )) {
+        if (data.lotteryId !== lotteryId) continue;
+        switch (name) {
+          case "ticketPurchased":
+            totals.ticketsSold += 1;
+            totals.sales.iadd(data.price);
+            break;
+          case "ticketsReserved":
+            totals.ticketsSold += data.count;
+            totals.sales.iadd(data.price.muln(data.count));
+            break;
+          case "ticketRefunded":
+            totals.graceRefunds.iadd(data.amount);
+            break;
+          case "drawBountyPaid":
+            totals.bountyPaid.iadd(data.amount);
+            break;
+          case "prizeClaimed":
+            totals.prizePaid.iadd(
+              data.amount.add(data.rolledOver).add(data.fee)
+            );
+            break;
+          case "fundsRecovered":
+            totals.refunded.iadd(data.amount);
+            break;
+        }
+      }
+    }
+  }
+
+  return {
+    ticketsSold: totals.ticketsSold,
+    sales: totals.sales.toString(),
+    graceRefunds: totals.graceRefunds.toString(),
+    bountyPaid: totals.bountyPaid.toString(),
+    prizePaid: totals.prizePaid.toString(),
+    refunded: totals.refunded.toString(),
+  };
+}
+
+// The bytes a report's signature covers: the report without its signer and signature, as JSON
+const signedBytes = ({ signer, signature, ...body }: FairnessReport) =>
+  Buffer.from(JSON.stringify(body));
+
+// Build a fairness report for a drawn lottery and sign it with the operator's keypair
+// The winner is recomputed from the seed the draw stored on the lottery, and the pot, bounty and payouts are
+// checked against the lottery's events. Publish the report as JSON; anyone can check it with `verifyFairnessReport`
+export async function buildFairnessReport(
+  program: Program<Lottery>,
+  lotteryId: number,
+  operator: anchor.web3.Keypair
+): Promise<FairnessReport> {
+  const [lottery] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from(LOTTERY_SEED), u32Seed(lotteryId)],
+    program.programId
+  );
+  const { context, value } =
+    await program.provider.connection.getAccountInfoAndContext(lottery);
+  if (!value) {
+    throw new Error(`lottery ${lotteryId} doesn't exist`);
+  }
+  const account = program.coder.accounts.decode("lottery", value.data);
+  if (account.winnerId === null) {
+    throw new Error(`lottery ${lotteryId} has no winner yet`);
+  }
+
+  const seed = Buffer.from(account.drawSeed);
+  const method: DrawMethod = account.committee
+    ? "committee"
+    : account.commitment
+    ? "commitReveal"
+    : account.drawEpoch
+    ? "epochSlotHash"
+    : "recentSlotHashes";
+  const recomputedWinnerId = uniformIndex(seed, account.lastTicketId);
+
+  const flows = await flowTotals(program, lottery, lotteryId);
+  // Refunded tickets keep their IDs, so the draw counts them but the pot doesn't
+  const pot = account.ticketPrice.muln(
+    account.lastTicketId - account.refundedTickets
+  );
+  const jackpot = pot.add(account.carriedOver).sub(account.bountyPaid);
+  const paidOut = new anchor.BN(flows.prizePaid).add(
+    new anchor.BN(flows.refunded)
+  );
+
+  const report: FairnessReport = {
+    programId: program.programId.toBase58(),
+    lotteryId,
+    slot: context.slot,
+    draw: {
+      method,
+      seed: seed.toString("hex"),
+      redraws: account.redraws,
+      ticketCount: account.lastTicketId,
+      winnerId: account.winnerId,
+      recomputedWinnerId,
+    },
+    flows,
+    checks: {
+      winnerMatches: recomputedWinnerId === account.winnerId,
+      ticketsMatch: flows.ticketsSold === account.lastTicketId,
+      salesMatch: new anchor.BN(flows.sales)
+        .sub(new anchor.BN(flows.graceRefunds))
+        .eq(pot),
+      bountyMatches: new anchor.BN(flows.bountyPaid).eq(account.bountyPaid),
+      payoutsWithinJackpot: paidOut.lte(jackpot),
+    },
+    signer: operator.publicKey.toBase58(),
+    signature: "",
+  };
+
+  const key = createPrivateKey({
+    key: Buffer.concat([
+      ED25519_PKCS8_PREFIX,
+      Buffer.from(operator.secretKey.subarray(0, 32)),
+    ]),
+    format: "der",
+    type: "pkcs8",
+  });
+  report.signature = sign(null, signedBytes(report), key).toString("base64");
+  return report;
+}
+
+// Check a published report: the signature must be the signer's, and the winner must follow from the seed
+// Whether the seed and flows match the chain is up to an RPC node or indexer the verifier trusts, as for winner bundles
+export function verifyFairnessReport(report: FairnessReport): boolean {
+  try {
+    const key = createPublicKey({
+      key: Buffer.concat([
+        ED25519_SPKI_PREFIX,
+        new anchor.web3.PublicKey(report.signer).toBuffer(),
+      ]),
+      format: "der",
+      type: "spki",
+    });
+    const signed = verify(
+      null,
+      signedBytes(report),
+      key,
+      Buffer.from(report.signature, "base64")
+    );
+    const { seed, ticketCount, winnerId } = report.draw;
+    return (
+      signed && uniformIndex(Buffer.from(seed, "hex"), ticketCount) === winnerId
+    );
+  } catch (err) {
+    return false;
+  }
+}
+
+// Print a lottery's signed fairness report with `yarn fairness <lottery_id>`.
+// ANCHOR_PROVIDER_URL picks the cluster and ANCHOR_WALLET the operator's keypair, which signs the report.
+if (require.main === module) {
+  anchor.setProvider(anchor.AnchorProvider.env());
+  const program = anchor.workspace.Lottery as Program<Lottery>;
+  const operator = (
+    (program.provider as anchor.AnchorProvider).wallet as anchor.Wallet
+  ).payer;
+
+  buildFairnessReport(program, Number(process.argv[2]), operator)
+    .then((report) => console.log(JSON.stringify(report, null, 2)))
+    .catch((err) => {
+      console.error(err);
+      process.exit(1);
+    });
+}
@@ -0,0 +1,437 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Lottery } from "../target/types/lottery";
+import { checkProgramVersion } from "./version";
+import {
+  NotificationFields,
+  NotificationKind,
+  NotificationSink,
+  WebhookFormat,
+  webhookSink,
+} from "./notify";
+
+const MASTER_SEED = "master";
+const LOTTERY_SEED = "lottery";
+const TICKET_SEED = "ticket";
+const PROFILE_SEED = "profile";
+const COMMITTEE_SEED = "committee";
+
+// Must match FEATURE_ARCHIVAL in programs/lottery/src/constants.rs
+const FEATURE_ARCHIVAL = 1 << 2;
+
+// Must match MAX_TICKETS_PER_COUNT in programs/lottery/src/constants.rs
+const MAX_TICKETS_PER_COUNT = 25;
+
+// Offset of `authority` in a lottery account: the 8 byte discriminator and the 4 byte id come first
+const LOTTERY_AUTHORITY_OFFSET = 8 + 4;
+
+export type KeeperOptions = {
+  pollIntervalMs: number; // How often every lottery is rescanned, on top of the event subscriptions
+  minTxIntervalMs: number; // The least time between two transactions, to stay within RPC rate limits
+  archive: boolean; // Archive lotteries once their prize is claimed, if the archival module is enabled
+  sinks: NotificationSink[]; // Where new lotteries and drawn winners of the creator are announced, see `notify.ts`
+};
+
+const defaultOptions: KeeperOptions = {
+  pollIntervalMs: 30_000,
+  minTxIntervalMs: 2_000,
+  archive: true,
+  sinks: [],
+};
+
+type LotteryAccount = Awaited<
+  ReturnType<Program<Lottery>["account"]["lottery"]["fetch"]>
+>;
+type TicketAccount = Awaited<
+  ReturnType<Program<Lottery>["account"]["ticket"]["fetch"]>
+>;
+
+const u32Seed = (value: number) =>
+  new anchor.BN(value).toArrayLike(Buffer, "le", 4);
+
+const toSol = (lamports: anchor.BN) =>
+  (lamports.toNumber() / anchor.web3.LAMPORTS_PER_SOL).toString();
+
+// Whether code tickets can still be redeemed after the draw, like `Lottery::redeems_after_draw` on-chain
+const redeemsAfterDraw = (lottery: LotteryAccount) =>
+  !lottery.noSelfEntry && lottery.matchBits === 0;
+
+// Whether a ticket can win, like `Lottery::can_win` on-chain
+const canWin = (lottery: LotteryAccount, ticket: TicketAccount) =>
+  !ticket.refunded && (ticket.codeHash === null || redeemsAfterDraw(lottery));
+
+// The tickets that can win once drawn, like `Lottery::eligible_tickets` on-chain
+const eligibleTickets = (lottery: LotteryAccount) =>
+  lottery.lastTicketId -
+  lottery.refundedTickets -
+  (redeemsAfterDraw(lottery) ? 0 : lottery.unredeemedCodes);
+
+// Keeps the lotteries of one creator moving: the wallet of the program's provider must be that creator.
+// It draws epoch-locked lotteries once their draw epoch is over and lotteries with an end time once it has
+// passed (finalizing committee lotteries from their shares), redraws lotteries whose drawn ticket can't win,
+// counts the matching tickets of match mode lotteries, announces every single winner it sees drawn, and
+// delivers the winner callback of and archives lamport lotteries whose prize was claimed. Lotteries without a draw
+// epoch or end time are drawn by hand.
+// New lotteries and drawn winners of the creator are also posted to the configured notification sinks.
+export class Keeper {
+  private readonly options: KeeperOptions;
+  private readonly authority: anchor.web3.PublicKey;
+  private readonly master: anchor.web3.PublicKey;
+  private readonly pending = new Set<string>(); // Actions queued or in flight, so a scan doesn't repeat them
+  private readonly announced = new Set<number>(); // Lotteries announced so far; kept in memory, so a restart announces unclaimed winners once more
+  private queue: Promise<void> = Promise.resolve();
+  private lastTx = 0;
+  private listeners: number[] = [];
+  private timer?: NodeJS.Timeout;
+
+  constructor(
+    private readonly program: Program<Lottery>,
+    options: Partial<KeeperOptions> = {}
+  ) {
+    this.options = { ...defaultOptions, ...options };
+    this.authority = program.provider.publicKey;
+    [this.master] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from(MASTER_SEED)],
+      program.programId
+    );
+  }
+
+  // Subscribe to the program's events and start scanning
+  async start() {
+    await checkProgramVersion(this.program, { strict: true });
+
+    // Events make the keeper react right away; the periodic scan catches whatever a dropped websocket missed
+    this.listeners = [
+      // The scan announces a single winner, or starts counting a match mode lottery's tickets
+      this.program.addEventListener("winnerPicked", ({ lotteryId }) => {
+        this.scan();
+        this.notifyWinner(lotteryId);
+      }),
+      this.program.addEventListener("matchesCounted", () => this.scan()),
+      this.program.addEventListener("winnerRedrawn", ({ lotteryId }) => {
+        this.announce(lotteryId);
+        this.notifyWinner(lotteryId);
+      }),
+      this.program.addEventListener("prizeClaimed", () => this.scan()),
+      this.program.addEventListener(
+        "lotteryCreated",
+        ({ lotteryId, authority, ticketPrice, drawEpoch }) => {
+          if (!authority.equals(this.authority)) return;
+          this.notify("lotteryCreated", {
+            lotteryId: lotteryId.toString(),
+            authority: authority.toBase58(),
+            ticketPrice: toSol(ticketPrice),
+            drawEpoch: drawEpoch?.toString() ?? "none",
+          });
+        }
+      ),
+    ];
+    await this.scan();
+    this.timer = setInterval(() => this.scan(), this.options.pollIntervalMs);
+  }
+
+  async stop() {
+    clearInterval(this.timer);
+    await Promise.all(
+      this.listeners.map((listener) =>
+        this.program.removeEventListener(listener)
+      )
+    );
+    await this.queue;
+  }
+
+  // Check every lottery of the creator and queue what it is eligible for
+  async scan() {
+    try {
+      const [lotteries, { epoch }, master] = await Promise.all([
+        this.program.account.lottery.all([
+          {
+            memcmp: {
+              offset: LOTTERY_AUTHORITY_OFFSET,
+              bytes: this.authority.toBase58(),
+            },
+          },
+        ]),
+        this.program.provider.connection.getEpochInfo(),
+        this.program.account.master.fetch(this.master),
+      ]);
+      const archival =
+        this.options.archive &&
+        (master.features.toNumber() & FEATURE_ARCHIVAL) !== 0;
+
+      for (const { account } of lotteries) {
+        if (this.canDraw(account, epoch))
+          this.draw(account.id, account.committee);
+        else if (
+          account.winnerId !== null &&
+          account.matchBits > 0 &&
+          account.matchCursor < account.lastTicketId
+        )
+          this.countMatches(account);
+        else if (
+          account.winnerId !== null &&
+          !account.claimed &&
+          account.matchCount === 0
+        )
+          this.announce(account.id);
+        else if (
+          account.claimed &&
+          account.callback !== null &&
+          !account.winnerNotified
+        )
+          this.notifyWinner(account);
+        else if (account.claimed && archival && account.paymentMint === null)
+          this.archive(account);
+      }
+    } catch (err) {
+      console.error("scan failed:", err);
+    }
+  }
+
+  // Epoch-locked lotteries can be drawn once the draw epoch is over, and lotteries with an end time once it has
+  // passed; the cluster clock can lag behind ours, in which case a permissionless draw succeeds without drawing
+  // (returning `notDue`) and the creator's own fails, and either is retried on the next scan
+  private canDraw(lottery: LotteryAccount, epoch: number) {
+    const now = Math.floor(Date.now() / 1000);
+    const due =
+      lottery.drawEpoch !== null
+        ? epoch > lottery.drawEpoch.toNumber()
+        : !lottery.endTime.isZero() && now >= lottery.endTime.toNumber();
+    return lottery.winnerId === null && eligibleTickets(lottery) > 0 && due;
+  }
+
+  // Committee lotteries are finalized from their members' shares instead; until the reveal window has closed that
+  // returns `notDue` without drawing, and is retried on the next scan
+  private draw(lotteryId: number, committee: boolean) {
+    const lottery = this.lotteryPda(lotteryId);
+    const accounts = { lottery, caller: this.authority };
+    this.send(`draw:${lotteryId}`, () =>
+      committee
+        ? this.program.methods
+            .finalizeDraw(lotteryId)
+            .accountsPartial({
+              draw: accounts,
+              committee: anchor.web3.PublicKey.findProgramAddressSync(
+                [Buffer.from(COMMITTEE_SEED), lottery.toBuffer()],
+                this.program.programId
+              )[0],
+            })
+            .rpc()
+        : this.program.methods
+            .pickWinner(lotteryId)
+            .accountsPartial(accounts)
+            .rpc()
+    );
+  }
+
+  // Count the next batch of a drawn match mode lottery's tickets; the scan after the last batch moves on
+  private countMatches(lottery: LotteryAccount) {
+    this.send(`count:${lottery.id}:${lottery.matchCursor}`, () => {
+      const lotteryPda = this.lotteryPda(lottery.id);
+      const count = Math.min(
+        MAX_TICKETS_PER_COUNT,
+        lottery.lastTicketId - lottery.matchCursor
+      );
+      return this.program.methods
+        .countMatches(lottery.id)
+        .accountsPartial({ lottery: lotteryPda, master: this.master })
+        .remainingAccounts(
+          Array.from({ length: count }, (_, i) => ({
+            pubkey: this.ticketPda(lotteryPda, lottery.matchCursor + i),
+            isSigner: false,
+            isWritable: false,
+          }))
+        )
+        .rpc();
+    });
+  }
+
+  // A drawn ticket that can't win, because it was refunded within its grace period or is a code ticket that can no
+  // longer be redeemed, can't be announced; the lottery is redrawn instead, and the new winner announced once
+  // `winnerRedrawn` comes in or on the next scan
+  private announce(lotteryId: number) {
+    if (this.announced.has(lotteryId)) return;
+    this.send(`announce:${lotteryId}`, async () => {
+      const lottery = this.lotteryPda(lotteryId);
+      const account = await this.program.account.lottery.fetch(lottery);
+      const winningTicket = this.ticketPda(lottery, account.winnerId);
+      const ticket = await this.program.account.ticket.fetch(winningTicket);
+      const winner = ticket.authority;
+      if (!canWin(account, ticket)) {
+        return this.program.methods
+          .redrawVoidWinner(lotteryId)
+          .accountsPartial({ lottery, winningTicket, master: this.master })
+          .rpc();
+      }
+      const [profile] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from(PROFILE_SEED), winner.toBuffer()],
+        this.program.programId
+      );
+      const hasProfile =
+        (await this.program.account.profile.fetchNullable(profile)) !== null;
+
+      const signature = await this.program.methods
+        .announceWinner(lotteryId)
+        .accountsPartial({
+          lottery,
+          winningTicket,
+          profile: hasProfile ? profile : null,
+          master: this.master,
+        })
+        .rpc();
+      this.announced.add(lotteryId);
+      return signature;
+    });
+  }
+
+  // Tell the sinks about a drawn winner of one of the creator's lotteries
+  private async notifyWinner(lotteryId: number) {
+    if (this.options.sinks.length === 0) return;
+    try {
+      const lottery = await this.program.account.lottery.fetch(
+        this.lotteryPda(lotteryId)
+      );
+      // Match mode lotteries have many winners, which claim their shares on their own
+      if (!lottery.authority.equals(this.authority) || lottery.matchBits > 0)
+        return;
+      const ticket = await this.program.account.ticket.fetch(
+        this.ticketPda(this.lotteryPda(lotteryId), lottery.winnerId)
+      );
+      const winner = ticket.authority;
+      // Winners that can't win are redrawn, and the new winner is notified then
+      if (!canWin(lottery, ticket)) return;
+
+      // The winner's share of the jackpot, exactly like `Lottery::split_jackpot` on-chain
+      const prize = lottery.ticketPrice
+        .muln(lottery.lastTicketId - lottery.refundedTickets)
+        .add(lottery.carriedOver)
+        .sub(lottery.bountyPaid)
+        .muln(lottery.winnerShareBps)
+        .divn(10_000);
+
+      this.notify("winnerPicked", {
+        lotteryId: lotteryId.toString(),
+        winnerId: lottery.winnerId.toString(),
+        winner: winner.toBase58(),
+        prize: toSol(prize),
+      });
+    } catch (err) {
+      console.error(`notify winner of ${lotteryId} failed:`, err);
+    }
+  }
+
+  // Deliver a notification to every sink; a failing sink doesn't keep the others from being notified
+  private notify(kind: NotificationKind, fields: NotificationFields) {
+    for (const sink of this.options.sinks) {
+      sink
+        .notify(kind, fields)
+        .catch((err) => console.error(`${kind} notification failed:`, err));
+    }
+  }
+
+  // Deliver a claimed lottery's winner callback, which has to happen before the lottery can be archived
+  private notifyWinner(lottery: LotteryAccount) {
+    this.send(`notify:${lottery.id}`, () => {
+      const lotteryPda = this.lotteryPda(lottery.id);
+      const ticket = this.ticketPda(lotteryPda, lottery.winnerId);
+      return this.program.account.ticket.fetch(ticket).then(({ authority }) =>
+        this.program.methods
+          .notifyWinner(lottery.id)
+          .accountsPartial({
+            lottery: lotteryPda,
+            ticket,
+            winner: authority,
+            callbackProgram: lottery.callback.program,
+          })
+          .rpc()
+      );
+    });
+  }
+
+  private archive(lottery: LotteryAccount) {
+    this.send(`archive:${lottery.id}`, () => {
+      const lotteryPda = this.lotteryPda(lottery.id);
+      return this.program.methods
+        .archiveLottery(lottery.id)
+        .accountsPartial({
+          lottery: lotteryPda,
+          winningTicket: this.ticketPda(lotteryPda, lottery.winnerId),
+          authority: this.authority,
+        })
+        .rpc();
+    });
+  }
+
+  // Queue a transaction, at most one at a time and no closer than `minTxIntervalMs` to the previous one
+  private send(key: string, tx: () => Promise<string>) {
+    if (this.pending.has(key)) return;
+    this.pending.add(key);
+
+    this.queue = this.queue.then(async () => {
+      const wait = this.lastTx + this.options.minTxIntervalMs - Date.now();
+      if (wait > 0) await new Promise((resolve) => setTimeout(resolve, wait));
+      this.lastTx = Date.now();
+
+      try {
+        console.log(`${key}: ${await tx()}`);
+      } catch (err) {
+        // Another keeper or the creator may have been first; the next scan sees the new state
+        console.error(`${key} failed:`, err);
+      } finally {
+        this.pending.delete(key);
+      }
+    });
+  }
+
+  private lotteryPda(lotteryId: number) {
+    return anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from(LOTTERY_SEED), u32Seed(lotteryId)],
+      this.program.programId
+    )[0];
+  }
+
+  private ticketPda(lottery: anchor.web3.PublicKey, ticketId: number) {
+    return anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from(TICKET_SEED), lottery.toBuffer(), u32Seed(ticketId)],
+      this.program.programId
+    )[0];
+  }
+}
+
+// Run the keeper from the workspace root with `yarn keeper`.
+// ANCHOR_PROVIDER_URL picks the cluster and ANCHOR_WALLET the creator's keypair;
+// KEEPER_POLL_INTERVAL_MS, KEEPER_MIN_TX_INTERVAL_MS and KEEPER_ARCHIVE=false override the defaults.
+// KEEPER_WEBHOOK_URL adds a webhook sink: KEEPER_WEBHOOK_FORMAT is discord, telegram (with KEEPER_TELEGRAM_CHAT_ID)
+// or json, and KEEPER_CREATED_TEMPLATE and KEEPER_WINNER_TEMPLATE replace the default messages.
+if (require.main === module) {
+  anchor.setProvider(anchor.AnchorProvider.env());
+  const program = anchor.workspace.Lottery as Program<Lottery>;
+  const env = process.env;
+
+  const keeper = new Keeper(program, {
+    ...(env.KEEPER_POLL_INTERVAL_MS && {
+      pollIntervalMs: Number(env.KEEPER_POLL_INTERVAL_MS),
+    }),
+    ...(env.KEEPER_MIN_TX_INTERVAL_MS && {
+      minTxIntervalMs: Number(env.KEEPER_MIN_TX_INTERVAL_MS),
+    }),
+    archive: env.KEEPER_ARCHIVE !== "false",
+    sinks: env.KEEPER_WEBHOOK_URL
+      ? [
+          webhookSink(env.KEEPER_WEBHOOK_URL, {
+            format: (env.KEEPER_WEBHOOK_FORMAT ?? "json") as WebhookFormat,
+            chatId: env.KEEPER_TELEGRAM_CHAT_ID,
+            templates: {
+              lotteryCreated: env.KEEPER_CREATED_TEMPLATE,
+              winnerPicked: env.KEEPER_WINNER_TEMPLATE,
+            },
+          }),
+        ]
+      : [],
+  });
+  keeper.start().catch((err) => {
+    console.error(err);
+    process.exit(1);
+  });
+  process.on("SIGINT", () => keeper.stop().then(() => process.exit(0)));
+}
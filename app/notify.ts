@@ -0,0 +1,76 @@
+// Notification sinks for the keeper, which announce lottery events in community chats.
+// A sink receives the kind of event and its fields as strings, and decides how to deliver them;
+// `webhookSink` covers Discord, Telegram and generic JSON webhooks.
+
+export type NotificationKind = "lotteryCreated" | "winnerPicked";
+
+// The fields a message template can use, e.g. "Lottery {lotteryId} was won by {winner}"
+export type NotificationFields = Record<string, string>;
+
+export interface NotificationSink {
+  notify(kind: NotificationKind, fields: NotificationFields): Promise<void>;
+}
+
+export type WebhookFormat = "discord" | "telegram" | "json";
+
+// Default messages; every field of the event can be used as `{field}`
+export const defaultTemplates: Record<NotificationKind, string> = {
+  lotteryCreated:
+    "Lottery #{lotteryId} is open! Tickets cost {ticketPrice} SOL.",
+  winnerPicked:
+    "Lottery #{lotteryId} has a winner: ticket #{winnerId}, owned by {winner}, wins {prize} SOL!",
+};
+
+// Fill a template's `{field}` placeholders; unknown fields are left as they are
+export function renderTemplate(
+  template: string,
+  fields: NotificationFields
+): string {
+  return template.replace(/\{(\w+)\}/g, (placeholder, name) =>
+    name in fields ? fields[name] : placeholder
+  );
+}
+
+// POST a rendered message to a webhook.
+// Discord webhooks take `{ content }`, Telegram's sendMessage endpoint (https://api.telegram.org/bot<token>/sendMessage)
+// takes `{ chat_id, text }`, and the json format posts `{ kind, message, fields }` for custom receivers.
+export function webhookSink(
+  url: string,
+  {
+    format = "json",
+    templates = {},
+    chatId,
+  }: {
+    format?: WebhookFormat;
+    templates?: Partial<Record<NotificationKind, string>>;
+    chatId?: string; // Telegram chat to post in, required by the telegram format
+  } = {}
+): NotificationSink {
+  if (format === "telegram" && !chatId) {
+    throw new Error("the telegram format needs a chat id");
+  }
+
+  return {
+    async notify(kind, fields) {
+      const message = renderTemplate(
+        templates[kind] ?? defaultTemplates[kind],
+        fields
+      );
+      const body =
+        format === "discord"
+          ? { content: message }
+          : format === "telegram"
+          ? { chat_id: chatId, text: message }
+          : { kind, message, fields };
+
+      const response = await fetch(url, {
+        method: "POST",
+        headers: { "Content-Type": "application/json" },
+        body: JSON.stringify(body),
+      });
+      if (!response.ok) {
+        throw new Error(`webhook answered ${response.status}`);
+      }
+    },
+  };
+}
@@ -0,0 +1,80 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Lottery } from "../target/types/lottery";
+
+const LOTTERY_SEED = "lottery";
+const RECEIPT_SEED = "receipt";
+
+// Mirrors MAX_REFUNDS_PER_BATCH in the program's constants
+export const MAX_REFUNDS_PER_BATCH = 8;
+
+// Offset of `lottery_id` in a ticket account: discriminator, id and authority come first
+const TICKET_LOTTERY_ID_OFFSET = 8 + 4 + 32;
+
+const u32Seed = (value: number) =>
+  new anchor.BN(value).toArrayLike(Buffer, "le", 4);
+
+const pda = (programId: anchor.web3.PublicKey, seeds: Buffer[]) =>
+  anchor.web3.PublicKey.findProgramAddressSync(seeds, programId)[0];
+
+// Refund every open ticket of a cancelled lottery with `refund_batch`, MAX_REFUNDS_PER_BATCH tickets per transaction
+// Tickets already paid out by `recover_funds` are left out. Returns the signatures of the transactions sent
+export async function refundCancelledLottery(
+  program: Program<Lottery>,
+  lotteryId: number
+): Promise<string[]> {
+  const programId = program.programId;
+  const lottery = pda(programId, [
+    Buffer.from(LOTTERY_SEED),
+    u32Seed(lotteryId),
+  ]);
+  if (!(await program.account.lottery.fetch(lottery)).cancelled) {
+    throw new Error(`lottery ${lotteryId} isn't cancelled`);
+  }
+
+  const tickets = await program.account.ticket.all([
+    {
+      memcmp: {
+        offset: TICKET_LOTTERY_ID_OFFSET,
+        bytes: anchor.utils.bytes.bs58.encode(u32Seed(lotteryId)),
+      },
+    },
+  ]);
+  const refunds = tickets.map(({ publicKey, account }) => ({
+    ticket: publicKey,
+    receipt: pda(programId, [
+      Buffer.from(RECEIPT_SEED),
+      lottery.toBuffer(),
+      publicKey.toBuffer(),
+    ]),
+    owner: account.authority,
+  }));
+
+  // The program skips tickets with a receipt, so leave them out rather than spend account space on them
+  const receipts = await program.provider.connection.getMultipleAccountsInfo(
+    refunds.map(({ receipt }) => receipt)
+  );
+  const open = refunds
+    .filter((_, i) => receipts[i] === null)
+    .sort((a, b) => Buffer.compare(a.ticket.toBuffer(), b.ticket.toBuffer()));
+
+  // `batch::validate_batch` wants the tickets in strictly increasing address order, which sorting once gives every chunk
+  const signatures = [];
+  for (let i = 0; i < open.length; i += MAX_REFUNDS_PER_BATCH) {
+    const remainingAccounts = open
+      .slice(i, i + MAX_REFUNDS_PER_BATCH)
+      .flatMap(({ ticket, receipt, owner }) => [
+        { pubkey: ticket, isSigner: false, isWritable: true },
+        { pubkey: receipt, isSigner: false, isWritable: false },
+        { pubkey: owner, isSigner: false, isWritable: true },
+      ]);
+    signatures.push(
+      await program.methods
+        .refundBatch(lotteryId)
+        .accountsPartial({ lottery })
+        .remainingAccounts(remainingAccounts)
+        .rpc()
+    );
+  }
+  return signatures;
+}
@@ -0,0 +1,97 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Lottery } from "../target/types/lottery";
+
+const MASTER_SEED = "master";
+const LOTTERY_SEED = "lottery";
+
+// The most rounds one transaction can create; each round adds a lottery account and a create_lottery
+// instruction, and this keeps the transaction safely within its 1232 byte size limit
+export const MAX_ROUNDS_PER_TRANSACTION = 8;
+
+export type RoundSchedule = {
+  count: number; // How many rounds to create, at most MAX_ROUNDS_PER_TRANSACTION
+  ticketPrice: anchor.BN; // The ticket price of every round, in lamports
+  firstDrawEpoch: number; // The epoch the first round's sales close and its draw is locked to
+  epochsPerRound: number; // How many epochs apart the following rounds close
+  noSelfEntry?: boolean;
+  currency?: string; // ISO 4217 code, see `set_display_hints`
+  locale?: string; // BCP 47 tag, see `set_display_hints`
+};
+
+// Build one transaction that creates a whole schedule of epoch-locked rounds for `creator`, so a month of rounds
+// can be published at once. Every round is a regular lottery: it sells tickets as soon as it's created, and its
+// sales close and its draw is locked to the end of its draw epoch. The rounds take the next lottery IDs, so the
+// transaction fails, creating none of them, if another lottery is created before it lands; rebuild it then.
+// The creator signs and sends the returned transaction.
+export async function buildCreateRoundsTransaction(
+  program: Program<Lottery>,
+  creator: anchor.web3.PublicKey,
+  {
+    count,
+    ticketPrice,
+    firstDrawEpoch,
+    epochsPerRound,
+    noSelfEntry = false,
+    currency = "USD",
+    locale = "en-US",
+  }: RoundSchedule
+): Promise<{
+  transaction: anchor.web3.VersionedTransaction;
+  lotteryIds: number[];
+}> {
+  if (count < 1 || count > MAX_ROUNDS_PER_TRANSACTION) {
+    throw new Error(
+      `between 1 and ${MAX_ROUNDS_PER_TRANSACTION} rounds fit in one transaction`
+    );
+  }
+  if (epochsPerRound < 1) {
+    throw new Error("rounds must close at least one epoch apart");
+  }
+
+  const [master] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from(MASTER_SEED)],
+    program.programId
+  );
+  const { lastId } = await program.account.master.fetch(master);
+
+  const lotteryIds = Array.from({ length: count }, (_, i) => lastId + i);
+  const instructions = await Promise.all(
+    lotteryIds.map((lotteryId, i) =>
+      program.methods
+        .createLottery(
+          ticketPrice,
+          new anchor.BN(firstDrawEpoch + i * epochsPerRound),
+          new anchor.BN(0), // Rounds end with their draw epoch instead of an end time
+          noSelfEntry,
+          { lamports: {} },
+          [...Buffer.from(currency)],
+          locale
+        )
+        .accountsPartial({
+          lottery: anchor.web3.PublicKey.findProgramAddressSync(
+            [
+              Buffer.from(LOTTERY_SEED),
+              new anchor.BN(lotteryId).toArrayLike(Buffer, "le", 4),
+            ],
+            program.programId
+          )[0],
+          master,
+          authority: creator,
+        })
+        .instruction()
+    )
+  );
+
+  const { blockhash } =
+    await program.provider.connection.getLatestBlockhash();
+  const message = new anchor.web3.TransactionMessage({
+    payerKey: creator,
+    recentBlockhash: blockhash,
+    instructions,
+  }).compileToV0Message();
+  return {
+    transaction: new anchor.web3.VersionedTransaction(message),
+    lotteryIds,
+  };
+}
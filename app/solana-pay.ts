@@ -0,0 +1,86 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Lottery } from "../target/types/lottery";
+
+const LOTTERY_SEED = "lottery";
+const TICKET_SEED = "ticket";
+
+// Build a Solana Pay transaction request URL for buying a ticket.
+// `link` is the HTTPS endpoint that will answer the wallet with a `buy_ticket` transaction,
+// the lottery ID and reference key are passed along to it as query parameters.
+export function encodeBuyTicketURL({
+  link,
+  lotteryId,
+  reference,
+  label,
+  message,
+}: {
+  link: string;
+  lotteryId: number;
+  reference: anchor.web3.PublicKey;
+  label?: string;
+  message?: string;
+}): string {
+  const separator = link.includes("?") ? "&" : "?";
+  const request =
+    `${link}${separator}lotteryId=${lotteryId}` +
+    `&reference=${reference.toBase58()}`;
+
+  const params: string[] = [];
+  if (label) params.push(`label=${encodeURIComponent(label)}`);
+  if (message) params.push(`message=${encodeURIComponent(message)}`);
+
+  const url = `solana:${encodeURIComponent(request)}`;
+  return params.length ? `${url}?${params.join("&")}` : url;
+}
+
+// Build a `buy_ticket` instruction carrying the Solana Pay reference as a read-only key
+// The purchase is rejected if it lands more than `ttlSeconds` from now, or if `termsHash`
+// (the hash of the terms shown to the buyer, the lottery's current terms by default) is stale
+export async function buildBuyTicketInstruction(
+  program: Program<Lottery>,
+  buyer: anchor.web3.PublicKey,
+  lotteryId: number,
+  reference: anchor.web3.PublicKey,
+  ttlSeconds = 120,
+  termsHash?: number[]
+): Promise<anchor.web3.TransactionInstruction> {
+  const [lottery] = anchor.web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from(LOTTERY_SEED),
+      new anchor.BN(lotteryId).toArrayLike(Buffer, "le", 4),
+    ],
+    program.programId
+  );
+  const lotteryAccount = await program.account.lottery.fetch(lottery);
+  const [ticket] = anchor.web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from(TICKET_SEED),
+      lottery.toBuffer(),
+      new anchor.BN(lotteryAccount.lastTicketId).toArrayLike(Buffer, "le", 4),
+    ],
+    program.programId
+  );
+
+  const validUntil = new anchor.BN(Math.floor(Date.now() / 1000) + ttlSeconds);
+
+  return program.methods
+    .buyTicket(lotteryId, validUntil, termsHash ?? lotteryAccount.termsHash)
+    .accountsPartial({ lottery, ticket, buyer, reference })
+    .instruction();
+}
+
+// Find the signature of the first confirmed purchase that included the given reference key
+export async function findTicketPurchase(
+  connection: anchor.web3.Connection,
+  reference: anchor.web3.PublicKey
+): Promise<string | null> {
+  const signatures = await connection.getSignaturesForAddress(
+    reference,
+    {},
+    "confirmed"
+  );
+  return signatures.length
+    ? signatures[signatures.length - 1].signature
+    : null;
+}
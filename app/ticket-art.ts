@@ -0,0 +1,47 @@
+import * as anchor from "@coral-xyz/anchor";
+import { createHash } from "crypto";
+
+export type TicketArtTraits = {
+  hue: number; // Base colour, 0-359 degrees
+  accentHue: number; // Second colour, 0-359 degrees
+  pattern: number; // Index into the frontend's list of patterns, 0-15
+  rotation: number; // Pattern rotation, 0-359 degrees
+  density: number; // Pattern density, 1-8
+};
+
+// Derive a ticket's art seed exactly like `Ticket::art_seed` on-chain: sha256(lottery ++ ticket id ++ owner)
+export function ticketArtSeed(
+  lottery: anchor.web3.PublicKey,
+  ticketId: number,
+  owner: anchor.web3.PublicKey
+): Buffer {
+  return createHash("sha256")
+    .update(lottery.toBuffer())
+    .update(new anchor.BN(ticketId).toArrayLike(Buffer, "le", 4))
+    .update(owner.toBuffer())
+    .digest();
+}
+
+// Check that the art seed stored on a ticket (or emitted in an event) belongs to it
+export function verifyTicketArtSeed(
+  artSeed: number[] | Uint8Array,
+  lottery: anchor.web3.PublicKey,
+  ticketId: number,
+  owner: anchor.web3.PublicKey
+): boolean {
+  return ticketArtSeed(lottery, ticketId, owner).equals(Buffer.from(artSeed));
+}
+
+// Turn an art seed into traits a frontend can render; every frontend using this gets the same art for a ticket
+export function ticketArtTraits(
+  artSeed: number[] | Uint8Array
+): TicketArtTraits {
+  const seed = Buffer.from(artSeed);
+  return {
+    hue: seed.readUInt16LE(0) % 360,
+    accentHue: seed.readUInt16LE(2) % 360,
+    pattern: seed[4] % 16,
+    rotation: seed.readUInt16LE(5) % 360,
+    density: (seed[7] % 8) + 1,
+  };
+}
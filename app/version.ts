@@ -0,0 +1,63 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Lottery } from "../target/types/lottery";
+
+// The interface version this client was built against, read from the IDL's PROGRAM_VERSION constant
+export function clientProgramVersion(program: Program<Lottery>): number {
+  const constant = program.idl.constants.find(
+    ({ name }) => name === "PROGRAM_VERSION"
+  );
+  return Number(constant.value);
+}
+
+// Compare the deployed program's version with the one this client was built against.
+// A mismatch only logs a warning unless `strict` is set, in which case it throws.
+export async function checkProgramVersion(
+  program: Program<Lottery>,
+  { strict = false }: { strict?: boolean } = {}
+): Promise<number> {
+  const expected = clientProgramVersion(program);
+  const deployed: number = await program.methods.getVersion().view();
+
+  if (deployed !== expected) {
+    const message =
+      `lottery program at ${program.programId.toBase58()} is version ` +
+      `${deployed}, but this client was built for version ${expected}`;
+    if (strict) throw new Error(message);
+    console.warn(message);
+  }
+  return deployed;
+}
+
+export type UpgradeAuthority = "immutable" | "admin" | "governance";
+
+// Check who can upgrade the deployed program with `verify_upgrade_authority`.
+// Resolves to who controls upgrades if that is no one, the program admin or `governance`; otherwise it warns,
+// or throws in strict mode, so frontends can tell users the program is controlled by an unexpected key.
+export async function checkUpgradeAuthority(
+  program: Program<Lottery>,
+  {
+    governance = null,
+    strict = false,
+  }: { governance?: anchor.web3.PublicKey | null; strict?: boolean } = {}
+): Promise<UpgradeAuthority | null> {
+  const [programData] = anchor.web3.PublicKey.findProgramAddressSync(
+    [program.programId.toBuffer()],
+    anchor.web3.BPF_LOADER_UPGRADEABLE_PROGRAM_ID
+  );
+
+  try {
+    const authority = await program.methods
+      .verifyUpgradeAuthority()
+      .accountsPartial({ programData, governance })
+      .view();
+    return Object.keys(authority)[0] as UpgradeAuthority;
+  } catch (err) {
+    const message =
+      `lottery program at ${program.programId.toBase58()} can be upgraded ` +
+      `by an unexpected key: ${err}`;
+    if (strict) throw new Error(message);
+    console.warn(message);
+    return null;
+  }
+}
@@ -0,0 +1,170 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Lottery } from "../target/types/lottery";
+
+const LOTTERY_SEED = "lottery";
+const TICKET_SEED = "ticket";
+const RECEIPT_SEED = "receipt";
+
+export type WinnerEntry = {
+  lotteryId: number;
+  ticketId: number;
+  winner: string; // The winning ticket's owner
+  prize: string; // The winner's share of the jackpot, in lamports
+  claimed: boolean;
+  claimedAt: number | null; // Unix timestamp of the claim, from the claim receipt
+};
+
+// A raw account as it was at the bundle's slot, so anyone can decode it again with the program's IDL
+export type AccountSnapshot = {
+  pubkey: string;
+  owner: string;
+  lamports: number;
+  data: string; // base64
+};
+
+// Everything needed to publish a lottery's result: the winners and the accounts they were read from, all at one slot
+export type WinnerBundle = {
+  programId: string;
+  slot: number;
+  winners: WinnerEntry[];
+  accounts: AccountSnapshot[];
+};
+
+const u32Seed = (value: number) =>
+  new anchor.BN(value).toArrayLike(Buffer, "le", 4);
+
+const pda = (programId: anchor.web3.PublicKey, seeds: Buffer[]) =>
+  anchor.web3.PublicKey.findProgramAddressSync(seeds, programId)[0];
+
+// The accounts a lottery's result is read from: the lottery, its winning ticket and the winner's claim receipt
+function resultAccounts(
+  programId: anchor.web3.PublicKey,
+  lotteryId: number,
+  winnerId: number
+) {
+  const lottery = pda(programId, [
+    Buffer.from(LOTTERY_SEED),
+    u32Seed(lotteryId),
+  ]);
+  const ticket = pda(programId, [
+    Buffer.from(TICKET_SEED),
+    lottery.toBuffer(),
+    u32Seed(winnerId),
+  ]);
+  const receipt = pda(programId, [
+    Buffer.from(RECEIPT_SEED),
+    lottery.toBuffer(),
+    ticket.toBuffer(),
+  ]);
+  return [lottery, ticket, receipt];
+}
+
+// Derive the winners from decoded accounts; shared by the export and the verification so they can't disagree
+function winnersFrom(
+  program: Program<Lottery>,
+  snapshots: (AccountSnapshot | null)[]
+): WinnerEntry[] {
+  const [lotteryData, ticketData, receiptData] = snapshots.map((snapshot) =>
+    snapshot ? Buffer.from(snapshot.data, "base64") : null
+  );
+  const lottery = program.coder.accounts.decode("lottery", lotteryData);
+  const ticket = program.coder.accounts.decode("ticket", ticketData);
+  if (lottery.winnerId !== ticket.id) {
+    throw new Error(`ticket ${ticket.id} didn't win lottery ${lottery.id}`);
+  }
+  // Refunds leave receipts too, so a receipt only dates the claim once the lottery is claimed
+  const receipt =
+    receiptData && lottery.claimed
+      ? program.coder.accounts.decode("claimReceipt", receiptData)
+      : null;
+
+  // The winner's share of the jackpot, exactly like `Lottery::split_jackpot` on-chain
+  const prize = lottery.ticketPrice
+    .muln(lottery.lastTicketId)
+    .add(lottery.carriedOver)
+    .sub(lottery.bountyPaid)
+    .muln(lottery.winnerShareBps)
+    .divn(10_000);
+
+  return [
+    {
+      lotteryId: lottery.id,
+      ticketId: ticket.id,
+      winner: ticket.authority.toBase58(),
+      prize: prize.toString(),
+      claimed: lottery.claimed,
+      claimedAt: receipt ? receipt.claimedAt.toNumber() : null,
+    },
+  ];
+}
+
+// Export the result of a drawn lottery as a bundle that can be published and checked with `verifyWinnerBundle`.
+// All accounts are read in one request, so the bundle reflects a single slot. A lottery has one winner today;
+// the bundle lists winners so the format stays the same once a lottery can have several.
+export async function exportWinnerBundle(
+  program: Program<Lottery>,
+  lotteryId: number
+): Promise<WinnerBundle> {
+  const [lottery] = resultAccounts(program.programId, lotteryId, 0);
+  const { winnerId } = await program.account.lottery.fetch(lottery);
+  if (winnerId === null) {
+    throw new Error(`lottery ${lotteryId} has no winner yet`);
+  }
+
+  const keys = resultAccounts(program.programId, lotteryId, winnerId);
+  const { context, value } =
+    await program.provider.connection.getMultipleAccountsInfoAndContext(keys);
+  const accounts = value.map((info, i) =>
+    info
+      ? {
+          pubkey: keys[i].toBase58(),
+          owner: info.owner.toBase58(),
+          lamports: info.lamports,
+          data: info.data.toString("base64"),
+        }
+      : null
+  );
+  if (!accounts[0] || !accounts[1]) {
+    throw new Error(`lottery ${lotteryId} changed while it was exported`);
+  }
+
+  return {
+    programId: program.programId.toBase58(),
+    slot: context.slot,
+    winners: winnersFrom(program, accounts),
+    accounts: accounts.filter(
+      (account): account is AccountSnapshot => account !== null
+    ),
+  };
+}
+
+// Check a published bundle: the snapshots must be accounts of the program at the addresses the winners imply,
+// and decoding them must give exactly the listed winners. This checks the bundle is self-consistent;
+// whether the snapshots match the chain at `slot` is up to an RPC node or indexer the verifier trusts.
+export function verifyWinnerBundle(
+  program: Program<Lottery>,
+  bundle: WinnerBundle
+): boolean {
+  if (bundle.programId !== program.programId.toBase58()) return false;
+
+  return bundle.winners.every(({ lotteryId, ticketId }) => {
+    const keys = resultAccounts(program.programId, lotteryId, ticketId);
+    const snapshots = keys.map(
+      (key) =>
+        bundle.accounts.find(({ pubkey }) => pubkey === key.toBase58()) ?? null
+    );
+    if (!snapshots[0] || !snapshots[1]) return false;
+    if (snapshots.some((s) => s && s.owner !== bundle.programId)) return false;
+
+    try {
+      const [derived] = winnersFrom(program, snapshots);
+      const listed = bundle.winners.find(
+        (winner) => winner.lotteryId === lotteryId
+      );
+      return JSON.stringify(derived) === JSON.stringify(listed);
+    } catch (err) {
+      return false;
+    }
+  });
+}
@@ -0,0 +1,85 @@
+// Checks for instructions that take a batch of accounts as remaining accounts
+// Remaining accounts aren't validated by Anchor, so a crafted list could repeat an account to count (or pay) it twice,
+// or slip in an account of another program; every batch goes through these checks before it is used.
+// `app/batch.ts` builds lists that pass them. Accounts created from a batch go through `create_pda_account`,
+// since Anchor's `init` doesn't cover remaining accounts either.
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{
+        program::{invoke, invoke_signed},
+        system_instruction::{allocate, assign, create_account, transfer},
+    },
+};
+
+use crate::error::LotteryError;
+
+// Check that a batch has at most `max` accounts, in strictly increasing address order
+// Strict order rules out duplicates in a single pass and gives every batch one canonical form
+pub fn validate_batch(accounts: &[AccountInfo], max: usize) -> Result<()> {
+    if accounts.len() > max {
+        return err!(LotteryError::TooManyAccounts);
+    }
+    for pair in accounts.windows(2) {
+        if pair[0].key == pair[1].key {
+            return err!(LotteryError::DuplicateAccount);
+        }
+        if pair[0].key > pair[1].key {
+            return err!(LotteryError::AccountsOutOfOrder);
+        }
+    }
+    Ok(())
+}
+
+// Create a rent-exempt account of `space` bytes owned by this program at a PDA, paid by `payer`
+// Does what Anchor's `init` does: anyone can send lamports to an address before it is created, which would make a
+// plain `create_account` fail, so an address that already holds lamports is topped up, allocated and assigned instead
+pub fn create_pda_account<'info>(
+    payer: &AccountInfo<'info>,
+    account: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+    signer_seeds: &[&[u8]],
+) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(space);
+    let lamports = account.lamports();
+    if lamports == 0 {
+        return invoke_signed(
+            &create_account(payer.key, account.key, rent, space as u64, &crate::ID),
+            &[payer.clone(), account.clone(), system_program.clone()],
+            &[signer_seeds],
+        )
+        .map_err(Into::into);
+    }
+
+    if lamports < rent {
+        invoke(
+            &transfer(payer.key, account.key, rent - lamports),
+            &[payer.clone(), account.clone(), system_program.clone()],
+        )?;
+    }
+    invoke_signed(
+        &allocate(account.key, space as u64),
+        &[account.clone(), system_program.clone()],
+        &[signer_seeds],
+    )?;
+    invoke_signed(
+        &assign(account.key, &crate::ID),
+        &[account.clone(), system_program.clone()],
+        &[signer_seeds],
+    )?;
+    Ok(())
+}
+
+// Validate a batch and deserialize its accounts as `T`
+// Each account must be owned by this program and carry `T`'s discriminator, which `Account::try_from` checks
+pub fn load_batch<'info, T>(
+    accounts: &'info [AccountInfo<'info>],
+    max: usize,
+) -> Result<Vec<Account<'info, T>>>
+where
+    T: AccountSerialize + AccountDeserialize + Owner + Clone,
+{
+    validate_batch(accounts, max)?;
+    accounts.iter().map(Account::try_from).collect()
+}
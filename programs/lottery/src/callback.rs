@@ -0,0 +1,66 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::{
+        instruction::{AccountMeta, Instruction},
+        program::invoke_signed,
+    },
+};
+
+use crate::{error::LotteryError, pdas, Lottery, WinnerCallback};
+
+// Check that a program can be registered as a winner callback
+// It must be an executable program other than this one (no re-entry) and the system program
+pub fn validate_callback_target(callback: &WinnerCallback, program: &AccountInfo) -> Result<()> {
+    if program.key() != callback.program {
+        return err!(LotteryError::CallbackProgramMismatch);
+    }
+    if !program.executable || callback.program == crate::ID || callback.program == System::id() {
+        return err!(LotteryError::InvalidCallbackProgram);
+    }
+    Ok(())
+}
+
+// Invoke the lottery's winner callback, see `notify_winner`
+// The callback receives the lottery account as a signer, so it can verify the call came from this program
+// by checking the lottery's owner and signature, followed by the winner account; the instruction data is
+// the registered discriminator followed by the lottery ID
+pub fn invoke_winner_callback<'info>(
+    lottery: &mut Account<'info, Lottery>,
+    bump: u8,
+    program: &UncheckedAccount<'info>,
+    winner: &AccountInfo<'info>,
+) -> Result<()> {
+    let Some(callback) = lottery.callback else {
+        return err!(LotteryError::NoWinnerCallback);
+    };
+    if program.key() != callback.program {
+        return err!(LotteryError::CallbackProgramMismatch);
+    }
+
+    // Persist the delivery first, so the callback sees the lottery as notified and can't re-enter it
+    lottery.exit(&crate::ID)?;
+
+    let mut data = callback.discriminator.to_vec();
+    data.extend_from_slice(&lottery.id.to_le_bytes());
+    let instruction = Instruction {
+        program_id: callback.program,
+        accounts: vec![
+            AccountMeta::new_readonly(lottery.key(), true),
+            AccountMeta::new_readonly(winner.key(), false),
+        ],
+        data,
+    };
+
+    let id = lottery.id.to_le_bytes();
+    let [prefix, id] = pdas::lottery_seeds(&id);
+    invoke_signed(
+        &instruction,
+        &[
+            lottery.to_account_info(),
+            winner.clone(),
+            program.to_account_info(),
+        ],
+        &[&[prefix, id, &[bump]]],
+    )?;
+    Ok(())
+}
@@ -1,4 +1,210 @@
+use anchor_lang::prelude::*;
+
+// Version of the program's instruction and account interface, returned by `get_version` and exported in the IDL
+// Bump it whenever an instruction or account layout changes in a way older clients can't handle
+#[constant]
+pub const PROGRAM_VERSION: u32 = 1;
+
+// Deployment profile the program was built for, exported in the IDL so clients can check what they talk to
+// Select it with the `devnet` or `mainnet` cargo feature (e.g. `anchor build -- --features mainnet`); builds
+// without either are for localnet. Devnet builds keep periods short for testing, mainnet builds start strict
+#[constant]
+pub const DEPLOYMENT_PROFILE: &str = if cfg!(feature = "mainnet") {
+    "mainnet"
+} else if cfg!(feature = "devnet") {
+    "devnet"
+} else {
+    "localnet"
+};
+
+#[cfg(all(feature = "devnet", feature = "mainnet"))]
+compile_error!(
+    "the `devnet` and `mainnet` features select different deployment profiles, enable only one"
+);
+
+// A mainnet artifact must never let the admin move the clock
+#[cfg(all(feature = "mainnet", feature = "test-clock"))]
+compile_error!("the `test-clock` feature can't be used in a mainnet build");
+
 // Define constant seed strings for deriving account addresses
 pub const MASTER_SEED: &str = "master";
 pub const LOTTERY_SEED: &str = "lottery";
 pub const TICKET_SEED: &str = "ticket";
+pub const SUMMARY_SEED: &str = "summary";
+pub const RECEIPT_SEED: &str = "receipt";
+pub const AUDIT_LOG_SEED: &str = "audit_log";
+pub const PROFILE_SEED: &str = "profile";
+pub const LEADERBOARD_SEED: &str = "leaderboard";
+pub const SEASON_SEED: &str = "season";
+pub const SEASON_REWARD_SEED: &str = "season_reward";
+pub const RENT_POOL_SEED: &str = "rent_pool";
+pub const PRIZE_RIGHT_SEED: &str = "prize_right";
+pub const VOUCHER_SEED: &str = "voucher";
+pub const CREATOR_SEED: &str = "creator";
+pub const DISPUTE_SEED: &str = "dispute";
+pub const DISCREPANCY_SEED: &str = "discrepancy";
+pub const RESERVATION_SEED: &str = "reservation";
+pub const VAULT_SEED: &str = "vault";
+pub const CONFIG_SEED: &str = "config";
+pub const COMMITTEE_SEED: &str = "committee";
+pub const INVENTORY_SEED: &str = "inventory";
+pub const FEE_LEDGER_SEED: &str = "fee_ledger";
+
+// The prize tier of a lottery's winning ticket, see `get_ticket_status`; lotteries have a single prize tier for now
+pub const WINNING_TIER: u8 = 1;
+
+// Number of recent slot hashes mixed together when drawing without an epoch lock
+pub const SLOT_HASH_SAMPLES: usize = 8;
+
+// Slots between `commit_randomness` and the slot whose hash `reveal_and_pick` mixes in
+pub const COMMIT_REVEAL_DELAY_SLOTS: u64 = 10;
+
+// Slots after that slot the reveal can still come in; well within the 512 slots the SlotHashes sysvar keeps,
+// so the hash mixed in is always the recorded one for that slot
+pub const MAX_REVEAL_DELAY_SLOTS: u64 = 256;
+
+// Entropy committees, see `set_committee`
+pub const MAX_COMMITTEE_MEMBERS: usize = 8; // Members of one lottery's committee
+pub const COMMITTEE_REVEAL_SLOTS: u64 = 150; // Slots after the first revealed share the other members can reveal theirs in
+
+// Version stored on newly created lotteries
+// Version 1 lotteries drew winners in 1..=last_ticket_id, which could select a ticket that was never created;
+// version 2 draws in 0..last_ticket_id, matching the 0-based ticket IDs
+pub const LOTTERY_VERSION: u8 = 2;
+
+// Bits of the master account's `features` field, each enabling an optional module
+pub const FEATURE_EPOCH_DRAWS: u64 = 1 << 0; // Lotteries with draws locked to an epoch boundary
+pub const FEATURE_CODE_TICKETS: u64 = 1 << 1; // Redemption-code tickets for offline sales
+pub const FEATURE_ARCHIVAL: u64 = 1 << 2; // Archiving finished lotteries into summary accounts
+pub const FEATURE_LEGACY_INSTRUCTIONS: u64 = 1 << 3; // Deprecated instruction names, kept during a migration window
+
+// Features enabled when the master account is first initialized
+// Mainnet deployments start without deprecated instruction names, so integrators can't come to depend on them
+#[cfg(not(feature = "mainnet"))]
+pub const DEFAULT_FEATURES: u64 =
+    FEATURE_EPOCH_DRAWS | FEATURE_CODE_TICKETS | FEATURE_ARCHIVAL | FEATURE_LEGACY_INSTRUCTIONS;
+#[cfg(feature = "mainnet")]
+pub const DEFAULT_FEATURES: u64 = FEATURE_EPOCH_DRAWS | FEATURE_CODE_TICKETS | FEATURE_ARCHIVAL;
+
+// Bounds enforced when creating a lottery
+pub const MIN_TICKET_PRICE: u64 = 1; // Tickets can't be free
+pub const MAX_TICKET_PRICE: u64 = 1_000 * 1_000_000_000; // 1,000 SOL
+pub const MAX_DRAW_EPOCHS_AHEAD: u64 = 52; // Roughly 100 days of ~2 day epochs
+pub const MAX_END_TIME_AHEAD: i64 = 100 * 24 * 60 * 60; // 100 days in seconds, like the draw epoch limit
+
+// Claims a player can self-attest on their profile with `attest`, combined into a lottery's attestation policy
+pub const ATTEST_LEGAL_AGE: u8 = 1 << 0; // The player is of legal gambling age where they live
+pub const ATTEST_JURISDICTION: u8 = 1 << 1; // Lotteries are legal in the player's declared jurisdiction
+pub const ATTEST_ALL: u8 = ATTEST_LEGAL_AGE | ATTEST_JURISDICTION;
+
+// A winner share of 100%, in basis points; creators can pay less and roll the rest over
+pub const MAX_WINNER_SHARE_BPS: u16 = 10_000;
+
+// Maximum size of the opaque `extra` data creators can attach to a lottery
+pub const MAX_EXTRA_LEN: usize = 128;
+
+// Maximum size of a lottery's locale hint, enough for BCP 47 tags like "zh-Hant-TW"
+pub const MAX_LOCALE_LEN: usize = 16;
+
+// Largest block of house tickets `stock_inventory` takes, so its sold tickets fit a 32 byte bitmap
+pub const MAX_INVENTORY_BLOCK: u16 = 256;
+
+// Named lotteries, see `create_named_lottery`
+pub const MAX_LOTTERY_NAME_LEN: usize = 32; // The name is a PDA seed, which can't be longer
+pub const NAMED_LOTTERY_ID_BASE: u32 = 1 << 31; // Named lotteries take IDs from here on, the counter stays below
+
+// Number of undrawn lotteries a single creator can have at once, until the admin changes it
+pub const DEFAULT_MAX_OPEN_LOTTERIES: u32 = 25;
+
+// The most a draw can pay its caller, in basis points of the jackpot, until the admin changes it
+pub const DEFAULT_MAX_DRAW_BOUNTY_BPS: u16 = 100; // 1%
+
+// The most the protocol fee can be, in basis points of a prize, whatever the config authority sets
+pub const MAX_FEE_BPS: u16 = 1_000; // 10%
+
+// The protocol fee a new deployment starts with, until the config authority changes it
+pub const DEFAULT_FEE_BPS: u16 = 0;
+
+// Number of admin actions kept in the audit log before the oldest are overwritten
+pub const AUDIT_LOG_CAPACITY: usize = 32;
+
+// Kinds of admin actions recorded in the audit log
+pub const AUDIT_SET_FEATURES: u8 = 1;
+pub const AUDIT_CLOSE_SEASON: u8 = 2;
+pub const AUDIT_COMMIT_SEASON_REWARDS: u8 = 3;
+pub const AUDIT_SET_RECOVERY_PERIOD: u8 = 4;
+pub const AUDIT_SET_MAX_OPEN_LOTTERIES: u8 = 5;
+pub const AUDIT_RESOLVE_DISPUTE: u8 = 6;
+pub const AUDIT_SET_PAYOUT_LIMIT: u8 = 7;
+pub const AUDIT_RESUME_CLAIMS: u8 = 8;
+pub const AUDIT_SET_MAX_DRAW_BOUNTY: u8 = 9;
+pub const AUDIT_SCHEDULE_FORCE_RESOLVE: u8 = 10;
+pub const AUDIT_FORCE_RESOLVE: u8 = 11;
+pub const AUDIT_UPDATE_CONFIG: u8 = 12;
+pub const AUDIT_SET_CREATOR_FEE: u8 = 13;
+
+// Protocol fees counted per period for the treasury's accounting, see `get_fee_report`
+pub const FEE_PERIOD: i64 = 7 * 24 * 60 * 60; // Seconds counted together, one week
+pub const FEE_LEDGER_PERIODS: usize = 26; // Periods kept before the oldest is overwritten, half a year
+
+// Number of players ranked on each leaderboard
+pub const LEADERBOARD_SIZE: usize = 10;
+
+// Number of tickets `buy_tickets` buys in one call, keeping it within the compute budget and account limit
+pub const MAX_TICKETS_PER_BUY: usize = 20;
+
+// Number of tickets `refund_batch` refunds in one call, keeping its three accounts per ticket within a transaction
+pub const MAX_REFUNDS_PER_BATCH: usize = 8;
+
+// Number of lotteries `get_creator_dashboard` aggregates in one call, keeping it within the compute budget
+pub const MAX_DASHBOARD_LOTTERIES: usize = 20;
+
+// Number of lotteries `check_lottery_health` checks in one call, keeping it within the compute budget
+pub const MAX_HEALTH_CHECK_LOTTERIES: usize = 50;
+
+// Warnings reported by `check_lottery_health`
+pub const HEALTH_DRAW_OVERDUE: u8 = 1 << 0; // The draw epoch is over but no winner was drawn
+pub const HEALTH_CLAIM_EXPIRING: u8 = 1 << 1; // The prize is unclaimed and the claim window closes within CLAIM_EXPIRY_WARNING
+pub const HEALTH_CLAIM_EXPIRED: u8 = 1 << 2; // The claim window closed and ticket owners can recover their funds
+pub const HEALTH_VAULT_SHORT: u8 = 1 << 3; // The lottery account holds less than the jackpot it owes
+pub const HEALTH_DISPUTED: u8 = 1 << 4; // A challenge of the draw is pending
+
+// How long before the claim window closes `check_lottery_health` starts warning, in seconds
+pub const CLAIM_EXPIRY_WARNING: i64 = 7 * 24 * 60 * 60; // One week
+
+// How long a drawn prize stays claimable before ticket owners can recover their funds, in seconds
+// Devnet builds use a day (and allow an hour) so testers can go through recovery without waiting a month
+#[cfg(not(feature = "devnet"))]
+pub const DEFAULT_RECOVERY_PERIOD: i64 = 365 * 24 * 60 * 60; // One year
+#[cfg(not(feature = "devnet"))]
+pub const MIN_RECOVERY_PERIOD: i64 = 30 * 24 * 60 * 60; // The admin can't shorten it below 30 days
+#[cfg(feature = "devnet")]
+pub const DEFAULT_RECOVERY_PERIOD: i64 = 24 * 60 * 60; // One day
+#[cfg(feature = "devnet")]
+pub const MIN_RECOVERY_PERIOD: i64 = 60 * 60; // One hour
+
+// How long after `schedule_force_resolve` the admin can force-resolve a stuck lottery, in seconds
+// Devnet builds use a minute so testers can go through it
+#[cfg(not(feature = "devnet"))]
+pub const FORCE_RESOLVE_DELAY: i64 = 7 * 24 * 60 * 60; // One week
+#[cfg(feature = "devnet")]
+pub const FORCE_RESOLVE_DELAY: i64 = 60; // One minute
+
+// The longest a creator can hold claims back after the draw, see `set_claim_cooldown`
+// Half the shortest recovery period, so every winner keeps a window to claim in
+pub const MAX_CLAIM_COOLDOWN: i64 = MIN_RECOVERY_PERIOD / 2;
+
+// The longest refund grace period a creator can offer after each purchase, see `set_refund_grace`
+pub const MAX_REFUND_GRACE_SLOTS: u64 = 9_000; // About an hour of 400ms slots
+
+// Lotteries that split the jackpot among every ticket matching the draw seed, see `set_match_bits`
+pub const MAX_MATCH_BITS: u8 = 24; // The longest prefix a ticket must match, about one in 16 million tickets
+pub const MAX_TICKETS_PER_COUNT: usize = 25; // Tickets `count_matches` checks in one call, within the compute budget
+
+// Circuit breaker on payouts, see `Master::record_payout`
+pub const PAYOUT_WINDOW: i64 = 24 * 60 * 60; // Seconds of payouts counted together
+pub const DEFAULT_PAYOUT_LIMIT_BPS: u32 = 10_000; // A window may pay out at most the pots owed, 0 disables the breaker
+
+// Challenges of a draw, see `challenge_draw`
+pub const DISPUTE_WINDOW: i64 = 24 * 60 * 60; // Seconds after the draw during which it can be challenged
+pub const DISPUTE_BOND: u64 = 100_000_000; // 0.1 SOL, slashed if the challenge is rejected
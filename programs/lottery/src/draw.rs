@@ -0,0 +1,347 @@
+use anchor_lang::prelude::*;
+use solana_sha256_hasher::{hash, hashv};
+
+use crate::error::LotteryError;
+
+// Each SlotHashes sysvar entry is a u64 slot followed by a 32 byte bank hash
+const SLOT_HASH_ENTRY_LEN: usize = 8 + 32;
+
+// Find the hash of the first slot at or after `slot` in the SlotHashes sysvar, the same hash whenever it's called
+// The sysvar only keeps the most recent slots; once `slot` has aged out, the oldest remaining entry would change with
+// every new slot and let the caller pick among hashes, so this fails with `EntropyUnavailable` instead
+pub fn slot_hash_at_or_after(slot_hashes: &AccountInfo, slot: u64) -> Result<[u8; 32]> {
+    first_hash_at_or_after(&slot_hashes.try_borrow_data()?, slot)
+        .ok_or(error!(LotteryError::EntropyUnavailable))
+}
+
+// The entropy of an epoch-locked draw: the hash of the first slot of the epoch after `draw_epoch`, which didn't exist
+// while tickets could be bought. If nobody drew the lottery before that hash aged out of the SlotHashes sysvar, the
+// first slot of the earliest later epoch whose hash is still recorded stands in for it, so a missed draw waits for the
+// next epoch to start instead of getting stuck. Every candidate is unknown until its epoch begins, and at any time only
+// the earliest recorded one counts, so the caller can't pick among hashes
+pub fn epoch_slot_hash(
+    slot_hashes: &AccountInfo,
+    schedule: &EpochSchedule,
+    draw_epoch: u64,
+) -> Result<[u8; 32]> {
+    first_epoch_hash(&slot_hashes.try_borrow_data()?, schedule, draw_epoch)
+        .ok_or(error!(LotteryError::EntropyUnavailable))
+}
+
+// Look the first slot of each epoch after `draw_epoch` up in raw SlotHashes sysvar data, see `epoch_slot_hash`
+// Only epochs that started within the recorded slots can have a hash, so at most a few are tried
+fn first_epoch_hash(data: &[u8], schedule: &EpochSchedule, draw_epoch: u64) -> Option<[u8; 32]> {
+    let (newest_slot, _) = entries(data).next()?;
+    let (oldest_slot, _) = entries(data).last()?;
+    let first_epoch = (draw_epoch + 1).max(schedule.get_epoch(oldest_slot));
+    (first_epoch..=schedule.get_epoch(newest_slot))
+        .find_map(|epoch| first_hash_at_or_after(data, schedule.get_first_slot_in_epoch(epoch)))
+}
+
+// Look `slot` up in raw SlotHashes sysvar data, see `slot_hash_at_or_after`
+// Entries are stored newest first, so the last entry that isn't older than `slot` is the one we want; it is only the
+// right one if an entry at or before `slot` is still recorded, i.e. nothing between them has aged out
+fn first_hash_at_or_after(data: &[u8], slot: u64) -> Option<[u8; 32]> {
+    let (oldest_slot, _) = entries(data).last()?;
+    if oldest_slot > slot {
+        return None;
+    }
+    entries(data)
+        .take_while(|(entry_slot, _)| *entry_slot >= slot)
+        .last()
+        .map(|(_, entry_hash)| entry_hash)
+}
+
+// Mix the most recent `samples` slot hashes with the lottery key and the final ticket count
+// A single leader can only influence one of the sampled hashes, and the result is bound to this exact lottery state
+pub fn recent_slot_hashes_seed(
+    slot_hashes: &AccountInfo,
+    samples: usize,
+    lottery: &Pubkey,
+    ticket_count: u32,
+) -> Result<[u8; 32]> {
+    let data = slot_hashes.try_borrow_data()?;
+    let recent: Vec<[u8; 32]> = entries(&data)
+        .take(samples)
+        .map(|(_, entry_hash)| entry_hash)
+        .collect();
+
+    if recent.len() < samples {
+        return err!(LotteryError::EntropyUnavailable);
+    }
+
+    let ticket_count = ticket_count.to_le_bytes();
+    let mut seed: Vec<&[u8]> = recent.iter().map(|entry_hash| &entry_hash[..]).collect();
+    seed.push(lottery.as_ref());
+    seed.push(&ticket_count);
+
+    Ok(hashv(&seed).to_bytes())
+}
+
+// Mix the slot hash of an epoch-locked draw with the lottery key and the final ticket count, so lotteries locked to
+// the same epoch don't all draw the same index
+pub fn epoch_seed(slot_hash: [u8; 32], lottery: &Pubkey, ticket_count: u32) -> [u8; 32] {
+    hashv(&[&slot_hash, lottery.as_ref(), &ticket_count.to_le_bytes()]).to_bytes()
+}
+
+// Mix a revealed commit-reveal secret with the hash of the slot after the commitment, the lottery key and the ticket count
+// The authority fixed the secret before the slot hash existed, and no leader knows the secret, so neither can pick the result
+pub fn commit_reveal_seed(
+    secret: [u8; 32],
+    slot_hash: [u8; 32],
+    lottery: &Pubkey,
+    ticket_count: u32,
+) -> [u8; 32] {
+    hashv(&[
+        &secret,
+        &slot_hash,
+        lottery.as_ref(),
+        &ticket_count.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+// Mix the shares an entropy committee revealed, in member order, with the hash of the first slot after its reveal
+// window, the lottery key and the ticket count. Each share was fixed by its commitment before sales ended, and the slot
+// hash didn't exist while shares could still be revealed, so a member can't tell whether withholding its share helps
+pub fn committee_seed(
+    shares: &[[u8; 32]],
+    slot_hash: [u8; 32],
+    lottery: &Pubkey,
+    ticket_count: u32,
+) -> [u8; 32] {
+    let ticket_count = ticket_count.to_le_bytes();
+    let mut seed: Vec<&[u8]> = shares.iter().map(|share| &share[..]).collect();
+    seed.extend([&slot_hash[..], lottery.as_ref(), &ticket_count[..]]);
+    hashv(&seed).to_bytes()
+}
+
+// Derive the seed of a redraw from the seed whose drawn ticket can't win, see `redraw_void_winner`
+// Nothing but the previous seed goes in, so anyone can follow a chain of redraws from the original draw
+pub fn redraw_seed(seed: [u8; 32]) -> [u8; 32] {
+    hashv(&[b"redraw", &seed]).to_bytes()
+}
+
+// Whether the first `bits` bits of two hashes agree, reading each byte from its most significant bit
+// Match mode lotteries pay every ticket whose art seed agrees with the draw seed this way, see `set_match_bits`
+pub fn prefix_matches(a: &[u8; 32], b: &[u8; 32], bits: u8) -> bool {
+    let bytes = usize::from(bits / 8);
+    let rest = bits % 8;
+    a[..bytes] == b[..bytes] && (rest == 0 || (a[bytes] ^ b[bytes]) >> (8 - rest) == 0)
+}
+
+// Map a 32 byte seed to an index in 0..count with every index equally likely
+// Each 8 byte chunk of the seed is tried in turn and rejected if it falls in the short last block of 2^64 that
+// would favour low indexes; if all four are rejected the seed is rehashed and the process repeats
+pub fn uniform_index(mut seed: [u8; 32], count: u32) -> u32 {
+    loop {
+        for chunk in seed.chunks_exact(8) {
+            if let Some(index) =
+                index_from_value(u64::from_le_bytes(chunk.try_into().unwrap()), count)
+            {
+                return index;
+            }
+        }
+        seed = hash(&seed).to_bytes();
+    }
+}
+
+// Map a single 64-bit value to an index in 0..count, or None if the value has to be rejected
+pub fn index_from_value(value: u64, count: u32) -> Option<u32> {
+    let count = u64::from(count);
+    (value <= last_accepted_value(count)).then(|| (value % count) as u32)
+}
+
+// The largest value that still belongs to a complete block of `count` values
+pub fn last_accepted_value(count: u64) -> u64 {
+    u64::MAX - (u64::MAX % count + 1) % count
+}
+
+// Iterate over the (slot, hash) entries of the raw SlotHashes sysvar data, newest first
+fn entries(data: &[u8]) -> impl Iterator<Item = (u64, [u8; 32])> + '_ {
+    data.get(8..)
+        .unwrap_or_default()
+        .chunks_exact(SLOT_HASH_ENTRY_LEN)
+        .map(|entry| {
+            let (entry_slot, entry_hash) = entry.split_at(8);
+            (
+                u64::from_le_bytes(entry_slot.try_into().unwrap()),
+                <[u8; 32]>::try_from(entry_hash).unwrap(),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Deterministic seeds so the statistical checks can't flake
+    fn seed(i: u32) -> [u8; 32] {
+        hash(&i.to_le_bytes()).to_bytes()
+    }
+
+    // Raw SlotHashes data for the given slots, newest first, each hashed to a distinct value
+    fn slot_hashes(slots: &[u64]) -> Vec<u8> {
+        let mut data = (slots.len() as u64).to_le_bytes().to_vec();
+        for slot in slots {
+            data.extend_from_slice(&slot.to_le_bytes());
+            data.extend_from_slice(&hash(&slot.to_le_bytes()).to_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn slot_hash_is_the_first_recorded_at_or_after_the_slot() {
+        let data = slot_hashes(&[105, 104, 102, 100]);
+        let hash_of = |slot: u64| Some(hash(&slot.to_le_bytes()).to_bytes());
+        assert_eq!(first_hash_at_or_after(&data, 102), hash_of(102));
+        // Slot 103 was skipped, so the next recorded slot stands in for it
+        assert_eq!(first_hash_at_or_after(&data, 103), hash_of(104));
+        assert_eq!(first_hash_at_or_after(&data, 100), hash_of(100));
+        assert_eq!(first_hash_at_or_after(&data, 106), None);
+    }
+
+    #[test]
+    fn aged_out_slot_has_no_hash() {
+        // Slot 100 rolled out of the sysvar; the oldest entry left changes every slot, so it can't stand in
+        assert_eq!(
+            first_hash_at_or_after(&slot_hashes(&[105, 104, 101]), 100),
+            None
+        );
+        assert_eq!(first_hash_at_or_after(&slot_hashes(&[]), 100), None);
+    }
+
+    #[test]
+    fn epoch_draw_uses_the_first_slot_of_the_next_epoch() {
+        // 32 slot epochs, so epoch 3 starts at slot 96
+        let schedule = EpochSchedule::custom(32, 32, false);
+        let recorded =
+            |slots: std::ops::RangeInclusive<u64>| slot_hashes(&slots.rev().collect::<Vec<_>>());
+        let hash_of = |slot: u64| Some(hash(&slot.to_le_bytes()).to_bytes());
+        assert_eq!(
+            first_epoch_hash(&recorded(90..=100), &schedule, 2),
+            hash_of(96)
+        );
+        // Later epochs don't count while the draw epoch's own hash is still there
+        assert_eq!(
+            first_epoch_hash(&recorded(90..=140), &schedule, 2),
+            hash_of(96)
+        );
+        // Not before the next epoch has started
+        assert_eq!(first_epoch_hash(&recorded(80..=95), &schedule, 2), None);
+    }
+
+    #[test]
+    fn missed_epoch_draw_waits_for_the_next_epoch() {
+        let schedule = EpochSchedule::custom(32, 32, false);
+        let recorded =
+            |slots: std::ops::RangeInclusive<u64>| slot_hashes(&slots.rev().collect::<Vec<_>>());
+        let hash_of = |slot: u64| Some(hash(&slot.to_le_bytes()).to_bytes());
+        // Slot 96 aged out, and so did 128, the first slot of epoch 4; epoch 5 starts at 160
+        assert_eq!(first_epoch_hash(&recorded(140..=150), &schedule, 2), None);
+        assert_eq!(
+            first_epoch_hash(&recorded(140..=170), &schedule, 2),
+            hash_of(160)
+        );
+        // A skipped first slot is stood in for by the next recorded one, like in `slot_hash_at_or_after`
+        let mut skipped = recorded(140..=170);
+        let entry = (170 - 160) * SLOT_HASH_ENTRY_LEN + 8;
+        skipped.drain(entry..entry + SLOT_HASH_ENTRY_LEN);
+        assert_eq!(first_epoch_hash(&skipped, &schedule, 2), hash_of(161));
+    }
+
+    #[test]
+    fn index_is_always_in_range() {
+        for count in 1..=64 {
+            for i in 0..500 {
+                assert!(uniform_index(seed(i), count) < count);
+            }
+        }
+    }
+
+    #[test]
+    fn commit_reveal_seed_needs_both_halves() {
+        let lottery = Pubkey::new_from_array([1; 32]);
+        let seed = commit_reveal_seed([2; 32], [3; 32], &lottery, 10);
+        assert_eq!(seed, commit_reveal_seed([2; 32], [3; 32], &lottery, 10));
+        assert_ne!(seed, commit_reveal_seed([4; 32], [3; 32], &lottery, 10));
+        assert_ne!(seed, commit_reveal_seed([2; 32], [4; 32], &lottery, 10));
+    }
+
+    #[test]
+    fn committee_seed_depends_on_every_share() {
+        let lottery = Pubkey::new_from_array([1; 32]);
+        let seed = committee_seed(&[[2; 32], [3; 32]], [4; 32], &lottery, 10);
+        assert_ne!(seed, committee_seed(&[[2; 32]], [4; 32], &lottery, 10));
+        assert_ne!(
+            seed,
+            committee_seed(&[[3; 32], [2; 32]], [4; 32], &lottery, 10)
+        );
+        assert_ne!(
+            seed,
+            committee_seed(&[[2; 32], [3; 32]], [5; 32], &lottery, 10)
+        );
+    }
+
+    #[test]
+    fn single_ticket_always_wins() {
+        for i in 0..100 {
+            assert_eq!(uniform_index(seed(i), 1), 0);
+        }
+    }
+
+    #[test]
+    fn biased_values_are_rejected() {
+        // 2^64 % 3 == 1, so u64::MAX is the only value outside a complete block and must be skipped
+        let mut seed = [0u8; 32];
+        seed[..8].copy_from_slice(&u64::MAX.to_le_bytes());
+        seed[8..16].copy_from_slice(&5u64.to_le_bytes());
+        assert_eq!(uniform_index(seed, 3), 2);
+
+        // u64::MAX - 1 is the last accepted value
+        seed[..8].copy_from_slice(&(u64::MAX - 1).to_le_bytes());
+        assert_eq!(uniform_index(seed, 3), ((u64::MAX - 1) % 3) as u32);
+    }
+
+    #[test]
+    fn prefixes_match_bit_by_bit() {
+        let draw = [0b1011_0110; 32];
+        let mut ticket = draw;
+        ticket[1] = 0b1011_1110; // Differs from the draw in bit 12
+        assert!(prefix_matches(&ticket, &draw, 0));
+        assert!(prefix_matches(&ticket, &draw, 12));
+        assert!(!prefix_matches(&ticket, &draw, 13));
+        assert!(!prefix_matches(&ticket, &draw, 24));
+        assert!(prefix_matches(&draw, &draw, 24));
+    }
+
+    #[test]
+    fn fully_rejected_seed_is_rehashed() {
+        let seed = [0xff; 32];
+        assert_eq!(
+            uniform_index(seed, 3),
+            uniform_index(hash(&seed).to_bytes(), 3)
+        );
+    }
+
+    #[test]
+    fn distribution_passes_chi_squared() {
+        // 7 buckets, 70_000 draws; the chi-squared critical value for 6 degrees of freedom at p = 0.001 is 22.458
+        let count = 7;
+        let draws = 70_000;
+        let mut buckets = [0u32; 7];
+        for i in 0..draws {
+            buckets[uniform_index(seed(i), count) as usize] += 1;
+        }
+
+        let expected = f64::from(draws) / f64::from(count);
+        let chi_squared: f64 = buckets
+            .iter()
+            .map(|&observed| (f64::from(observed) - expected).powi(2) / expected)
+            .sum();
+        assert!(
+            chi_squared < 22.458,
+            "chi-squared {chi_squared} too high: {buckets:?}"
+        );
+    }
+}
@@ -16,4 +16,351 @@ pub enum LotteryError {
 
     #[msg("The prize has already been claimed.")]
     AlreadyClaimed,
+
+    #[msg("The draw epoch has already passed.")]
+    InvalidDrawEpoch,
+
+    #[msg("Ticket sales have ended for this draw epoch.")]
+    DrawEpochPassed,
+
+    #[msg("The draw epoch has not ended yet.")]
+    DrawEpochNotReached,
+
+    #[msg("No slot hash is available to draw from.")]
+    EntropyUnavailable,
+
+    #[msg("Invalid redemption code.")]
+    InvalidRedemptionCode,
+
+    #[msg("The ticket has already been redeemed.")]
+    TicketAlreadyRedeemed,
+
+    #[msg("The ticket's redemption code has not been redeemed.")]
+    TicketNotRedeemed,
+
+    #[msg("The lottery has not finished yet.")]
+    LotteryNotFinished,
+
+    #[msg("The lottery ID doesn't match the lottery account.")]
+    LotteryIdMismatch,
+
+    #[msg("The ticket ID doesn't match the ticket account.")]
+    TicketIdMismatch,
+
+    #[msg("This feature is disabled.")]
+    FeatureDisabled,
+
+    #[msg("The ticket price is too low.")]
+    TicketPriceTooLow,
+
+    #[msg("The ticket price is too high.")]
+    TicketPriceTooHigh,
+
+    #[msg("The draw epoch is too far in the future.")]
+    DrawEpochTooFar,
+
+    #[msg("The pot is too large to pay out.")]
+    PotOverflow,
+
+    #[msg("The season hasn't been closed yet.")]
+    SeasonNotClosed,
+
+    #[msg("The season's rewards have already been committed.")]
+    SeasonRewardsAlreadyCommitted,
+
+    #[msg("The season's rewards haven't been committed yet.")]
+    SeasonRewardsNotCommitted,
+
+    #[msg("The reward proof doesn't match the season's reward root.")]
+    InvalidRewardProof,
+
+    #[msg("The season's reward funds are exhausted.")]
+    SeasonRewardsExhausted,
+
+    #[msg("Too many accounts were passed.")]
+    TooManyAccounts,
+
+    #[msg("The same account was passed more than once.")]
+    DuplicateAccount,
+
+    #[msg("The lottery wasn't created by this creator.")]
+    LotteryCreatorMismatch,
+
+    #[msg("The quantity must be at least one.")]
+    InvalidQuantity,
+
+    #[msg("The buyer doesn't have enough lamports.")]
+    InsufficientFunds,
+
+    #[msg("The transaction landed after its deadline.")]
+    TransactionExpired,
+
+    #[msg("The lottery authority can't enter this lottery.")]
+    SelfEntryForbidden,
+
+    #[msg("Only the ticket owner or their delegate can claim the prize.")]
+    NotAuthorizedToClaim,
+
+    #[msg("The recovery period is too short.")]
+    RecoveryPeriodTooShort,
+
+    #[msg("The claim window has expired; ticket owners can recover their funds instead.")]
+    ClaimWindowExpired,
+
+    #[msg("Funds can't be recovered from this lottery yet.")]
+    RecoveryNotOpen,
+
+    #[msg("This instruction doesn't apply to the lottery's prize kind.")]
+    WrongPrizeKind,
+
+    #[msg("The callback program account doesn't match the lottery's callback.")]
+    CallbackProgramMismatch,
+
+    #[msg("The callback program must be an executable program other than this one and the system program.")]
+    InvalidCallbackProgram,
+
+    #[msg("The callback can't be changed once tickets have been sold.")]
+    CallbackLocked,
+
+    #[msg("The voucher doesn't have enough credit.")]
+    InsufficientVoucherBalance,
+
+    #[msg("The creator already has the maximum number of open lotteries")]
+    TooManyOpenLotteries,
+
+    #[msg("The terms hash doesn't match the lottery's current terms")]
+    TermsMismatch,
+
+    #[msg("The terms can't be changed after tickets were sold")]
+    TermsLocked,
+
+    #[msg("The lottery requires self-attestations missing from the player's profile")]
+    AttestationRequired,
+
+    #[msg("The attestation claims unknown bits or names an invalid jurisdiction")]
+    InvalidAttestation,
+
+    #[msg("Only ticket owners of the lottery can challenge its draw")]
+    NotAParticipant,
+
+    #[msg("The window to challenge this draw has closed")]
+    DisputeWindowClosed,
+
+    #[msg("The draw is under dispute")]
+    DrawDisputed,
+
+    #[msg("The winner share must be between 1 and 10,000 basis points")]
+    InvalidWinnerShare,
+
+    #[msg("The extra data is longer than 128 bytes")]
+    ExtraTooLong,
+
+    #[msg("The lottery can't be reconciled while refunds are open")]
+    CannotReconcile,
+
+    #[msg("Batch accounts must be passed in strictly increasing address order.")]
+    AccountsOutOfOrder,
+
+    #[msg(
+        "The currency must be an ISO 4217 code and the locale a BCP 47 tag of at most 16 bytes."
+    )]
+    InvalidDisplayHints,
+
+    #[msg("The program data account doesn't belong to this program.")]
+    ProgramDataMismatch,
+
+    #[msg("The program can be upgraded by an unexpected key.")]
+    UnexpectedUpgradeAuthority,
+
+    #[msg("Payouts are paused by the circuit breaker until the admin resumes them.")]
+    ClaimsPaused,
+
+    #[msg("A reservation must cover at least one ticket.")]
+    InvalidReservation,
+
+    #[msg("Every ticket of the reservation has been assigned.")]
+    ReservationExhausted,
+
+    #[msg("The lottery can't be drawn in the same slot as its last sale.")]
+    DrawInSaleSlot,
+
+    #[msg("The claim cooldown must be between 0 and MAX_CLAIM_COOLDOWN seconds.")]
+    InvalidClaimCooldown,
+
+    #[msg("The prize can't be claimed until the lottery's claim cooldown has passed.")]
+    ClaimCooldown,
+
+    #[msg("The lottery has drawn its winner and no longer sells tickets.")]
+    SalesClosed,
+
+    #[msg("A draw bounty can be at most 10,000 basis points of the jackpot.")]
+    InvalidDrawBounty,
+
+    #[msg("Only the lottery authority can draw a lottery that isn't epoch-locked.")]
+    NotLotteryAuthority,
+
+    #[msg("The lottery can't be force-resolved before its scheduled time.")]
+    ForceResolveNotReady,
+
+    #[msg("The lottery was cancelled.")]
+    LotteryCancelled,
+
+    #[msg("The lottery is committed to a secret and can only be drawn with reveal_and_pick.")]
+    DrawCommitted,
+
+    #[msg("The lottery has no randomness commitment to reveal.")]
+    NoCommitment,
+
+    #[msg("The revealed secret doesn't match the commitment.")]
+    RevealMismatch,
+
+    #[msg("The reveal has to wait until the slot after the commitment delay.")]
+    RevealTooEarly,
+
+    #[msg("The reveal came too late; the slot hash it mixes in is no longer available.")]
+    RevealExpired,
+
+    #[msg("Epoch-locked lotteries draw from the slot hash of their draw epoch.")]
+    EpochLockedDraw,
+
+    #[msg("This isn't available for lotteries paid in an SPL token.")]
+    UnsupportedForTokenLottery,
+
+    #[msg("A token lottery needs its vault, the payer's or payee's token account and the token program.")]
+    TokenAccountsMissing,
+
+    #[msg("The token account must belong to the account being paid.")]
+    TokenAccountOwnerMismatch,
+
+    #[msg("The end time must be in the future and at most 100 days ahead.")]
+    InvalidEndTime,
+
+    #[msg("The lottery's sales have ended.")]
+    SalesEnded,
+
+    #[msg("The lottery can't be drawn before its end time.")]
+    DrawBeforeEndTime,
+
+    #[msg("The ticket accounts don't match the next ticket IDs.")]
+    TicketAccountMismatch,
+
+    #[msg("The protocol fee is above the maximum.")]
+    InvalidFee,
+
+    #[msg("The lottery hasn't been cancelled.")]
+    LotteryNotCancelled,
+
+    #[msg("The lottery has no winner callback.")]
+    NoWinnerCallback,
+
+    #[msg("The prize hasn't been claimed yet.")]
+    PrizeNotClaimed,
+
+    #[msg("The winner callback has already been delivered.")]
+    WinnerAlreadyNotified,
+
+    #[msg("Only lotteries that anyone can draw, i.e. epoch-locked or committee ones, pay a draw bounty.")]
+    DrawNotPermissionless,
+
+    #[msg("The lottery counter has reached the IDs reserved for named lotteries.")]
+    LotteryIdsExhausted,
+
+    #[msg("The lottery name must be 1 to 32 printable ASCII characters.")]
+    InvalidLotteryName,
+
+    #[msg("The lottery ID isn't the one derived from the creator and the name.")]
+    LotteryNameMismatch,
+
+    #[msg(
+        "The committee needs 1 to 8 distinct members and a threshold between 1 and their number."
+    )]
+    InvalidCommittee,
+
+    #[msg(
+        "A committee draws after the end time, so the lottery needs one and can't be epoch-locked."
+    )]
+    CommitteeNeedsEndTime,
+
+    #[msg("The lottery is drawn by its committee with finalize_draw.")]
+    CommitteeDraw,
+
+    #[msg("The signer isn't a member of the lottery's committee.")]
+    NotCommitteeMember,
+
+    #[msg("The member already committed to a share.")]
+    ShareAlreadyCommitted,
+
+    #[msg("The member didn't commit to a share before the end time.")]
+    ShareNotCommitted,
+
+    #[msg("The member already revealed their share.")]
+    ShareAlreadyRevealed,
+
+    #[msg("The committee's reveal window has closed.")]
+    RevealWindowClosed,
+
+    #[msg("The committee's reveal window hasn't closed yet.")]
+    RevealWindowOpen,
+
+    #[msg("Not enough committee members revealed their shares.")]
+    CommitteeThresholdNotMet,
+
+    #[msg("An inventory block holds 1 to 256 tickets.")]
+    InvalidInventory,
+
+    #[msg("This inventory ticket was already sold.")]
+    InventoryTicketSold,
+
+    #[msg("Minimum ticket counts need a lottery with an end time.")]
+    MinTicketsNeedEndTime,
+
+    #[msg("The lottery has no minimum ticket count to close sales at.")]
+    NoMinTickets,
+
+    #[msg("Sales can only end early once the minimum ticket count is sold.")]
+    MinTicketsNotSold,
+
+    #[msg("The refund accounts don't match the tickets, their receipts and their owners.")]
+    RefundAccountMismatch,
+
+    #[msg("A committee lottery can't be cancelled once its shares can be revealed.")]
+    CommitteeRevealStarted,
+
+    #[msg("Lotteries drawn from recent slot hashes can't be drawn through another program.")]
+    DrawThroughCpi,
+
+    #[msg("Only the program's upgrade authority can initialize the master account.")]
+    NotUpgradeAuthority,
+
+    #[msg("A fee report must end at or after its first period and span at most the periods the fee ledger keeps.")]
+    InvalidFeeReportRange,
+
+    #[msg("The refund grace period can be at most MAX_REFUND_GRACE_SLOTS slots, and token lotteries don't offer one.")]
+    InvalidRefundGrace,
+
+    #[msg("The ticket's refund grace period is over, or it never had one.")]
+    RefundGraceOver,
+
+    #[msg("This ticket was refunded during its grace period and can't win or be refunded again.")]
+    TicketRefunded,
+
+    #[msg("Only a drawn lottery whose winning ticket was refunded or is an unredeemed code ticket that can no longer be redeemed can be redrawn.")]
+    WinnerNotVoid,
+
+    #[msg("Match mode matches at most MAX_MATCH_BITS bits.")]
+    InvalidMatchBits,
+
+    #[msg("Match mode needs a lamport or token prize without a winner callback or refund grace period.")]
+    MatchModeUnsupported,
+
+    #[msg("The lottery doesn't split its jackpot among matching tickets.")]
+    NotMatchMode,
+
+    #[msg("Matching tickets can only claim once count_matches has checked every ticket.")]
+    MatchesNotCounted,
+
+    #[msg(
+        "Code tickets of a no_self_entry or match mode lottery must be redeemed before the draw."
+    )]
+    RedemptionClosed,
 }
@@ -0,0 +1,181 @@
+// A table of the program's error codes and a stable key for each, for clients that show localized error messages
+// Keys are the `LotteryError` variant names, which don't change when variants are added, so translations can be keyed
+// on them instead of on the numeric codes or the English messages. The table is generated from the list below, and
+// the exhaustive match makes the crate fail to compile until every new variant is listed. Only compiled for
+// off-chain users of the crate, with the `client` feature.
+
+use anchor_lang::error::ERROR_CODE_OFFSET;
+
+use crate::error::LotteryError;
+
+macro_rules! error_keys {
+    ($($variant:ident,)*) => {
+        // Every error code of the program with its key, in code order
+        pub const ERROR_KEYS: &[(u32, &str)] = &[$(
+            (ERROR_CODE_OFFSET + LotteryError::$variant as u32, stringify!($variant)),
+        )*];
+
+        // Never called; only here so a variant missing from the list is a compile error
+        #[allow(dead_code)]
+        fn listed(error: LotteryError) {
+            match error {
+                $(LotteryError::$variant => {})*
+            }
+        }
+    };
+}
+
+error_keys! {
+    WinnerAlreadyExists,
+    NoTickets,
+    WinnerNotChosen,
+    InvalidWinner,
+    AlreadyClaimed,
+    InvalidDrawEpoch,
+    DrawEpochPassed,
+    DrawEpochNotReached,
+    EntropyUnavailable,
+    InvalidRedemptionCode,
+    TicketAlreadyRedeemed,
+    TicketNotRedeemed,
+    LotteryNotFinished,
+    LotteryIdMismatch,
+    TicketIdMismatch,
+    FeatureDisabled,
+    TicketPriceTooLow,
+    TicketPriceTooHigh,
+    DrawEpochTooFar,
+    PotOverflow,
+    SeasonNotClosed,
+    SeasonRewardsAlreadyCommitted,
+    SeasonRewardsNotCommitted,
+    InvalidRewardProof,
+    SeasonRewardsExhausted,
+    TooManyAccounts,
+    DuplicateAccount,
+    LotteryCreatorMismatch,
+    InvalidQuantity,
+    InsufficientFunds,
+    TransactionExpired,
+    SelfEntryForbidden,
+    NotAuthorizedToClaim,
+    RecoveryPeriodTooShort,
+    ClaimWindowExpired,
+    RecoveryNotOpen,
+    WrongPrizeKind,
+    CallbackProgramMismatch,
+    InvalidCallbackProgram,
+    CallbackLocked,
+    InsufficientVoucherBalance,
+    TooManyOpenLotteries,
+    TermsMismatch,
+    TermsLocked,
+    AttestationRequired,
+    InvalidAttestation,
+    NotAParticipant,
+    DisputeWindowClosed,
+    DrawDisputed,
+    InvalidWinnerShare,
+    ExtraTooLong,
+    CannotReconcile,
+    AccountsOutOfOrder,
+    InvalidDisplayHints,
+    ProgramDataMismatch,
+    UnexpectedUpgradeAuthority,
+    ClaimsPaused,
+    InvalidReservation,
+    ReservationExhausted,
+    DrawInSaleSlot,
+    InvalidClaimCooldown,
+    ClaimCooldown,
+    SalesClosed,
+    InvalidDrawBounty,
+    NotLotteryAuthority,
+    ForceResolveNotReady,
+    LotteryCancelled,
+    DrawCommitted,
+    NoCommitment,
+    RevealMismatch,
+    RevealTooEarly,
+    RevealExpired,
+    EpochLockedDraw,
+    UnsupportedForTokenLottery,
+    TokenAccountsMissing,
+    TokenAccountOwnerMismatch,
+    InvalidEndTime,
+    SalesEnded,
+    DrawBeforeEndTime,
+    TicketAccountMismatch,
+    InvalidFee,
+    LotteryNotCancelled,
+    NoWinnerCallback,
+    PrizeNotClaimed,
+    WinnerAlreadyNotified,
+    DrawNotPermissionless,
+    LotteryIdsExhausted,
+    InvalidLotteryName,
+    LotteryNameMismatch,
+    InvalidCommittee,
+    CommitteeNeedsEndTime,
+    CommitteeDraw,
+    NotCommitteeMember,
+    ShareAlreadyCommitted,
+    ShareNotCommitted,
+    ShareAlreadyRevealed,
+    RevealWindowClosed,
+    RevealWindowOpen,
+    CommitteeThresholdNotMet,
+    InvalidInventory,
+    InventoryTicketSold,
+    MinTicketsNeedEndTime,
+    NoMinTickets,
+    MinTicketsNotSold,
+    RefundAccountMismatch,
+    CommitteeRevealStarted,
+    DrawThroughCpi,
+    NotUpgradeAuthority,
+    InvalidFeeReportRange,
+    InvalidRefundGrace,
+    RefundGraceOver,
+    TicketRefunded,
+    WinnerNotVoid,
+    InvalidMatchBits,
+    MatchModeUnsupported,
+    NotMatchMode,
+    MatchesNotCounted,
+    RedemptionClosed,
+}
+
+// The key of an error code returned by the program, None if the code isn't one of its errors
+pub fn error_key(code: u32) -> Option<&'static str> {
+    ERROR_KEYS
+        .iter()
+        .find(|(error_code, _)| *error_code == code)
+        .map(|(_, key)| *key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Codes are assigned in declaration order, so a complete list in the same order has no gaps
+    #[test]
+    fn codes_are_listed_in_order() {
+        for (i, (code, _)) in ERROR_KEYS.iter().enumerate() {
+            assert_eq!(*code, ERROR_CODE_OFFSET + i as u32);
+        }
+    }
+
+    #[test]
+    fn codes_match_the_program_errors() {
+        assert_eq!(
+            error_key(u32::from(LotteryError::SalesClosed)),
+            Some("SalesClosed")
+        );
+        assert_eq!(
+            error_key(u32::from(LotteryError::WinnerAlreadyExists)),
+            Some("WinnerAlreadyExists")
+        );
+        assert_eq!(error_key(ERROR_CODE_OFFSET - 1), None);
+    }
+}
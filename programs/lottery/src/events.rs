@@ -0,0 +1,404 @@
+use anchor_lang::prelude::*;
+
+// Emitted whenever a ticket is bought
+#[event]
+pub struct TicketPurchased {
+    pub seq: u64,                  // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32,           // The ID of the lottery the ticket belongs to
+    pub ticket_id: u32,            // The ID of the newly created ticket
+    pub buyer: Pubkey,             // The public key of the ticket owner
+    pub price: u64,                // The amount of lamports paid for the ticket, see `Ticket::paid`
+    pub art_seed: [u8; 32],        // The seed of the ticket's generative art, see `Ticket::art_seed`
+    pub reference: Option<Pubkey>, // The Solana Pay reference key passed with the purchase, if any
+    pub currency: [u8; 3],         // The lottery's currency hint for displaying the price, see `Lottery::currency`
+    pub locale: String,            // The lottery's locale hint for formatting the price, see `Lottery::locale`
+}
+
+// Emitted when a redemption code is used to bind a code ticket to a wallet
+#[event]
+pub struct CodeTicketRedeemed {
+    pub seq: u64,           // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32,    // The ID of the lottery the ticket belongs to
+    pub ticket_id: u32,     // The ID of the redeemed ticket
+    pub owner: Pubkey,      // The wallet that now owns the ticket
+    pub art_seed: [u8; 32], // The ticket's new art seed, derived for its new owner
+}
+
+// Emitted when a finished lottery is archived into a summary account
+#[event]
+pub struct LotteryArchived {
+    pub seq: u64,        // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32, // The ID of the archived lottery
+    pub winner: Pubkey,  // The wallet that claimed the prize
+    pub pot: u64,        // The prize paid out, in lamports
+}
+
+// Emitted when a winner claims their prize
+#[event]
+pub struct PrizeClaimed {
+    pub seq: u64,         // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32,  // The ID of the lottery the prize was paid from
+    pub ticket_id: u32,   // The ID of the winning ticket
+    pub winner: Pubkey,   // The wallet that was paid
+    pub amount: u64,      // The amount paid out, in lamports
+    pub rolled_over: u64, // The part of the jackpot kept for the creator's next lottery, in lamports
+    pub fee: u64,         // The protocol fee paid to the treasury out of the prize, in lamports
+}
+
+// Emitted when the config authority changes the protocol fee or treasury
+#[event]
+pub struct ConfigUpdated {
+    pub seq: u64,         // The event's sequence number, see `Master::next_seq`
+    pub fee_bps: u16,     // The new protocol fee, in basis points of a prize
+    pub treasury: Pubkey, // The new wallet the fee is paid to
+}
+
+// Emitted when the config authority sets or removes a creator's protocol fee override
+#[event]
+pub struct CreatorFeeSet {
+    pub seq: u64,             // The event's sequence number, see `Master::next_seq`
+    pub creator: Pubkey,      // The lottery creator the override applies to
+    pub fee_bps: Option<u16>, // The creator's protocol fee, in basis points of a prize; None if the config's applies again
+}
+
+// Emitted when the admin changes the enabled modules
+#[event]
+pub struct FeaturesUpdated {
+    pub seq: u64,      // The event's sequence number, see `Master::next_seq`
+    pub features: u64, // The new feature bitfield
+}
+
+// Emitted when a lottery is created
+#[event]
+pub struct LotteryCreated {
+    pub seq: u64,                // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32,         // The ID of the new lottery
+    pub authority: Pubkey,       // The creator of the lottery
+    pub ticket_price: u64,       // The price of a ticket, in lamports
+    pub draw_epoch: Option<u64>, // The epoch the draw is locked to, if any
+}
+
+// Emitted when a lottery is created under a name, after its `LotteryCreated`
+#[event]
+pub struct LotteryNamed {
+    pub seq: u64,          // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32,   // The ID derived from the creator and the name
+    pub authority: Pubkey, // The creator of the lottery
+    pub name: String,      // The name of the lottery
+}
+
+// Emitted when a winner is drawn
+#[event]
+pub struct WinnerPicked {
+    pub seq: u64,        // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32, // The ID of the lottery that was drawn
+    pub winner_id: u32,  // The ID of the winning ticket
+    pub seed: [u8; 32],  // The seed the winner was drawn from, see `Lottery::draw_seed`
+}
+
+// Emitted when a draw pays its caller a bounty out of the jackpot
+#[event]
+pub struct DrawBountyPaid {
+    pub seq: u64,        // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32, // The ID of the lottery that was drawn
+    pub caller: Pubkey,  // The account that called `pick_winner`
+    pub amount: u64,     // The lamports paid
+}
+
+// Emitted when a drawn winner is announced, so notification services can reach them
+#[event]
+pub struct WinnerAnnounced {
+    pub seq: u64,                           // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32,                    // The ID of the lottery that was drawn
+    pub winner_id: u32,                     // The ID of the winning ticket
+    pub winner: Pubkey,                     // The owner of the winning ticket
+    pub notification_key: Option<[u8; 32]>, // The winner's registered notification key, if any
+}
+
+// Emitted when a claimed lottery's winner callback was delivered, see `notify_winner`
+#[event]
+pub struct WinnerNotified {
+    pub seq: u64,        // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32, // The ID of the lottery whose winner was delivered
+    pub program: Pubkey, // The callback program that was invoked
+    pub winner: Pubkey,  // The owner of the winning ticket, passed to the callback
+}
+
+// Emitted when the admin closes a season, summarizing its stats
+#[event]
+pub struct SeasonClosed {
+    pub seq: u64,       // The event's sequence number, see `Master::next_seq`
+    pub season: u32,    // The ID of the closed season
+    pub lotteries: u32, // The number of lotteries created during the season
+    pub tickets: u64,   // The number of tickets sold during the season
+    pub volume: u64,    // The lamports spent on tickets during the season
+    pub payouts: u64,   // The lamports paid out to winners during the season
+}
+
+// Emitted when the admin commits and funds the rewards of a closed season
+#[event]
+pub struct SeasonRewardsCommitted {
+    pub seq: u64,       // The event's sequence number, see `Master::next_seq`
+    pub season: u32,    // The ID of the season
+    pub root: [u8; 32], // The Merkle root of the reward allocations
+    pub total: u64,     // The lamports funded for the rewards
+}
+
+// Emitted when a player claims their season reward
+#[event]
+pub struct SeasonRewardClaimed {
+    pub seq: u64,        // The event's sequence number, see `Master::next_seq`
+    pub season: u32,     // The ID of the season
+    pub claimer: Pubkey, // The wallet that claimed the reward
+    pub amount: u64,     // The lamports paid out
+}
+
+// Emitted when a ticket owner authorizes or revokes a claim delegate
+#[event]
+pub struct TicketDelegateSet {
+    pub seq: u64,                 // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32,          // The ID of the lottery the ticket belongs to
+    pub ticket_id: u32,           // The ID of the ticket
+    pub delegate: Option<Pubkey>, // The new delegate, None if revoked
+}
+
+// Emitted when a ticket owner recovers their share of a prize that was never claimed, or of a cancelled lottery
+#[event]
+pub struct FundsRecovered {
+    pub seq: u64,        // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32, // The ID of the lottery the funds were recovered from
+    pub ticket_id: u32,  // The ID of the ticket
+    pub owner: Pubkey,   // The wallet that was refunded
+    pub amount: u64,     // The lamports refunded
+}
+
+// Emitted when the winner of a right lottery claims their right
+#[event]
+pub struct RightGranted {
+    pub seq: u64,        // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32, // The ID of the lottery
+    pub winner: Pubkey,  // The wallet holding the right
+}
+
+// Emitted when lottery credit is bought for a recipient
+#[event]
+pub struct VoucherPurchased {
+    pub seq: u64,          // The event's sequence number, see `Master::next_seq`
+    pub recipient: Pubkey, // The wallet the credit belongs to
+    pub payer: Pubkey,     // The wallet that paid for the credit
+    pub amount: u64,       // The lamports of credit bought
+    pub balance: u64,      // The recipient's credit after the purchase
+}
+
+// Emitted whenever a deprecated instruction is called, so integrators can find callers that still need migrating
+#[event]
+pub struct DeprecatedInstructionUsed {
+    pub seq: u64,            // The event's sequence number, see `Master::next_seq`
+    pub instruction: String, // The name of the deprecated instruction
+    pub replacement: String, // The name of the instruction to call instead
+}
+
+// Emitted when a ticket owner challenges a draw
+#[event]
+pub struct DrawChallenged {
+    pub seq: u64,           // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32,    // The ID of the lottery whose draw was challenged
+    pub challenger: Pubkey, // The wallet that posted the bond
+    pub bond: u64,          // The escrowed bond, in lamports
+}
+
+// Emitted when the admin rules on a challenged draw
+#[event]
+pub struct DisputeResolved {
+    pub seq: u64,           // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32,    // The ID of the lottery whose draw was challenged
+    pub challenger: Pubkey, // The wallet that posted the bond
+    pub upheld: bool,       // Whether the draw was voided and the bond returned
+}
+
+// Emitted when a lottery's balance is reconciled against what it owes
+#[event]
+pub struct LotteryReconciled {
+    pub seq: u64,        // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32, // The ID of the reconciled lottery
+    pub expected: u64,   // The lamports the lottery owed, above its rent
+    pub actual: u64,     // The lamports the lottery held, above its rent
+    pub absorbed: u64,   // The part of the surplus added to the jackpot
+}
+
+// Emitted when closing a lottery sweeps lamports beyond its rent to the recipient
+#[event]
+pub struct DustSwept {
+    pub seq: u64,          // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32,   // The ID of the closed lottery
+    pub recipient: Pubkey, // The wallet that received the lamports
+    pub amount: u64,       // The lamports swept on top of the rent
+}
+
+// Emitted when a lottery is created and whenever its display hints change
+#[event]
+pub struct DisplayHintsSet {
+    pub seq: u64,          // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32,   // The ID of the lottery
+    pub currency: [u8; 3], // The ISO 4217 currency code to display prices in, all zeros if unset
+    pub locale: String,    // The BCP 47 locale to format prices with, empty if unset
+}
+
+// Emitted when a payout would exceed the circuit breaker's limit, which pauses payouts until the admin resumes them
+#[event]
+pub struct PayoutsPaused {
+    pub seq: u64,            // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32,     // The ID of the lottery whose payout tripped the breaker
+    pub amount: u64,         // The payout that was held back, in lamports
+    pub window_payouts: u64, // The lamports already paid out in the current window
+    pub window_budget: u64,  // The pots owed at the window's start plus those drawn since, in lamports
+}
+
+// Emitted when a block of ticket IDs is reserved for a partner
+#[event]
+pub struct TicketsReserved {
+    pub seq: u64,             // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32,      // The ID of the lottery the block belongs to
+    pub partner: Pubkey,      // The partner the block is reserved for
+    pub first_ticket_id: u32, // The first ticket ID of the block
+    pub count: u32,           // The number of tickets in the block
+    pub price: u64,           // The lamports paid for each ticket
+}
+
+// Emitted when a partner hands a reserved ticket to an end user
+#[event]
+pub struct ReservedTicketAssigned {
+    pub seq: u64,           // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32,    // The ID of the lottery the ticket belongs to
+    pub ticket_id: u32,     // The ID of the assigned ticket
+    pub partner: Pubkey,    // The partner that handed out the ticket
+    pub owner: Pubkey,      // The end user who now owns the ticket
+    pub art_seed: [u8; 32], // The seed of the ticket's generative art, see `Ticket::art_seed`
+}
+
+// Emitted when the admin starts the timelock on force-resolving a lottery
+#[event]
+pub struct ForceResolveScheduled {
+    pub seq: u64,           // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32,    // The ID of the stuck lottery
+    pub executable_at: i64, // Unix timestamp from which `force_resolve` can run
+}
+
+// Emitted when a lottery's authority cancels it before the draw
+#[event]
+pub struct LotteryCancelled {
+    pub seq: u64,        // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32, // The ID of the cancelled lottery
+}
+
+// Emitted when the admin force-resolves a stuck lottery as cancelled
+#[event]
+pub struct LotteryForceResolved {
+    pub seq: u64,        // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32, // The ID of the cancelled lottery
+    pub drawn: bool,     // Whether a winner had been drawn before the cancellation
+}
+
+// Emitted when a lottery's authority commits to the secret its draw will reveal
+#[event]
+pub struct RandomnessCommitted {
+    pub seq: u64,             // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32,      // The ID of the committed lottery
+    pub commitment: [u8; 32], // The SHA-256 hash of the secret
+    pub reveal_slot: u64,     // The slot whose hash the draw mixes in; the reveal can come after it
+}
+
+// Emitted when a lottery's authority hands its draw to an entropy committee
+#[event]
+pub struct CommitteeSet {
+    pub seq: u64,             // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32,      // The ID of the lottery
+    pub members: Vec<Pubkey>, // The committee's entropy providers
+    pub threshold: u8,        // The shares the draw needs
+}
+
+// Emitted when a committee member commits to its share
+#[event]
+pub struct ShareCommitted {
+    pub seq: u64,             // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32,      // The ID of the lottery
+    pub member: Pubkey,       // The committee member
+    pub commitment: [u8; 32], // The SHA-256 hash of the share
+}
+
+// Emitted when a committee member reveals its share
+#[event]
+pub struct ShareRevealed {
+    pub seq: u64,          // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32,   // The ID of the lottery
+    pub member: Pubkey,    // The committee member
+    pub share: [u8; 32],   // The revealed share
+    pub entropy_slot: u64, // The slot whose hash `finalize_draw` mixes in
+}
+
+// Emitted when the authority stocks a block of house tickets
+#[event]
+pub struct InventoryStocked {
+    pub seq: u64,            // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32,     // The ID of the lottery the block belongs to
+    pub block_id: u32,       // The ID of the block
+    pub count: u16,          // The number of tickets in the block
+    pub code_root: [u8; 32], // The Merkle root of the block's redemption codes
+}
+
+// Emitted when a house ticket is sold, after the ticket's `TicketPurchased`
+#[event]
+pub struct InventoryTicketSold {
+    pub seq: u64,        // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32, // The ID of the lottery
+    pub block_id: u32,   // The ID of the block the ticket came from
+    pub index: u16,      // The ticket's index in the block
+    pub ticket_id: u32,  // The ticket ID it was sold as
+}
+
+// Emitted when the unsold rest of an inventory block is voided
+#[event]
+pub struct InventoryVoided {
+    pub seq: u64,        // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32, // The ID of the lottery
+    pub block_id: u32,   // The ID of the block
+    pub unsold: u16,     // The number of tickets that were never sold
+}
+
+// Emitted when the lottery authority ends sales before the end time
+#[event]
+pub struct SalesClosedEarly {
+    pub seq: u64,          // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32,   // The ID of the lottery
+    pub tickets_sold: u32, // The number of tickets sold, at least the lottery's `min_tickets`
+    pub end_time: i64,     // The new end time, when sales closed
+}
+
+// Emitted when a ticket owner takes their ticket back within the lottery's refund grace period
+#[event]
+pub struct TicketRefunded {
+    pub seq: u64,        // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32, // The ID of the lottery
+    pub ticket_id: u32,  // The ID of the refunded ticket, which keeps its ID but can no longer win
+    pub owner: Pubkey,   // The ticket owner, who was refunded
+    pub amount: u64,     // The lamports refunded, what the ticket paid
+}
+
+// Emitted when a lottery whose drawn ticket can't win is redrawn, see `Lottery::can_win`
+#[event]
+pub struct WinnerRedrawn {
+    pub seq: u64,        // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32, // The ID of the lottery
+    pub void_id: u32,    // The ticket the previous draw picked, which was refunded or is an unredeemed code ticket
+    pub winner_id: u32,  // The newly drawn ticket
+    pub seed: [u8; 32],  // The seed it was drawn from, see `draw::redraw_seed`
+}
+
+// Emitted when `count_matches` has checked every ticket of a match mode lottery
+#[event]
+pub struct MatchesCounted {
+    pub seq: u64,         // The event's sequence number, see `Master::next_seq`
+    pub lottery_id: u32,  // The ID of the lottery
+    pub match_bits: u8,   // The prefix length in bits each matching ticket shares with the draw seed
+    pub match_count: u32, // The tickets that matched and split the prize, 0 if the drawn winner takes it all
+}
@@ -1,15 +1,43 @@
 // Import necessary modules and traits from the anchor_lang crate
 use anchor_lang::{
     prelude::*, // Brings common types like AccountInfo, ProgramResult, etc., into scope
-    solana_program::{clock::Clock, hash::hash, program::invoke, system_instruction::transfer}, // Importing specific Solana program modules
+    solana_program::{
+        clock::Clock,
+        instruction::{get_stack_height, TRANSACTION_LEVEL_STACK_HEIGHT},
+        program::invoke,
+        system_instruction::transfer,
+        sysvar::SysvarId,
+    }, // Importing specific Solana program modules
 };
+use anchor_spl::token::{Mint, Token, TokenAccount}; // SPL token accounts, for lotteries paid in a token
+use solana_sha256_hasher::{hash, hashv}; // SHA-256 hashing, split out of solana_program in Solana 2.x
 
 // Import constants and error definitions
+mod batch;
+mod callback;
 mod constants;
+mod draw;
 mod error;
-use crate::{constants::*, error::*};
+#[cfg(any(feature = "client", test))]
+pub mod error_keys;
+mod events;
+mod math;
+mod merkle;
+pub mod pdas;
+mod time;
+mod token;
+mod validation;
+mod verification;
+use crate::{constants::*, error::*, events::*};
 
 // Declare the program ID - this is the unique address of this smart contract program
+// Each deployment profile (see DEPLOYMENT_PROFILE) has its own entry; they only differ if a cluster is deployed from
+// another program keypair, so change the matching one (and Anchor.toml) when it is
+#[cfg(not(any(feature = "devnet", feature = "mainnet")))]
+declare_id!("FpDJiceCWU5Zdyd8arskS9fvpZY9kzypC4q3Ak6jadmB");
+#[cfg(feature = "devnet")]
+declare_id!("FpDJiceCWU5Zdyd8arskS9fvpZY9kzypC4q3Ak6jadmB");
+#[cfg(feature = "mainnet")]
 declare_id!("FpDJiceCWU5Zdyd8arskS9fvpZY9kzypC4q3Ak6jadmB");
 
 // Define the lottery module, which will contain all program instructions
@@ -19,310 +47,6520 @@ mod lottery {
 
     // Function to initialize the master account
     // The master account keeps track of the last lottery ID
-    pub fn init_master(_ctx: Context<InitMaster>) -> Result<()> {
-        // This function currently doesn't do anything beyond successful initialization
+    // Calling it again once the master account exists is a no-op, so deployment scripts can always call it
+    pub fn init_master(ctx: Context<InitMaster>) -> Result<()> {
+        let master = &mut ctx.accounts.master; // Get a mutable reference to the master account
+
+        // The first call makes its payer the program admin, so only whoever deployed the program (its upgrade
+        // authority) may make it; otherwise anyone could front-run the deploy and take over the admin role
+        if !master.initialized
+            && ctx.accounts.program_data.upgrade_authority_address != Some(ctx.accounts.payer.key())
+        {
+            return err!(LotteryError::NotUpgradeAuthority);
+        }
+
+        // Set up the config when it was just created, which for older deployments is after the master account;
+        // the program admin starts out as its authority and treasury
+        let config = &mut ctx.accounts.config;
+        if config.authority == Pubkey::default() {
+            let admin = if master.initialized {
+                master.authority
+            } else {
+                ctx.accounts.payer.key()
+            };
+            config.authority = admin;
+            config.treasury = admin;
+            config.fee_bps = DEFAULT_FEE_BPS;
+        }
+
+        // `init_if_needed` hands us the existing account on later calls; never reinitialize it
+        if master.initialized {
+            msg!("Master account already initialized");
+            return Ok(());
+        }
+
+        master.initialized = true;
+        master.authority = ctx.accounts.payer.key(); // The upgrade authority becomes the program admin
+        master.features = DEFAULT_FEATURES; // Start with the deployment profile's modules enabled
+        master.recovery_period = DEFAULT_RECOVERY_PERIOD; // Give winners the profile's claim window before buyers can recover funds
+        master.max_open_lotteries = DEFAULT_MAX_OPEN_LOTTERIES; // Cap how many undrawn lotteries one creator can have
+        master.payout_limit_bps = DEFAULT_PAYOUT_LIMIT_BPS; // Arm the payout circuit breaker
+        master.max_draw_bounty_bps = DEFAULT_MAX_DRAW_BOUNTY_BPS; // Cap what a draw can pay its caller
+        ctx.accounts.season.started_at = time::now(&ctx.accounts.master)?; // Season 0 starts with the program
+
+        // Log which profile's defaults were applied
+        msg!("Deployment profile: {}", DEPLOYMENT_PROFILE);
+
         Ok(()) // Return an Ok result to indicate success
     }
 
-    // Function to create a new lottery
-    // Initializes a lottery account and sets up its parameters
-    pub fn create_lottery(ctx: Context<CreateLottery>, ticket_price: u64) -> Result<()> {
-        // Create a lottery account to hold information about the current lottery
-        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+    // Function for clients to check which interface version the deployed program speaks
+    // Clients compare it to the PROGRAM_VERSION constant in the IDL they were built with
+    pub fn get_version(_ctx: Context<GetVersion>) -> Result<u32> {
+        Ok(PROGRAM_VERSION)
+    }
+
+    // Function for frontends to check who can upgrade the deployed program, before trusting it with funds
+    // Succeeds if the program is immutable or upgradeable by the master admin or the passed governance account (e.g. a
+    // multisig or DAO the frontend expects), and fails with `UnexpectedUpgradeAuthority` otherwise; transactions can
+    // also include it as a guard
+    pub fn verify_upgrade_authority(
+        ctx: Context<VerifyUpgradeAuthority>,
+    ) -> Result<UpgradeAuthority> {
+        validation::validate_upgrade_authority(
+            ctx.accounts.program_data.upgrade_authority_address,
+            &ctx.accounts.master.authority,
+            ctx.accounts
+                .governance
+                .as_ref()
+                .map(|governance| governance.key),
+        )
+    }
+
+    // Function for the admin to enable or disable program modules at runtime
+    // `features` replaces the whole bitfield, see the FEATURE_* constants
+    pub fn set_features(ctx: Context<SetFeatures>, features: u64) -> Result<()> {
         let master = &mut ctx.accounts.master; // Get a mutable reference to the master account
 
-        // Set up the lottery account with relevant details
-        lottery.id = master.last_id; // Assign the new lottery ID
-        lottery.authority = ctx.accounts.authority.key(); // Set the authority for the lottery
-        lottery.ticket_price = ticket_price; // Set the price for lottery tickets
+        master.features = features;
 
-        // Increment the last lottery ID stored in the master account
-        master.last_id += 1;
+        // Keep an on-chain trail of the change
+        ctx.accounts.audit_log.record(
+            ctx.accounts.authority.key(),
+            AUDIT_SET_FEATURES,
+            features,
+            time::now(master)?,
+        );
 
-        // Log information about the newly created lottery
-        msg!("Lottery with ID : {}", lottery.id);
-        msg!("Authority: {}", lottery.authority);
-        msg!("Lottery ticket price: {}", lottery.ticket_price);
+        // Log the new feature set
+        msg!("Features: {:#b}", master.features);
 
-        Ok(()) // Return an Ok result to indicate success
+        emit!(FeaturesUpdated {
+            seq: ctx.accounts.master.next_seq(),
+            features,
+        });
+
+        Ok(())
     }
 
-    // Function to buy a ticket for a lottery
-    // Creates a ticket account and transfers the ticket price to the lottery PDA
-    pub fn buy_ticket(ctx: Context<BuyTicket>, lottery_id: u32) -> Result<()> {
-        // Get references to the accounts involved
-        let lottery = &mut ctx.accounts.lottery;
-        let ticket = &mut ctx.accounts.ticket;
-        let buyer = &mut ctx.accounts.buyer;
+    // Function for the admin to change how long a drawn prize stays claimable before buyers can recover their funds
+    // Uses the same accounts as `set_features`
+    pub fn set_recovery_period(ctx: Context<SetFeatures>, recovery_period: i64) -> Result<()> {
+        // A short period would let the admin cut winners off before they had a fair chance to claim
+        if recovery_period < MIN_RECOVERY_PERIOD {
+            return err!(LotteryError::RecoveryPeriodTooShort);
+        }
 
-        // Check if a winner already exists, return an error if so
-        if lottery.winner_id.is_some() {
-            return err!(LotteryError::WinnerAlreadyExists);
+        ctx.accounts.master.recovery_period = recovery_period;
+
+        // Keep an on-chain trail of the change
+        ctx.accounts.audit_log.record(
+            ctx.accounts.authority.key(),
+            AUDIT_SET_RECOVERY_PERIOD,
+            recovery_period as u64,
+            time::now(&ctx.accounts.master)?,
+        );
+
+        // Log the new period
+        msg!("Recovery period: {} seconds", recovery_period);
+
+        Ok(())
+    }
+
+    // Function for the admin to change how many undrawn lotteries a single creator can have at once
+    // Uses the same accounts as `set_features`; lowering it doesn't affect lotteries that are already open
+    pub fn set_max_open_lotteries(
+        ctx: Context<SetFeatures>,
+        max_open_lotteries: u32,
+    ) -> Result<()> {
+        ctx.accounts.master.max_open_lotteries = max_open_lotteries;
+
+        // Keep an on-chain trail of the change
+        ctx.accounts.audit_log.record(
+            ctx.accounts.authority.key(),
+            AUDIT_SET_MAX_OPEN_LOTTERIES,
+            max_open_lotteries as u64,
+            time::now(&ctx.accounts.master)?,
+        );
+
+        // Log the new cap
+        msg!("Max open lotteries per creator: {}", max_open_lotteries);
+
+        Ok(())
+    }
+
+    // Function for the admin to cap draw bounties, in basis points of the jackpot, see `set_draw_bounty`
+    // Uses the same accounts as `set_features`; the cap applies when a draw pays out, so it also covers existing lotteries
+    pub fn set_max_draw_bounty(ctx: Context<SetFeatures>, max_draw_bounty_bps: u16) -> Result<()> {
+        if max_draw_bounty_bps > MAX_WINNER_SHARE_BPS {
+            return err!(LotteryError::InvalidDrawBounty);
+        }
+        ctx.accounts.master.max_draw_bounty_bps = max_draw_bounty_bps;
+
+        // Keep an on-chain trail of the change
+        ctx.accounts.audit_log.record(
+            ctx.accounts.authority.key(),
+            AUDIT_SET_MAX_DRAW_BOUNTY,
+            max_draw_bounty_bps as u64,
+            time::now(&ctx.accounts.master)?,
+        );
+
+        // Log the new cap
+        msg!("Max draw bounty: {} bps", max_draw_bounty_bps);
+
+        Ok(())
+    }
+
+    // Function for the config authority to change the protocol fee, at most MAX_FEE_BPS, and the treasury it's paid to
+    // The fee applies when a prize is claimed, so it also covers existing lotteries
+    pub fn update_config(ctx: Context<UpdateConfig>, fee_bps: u16, treasury: Pubkey) -> Result<()> {
+        if fee_bps > MAX_FEE_BPS {
+            return err!(LotteryError::InvalidFee);
+        }
+        let config = &mut ctx.accounts.config;
+        config.fee_bps = fee_bps;
+        config.treasury = treasury;
+
+        // Keep an on-chain trail of the change
+        ctx.accounts.audit_log.record(
+            ctx.accounts.authority.key(),
+            AUDIT_UPDATE_CONFIG,
+            fee_bps as u64,
+            time::now(&ctx.accounts.master)?,
+        );
+
+        // Log the new fee
+        msg!("Protocol fee: {} bps to {}", fee_bps, treasury);
+
+        emit!(ConfigUpdated {
+            seq: ctx.accounts.master.next_seq(),
+            fee_bps,
+            treasury,
+        });
+
+        Ok(())
+    }
+
+    // Function for the config authority to charge one creator a different protocol fee, e.g. 0 for charity partners
+    // `fee_bps` replaces the config's fee on the creator's prizes, at most MAX_FEE_BPS; None goes back to the config's.
+    // Creates the creator's account if they haven't made a lottery yet, so partners can be set up ahead of time
+    pub fn set_creator_fee(
+        ctx: Context<SetCreatorFee>,
+        creator: Pubkey,
+        fee_bps: Option<u16>,
+    ) -> Result<()> {
+        if fee_bps.is_some_and(|fee_bps| fee_bps > MAX_FEE_BPS) {
+            return err!(LotteryError::InvalidFee);
         }
+        let creator_account = &mut ctx.accounts.creator_account;
+        creator_account.authority = creator;
+        creator_account.fee_bps = fee_bps;
+
+        // Keep an on-chain trail of the change; u64::MAX stands for removing the override
+        ctx.accounts.audit_log.record(
+            ctx.accounts.authority.key(),
+            AUDIT_SET_CREATOR_FEE,
+            fee_bps.map_or(u64::MAX, u64::from),
+            time::now(&ctx.accounts.master)?,
+        );
+
+        // Log the new override
+        msg!("Protocol fee override for {}: {:?} bps", creator, fee_bps);
+
+        emit!(CreatorFeeSet {
+            seq: ctx.accounts.master.next_seq(),
+            creator,
+            fee_bps,
+        });
+
+        Ok(())
+    }
+
+    // Function for the admin to move the program's clock in `test-clock` builds, see the `time` module
+    // `clock_offset` replaces the current offset, so tests can also move the clock back; only compiled for test builds
+    #[cfg(feature = "test-clock")]
+    pub fn warp_clock(ctx: Context<SetFeatures>, clock_offset: i64) -> Result<()> {
+        ctx.accounts.master.clock_offset = clock_offset;
+
+        // Log the new offset
+        msg!("Clock offset: {} seconds", clock_offset);
+
+        Ok(())
+    }
+
+    // Function for the admin to set how much a payout window may pay out, in basis points of its budget
+    // Uses the same accounts as `set_features`; 0 disables the circuit breaker
+    pub fn set_payout_limit(ctx: Context<SetFeatures>, payout_limit_bps: u32) -> Result<()> {
+        ctx.accounts.master.payout_limit_bps = payout_limit_bps;
+
+        // Keep an on-chain trail of the change
+        ctx.accounts.audit_log.record(
+            ctx.accounts.authority.key(),
+            AUDIT_SET_PAYOUT_LIMIT,
+            payout_limit_bps as u64,
+            time::now(&ctx.accounts.master)?,
+        );
+
+        // Log the new limit
+        msg!("Payout limit: {} bps", payout_limit_bps);
+
+        Ok(())
+    }
+
+    // Function for the admin to resume payouts after reviewing why the circuit breaker tripped
+    // Uses the same accounts as `set_features`; starts a fresh payout window
+    pub fn resume_claims(ctx: Context<SetFeatures>) -> Result<()> {
+        let master = &mut ctx.accounts.master; // Get a mutable reference to the master account
+        let now = time::now(master)?;
+
+        master.claims_paused = false;
+        master.restart_payout_window(now);
+
+        // Keep an on-chain trail of the review
+        ctx.accounts
+            .audit_log
+            .record(ctx.accounts.authority.key(), AUDIT_RESUME_CLAIMS, 0, now);
+
+        // Log the resumption
+        msg!("Payouts resumed");
+
+        Ok(())
+    }
+
+    // Function for the admin to close the current season and start the next one
+    // The closed season's account keeps its stats; activity from now on is counted in the new season
+    pub fn close_season(ctx: Context<CloseSeason>) -> Result<()> {
+        let master = &mut ctx.accounts.master; // Get a mutable reference to the master account
+        let season = &mut ctx.accounts.season; // Get a mutable reference to the season being closed
+        let next_season = &mut ctx.accounts.next_season; // Get a mutable reference to the season being started
+        let now = time::now(master)?;
+
+        season.closed_at = now;
+        master.season += 1;
+        next_season.id = master.season;
+        next_season.started_at = now;
+
+        // Keep an on-chain trail of the change
+        ctx.accounts.audit_log.record(
+            ctx.accounts.authority.key(),
+            AUDIT_CLOSE_SEASON,
+            season.id.into(),
+            now,
+        );
+
+        // Log the rollover
+        msg!(
+            "Season {} closed, season {} started",
+            season.id,
+            next_season.id
+        );
+
+        emit!(SeasonClosed {
+            seq: ctx.accounts.master.next_seq(),
+            season: season.id,
+            lotteries: season.lotteries,
+            tickets: season.tickets,
+            volume: season.volume,
+            payouts: season.payouts,
+        });
+
+        Ok(())
+    }
 
-        // Transfer SOL from the buyer to the lottery account using a system instruction
+    // Function for the admin to commit the reward allocations of a closed season
+    // `root` is the Merkle root of (player, amount) allocations computed off-chain from the season's stats;
+    // `total` lamports are moved into the season account to fund the claims
+    pub fn commit_season_rewards(
+        ctx: Context<CommitSeasonRewards>,
+        _season_id: u32,
+        root: [u8; 32],
+        total: u64,
+    ) -> Result<()> {
+        let season = &mut ctx.accounts.season; // Get a mutable reference to the season account
+        let authority = &ctx.accounts.authority;
+
+        // Fund the rewards up front so every valid claim can be paid
         invoke(
-            &transfer(&buyer.key(), &lottery.key(), lottery.ticket_price),
+            &transfer(&authority.key(), &season.key(), total),
             &[
-                buyer.to_account_info(),
-                lottery.to_account_info(),
+                authority.to_account_info(),
+                season.to_account_info(),
                 ctx.accounts.system_program.to_account_info(),
             ],
         )?;
 
-        ticket.id = lottery.last_ticket_id;
-        ticket.lottery_id = lottery_id;
-        ticket.authority = buyer.key();
+        season.reward_root = Some(root);
+        season.rewards_total = total;
 
-        // Increment the last ticket ID and assign it to the new ticket
-        lottery.last_ticket_id += 1;
+        // Keep an on-chain trail of the change
+        ctx.accounts.audit_log.record(
+            authority.key(),
+            AUDIT_COMMIT_SEASON_REWARDS,
+            total,
+            time::now(&ctx.accounts.master)?,
+        );
 
-        // Log information about the newly created ticket
-        msg!("Ticket ID: {}", ticket.id);
-        msg!("Ticket authority: {}", ticket.authority);
+        // Log the commitment
+        msg!("Season {} rewards committed: {} lamports", season.id, total);
+
+        emit!(SeasonRewardsCommitted {
+            seq: ctx.accounts.master.next_seq(),
+            season: season.id,
+            root,
+            total,
+        });
+
+        Ok(())
+    }
+
+    // Function for a player to claim their reward from a season
+    // The claim receipt can only be created once per player and season, so a reward can't be claimed twice
+    pub fn claim_season_reward(
+        ctx: Context<ClaimSeasonReward>,
+        _season_id: u32,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let season = &mut ctx.accounts.season; // Get a mutable reference to the season account
+        let claimer = &ctx.accounts.claimer;
+
+        // The allocation must be part of the committed tree
+        let root = season
+            .reward_root
+            .ok_or(LotteryError::SeasonRewardsNotCommitted)?;
+        if !merkle::verify(&proof, root, merkle::leaf(&claimer.key(), amount)) {
+            return err!(LotteryError::InvalidRewardProof);
+        }
+
+        // Never pay out more than was funded, even if the tree over-allocates
+        let rewards_claimed = season
+            .rewards_claimed
+            .checked_add(amount)
+            .filter(|claimed| *claimed <= season.rewards_total)
+            .ok_or(LotteryError::SeasonRewardsExhausted)?;
+
+        // Transfer the reward from the season account to the claimer
+        **season.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **claimer.to_account_info().try_borrow_mut_lamports()? += amount;
+
+        season.rewards_claimed = rewards_claimed;
+
+        // Record the claim; the receipt's existence blocks a second claim
+        let receipt = &mut ctx.accounts.receipt;
+        receipt.season = season.id;
+        receipt.claimer = claimer.key();
+        receipt.amount = amount;
+        receipt.claimed_at = time::now(&ctx.accounts.master)?;
+
+        // Log the claim
+        msg!(
+            "{} claimed {} lamports of season {} rewards",
+            receipt.claimer,
+            amount,
+            season.id
+        );
+
+        emit!(SeasonRewardClaimed {
+            seq: ctx.accounts.master.next_seq(),
+            season: season.id,
+            claimer: receipt.claimer,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    // Function to create a new lottery
+    // Initializes a lottery account and sets up its parameters
+    // Passing a `draw_epoch` locks the draw to the end of that epoch instead of letting the authority pick the time
+    // Setting `no_self_entry` bars the authority from holding tickets in their own lottery; it can't be changed later
+    // `prize_kind` chooses between paying the pot to the winner and granting them a right, see `PrizeKind`
+    // A non-zero `end_time` fixes when sales end and the draw opens, so the authority can't shorten or extend the sales window
+    #[allow(clippy::too_many_arguments)] // Instruction arguments are what clients pass, so they stay flat
+    pub fn create_lottery(
+        ctx: Context<CreateLottery>,
+        ticket_price: u64,
+        draw_epoch: Option<u64>,
+        end_time: i64,
+        no_self_entry: bool,
+        prize_kind: PrizeKind,
+        currency: [u8; 3],
+        locale: String,
+    ) -> Result<()> {
+        // The counter stops short of the IDs named lotteries are created under, see `create_named_lottery`
+        let id = ctx.accounts.master.last_id;
+        if id >= NAMED_LOTTERY_ID_BASE {
+            return err!(LotteryError::LotteryIdsExhausted);
+        }
+
+        open_lottery(
+            &mut ctx.accounts.lottery,
+            &mut ctx.accounts.master,
+            &mut ctx.accounts.creator,
+            &mut ctx.accounts.season,
+            ctx.accounts.authority.key(),
+            id,
+            NewLottery {
+                ticket_price,
+                draw_epoch,
+                end_time,
+                no_self_entry,
+                prize_kind,
+                currency,
+                locale,
+            },
+        )?;
+
+        // Increment the last lottery ID stored in the master account
+        ctx.accounts.master.last_id += 1;
 
         Ok(()) // Return an Ok result to indicate success
     }
 
-    // Function to select a winner for the lottery
-    pub fn pick_winner(ctx: Context<PickWinner>, _lottery_id: u32) -> Result<()> {
-        // Get a mutable reference to the lottery account
-        let lottery = &mut ctx.accounts.lottery;
+    // Function to create a lottery addressed by its creator and a name instead of the master account's counter
+    // The lottery ID is `pdas::named_lottery_id(authority, name)`, so integrators can derive the lottery's address,
+    // and those of its tickets, from the name alone. A lottery name account (seeds `[LOTTERY_SEED, authority, name]`)
+    // reserves the name, so a creator can't reuse it. Named IDs are in the upper half of the ID range, which the
+    // counter never reaches; takes the same parameters as `create_lottery`
+    #[allow(clippy::too_many_arguments)] // Instruction arguments are what clients pass, so they stay flat
+    pub fn create_named_lottery(
+        ctx: Context<CreateNamedLottery>,
+        name: String,
+        lottery_id: u32,
+        ticket_price: u64,
+        draw_epoch: Option<u64>,
+        end_time: i64,
+        no_self_entry: bool,
+        prize_kind: PrizeKind,
+        currency: [u8; 3],
+        locale: String,
+    ) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
 
-        // Check if a winner has already been selected
-        if lottery.winner_id.is_some() {
-            return err!(LotteryError::WinnerAlreadyExists);
+        validation::validate_lottery_name(&name)?;
+
+        // The lottery account is derived from `lottery_id` like every other lottery, so it must be the name's ID
+        if lottery_id != pdas::named_lottery_id(&authority, &name) {
+            return err!(LotteryError::LotteryNameMismatch);
         }
 
-        // Check if there are any tickets purchased
-        if lottery.last_ticket_id == 0 {
-            return err!(LotteryError::NoTickets);
+        open_lottery(
+            &mut ctx.accounts.lottery,
+            &mut ctx.accounts.master,
+            &mut ctx.accounts.creator,
+            &mut ctx.accounts.season,
+            authority,
+            lottery_id,
+            NewLottery {
+                ticket_price,
+                draw_epoch,
+                end_time,
+                no_self_entry,
+                prize_kind,
+                currency,
+                locale,
+            },
+        )?;
+
+        // Record which lottery the name belongs to, so it can be looked up (and is never reused)
+        let lottery_name = &mut ctx.accounts.lottery_name;
+        lottery_name.lottery_id = lottery_id;
+        lottery_name.authority = authority;
+        lottery_name.name = name;
+
+        emit!(LotteryNamed {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id,
+            authority,
+            name: lottery_name.name.clone(),
+        });
+
+        // Log the lottery's name
+        msg!("Lottery name: {}", lottery_name.name);
+
+        Ok(())
+    }
+
+    // Function for the lottery authority to register (or remove, with None) a program to call once the prize is claimed
+    // Locked once the first ticket is sold, so buyers know what a win triggers
+    pub fn set_winner_callback(
+        ctx: Context<SetWinnerCallback>,
+        _lottery_id: u32,
+        callback: Option<WinnerCallback>,
+    ) -> Result<()> {
+        if let Some(callback) = &callback {
+            // Match mode can have many winners, and a callback tells a single one
+            if ctx.accounts.lottery.match_bits != 0 {
+                return err!(LotteryError::MatchModeUnsupported);
+            }
+            let program = ctx
+                .accounts
+                .callback_program
+                .as_ref()
+                .ok_or(LotteryError::CallbackProgramMismatch)?;
+            callback::validate_callback_target(callback, program)?;
+        }
+
+        ctx.accounts.lottery.callback = callback;
+
+        Ok(())
+    }
+
+    // Function for the lottery authority to publish the hash of the lottery's terms
+    // Buyers must pass the same hash to `buy_ticket`; locked once the first ticket is sold
+    pub fn set_terms(ctx: Context<SetTerms>, _lottery_id: u32, terms_hash: [u8; 32]) -> Result<()> {
+        ctx.accounts.lottery.terms_hash = terms_hash;
+
+        Ok(())
+    }
+
+    // Function for the lottery authority to store frontend-specific data on the lottery
+    // `extra` is opaque to the program and at most MAX_EXTRA_LEN bytes; `extra_version` tells frontends how to decode it
+    // Uses the same accounts as `set_terms`, so it can be updated until the first ticket is sold
+    pub fn set_extra(
+        ctx: Context<SetTerms>,
+        _lottery_id: u32,
+        extra_version: u8,
+        extra: Vec<u8>,
+    ) -> Result<()> {
+        if extra.len() > MAX_EXTRA_LEN {
+            return err!(LotteryError::ExtraTooLong);
+        }
+
+        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+        lottery.extra_version = extra_version;
+        lottery.extra = extra;
+
+        Ok(())
+    }
+
+    // Function for the lottery authority to change the currency and locale frontends display the lottery with
+    // The hints are display-only and don't affect what buyers pay, so they can change at any time
+    pub fn set_display_hints(
+        ctx: Context<SetDisplayHints>,
+        _lottery_id: u32,
+        currency: [u8; 3],
+        locale: String,
+    ) -> Result<()> {
+        validation::validate_display_hints(currency, &locale)?;
+
+        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+        lottery.currency = currency;
+        lottery.locale = locale;
+
+        emit!(DisplayHintsSet {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id: lottery.id,
+            currency: lottery.currency,
+            locale: lottery.locale.clone(),
+        });
+
+        Ok(())
+    }
+
+    // Function for the lottery authority to pay only part of the jackpot to the winner
+    // The rest rolls over into the creator's next lamport lottery; uses the same accounts as `set_terms`
+    pub fn set_winner_share(
+        ctx: Context<SetTerms>,
+        _lottery_id: u32,
+        winner_share_bps: u16,
+    ) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+
+        // A right lottery has no jackpot to split
+        if lottery.prize_kind != PrizeKind::Lamports {
+            return err!(LotteryError::WrongPrizeKind);
+        }
+        // The part that doesn't go to the winner rolls over as lamports
+        if lottery.payment_mint.is_some() {
+            return err!(LotteryError::UnsupportedForTokenLottery);
+        }
+        if winner_share_bps == 0 || winner_share_bps > MAX_WINNER_SHARE_BPS {
+            return err!(LotteryError::InvalidWinnerShare);
+        }
+
+        lottery.winner_share_bps = winner_share_bps;
+
+        Ok(())
+    }
+
+    // Function for the lottery authority to require self-attestations from buyers, see the ATTEST_* constants
+    // Uses the same accounts as `set_terms`, so it is locked once the first ticket is sold too
+    pub fn set_attestation_policy(
+        ctx: Context<SetTerms>,
+        _lottery_id: u32,
+        attestation_policy: u8,
+    ) -> Result<()> {
+        // A policy asking for unknown claims could never be met
+        if attestation_policy & !ATTEST_ALL != 0 {
+            return err!(LotteryError::InvalidAttestation);
+        }
+
+        ctx.accounts.lottery.attestation_policy = attestation_policy;
+
+        Ok(())
+    }
+
+    // Function for the lottery authority to delay claims by `claim_cooldown` seconds after the draw
+    // Gives the operator time for fairness checks or a winner announcement before funds move
+    // Uses the same accounts as `set_terms`, so buyers know the delay before they buy
+    pub fn set_claim_cooldown(
+        ctx: Context<SetTerms>,
+        _lottery_id: u32,
+        claim_cooldown: i64,
+    ) -> Result<()> {
+        validation::validate_claim_cooldown(claim_cooldown)?;
+
+        ctx.accounts.lottery.claim_cooldown = claim_cooldown;
+
+        Ok(())
+    }
+
+    // Function for the lottery authority to pay whoever draws the lottery a bounty out of the jackpot
+    // Epoch-locked and committee lotteries can be drawn by anyone, so a bounty gives keepers a reason to draw them on
+    // time. Other lotteries are only drawn by their authority, who would just pay the bounty to itself
+    // Uses the same accounts as `set_terms`, so buyers know how much of the jackpot goes to the draw before they buy
+    pub fn set_draw_bounty(
+        ctx: Context<SetTerms>,
+        _lottery_id: u32,
+        draw_bounty: DrawBounty,
+    ) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+
+        // A right can't be split, and its pot goes to the authority anyway
+        if lottery.prize_kind != PrizeKind::Lamports {
+            return err!(LotteryError::WrongPrizeKind);
+        }
+        // Bounties are paid in lamports
+        if lottery.payment_mint.is_some() {
+            return err!(LotteryError::UnsupportedForTokenLottery);
+        }
+        if !lottery.anyone_can_draw() {
+            return err!(LotteryError::DrawNotPermissionless);
+        }
+        if matches!(draw_bounty, DrawBounty::Bps(bps) if bps > MAX_WINNER_SHARE_BPS) {
+            return err!(LotteryError::InvalidDrawBounty);
+        }
+
+        lottery.draw_bounty = draw_bounty;
+
+        Ok(())
+    }
+
+    // Function for the lottery authority to advertise the ticket count the pot is guaranteed to reach
+    // Sales then only end before the end time with `close_sales_early` once `min_tickets` tickets are sold.
+    // Uses the same accounts as `set_terms`, so buyers know the minimum pot before they buy
+    pub fn set_min_tickets(
+        ctx: Context<SetTerms>,
+        _lottery_id: u32,
+        min_tickets: u32,
+    ) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+
+        // Without an end time, the authority can draw whenever it likes, so a minimum would promise nothing
+        if lottery.end_time == 0 {
+            return err!(LotteryError::MinTicketsNeedEndTime);
+        }
+
+        lottery.min_tickets = min_tickets;
+
+        Ok(())
+    }
+
+    // Function for the lottery authority to let buyers take a ticket back within `refund_grace_slots` slots of buying
+    // it, at most MAX_REFUND_GRACE_SLOTS; 0 turns the grace period off, which is the default. Uses the same accounts
+    // as `set_terms`, so buyers know whether they can change their mind before they buy. Lamport lotteries only
+    pub fn set_refund_grace(
+        ctx: Context<SetTerms>,
+        _lottery_id: u32,
+        refund_grace_slots: u64,
+    ) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+
+        if refund_grace_slots > MAX_REFUND_GRACE_SLOTS || lottery.payment_mint.is_some() {
+            return err!(LotteryError::InvalidRefundGrace);
+        }
+        // Match mode counts every ticket once after the draw, which a redraw would invalidate
+        if refund_grace_slots != 0 && lottery.match_bits != 0 {
+            return err!(LotteryError::MatchModeUnsupported);
+        }
+
+        lottery.refund_grace_slots = refund_grace_slots;
+
+        Ok(())
+    }
+
+    // Function for the lottery authority to split the prize among every ticket whose art seed shares its first
+    // `match_bits` bits with the draw seed, at most MAX_MATCH_BITS; 0, the default, draws a single winner. With k bits
+    // about one ticket in 2^k matches. After the draw, `count_matches` checks every ticket, and then each matching
+    // ticket claims an even share with `claim_prize`. If none matches, the drawn ticket wins the whole prize as usual.
+    // Uses the same accounts as `set_terms`, so buyers know how the prize is split before they buy
+    pub fn set_match_bits(ctx: Context<SetTerms>, _lottery_id: u32, match_bits: u8) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+
+        if match_bits > MAX_MATCH_BITS {
+            return err!(LotteryError::InvalidMatchBits);
+        }
+        // Rights have a single holder, a callback tells a single winner, and a refund grace period can redraw
+        if match_bits != 0
+            && (lottery.prize_kind != PrizeKind::Lamports
+                || lottery.callback.is_some()
+                || lottery.refund_grace_slots != 0)
+        {
+            return err!(LotteryError::MatchModeUnsupported);
+        }
+
+        lottery.match_bits = match_bits;
+
+        Ok(())
+    }
+
+    // Function for the lottery authority to sell tickets for an SPL token, e.g. USDC, instead of lamports
+    // Creates the lottery's vault token account for `mint`; the ticket price is then in the mint's base units.
+    // Locked once the first ticket is sold, like `set_terms`. Token lotteries pay the whole jackpot to the winner,
+    // so draw bounties, winner shares, rollovers and refund grace periods, which move lamports, aren't available to them
+    pub fn set_payment_mint(ctx: Context<SetPaymentMint>, _lottery_id: u32) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+
+        if lottery.draw_bounty != DrawBounty::None
+            || lottery.winner_share_bps != MAX_WINNER_SHARE_BPS
+            || lottery.refund_grace_slots != 0
+        {
+            return err!(LotteryError::UnsupportedForTokenLottery);
+        }
+
+        // Lamports rolled over into the lottery wait for the creator's next lamport lottery instead
+        if lottery.carried_over > 0 {
+            let creator = &mut ctx.accounts.creator;
+            **lottery.to_account_info().try_borrow_mut_lamports()? -= lottery.carried_over;
+            **creator.to_account_info().try_borrow_mut_lamports()? += lottery.carried_over;
+            creator.rollover += lottery.carried_over;
+            lottery.carried_over = 0;
         }
 
-        // Retrieve the current clock data from the Solana runtime
-        let clock = Clock::get()?;
+        lottery.payment_mint = Some(ctx.accounts.mint.key());
+
+        // Log the payment mint
+        msg!(
+            "Lottery id {} sells tickets for mint {}",
+            lottery.id,
+            ctx.accounts.mint.key()
+        );
+
+        Ok(())
+    }
+
+    // Function to buy a ticket for a lottery
+    // Creates a ticket account and transfers the ticket price to the lottery PDA
+    // An optional Solana Pay reference account can be passed so the purchase can be located by it
+    // `valid_until` is a unix timestamp after which the purchase is rejected, so a transaction that lands late
+    // doesn't buy into a lottery the buyer no longer expected to enter
+    // `terms_hash` acknowledges the lottery's current terms, see `set_terms`
+    pub fn buy_ticket(
+        ctx: Context<BuyTicket>,
+        lottery_id: u32,
+        valid_until: i64,
+        terms_hash: [u8; 32],
+    ) -> Result<()> {
+        // Get references to the accounts involved
+        let lottery = &mut ctx.accounts.lottery;
+        let ticket = &mut ctx.accounts.ticket;
+        let buyer = &mut ctx.accounts.buyer;
+        let clock = time::clock(&ctx.accounts.master)?; // Retrieve the current clock data, see the `time` module
+
+        // Reject transactions that landed after the buyer's deadline
+        if clock.unix_timestamp > valid_until {
+            return err!(LotteryError::TransactionExpired);
+        }
+
+        // Check that the lottery is still selling tickets
+        validation::validate_sale_open(lottery, &clock)?;
+
+        // Fail loudly when the client showed the buyer different terms than the lottery's
+        if terms_hash != lottery.terms_hash {
+            return err!(LotteryError::TermsMismatch);
+        }
+
+        // Require the self-attestations the operator asked for on the buyer's profile
+        if !lottery.attestation_met(ctx.accounts.profile.as_deref()) {
+            return err!(LotteryError::AttestationRequired);
+        }
+
+        // What the buyer pays for this ticket, recorded on it so refunds don't depend on the price later on
+        let paid = lottery.ticket_price;
+
+        match ctx.accounts.voucher.as_mut() {
+            // Vouchers hold lamports, which a token lottery can't take
+            Some(_) if lottery.payment_mint.is_some() => {
+                return err!(LotteryError::UnsupportedForTokenLottery);
+            }
+            // Spend the buyer's voucher credit, moving the lamports it holds to the lottery account
+            Some(voucher) => Voucher::spend(voucher, &lottery.to_account_info(), paid)?,
+            // Transfer the ticket price in tokens from the buyer's token account to the lottery's vault
+            None if lottery.payment_mint.is_some() => token::deposit(
+                ctx.accounts.buyer_token_account.as_ref(),
+                ctx.accounts.vault.as_ref(),
+                &buyer.to_account_info(),
+                ctx.accounts.token_program.as_ref(),
+                paid,
+            )?,
+            // Transfer SOL from the buyer to the lottery account using a system instruction
+            None => invoke(
+                &transfer(&buyer.key(), &lottery.key(), paid),
+                &[
+                    buyer.to_account_info(),
+                    lottery.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?,
+        }
+
+        ticket.id = lottery.last_ticket_id;
+        ticket.lottery_id = lottery_id;
+        ticket.authority = buyer.key();
+        ticket.code_hash = None;
+        ticket.delegate = None;
+        ticket.art_seed = Ticket::art_seed(&lottery.key(), ticket.id, &ticket.authority);
+        ticket.paid = paid;
+        // Voucher credit isn't refunded as lamports, so only tickets paid from the wallet get the grace period
+        ticket.bought_slot = if ctx.accounts.voucher.is_some() {
+            0
+        } else {
+            clock.slot
+        };
+        ticket.refunded = false;
+
+        // Increment the last ticket ID and assign it to the new ticket
+        lottery.last_ticket_id += 1;
+        lottery.last_sale_slot = clock.slot;
+
+        // Count the sale towards the current season; season volume is in lamports, so token sales only add a ticket
+        ctx.accounts
+            .season
+            .record_sale(if lottery.payment_mint.is_some() {
+                0
+            } else {
+                paid
+            });
+
+        // Refund the ticket account's rent out of the creator's rent pool, so the buyer only pays the ticket price
+        if let Some(rent_pool) = ctx.accounts.rent_pool.as_mut() {
+            RentPool::reimburse(
+                rent_pool,
+                &buyer.to_account_info(),
+                Rent::get()?.minimum_balance(TICKET_SPACE),
+            )?;
+        }
+
+        // Track the buyer's stats when they passed their profile, and rank them if the leaderboard was passed too
+        if let Some(profile) = ctx.accounts.profile.as_mut() {
+            profile.tickets_bought += 1;
+            if let Some(leaderboard) = &ctx.accounts.leaderboard {
+                leaderboard
+                    .load_mut()?
+                    .record_buyer(profile.owner, profile.tickets_bought);
+            }
+        }
+
+        // Log information about the newly created ticket
+        msg!("Ticket ID: {}", ticket.id);
+        msg!("Ticket authority: {}", ticket.authority);
+
+        // Emit the purchase so Solana Pay / point-of-sale flows can match it to their reference
+        emit!(TicketPurchased {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id,
+            ticket_id: ticket.id,
+            buyer: ticket.authority,
+            price: paid,
+            art_seed: ticket.art_seed,
+            reference: ctx
+                .accounts
+                .reference
+                .as_ref()
+                .map(|reference| reference.key()),
+            currency: lottery.currency,
+            locale: lottery.locale.clone(),
+        });
+
+        Ok(()) // Return an Ok result to indicate success
+    }
+
+    // Function to buy `count` tickets at once, paying `ticket_price * count` in a single transfer
+    // The new ticket accounts are passed as remaining accounts in ticket ID order, starting at the lottery's
+    // `last_ticket_id`, at most MAX_TICKETS_PER_BUY per call. They are created here exactly like `buy_ticket`'s,
+    // so draws, claims and refunds treat them the same; rent pool refunds, vouchers and Solana Pay references
+    // need `buy_ticket`.
+    pub fn buy_tickets<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BuyTickets<'info>>,
+        lottery_id: u32,
+        count: u32,
+        valid_until: i64,
+        terms_hash: [u8; 32],
+    ) -> Result<()> {
+        // Get references to the accounts involved
+        let lottery = &mut ctx.accounts.lottery;
+        let buyer = &ctx.accounts.buyer;
+        let clock = time::clock(&ctx.accounts.master)?; // Retrieve the current clock data, see the `time` module
+
+        // Run the same checks as `buy_ticket`
+        if clock.unix_timestamp > valid_until {
+            return err!(LotteryError::TransactionExpired);
+        }
+        validation::validate_sale_open(lottery, &clock)?;
+        if terms_hash != lottery.terms_hash {
+            return err!(LotteryError::TermsMismatch);
+        }
+        if !lottery.attestation_met(ctx.accounts.profile.as_deref()) {
+            return err!(LotteryError::AttestationRequired);
+        }
+
+        // One ticket account per ticket bought
+        if count == 0 {
+            return err!(LotteryError::InvalidQuantity);
+        }
+        if count as usize > MAX_TICKETS_PER_BUY {
+            return err!(LotteryError::TooManyAccounts);
+        }
+        if ctx.remaining_accounts.len() != count as usize {
+            return err!(LotteryError::TicketAccountMismatch);
+        }
+
+        // Every ticket costs the current price, recorded on each so refunds don't depend on the price later on
+        let paid = lottery.ticket_price;
+        let total = paid
+            .checked_mul(count.into())
+            .ok_or(LotteryError::PotOverflow)?;
+        // The pot must still be payable once these tickets are sold, as `simulate_buy` checks
+        lottery
+            .last_ticket_id
+            .checked_add(count)
+            .and_then(|tickets| lottery.ticket_price.checked_mul(tickets.into()))
+            .ok_or(LotteryError::PotOverflow)?;
+
+        if lottery.payment_mint.is_some() {
+            // Transfer the tickets' price in tokens from the buyer's token account to the lottery's vault
+            token::deposit(
+                ctx.accounts.buyer_token_account.as_ref(),
+                ctx.accounts.vault.as_ref(),
+                &buyer.to_account_info(),
+                ctx.accounts.token_program.as_ref(),
+                total,
+            )?;
+        } else {
+            // Transfer SOL from the buyer to the lottery account using a system instruction
+            invoke(
+                &transfer(&buyer.key(), &lottery.key(), total),
+                &[
+                    buyer.to_account_info(),
+                    lottery.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
+
+        let lottery_key = lottery.key();
+        for account in ctx.remaining_accounts {
+            let id = lottery.last_ticket_id;
+            let id_bytes = id.to_le_bytes();
+            let [prefix, lottery_seed, id_seed] = pdas::ticket_seeds(&lottery_key, &id_bytes);
+
+            // Each account must be the next ticket's address, which also rules out duplicates and gaps in the IDs
+            // Anchor can't derive it for remaining accounts, so the bump is searched for here
+            let (address, bump) =
+                Pubkey::find_program_address(&[prefix, lottery_seed, id_seed], &crate::ID);
+            if account.key() != address {
+                return err!(LotteryError::TicketAccountMismatch);
+            }
+
+            // Create the ticket account, paid by the buyer and signed for with the ticket's seeds
+            batch::create_pda_account(
+                &buyer.to_account_info(),
+                account,
+                &ctx.accounts.system_program.to_account_info(),
+                TICKET_SPACE,
+                &[prefix, lottery_seed, id_seed, &[bump]],
+            )?;
+
+            let ticket = Ticket {
+                id,
+                authority: buyer.key(),
+                lottery_id,
+                code_hash: None,
+                delegate: None,
+                art_seed: Ticket::art_seed(&lottery_key, id, &buyer.key()),
+                paid,
+                bought_slot: clock.slot,
+                refunded: false,
+            };
+            ticket.try_serialize(&mut &mut account.try_borrow_mut_data()?[..])?;
+
+            lottery.last_ticket_id += 1;
+
+            // Emit one purchase per ticket, so indexers see bulk purchases like single ones
+            emit!(TicketPurchased {
+                seq: ctx.accounts.master.next_seq(),
+                lottery_id,
+                ticket_id: id,
+                buyer: ticket.authority,
+                price: paid,
+                art_seed: ticket.art_seed,
+                reference: None,
+                currency: lottery.currency,
+                locale: lottery.locale.clone(),
+            });
+        }
+        lottery.last_sale_slot = clock.slot;
+
+        // Count the sales towards the current season; season volume is in lamports, so token sales only add tickets
+        ctx.accounts.season.record_sales(
+            count,
+            if lottery.payment_mint.is_some() {
+                0
+            } else {
+                total
+            },
+        );
+
+        // Track the buyer's stats when they passed their profile, and rank them if the leaderboard was passed too
+        if let Some(profile) = ctx.accounts.profile.as_mut() {
+            profile.tickets_bought += u64::from(count);
+            if let Some(leaderboard) = &ctx.accounts.leaderboard {
+                leaderboard
+                    .load_mut()?
+                    .record_buyer(profile.owner, profile.tickets_bought);
+            }
+        }
+
+        msg!("Tickets bought: {}", count);
+
+        Ok(()) // Return an Ok result to indicate success
+    }
+
+    // Function for the lottery authority to prefund the rent of ticket accounts
+    // The pool is created on the first call; buyers passing it to `buy_ticket` get their ticket rent refunded
+    pub fn fund_rent_pool(ctx: Context<FundRentPool>, _lottery_id: u32, amount: u64) -> Result<()> {
+        let rent_pool = &mut ctx.accounts.rent_pool; // Get a mutable reference to the rent pool account
+        let authority = &ctx.accounts.authority;
+
+        rent_pool.lottery = ctx.accounts.lottery.key();
+
+        // Move the funds into the pool
+        invoke(
+            &transfer(&authority.key(), &rent_pool.key(), amount),
+            &[
+                authority.to_account_info(),
+                rent_pool.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        // Log the funding
+        msg!("Rent pool funded with {} lamports", amount);
+
+        Ok(())
+    }
+
+    // Function for the lottery authority to close the rent pool once sales are over
+    // Whatever wasn't used, including the pool's own rent, goes back to the authority
+    pub fn close_rent_pool(ctx: Context<CloseRentPool>, _lottery_id: u32) -> Result<()> {
+        // Log how much of the pool was used
+        msg!(
+            "Rent pool closed after refunding {} lamports",
+            ctx.accounts.rent_pool.reimbursed
+        );
+
+        Ok(())
+    }
+
+    // Function to buy lottery credit for a recipient, e.g. as a gift or promotion
+    // The recipient's voucher is created on the first purchase and topped up by later ones;
+    // they spend it by passing it to `buy_ticket`
+    pub fn buy_voucher(ctx: Context<BuyVoucher>, recipient: Pubkey, amount: u64) -> Result<()> {
+        let voucher = &mut ctx.accounts.voucher; // Get a mutable reference to the voucher account
+        let payer = &ctx.accounts.payer;
+
+        // Move the credit into the voucher account
+        invoke(
+            &transfer(&payer.key(), &voucher.key(), amount),
+            &[
+                payer.to_account_info(),
+                voucher.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        voucher.owner = recipient;
+        voucher.balance = voucher
+            .balance
+            .checked_add(amount)
+            .ok_or(LotteryError::PotOverflow)?;
+
+        emit!(VoucherPurchased {
+            seq: ctx.accounts.master.next_seq(),
+            recipient,
+            payer: payer.key(),
+            amount,
+            balance: voucher.balance,
+        });
+
+        Ok(())
+    }
+
+    // Function to dry-run a purchase of `quantity` tickets without changing any state
+    // Runs the same checks as `buy_ticket` plus a balance check, and returns the code of the error
+    // the purchase would fail with (see `LotteryError`), so frontends can explain it before the user signs
+    pub fn simulate_buy(
+        ctx: Context<SimulateBuy>,
+        _lottery_id: u32,
+        quantity: u32,
+        terms_hash: [u8; 32],
+    ) -> Result<SimulatedBuy> {
+        let lottery = &ctx.accounts.lottery;
+        let buyer = &ctx.accounts.buyer;
+
+        let total_cost = lottery.ticket_price.checked_mul(quantity.into());
+        let ticket_rent = Rent::get()?.minimum_balance(TICKET_SPACE);
+
+        let check = || -> Result<()> {
+            if quantity == 0 {
+                return err!(LotteryError::InvalidQuantity);
+            }
+            validation::validate_sale_open(lottery, &time::clock(&ctx.accounts.master)?)?;
+            if terms_hash != lottery.terms_hash {
+                return err!(LotteryError::TermsMismatch);
+            }
+            if !lottery.attestation_met(ctx.accounts.profile.as_deref()) {
+                return err!(LotteryError::AttestationRequired);
+            }
+            if !lottery.accepts_player(&buyer.key()) {
+                return err!(LotteryError::SelfEntryForbidden);
+            }
+
+            // The pot must still be payable once these tickets are sold
+            lottery
+                .last_ticket_id
+                .checked_add(quantity)
+                .and_then(|tickets| lottery.ticket_price.checked_mul(tickets.into()))
+                .ok_or(LotteryError::PotOverflow)?;
+
+            // The buyer pays the tickets and the rent of each ticket account; a token lottery's tickets are paid
+            // in its mint, so only their rent is checked against the buyer's lamports
+            let lamport_cost = if lottery.payment_mint.is_some() {
+                Some(0)
+            } else {
+                total_cost
+            };
+            let required = lamport_cost
+                .zip(ticket_rent.checked_mul(quantity.into()))
+                .and_then(|(cost, rent)| cost.checked_add(rent))
+                .ok_or(LotteryError::PotOverflow)?;
+            if buyer.lamports() < required {
+                return err!(LotteryError::InsufficientFunds);
+            }
+            Ok(())
+        };
+
+        Ok(SimulatedBuy {
+            error_code: check().err().map(|error| match error {
+                Error::AnchorError(error) => error.error_code_number,
+                Error::ProgramError(error) => u64::from(error.program_error) as u32,
+            }),
+            total_cost: total_cost.unwrap_or(u64::MAX),
+        })
+    }
+
+    // Function for wallets to read what a ticket is owed via return data, computed with the same math as the payouts
+    // A winning ticket is owed its share of the jackpot; once refunds are open, every ticket is owed its recovery share.
+    // `gross` is the payout before fees, `fees` the ticket's part of the draw bounty plus the protocol fee on a prize,
+    // and `net` what is paid
+    pub fn get_ticket_status(
+        ctx: Context<GetTicketStatus>,
+        _lottery_id: u32,
+        _ticket_id: u32,
+    ) -> Result<TicketStatus> {
+        let lottery = &ctx.accounts.lottery;
+        let ticket = &ctx.accounts.ticket;
+        let now = time::now(&ctx.accounts.master)?;
+
+        // A refunded ticket drawn as the winner is owed nothing; the lottery is redrawn instead
+        let is_winner = !lottery.cancelled && lottery.wins(ticket);
+        let (gross, net) = if ticket.refunded {
+            (Some(0), Some(0))
+        } else if lottery.recovery_open(now, ctx.accounts.master.recovery_period) {
+            let tickets = u64::from(lottery.live_tickets());
+            let gross = math::pro_rata_floor(lottery.carried_over, tickets)
+                .and_then(|carried| ticket.paid.checked_add(carried));
+            (gross, lottery.recovery_share(ticket.paid))
+        } else if is_winner && lottery.prize_kind == PrizeKind::Lamports {
+            let gross = lottery
+                .pot()
+                .and_then(|pot| pot.checked_add(lottery.carried_over))
+                .and_then(|jackpot| math::bps_floor(jackpot, lottery.winner_share_bps))
+                .and_then(|prize| math::pro_rata_floor(prize, lottery.match_count.max(1).into()));
+            let net = lottery
+                .claim_split()
+                .and_then(|(price, _)| {
+                    math::split_bps(
+                        price,
+                        ctx.accounts.config.fee_bps_for(&ctx.accounts.creator),
+                    )
+                })
+                .map(|(_, net)| net);
+            (gross, net)
+        } else {
+            (Some(0), Some(0))
+        };
+        let (gross, net) = gross.zip(net).ok_or(LotteryError::PotOverflow)?;
+
+        // Receipts are only created by payouts, and a payout held back by the circuit breaker closes its receipt again
+        let receipt = &ctx.accounts.receipt;
+        let claimed = receipt.owner == &crate::ID && !receipt.data_is_empty();
+
+        Ok(TicketStatus {
+            is_winner,
+            tier: if is_winner { WINNING_TIER } else { 0 },
+            gross,
+            fees: gross.saturating_sub(net),
+            net,
+            claimed,
+        })
+    }
+
+    // Function for the authority to pre-purchase a ticket bound to a redemption code
+    // Only the hash of the code is stored; the ticket is held by the authority until the code is redeemed
+    pub fn create_code_ticket(
+        ctx: Context<CreateCodeTicket>,
+        lottery_id: u32,
+        code_hash: [u8; 32],
+    ) -> Result<()> {
+        // Get references to the accounts involved
+        let lottery = &mut ctx.accounts.lottery;
+        let ticket = &mut ctx.accounts.ticket;
+        let authority = &mut ctx.accounts.authority;
+
+        // Check that the lottery is still selling tickets
+        let clock = time::clock(&ctx.accounts.master)?;
+        validation::validate_sale_open(lottery, &clock)?;
+
+        // Code tickets are paid in lamports
+        if lottery.payment_mint.is_some() {
+            return err!(LotteryError::UnsupportedForTokenLottery);
+        }
+
+        // The authority pays the ticket price up front, exactly like a regular buyer
+        invoke(
+            &transfer(&authority.key(), &lottery.key(), lottery.ticket_price),
+            &[
+                authority.to_account_info(),
+                lottery.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        ticket.id = lottery.last_ticket_id;
+        ticket.lottery_id = lottery_id;
+        ticket.authority = authority.key();
+        ticket.code_hash = Some(code_hash);
+        ticket.delegate = None;
+        ticket.art_seed = Ticket::art_seed(&lottery.key(), ticket.id, &ticket.authority);
+        ticket.paid = lottery.ticket_price;
+        ticket.bought_slot = 0; // Bought by the authority for someone else, so there's no buyer to refund
+        ticket.refunded = false;
+
+        // Increment the last ticket ID and assign it to the new ticket
+        lottery.last_ticket_id += 1;
+        lottery.unredeemed_codes += 1;
+        lottery.last_sale_slot = clock.slot;
+
+        // Count the sale towards the current season
+        ctx.accounts.season.record_sale(lottery.ticket_price);
+
+        // Log information about the newly created ticket
+        msg!("Code ticket ID: {}", ticket.id);
+
+        emit!(TicketPurchased {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id,
+            ticket_id: ticket.id,
+            buyer: ticket.authority,
+            price: lottery.ticket_price,
+            art_seed: ticket.art_seed,
+            reference: None,
+            currency: lottery.currency,
+            locale: lottery.locale.clone(),
+        });
+
+        Ok(()) // Return an Ok result to indicate success
+    }
+
+    // Function to bind a code ticket to the wallet presenting the redemption code
+    // In a no_self_entry or match mode lottery the code must be redeemed while tickets are on sale, see
+    // `Lottery::redeems_after_draw`; a code ticket still held by the authority at the draw can't win
+    pub fn redeem_code_ticket(
+        ctx: Context<RedeemCodeTicket>,
+        _lottery_id: u32,
+        _ticket_id: u32,
+        code: Vec<u8>,
+    ) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+        let ticket = &mut ctx.accounts.ticket; // Get a mutable reference to the ticket account
+        let owner = &ctx.accounts.owner; // Get a reference to the wallet redeeming the code
+
+        // Redeeming enters the lottery just like buying, so the same attestations are required
+        if !lottery.attestation_met(ctx.accounts.profile.as_deref()) {
+            return err!(LotteryError::AttestationRequired);
+        }
+
+        // Redeeming can change who wins, so where it must happen before the draw it is treated like a sale: it
+        // closes with the sale, and the lottery isn't drawn in the slot of a redemption
+        let clock = time::clock(&ctx.accounts.master)?;
+        if !lottery.redeems_after_draw() {
+            if validation::validate_sale_open(lottery, &clock).is_err() {
+                return err!(LotteryError::RedemptionClosed);
+            }
+            lottery.last_sale_slot = clock.slot;
+        }
+
+        // Check that the ticket is still waiting for a code and that the code matches
+        match ticket.code_hash {
+            Some(code_hash) => {
+                if hash(&code).to_bytes() != code_hash {
+                    return err!(LotteryError::InvalidRedemptionCode);
+                }
+            }
+            None => return err!(LotteryError::TicketAlreadyRedeemed),
+        }
+
+        // Hand the ticket over to the redeemer, dropping any delegate the previous holder set
+        // The art seed is derived again, so the ticket's art belongs to the player rather than the operator
+        ticket.authority = owner.key();
+        ticket.code_hash = None;
+        ticket.delegate = None;
+        ticket.art_seed = Ticket::art_seed(&lottery.key(), ticket.id, &ticket.authority);
+        lottery.unredeemed_codes -= 1;
+
+        // Log the redemption
+        msg!("Ticket ID {} redeemed by {}", ticket.id, ticket.authority);
+
+        emit!(CodeTicketRedeemed {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id: ticket.lottery_id,
+            ticket_id: ticket.id,
+            owner: ticket.authority,
+            art_seed: ticket.art_seed,
+        });
+
+        Ok(())
+    }
+
+    // Function for the authority to reserve a block of ticket IDs for a partner, e.g. for a B2B distribution deal
+    // The partner pays for the whole block up front; its tickets enter the draw right away and are handed out to
+    // end users with `assign_reserved_ticket`. A ticket that wins before it is assigned can still be assigned
+    pub fn reserve_tickets(
+        ctx: Context<ReserveTickets>,
+        lottery_id: u32,
+        count: u32,
+    ) -> Result<()> {
+        // Get references to the accounts involved
+        let lottery = &mut ctx.accounts.lottery;
+        let reservation = &mut ctx.accounts.reservation;
+        let partner = &ctx.accounts.partner;
+
+        // Check that the lottery is still selling tickets
+        let clock = time::clock(&ctx.accounts.master)?;
+        validation::validate_sale_open(lottery, &clock)?;
+
+        // Reserved blocks are paid in lamports
+        if lottery.payment_mint.is_some() {
+            return err!(LotteryError::UnsupportedForTokenLottery);
+        }
+
+        if count == 0 {
+            return err!(LotteryError::InvalidReservation);
+        }
+        let last_ticket_id = lottery
+            .last_ticket_id
+            .checked_add(count)
+            .ok_or(LotteryError::PotOverflow)?;
+        let total = lottery
+            .ticket_price
+            .checked_mul(count.into())
+            .ok_or(LotteryError::PotOverflow)?;
+
+        // The partner pays for every ticket of the block, exactly like a regular buyer
+        invoke(
+            &transfer(&partner.key(), &lottery.key(), total),
+            &[
+                partner.to_account_info(),
+                lottery.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        reservation.lottery_id = lottery_id;
+        reservation.partner = partner.key();
+        reservation.first_ticket_id = lottery.last_ticket_id;
+        reservation.count = count;
+        reservation.assigned = 0;
+        reservation.price = lottery.ticket_price;
+
+        // Take the block's IDs, so the draw counts them from now on
+        lottery.last_ticket_id = last_ticket_id;
+        lottery.last_sale_slot = clock.slot;
+
+        // Count the sales towards the current season
+        ctx.accounts.season.record_sales(count, total);
+
+        // Log the reservation
+        msg!(
+            "Ticket IDs {} to {} reserved for {}",
+            reservation.first_ticket_id,
+            last_ticket_id - 1,
+            reservation.partner
+        );
+
+        emit!(TicketsReserved {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id,
+            partner: reservation.partner,
+            first_ticket_id: reservation.first_ticket_id,
+            count,
+            price: reservation.price,
+        });
+
+        Ok(())
+    }
+
+    // Function for a partner to hand the next ticket of their reserved block to an end user
+    // Tickets are assigned in ID order; the partner pays the ticket account's rent
+    pub fn assign_reserved_ticket(
+        ctx: Context<AssignReservedTicket>,
+        _lottery_id: u32,
+    ) -> Result<()> {
+        // Get references to the accounts involved
+        let lottery = &ctx.accounts.lottery;
+        let reservation = &mut ctx.accounts.reservation;
+        let ticket = &mut ctx.accounts.ticket;
+        let owner = &ctx.accounts.owner;
+
+        // The end user enters the lottery just like a buyer, so the same attestations are required
+        if !lottery.attestation_met(ctx.accounts.profile.as_deref()) {
+            return err!(LotteryError::AttestationRequired);
+        }
+
+        ticket.id = reservation.first_ticket_id + reservation.assigned;
+        ticket.lottery_id = reservation.lottery_id;
+        ticket.authority = owner.key();
+        ticket.code_hash = None;
+        ticket.delegate = None;
+        ticket.art_seed = Ticket::art_seed(&lottery.key(), ticket.id, &ticket.authority);
+        ticket.paid = reservation.price;
+        ticket.bought_slot = 0; // The block was paid when it was reserved, so single tickets can't be refunded
+        ticket.refunded = false;
+
+        reservation.assigned += 1;
+
+        // Log the assignment
+        msg!(
+            "Reserved ticket ID {} assigned to {}",
+            ticket.id,
+            ticket.authority
+        );
+
+        emit!(ReservedTicketAssigned {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id: ticket.lottery_id,
+            ticket_id: ticket.id,
+            partner: reservation.partner,
+            owner: ticket.authority,
+            art_seed: ticket.art_seed,
+        });
+
+        Ok(())
+    }
+
+    // Function for the authority to stock a block of unsold house tickets, e.g. for retail distribution
+    // Each ticket of the block is printed with a redemption code; only a Merkle root over `(index, sha256(code))` leaves
+    // is stored, see `merkle::code_leaf`. The tickets aren't paid for yet and don't enter the draw until someone buys
+    // one with `buy_from_inventory`; whatever is unsold when sales close is voided with `void_inventory`
+    pub fn stock_inventory(
+        ctx: Context<StockInventory>,
+        lottery_id: u32,
+        block_id: u32,
+        code_root: [u8; 32],
+        count: u16,
+    ) -> Result<()> {
+        let lottery = &ctx.accounts.lottery; // Get a reference to the lottery account
+        let inventory = &mut ctx.accounts.inventory; // Get a mutable reference to the inventory account
+
+        // Check that the lottery is still selling tickets
+        let clock = time::clock(&ctx.accounts.master)?;
+        validation::validate_sale_open(lottery, &clock)?;
+
+        // Inventory tickets are paid in lamports, like code tickets
+        if lottery.payment_mint.is_some() {
+            return err!(LotteryError::UnsupportedForTokenLottery);
+        }
+        if count == 0 || count > MAX_INVENTORY_BLOCK {
+            return err!(LotteryError::InvalidInventory);
+        }
+
+        inventory.lottery = lottery.key();
+        inventory.block_id = block_id;
+        inventory.code_root = code_root;
+        inventory.count = count;
+        inventory.sold = 0;
+        inventory.sold_map = [0; MAX_INVENTORY_BLOCK as usize / 8];
+
+        // Log the block
+        msg!(
+            "Inventory block {} stocked with {} tickets",
+            block_id,
+            count
+        );
+
+        emit!(InventoryStocked {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id,
+            block_id,
+            count,
+            code_root,
+        });
+
+        Ok(())
+    }
+
+    // Function to buy a ticket of a house inventory block by presenting its redemption code
+    // The buyer pays the ticket price like a regular buyer, and the ticket takes the next ticket ID; `proof` shows that
+    // `(index, sha256(code))` is one of the block's leaves, and every index can be sold once
+    pub fn buy_from_inventory(
+        ctx: Context<BuyFromInventory>,
+        lottery_id: u32,
+        block_id: u32,
+        index: u16,
+        code: Vec<u8>,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        // Get references to the accounts involved
+        let lottery = &mut ctx.accounts.lottery;
+        let inventory = &mut ctx.accounts.inventory;
+        let ticket = &mut ctx.accounts.ticket;
+        let buyer = &ctx.accounts.buyer;
+
+        // Check that the lottery is still selling tickets
+        let clock = time::clock(&ctx.accounts.master)?;
+        validation::validate_sale_open(lottery, &clock)?;
+
+        // The buyer enters the lottery just like a regular buyer, so the same attestations are required
+        if !lottery.attestation_met(ctx.accounts.profile.as_deref()) {
+            return err!(LotteryError::AttestationRequired);
+        }
+
+        // Check the code against the block, and that its ticket is still in stock
+        let leaf = merkle::code_leaf(index, hash(&code).to_bytes());
+        if index >= inventory.count || !merkle::verify(&proof, inventory.code_root, leaf) {
+            return err!(LotteryError::InvalidRedemptionCode);
+        }
+        if inventory.is_sold(index) {
+            return err!(LotteryError::InventoryTicketSold);
+        }
+
+        // The buyer pays the ticket price, exactly like a regular buyer
+        invoke(
+            &transfer(&buyer.key(), &lottery.key(), lottery.ticket_price),
+            &[
+                buyer.to_account_info(),
+                lottery.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        ticket.id = lottery.last_ticket_id;
+        ticket.lottery_id = lottery_id;
+        ticket.authority = buyer.key();
+        ticket.code_hash = None;
+        ticket.delegate = None;
+        ticket.art_seed = Ticket::art_seed(&lottery.key(), ticket.id, &ticket.authority);
+        ticket.paid = lottery.ticket_price;
+        ticket.bought_slot = clock.slot;
+        ticket.refunded = false;
+
+        // Increment the last ticket ID and assign it to the new ticket
+        lottery.last_ticket_id += 1;
+        lottery.last_sale_slot = clock.slot;
+
+        // Take the ticket out of stock
+        inventory.mark_sold(index);
+
+        // Count the sale towards the current season
+        ctx.accounts.season.record_sale(lottery.ticket_price);
+
+        // Log the sale
+        msg!(
+            "Inventory ticket {} of block {} sold as ticket ID {}",
+            index,
+            block_id,
+            ticket.id
+        );
+
+        emit!(TicketPurchased {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id,
+            ticket_id: ticket.id,
+            buyer: ticket.authority,
+            price: lottery.ticket_price,
+            art_seed: ticket.art_seed,
+            reference: None,
+            currency: lottery.currency,
+            locale: lottery.locale.clone(),
+        });
+
+        emit!(InventoryTicketSold {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id,
+            block_id,
+            index,
+            ticket_id: ticket.id,
+        });
+
+        Ok(())
+    }
+
+    // Function for the authority to void what is left of an inventory block once the lottery stopped selling tickets,
+    // at its end time, draw or cancellation. Closes the inventory account, returning its rent to the authority
+    // Called while sales are still open, it leaves the block alone and returns `CrankStatus::NotDue`
+    pub fn void_inventory(
+        ctx: Context<VoidInventory>,
+        lottery_id: u32,
+        block_id: u32,
+    ) -> Result<CrankStatus> {
+        let inventory = &ctx.accounts.inventory; // Get a reference to the inventory account
+
+        // Unsold tickets stay for sale until sales close
+        let clock = time::clock(&ctx.accounts.master)?;
+        if validation::validate_sale_open(&ctx.accounts.lottery, &clock).is_ok() {
+            return Ok(CrankStatus::NotDue);
+        }
+
+        let unsold = inventory.count - inventory.sold;
+
+        // Log the voided tickets
+        msg!(
+            "Inventory block {} voided with {} unsold tickets",
+            block_id,
+            unsold
+        );
+
+        emit!(InventoryVoided {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id,
+            block_id,
+            unsold,
+        });
+
+        // Close the inventory account, returning its rent to the authority
+        ctx.accounts
+            .inventory
+            .close(ctx.accounts.authority.to_account_info())?;
+
+        Ok(CrankStatus::Done)
+    }
+
+    // Function for the lottery authority to end sales before the lottery's end time
+    // Only allowed once `min_tickets` tickets are sold, so an early close never shrinks the advertised pot. A creator
+    // who wants to close sooner buys the remaining tickets at full price with `reserve_tickets` in the same
+    // transaction; the check here runs against the final count, so the two succeed or fail together
+    pub fn close_sales_early(ctx: Context<CloseSalesEarly>, lottery_id: u32) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+
+        // Check that the lottery is still selling tickets
+        let clock = time::clock(&ctx.accounts.master)?;
+        validation::validate_sale_open(lottery, &clock)?;
+
+        if lottery.min_tickets == 0 {
+            return err!(LotteryError::NoMinTickets);
+        }
+        if lottery.live_tickets() < lottery.min_tickets {
+            return err!(LotteryError::MinTicketsNotSold);
+        }
+
+        // Sales end now and the draw opens, exactly as if the end time had passed
+        lottery.end_time = clock.unix_timestamp;
+
+        // Log the early close
+        msg!(
+            "Lottery {} closed sales early with {} tickets sold",
+            lottery_id,
+            lottery.live_tickets()
+        );
+
+        emit!(SalesClosedEarly {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id,
+            tickets_sold: lottery.live_tickets(),
+            end_time: lottery.end_time,
+        });
+
+        Ok(())
+    }
+
+    // Function for a ticket owner to take their ticket back within the lottery's refund grace period
+    // Pays back what the ticket paid and takes it out of the pot. The ticket keeps its ID and account, marked as
+    // refunded, so ticket IDs stay dense and the draw still picks from 0..last_ticket_id; a draw that picks a refunded
+    // ticket is redone with `redraw_void_winner`. Season, profile and leaderboard counts of the purchase aren't reverted
+    pub fn refund_ticket(
+        ctx: Context<RefundTicket>,
+        lottery_id: u32,
+        ticket_id: u32,
+    ) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+        let ticket = &mut ctx.accounts.ticket; // Get a mutable reference to the ticket account
+        let clock = time::clock(&ctx.accounts.master)?;
+
+        // Only while sales are open, so the draw never sees a ticket disappear
+        validation::validate_sale_open(lottery, &clock)?;
+
+        if ticket.refunded {
+            return err!(LotteryError::TicketRefunded);
+        }
+        // Tickets without a purchase slot were never bought by their owner and have no grace period
+        if lottery.refund_grace_slots == 0
+            || ticket.bought_slot == 0
+            || clock.slot
+                > ticket
+                    .bought_slot
+                    .saturating_add(lottery.refund_grace_slots)
+        {
+            return err!(LotteryError::RefundGraceOver);
+        }
+
+        // Pay the ticket back out of the pot
+        let amount = ticket.paid;
+        **lottery.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx
+            .accounts
+            .authority
+            .to_account_info()
+            .try_borrow_mut_lamports()? += amount;
+
+        ticket.paid = 0;
+        ticket.refunded = true;
+        lottery.refunded_tickets += 1;
+
+        // Log the refund
+        msg!(
+            "Ticket {} of lottery {} refunded {} lamports",
+            ticket_id,
+            lottery_id,
+            amount
+        );
+
+        emit!(TicketRefunded {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id,
+            ticket_id,
+            owner: ticket.authority,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    // Function for a ticket owner to authorize (or revoke, with None) a delegate that may claim on their behalf
+    // The prize is still paid to the owner; the delegate only signs and pays for the claim receipt
+    pub fn set_ticket_delegate(
+        ctx: Context<SetTicketDelegate>,
+        _lottery_id: u32,
+        _ticket_id: u32,
+        delegate: Option<Pubkey>,
+    ) -> Result<()> {
+        let ticket = &mut ctx.accounts.ticket; // Get a mutable reference to the ticket account
+
+        ticket.delegate = delegate;
+
+        emit!(TicketDelegateSet {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id: ticket.lottery_id,
+            ticket_id: ticket.id,
+            delegate,
+        });
+
+        Ok(())
+    }
+
+    // Function to select a winner for the lottery
+    // Anyone can draw a permissionless lottery, which keepers do on a schedule: when it isn't due yet or was already
+    // drawn, that succeeds without drawing and the returned status says so. Its authority still gets an error
+    pub fn pick_winner(ctx: Context<PickWinner>, _lottery_id: u32) -> Result<CrankStatus> {
+        // Retrieve the current clock data, see the `time` module
+        let clock = time::clock(&ctx.accounts.master)?;
+        let lottery = &ctx.accounts.lottery;
+        if let Some((error, status)) = ctx.accounts.not_drawable(&clock) {
+            return if lottery.anyone_can_draw() {
+                Ok(status)
+            } else {
+                Err(error.into())
+            };
+        }
+
+        // A lottery with a randomness commitment can only be drawn by revealing it
+        if lottery.commitment.is_some() {
+            return err!(LotteryError::DrawCommitted);
+        }
+
+        // Neither can a lottery with an entropy committee, which draws from its members' shares
+        if lottery.committee {
+            return err!(LotteryError::CommitteeDraw);
+        }
+
+        let seed = match lottery.draw_epoch {
+            // Epoch-locked draws take their entropy from the first slot hash of the next epoch,
+            // which didn't exist yet while tickets could still be bought
+            Some(draw_epoch) => {
+                if clock.epoch <= draw_epoch {
+                    return Ok(CrankStatus::NotDue);
+                }
+
+                // The sysvar keeps about 512 slots; a lottery not drawn in time draws from the first slot hash of the
+                // next epoch to start instead, see `draw::epoch_slot_hash`
+                let slot_hash = draw::epoch_slot_hash(
+                    &ctx.accounts.slot_hashes,
+                    &EpochSchedule::get()?,
+                    draw_epoch,
+                )?;
+                draw::epoch_seed(slot_hash, &lottery.key(), lottery.last_ticket_id)
+            }
+
+            // Generate a pseudo-random number from several recent slot hashes, the lottery key and the final ticket count
+            // Note: This is harder to grind than a timestamp but still not a secure random number generator (e.g., an oracle)
+            None => {
+                // These hashes are known while the instruction runs, so a calling program could work out the winner
+                // and abort until it likes it; only the authority draws these lotteries, and only at the top level
+                if get_stack_height() != TRANSACTION_LEVEL_STACK_HEIGHT {
+                    return err!(LotteryError::DrawThroughCpi);
+                }
+                draw::recent_slot_hashes_seed(
+                    &ctx.accounts.slot_hashes,
+                    SLOT_HASH_SAMPLES,
+                    &lottery.key(),
+                    lottery.last_ticket_id,
+                )?
+            }
+        };
+
+        ctx.accounts.draw(seed, &clock)?;
+        Ok(CrankStatus::Done)
+    }
+
+    // Function for the authority to commit to a secret before drawing, an oracle-free alternative to `pick_winner`
+    // `commitment` is the SHA-256 hash of a 32 byte secret the authority keeps to itself. Committing closes sales,
+    // and the lottery is then drawn with `reveal_and_pick` once COMMIT_REVEAL_DELAY_SLOTS have passed
+    pub fn commit_randomness(
+        ctx: Context<CommitRandomness>,
+        _lottery_id: u32,
+        commitment: [u8; 32],
+    ) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+
+        // Check if there are any tickets purchased that can win
+        if lottery.eligible_tickets() == 0 {
+            return err!(LotteryError::NoTickets);
+        }
+
+        // Epoch-locked lotteries already draw from entropy that didn't exist while tickets were sold
+        if lottery.draw_epoch.is_some() {
+            return err!(LotteryError::EpochLockedDraw);
+        }
+
+        // Committee lotteries draw from their members' shares, which the authority can't replace with its own secret
+        if lottery.committee {
+            return err!(LotteryError::CommitteeDraw);
+        }
+
+        // Committing closes sales, which the authority can't do before the lottery's end time
+        let clock = time::clock(&ctx.accounts.master)?;
+        if clock.unix_timestamp < lottery.end_time {
+            return err!(LotteryError::DrawBeforeEndTime);
+        }
+
+        lottery.commitment = Some(commitment);
+        lottery.commit_slot = clock.slot;
+
+        // Log the commitment
+        msg!(
+            "Lottery id {} committed to its randomness in slot {}",
+            lottery.id,
+            lottery.commit_slot
+        );
+
+        emit!(RandomnessCommitted {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id: lottery.id,
+            commitment,
+            reveal_slot: lottery.commit_slot + COMMIT_REVEAL_DELAY_SLOTS,
+        });
+
+        Ok(())
+    }
+
+    // Function for the authority to reveal the secret committed with `commit_randomness` and draw the winner
+    // The secret is mixed with the hash of the slot COMMIT_REVEAL_DELAY_SLOTS after the commitment, which nobody knew
+    // when committing. The reveal must come within MAX_REVEAL_DELAY_SLOTS after that slot, while its hash is still in
+    // the SlotHashes sysvar; a lottery whose authority never reveals is left to the admin's `force_resolve`
+    pub fn reveal_and_pick(
+        ctx: Context<PickWinner>,
+        _lottery_id: u32,
+        secret: [u8; 32],
+    ) -> Result<()> {
+        let clock = time::clock(&ctx.accounts.master)?;
+        ctx.accounts.check_drawable(&clock)?;
+
+        let lottery = &ctx.accounts.lottery;
+        let commitment = lottery.commitment.ok_or(LotteryError::NoCommitment)?;
+        if hash(&secret).to_bytes() != commitment {
+            return err!(LotteryError::RevealMismatch);
+        }
+
+        // The slot hash mixed in must exist, and must still be the one recorded for that slot
+        let reveal_slot = lottery.commit_slot + COMMIT_REVEAL_DELAY_SLOTS;
+        if clock.slot <= reveal_slot {
+            return err!(LotteryError::RevealTooEarly);
+        }
+        if clock.slot > reveal_slot + MAX_REVEAL_DELAY_SLOTS {
+            return err!(LotteryError::RevealExpired);
+        }
+
+        let entropy = draw::slot_hash_at_or_after(&ctx.accounts.slot_hashes, reveal_slot)?;
+        let seed =
+            draw::commit_reveal_seed(secret, entropy, &lottery.key(), lottery.last_ticket_id);
+
+        ctx.accounts.draw(seed, &clock)
+    }
+
+    // Function for the lottery authority to hand the draw to a committee of entropy providers instead of drawing it
+    // Each member commits to a secret share with `commit_share` before the end time and reveals it with `reveal_share`
+    // after; `finalize_draw` then draws from every revealed share once `threshold` of them are in. One honest share is
+    // enough to keep the authority or the other members from picking the result, and members that don't reveal can't
+    // block the draw while `threshold` others do. Locked once the first ticket is sold, like `set_terms`; the lottery
+    // needs an end time, which is when commitments close and reveals open
+    pub fn set_committee(
+        ctx: Context<SetCommittee>,
+        lottery_id: u32,
+        members: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+        let committee = &mut ctx.accounts.committee; // Get a mutable reference to the committee account
+
+        if lottery.end_time == 0 || lottery.draw_epoch.is_some() {
+            return err!(LotteryError::CommitteeNeedsEndTime);
+        }
+        validation::validate_committee(&members, threshold)?;
+
+        committee.lottery = lottery.key();
+        committee.threshold = threshold;
+        committee.commitments = vec![None; members.len()];
+        committee.shares = vec![None; members.len()];
+        committee.members = members;
+        committee.reveal_slot = 0;
+
+        // From now on the lottery is only drawn with `finalize_draw`
+        lottery.committee = true;
+
+        // Log the committee
+        msg!(
+            "Lottery id {} drawn by a {}-of-{} committee",
+            lottery_id,
+            threshold,
+            committee.members.len()
+        );
+
+        emit!(CommitteeSet {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id,
+            members: committee.members.clone(),
+            threshold,
+        });
+
+        Ok(())
+    }
+
+    // Function for a committee member to commit to its share, the SHA-256 hash of a 32 byte secret it keeps to itself
+    // Commitments are only taken while the lottery sells tickets, so every share is fixed before the draw can start
+    pub fn commit_share(
+        ctx: Context<SubmitShare>,
+        lottery_id: u32,
+        commitment: [u8; 32],
+    ) -> Result<()> {
+        let committee = &mut ctx.accounts.committee; // Get a mutable reference to the committee account
+        let member = ctx.accounts.member.key();
+
+        let clock = time::clock(&ctx.accounts.master)?;
+        validation::validate_sale_open(&ctx.accounts.lottery, &clock)?;
+
+        let index = committee
+            .member_index(&member)
+            .ok_or(LotteryError::NotCommitteeMember)?;
+        if committee.commitments[index].is_some() {
+            return err!(LotteryError::ShareAlreadyCommitted);
+        }
+        committee.commitments[index] = Some(commitment);
+
+        emit!(ShareCommitted {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id,
+            member,
+            commitment,
+        });
+
+        Ok(())
+    }
+
+    // Function for a committee member to reveal the share it committed to, once the end time has passed
+    // The first reveal opens a window of COMMITTEE_REVEAL_SLOTS for the other members; shares revealed after it
+    // are rejected, so the slot hash `finalize_draw` mixes in can't be known to anyone still deciding whether to reveal
+    pub fn reveal_share(ctx: Context<SubmitShare>, lottery_id: u32, share: [u8; 32]) -> Result<()> {
+        let committee = &mut ctx.accounts.committee; // Get a mutable reference to the committee account
+        let member = ctx.accounts.member.key();
+
+        // Shares are revealed once sales have ended
+        let clock = time::clock(&ctx.accounts.master)?;
+        if clock.unix_timestamp < ctx.accounts.lottery.end_time {
+            return err!(LotteryError::DrawBeforeEndTime);
+        }
+
+        let index = committee
+            .member_index(&member)
+            .ok_or(LotteryError::NotCommitteeMember)?;
+        let commitment = committee.commitments[index].ok_or(LotteryError::ShareNotCommitted)?;
+        if hash(&share).to_bytes() != commitment {
+            return err!(LotteryError::RevealMismatch);
+        }
+        if committee.shares[index].is_some() {
+            return err!(LotteryError::ShareAlreadyRevealed);
+        }
+
+        if committee.reveal_slot == 0 {
+            committee.reveal_slot = clock.slot;
+        } else if clock.slot >= committee.entropy_slot() {
+            return err!(LotteryError::RevealWindowClosed);
+        }
+        committee.shares[index] = Some(share);
+
+        emit!(ShareRevealed {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id,
+            member,
+            share,
+            entropy_slot: committee.entropy_slot(),
+        });
+
+        Ok(())
+    }
+
+    // Function for anyone to draw a committee lottery once its reveal window has closed
+    // Mixes every revealed share with the hash of the first slot after the window, see `draw::committee_seed`.
+    // That hash stays in the SlotHashes sysvar for about 512 slots; a committee lottery not finalized in time, or whose
+    // members never reach the threshold, is cancelled by the admin with `force_resolve` so its tickets are refunded
+    // Like a permissionless `pick_winner`, it succeeds with a status when the draw isn't due or was already drawn
+    pub fn finalize_draw(ctx: Context<FinalizeDraw>, _lottery_id: u32) -> Result<CrankStatus> {
+        let clock = time::clock(&ctx.accounts.draw.master)?;
+        if let Some((_, status)) = ctx.accounts.draw.not_drawable(&clock) {
+            return Ok(status);
+        }
+
+        let committee = &ctx.accounts.committee;
+        let shares: Vec<[u8; 32]> = committee.shares.iter().flatten().copied().collect();
+        if shares.len() < usize::from(committee.threshold) {
+            return err!(LotteryError::CommitteeThresholdNotMet);
+        }
+
+        // The slot hash mixed in must exist, so the window has to be over
+        let entropy_slot = committee.entropy_slot();
+        if clock.slot <= entropy_slot {
+            return Ok(CrankStatus::NotDue);
+        }
+        let entropy = draw::slot_hash_at_or_after(&ctx.accounts.draw.slot_hashes, entropy_slot)?;
+
+        let lottery = &ctx.accounts.draw.lottery;
+        let seed = draw::committee_seed(&shares, entropy, &lottery.key(), lottery.last_ticket_id);
+
+        ctx.accounts.draw.draw(seed, &clock)?;
+        Ok(CrankStatus::Done)
+    }
+
+    // Function to redraw a lottery whose drawn ticket can't win, see `Lottery::can_win`: it was refunded within its
+    // grace period (see `refund_ticket`), or it is a code ticket whose code can no longer be redeemed
+    // Anyone can call it. The new seed is the hash of the previous one, see `draw::redraw_seed`, so nobody can steer
+    // the redraw and anyone can follow it from the original draw; it is repeated until a ticket that can win is drawn.
+    // The draw bounty isn't paid again, and the claim window starts over for the new winner. A match mode lottery
+    // only falls back to its drawn ticket when none matched, and counts its tickets again for the new seed
+    pub fn redraw_void_winner(ctx: Context<RedrawVoidWinner>, lottery_id: u32) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+        let void_id = ctx.accounts.winning_ticket.id;
+
+        let seed = draw::redraw_seed(lottery.draw_seed);
+        let winner_id = draw::uniform_index(seed, lottery.last_ticket_id);
+        lottery.winner_id = Some(winner_id);
+        lottery.draw_seed = seed;
+        lottery.drawn_at = time::now(&ctx.accounts.master)?;
+        lottery.redraws = lottery.redraws.saturating_add(1);
+        lottery.match_cursor = 0;
+        lottery.match_count = 0;
+
+        // Log the redraw
+        msg!(
+            "Lottery {} redrawn: ticket {} can't win, ticket {} wins",
+            lottery_id,
+            void_id,
+            winner_id
+        );
+
+        emit!(WinnerRedrawn {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id,
+            void_id,
+            winner_id,
+            seed,
+        });
+
+        Ok(())
+    }
+
+    // Function to count the tickets of a drawn match mode lottery that match the draw seed, see `set_match_bits`
+    // Anyone can call it. The ticket accounts are passed as remaining accounts in ticket ID order, starting at the
+    // lottery's `match_cursor`, at most MAX_TICKETS_PER_COUNT per call. Matching tickets can claim once every ticket
+    // is counted, so keepers call it right after the draw until it returns `AlreadyDone`
+    pub fn count_matches<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CountMatches<'info>>,
+        lottery_id: u32,
+    ) -> Result<CrankStatus> {
+        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+        let now = time::now(&ctx.accounts.master)?;
+
+        if lottery.match_bits == 0 {
+            return err!(LotteryError::NotMatchMode);
+        }
+        if lottery.winner_id.is_none() {
+            return Ok(CrankStatus::NotDue);
+        }
+        // Once refunds open nobody claims a share any more
+        if lottery.cancelled
+            || lottery.matches_counted()
+            || lottery.recovery_open(now, ctx.accounts.master.recovery_period)
+        {
+            return Ok(CrankStatus::AlreadyDone);
+        }
+
+        let accounts = ctx.remaining_accounts;
+        if accounts.len() > MAX_TICKETS_PER_COUNT {
+            return err!(LotteryError::TooManyAccounts);
+        }
+        if accounts.is_empty()
+            || accounts.len() > (lottery.last_ticket_id - lottery.match_cursor) as usize
+        {
+            return err!(LotteryError::TicketAccountMismatch);
+        }
+
+        let lottery_key = lottery.key();
+        for account in accounts {
+            // Anchor can't derive the addresses for remaining accounts, so the seeds are checked here; each ID has
+            // one address, so no ticket is counted twice
+            let id_bytes = lottery.match_cursor.to_le_bytes();
+            let [prefix, lottery_seed, id_seed] = pdas::ticket_seeds(&lottery_key, &id_bytes);
+            let (address, _) =
+                Pubkey::find_program_address(&[prefix, lottery_seed, id_seed], &crate::ID);
+            if account.key() != address {
+                return err!(LotteryError::TicketAccountMismatch);
+            }
+
+            let ticket = Account::<Ticket>::try_from(account)?;
+            if lottery.can_win(&ticket)
+                && draw::prefix_matches(&ticket.art_seed, &lottery.draw_seed, lottery.match_bits)
+            {
+                lottery.match_count += 1;
+            }
+            lottery.match_cursor += 1;
+        }
+
+        if lottery.matches_counted() {
+            // Log the count
+            msg!(
+                "Lottery {} has {} tickets matching its draw",
+                lottery_id,
+                lottery.match_count
+            );
+
+            emit!(MatchesCounted {
+                seq: ctx.accounts.master.next_seq(),
+                lottery_id,
+                match_bits: lottery.match_bits,
+                match_count: lottery.match_count,
+            });
+        }
+
+        Ok(CrankStatus::Done)
+    }
+
+    // Function to announce a drawn winner to notification services
+    // Anyone can call it once the draw happened; it emits the winner's registered notification key, if any
+    // This is a separate step because the winning ticket (and so its owner's profile) is only known after the draw
+    pub fn announce_winner(ctx: Context<AnnounceWinner>, _lottery_id: u32) -> Result<()> {
+        let ticket = &ctx.accounts.winning_ticket; // Get a reference to the winning ticket account
+
+        emit!(WinnerAnnounced {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id: ticket.lottery_id,
+            winner_id: ticket.id,
+            winner: ticket.authority,
+            notification_key: ctx
+                .accounts
+                .profile
+                .as_ref()
+                .and_then(|profile| profile.notification_key),
+        });
+
+        Ok(())
+    }
+
+    // Function for a ticket owner to challenge a draw by posting a bond
+    // Allowed within DISPUTE_WINDOW seconds of the draw while the prize is unclaimed; freezes claims until the admin
+    // rules on it with `resolve_dispute`
+    pub fn challenge_draw(
+        ctx: Context<ChallengeDraw>,
+        _lottery_id: u32,
+        _ticket_id: u32,
+    ) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+        let challenger = &ctx.accounts.challenger; // Get a reference to the challenging ticket owner
+        let now = time::now(&ctx.accounts.master)?;
+
+        if now > lottery.drawn_at + DISPUTE_WINDOW {
+            return err!(LotteryError::DisputeWindowClosed);
+        }
+
+        // Escrow the bond in the dispute account until the ruling
+        invoke(
+            &transfer(&challenger.key(), &ctx.accounts.dispute.key(), DISPUTE_BOND),
+            &[
+                challenger.to_account_info(),
+                ctx.accounts.dispute.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        let dispute = &mut ctx.accounts.dispute;
+        dispute.lottery = lottery.key();
+        dispute.challenger = challenger.key();
+        dispute.bond = DISPUTE_BOND;
+        dispute.opened_at = now;
+
+        // Freeze claims on the lottery
+        lottery.disputed = true;
+
+        // Log the challenge
+        msg!(
+            "{} challenged the draw of lottery id {}",
+            challenger.key(),
+            lottery.id
+        );
+
+        emit!(DrawChallenged {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id: lottery.id,
+            challenger: challenger.key(),
+            bond: DISPUTE_BOND,
+        });
+
+        Ok(())
+    }
+
+    // Function for the admin to rule on a challenged draw
+    // An upheld challenge returns the bond and voids the draw so the lottery can be drawn again;
+    // a rejected one slashes the bond to the admin. Either way the dispute account is closed and claims are unfrozen
+    pub fn resolve_dispute(
+        ctx: Context<ResolveDispute>,
+        _lottery_id: u32,
+        upheld: bool,
+    ) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+        let dispute = &ctx.accounts.dispute; // Get a reference to the dispute account
+
+        if upheld && (lottery.draw_epoch.is_some() || lottery.committee) {
+            // An epoch-locked or committee draw can't be repeated: its slot hash has left the SlotHashes sysvar by now,
+            // and any other entropy would break the lock (or let the authority replace the committee's shares). Cancel
+            // the lottery instead, so ticket owners recover their funds; the jackpot stays owed for the refunds
+            lottery.cancelled = true;
+        } else if upheld {
+            // Void the draw so it can be repeated; sales stay closed, as the voided result is public
+            lottery.winner_id = None;
+            lottery.drawn_at = 0;
+            lottery.draw_seed = [0; 32];
+            lottery.match_cursor = 0; // A match mode redraw is counted afresh
+            lottery.match_count = 0;
+            ctx.accounts.creator.open_lotteries += 1;
+
+            // The redraw records the jackpot as owed again
+            if lottery.holds_lamports() {
+                ctx.accounts
+                    .master
+                    .release_pot(lottery.jackpot().ok_or(LotteryError::PotOverflow)?);
+            }
+        } else {
+            // Slash the bond; the dispute account's rent still goes back to the challenger when it is closed
+            **dispute.to_account_info().try_borrow_mut_lamports()? -= dispute.bond;
+            **ctx.accounts.authority.try_borrow_mut_lamports()? += dispute.bond;
+        }
+
+        lottery.disputed = false;
+
+        // A voided commit-reveal draw commits again, as the revealed secret is public now
+        if upheld {
+            lottery.commitment = None;
+        }
+
+        // Keep an on-chain trail of the ruling
+        ctx.accounts.audit_log.record(
+            ctx.accounts.authority.key(),
+            AUDIT_RESOLVE_DISPUTE,
+            lottery.id as u64,
+            time::now(&ctx.accounts.master)?,
+        );
+
+        // Log the ruling
+        msg!("Dispute on lottery id {} upheld: {}", lottery.id, upheld);
+
+        emit!(DisputeResolved {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id: lottery.id,
+            challenger: dispute.challenger,
+            upheld,
+        });
+
+        Ok(())
+    }
+
+    // Function for the admin to start the timelock on force-resolving a stuck lottery, see `force_resolve`
+    // Scheduling is public through its event and the audit log, so ticket owners see it coming
+    pub fn schedule_force_resolve(ctx: Context<ForceResolve>, _lottery_id: u32) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+        let now = time::now(&ctx.accounts.master)?;
+
+        lottery.force_resolve_at = now.saturating_add(FORCE_RESOLVE_DELAY);
+
+        // Keep an on-chain trail of the admin action
+        ctx.accounts.audit_log.record(
+            ctx.accounts.authority.key(),
+            AUDIT_SCHEDULE_FORCE_RESOLVE,
+            lottery.id as u64,
+            now,
+        );
+
+        // Log the schedule
+        msg!(
+            "Lottery id {} can be force-resolved from {}",
+            lottery.id,
+            lottery.force_resolve_at
+        );
+
+        emit!(ForceResolveScheduled {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id: lottery.id,
+            executable_at: lottery.force_resolve_at,
+        });
+
+        Ok(())
+    }
+
+    // Function for the admin to cancel a lottery that is stuck, e.g. because its draw can't complete,
+    // once the timelock started by `schedule_force_resolve` has passed. Ticket owners then get their
+    // share of the jackpot back with `recover_funds`, without waiting for the recovery period
+    pub fn force_resolve(ctx: Context<ForceResolve>, _lottery_id: u32) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+        let now = time::now(&ctx.accounts.master)?;
+
+        if lottery.force_resolve_at == 0 || now < lottery.force_resolve_at {
+            return err!(LotteryError::ForceResolveNotReady);
+        }
+
+        // A pending challenge is settled by `resolve_dispute` first
+        if lottery.disputed {
+            return err!(LotteryError::DrawDisputed);
+        }
+
+        if lottery.winner_id.is_some() {
+            // The pot of a drawn right lottery goes to the authority, so there is nothing to refund
+            if lottery.prize_kind == PrizeKind::Right {
+                return err!(LotteryError::WrongPrizeKind);
+            }
+        } else {
+            // The lottery no longer counts as open, and the refunds are owed like a drawn pot is
+            ctx.accounts.creator.open_lotteries =
+                ctx.accounts.creator.open_lotteries.saturating_sub(1);
+            if lottery.payment_mint.is_none() {
+                ctx.accounts
+                    .master
+                    .record_pot(lottery.jackpot().ok_or(LotteryError::PotOverflow)?, now);
+            }
+        }
+
+        lottery.cancelled = true;
+
+        // Keep an on-chain trail of the admin action
+        ctx.accounts.audit_log.record(
+            ctx.accounts.authority.key(),
+            AUDIT_FORCE_RESOLVE,
+            lottery.id as u64,
+            now,
+        );
+
+        // Log the cancellation
+        msg!("Lottery id {} force-resolved as cancelled", lottery.id);
+
+        emit!(LotteryForceResolved {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id: lottery.id,
+            drawn: lottery.winner_id.is_some(),
+        });
+
+        Ok(())
+    }
+
+    // Function for a lottery's authority to call it off before the winner is drawn
+    // The lottery stops selling tickets and can't be drawn anymore; every ticket owner gets their ticket back with
+    // `claim_refund` (or `recover_funds`, which keeps the ticket), like after `force_resolve`. Not available once the
+    // draw is committed or an epoch-locked lottery's epoch is over, see below
+    pub fn cancel_lottery(ctx: Context<CancelLottery>, _lottery_id: u32) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+        let clock = time::clock(&ctx.accounts.master)?;
+        let now = clock.unix_timestamp;
+
+        // Once the draw's entropy exists the authority could work out the winner, so cancelling would let them
+        // abort draws they don't like: a committed draw's reveal slot is ruled out by the constraints, and an
+        // epoch-locked draw's slot hash exists once its epoch is over
+        if let Some(draw_epoch) = lottery.draw_epoch {
+            if clock.epoch > draw_epoch {
+                return err!(LotteryError::DrawEpochPassed);
+            }
+        }
+        // A committee draw's shares are revealed from the end time on, and once the reveal window closes the shares
+        // and the slot hash `finalize_draw` mixes in are all public. Past the end time only the admin's timelocked
+        // `force_resolve` cancels a committee lottery that can't be finalized
+        if lottery.committee && now >= lottery.end_time {
+            return err!(LotteryError::CommitteeRevealStarted);
+        }
+
+        // The lottery no longer counts as open, and the refunds are owed like a drawn pot is
+        ctx.accounts.creator.open_lotteries = ctx.accounts.creator.open_lotteries.saturating_sub(1);
+        if lottery.payment_mint.is_none() {
+            ctx.accounts
+                .master
+                .record_pot(lottery.jackpot().ok_or(LotteryError::PotOverflow)?, now);
+        }
+
+        lottery.cancelled = true;
+
+        // Log the cancellation
+        msg!("Lottery id {} cancelled", lottery.id);
+
+        emit!(LotteryCancelled {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id: lottery.id,
+        });
+
+        Ok(())
+    }
+
+    // Function for a ticket owner to get their ticket's share of a cancelled lottery back
+    // Pays the same share as `recover_funds` and closes the ticket account, returning its rent to the owner as well
+    // Returns `ClaimOutcome::Paused`, leaving the ticket open, when the refund trips the circuit breaker
+    pub fn claim_refund(
+        ctx: Context<ClaimRefund>,
+        _lottery_id: u32,
+        _ticket_id: u32,
+    ) -> Result<ClaimOutcome> {
+        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+        let ticket = &ctx.accounts.ticket; // Get a reference to the ticket account
+        let owner = &ctx.accounts.authority; // Get a reference to the ticket owner
+        let now = time::now(&ctx.accounts.master)?;
+
+        // A ticket refunded with `recover_funds` has a receipt, and is paid out at most once
+        if !ctx.accounts.receipt.data_is_empty() {
+            return err!(LotteryError::AlreadyClaimed);
+        }
+
+        let share = lottery
+            .recovery_share(ticket.paid)
+            .ok_or(LotteryError::PotOverflow)?;
+
+        // Hold the refund back if it trips the circuit breaker, like `recover_funds` does; the ticket stays open so
+        // it can be refunded once the admin resumes payouts
+        let master = &mut ctx.accounts.master;
+        if master.claims_paused {
+            return err!(LotteryError::ClaimsPaused);
+        }
+        if lottery.payment_mint.is_none() && !master.record_payout(share, now) {
+            emit!(PayoutsPaused {
+                seq: master.next_seq(),
+                lottery_id: lottery.id,
+                amount: share,
+                window_payouts: master.window_payouts,
+                window_budget: master.window_budget,
+            });
+            return Ok(ClaimOutcome::Paused);
+        }
+        if lottery.payment_mint.is_some() {
+            token::withdraw(
+                lottery,
+                ctx.bumps.lottery,
+                ctx.accounts.vault.as_ref(),
+                ctx.accounts.owner_token_account.as_ref(),
+                &owner.key(),
+                ctx.accounts.token_program.as_ref(),
+                share,
+            )?;
+        } else {
+            **lottery.to_account_info().try_borrow_mut_lamports()? -= share;
+            **owner.to_account_info().try_borrow_mut_lamports()? += share;
+        }
+
+        // Log the refund
+        msg!(
+            "{} was refunded {} lamports from lottery id {} with ticket id {}",
+            owner.key(),
+            share,
+            lottery.id,
+            ticket.id
+        );
+
+        emit!(FundsRecovered {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id: lottery.id,
+            ticket_id: ticket.id,
+            owner: owner.key(),
+            amount: share,
+        });
+
+        // Close the ticket account, so the refund can't be repeated
+        ctx.accounts
+            .ticket
+            .close(ctx.accounts.authority.to_account_info())?;
+
+        Ok(ClaimOutcome::Paid)
+    }
+
+    // Function for anyone to refund a batch of a cancelled lottery's tickets in one transaction
+    // The tickets are passed as remaining accounts, one (ticket, receipt, owner) triple each, in strictly increasing
+    // ticket address order and at most MAX_REFUNDS_PER_BATCH per call; `app/refunds.ts` builds the batches. Each ticket
+    // is refunded and closed like `claim_refund` would, paying its share and rent to the owner. Tickets already paid
+    // out by `recover_funds`, or already refunded and closed, are skipped, so a batch can be sent again. Token
+    // lotteries refund with `claim_refund`, which takes the token accounts. Returns `CrankStatus::AlreadyDone` when
+    // no ticket was left to refund, and `CrankStatus::Paused` when payouts are paused before the batch is through
+    pub fn refund_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RefundBatch<'info>>,
+        lottery_id: u32,
+    ) -> Result<CrankStatus> {
+        // Get references to the accounts involved
+        let lottery = &mut ctx.accounts.lottery;
+        let master = &mut ctx.accounts.master;
+        let now = time::now(master)?;
+
+        if lottery.payment_mint.is_some() {
+            return err!(LotteryError::UnsupportedForTokenLottery);
+        }
+        if master.claims_paused {
+            return Ok(CrankStatus::Paused);
+        }
+
+        // The tickets go through the batch checks, so none of them can be refunded twice in one call
+        let accounts = ctx.remaining_accounts;
+        if !accounts.len().is_multiple_of(3) {
+            return err!(LotteryError::RefundAccountMismatch);
+        }
+        let tickets: Vec<AccountInfo> = accounts.iter().step_by(3).cloned().collect();
+        batch::validate_batch(&tickets, MAX_REFUNDS_PER_BATCH)?;
+
+        let lottery_key = lottery.key();
+        let mut refunded = 0;
+        for refund in accounts.chunks_exact(3) {
+            let [ticket_info, receipt, owner] = refund else {
+                return err!(LotteryError::RefundAccountMismatch);
+            };
+
+            // A closed ticket was refunded already; whatever address was passed, it holds nothing to pay out
+            if ticket_info.data_is_empty() {
+                continue;
+            }
+            let ticket = Account::<Ticket>::try_from(ticket_info)?;
+
+            // Anchor can't derive the addresses for remaining accounts, so the seeds are checked here
+            let id_bytes = ticket.id.to_le_bytes();
+            let [prefix, lottery_seed, id_seed] = pdas::ticket_seeds(&lottery_key, &id_bytes);
+            let (ticket_address, _) =
+                Pubkey::find_program_address(&[prefix, lottery_seed, id_seed], &crate::ID);
+            let (receipt_address, _) = Pubkey::find_program_address(
+                &pdas::receipt_seeds(&lottery_key, ticket_info.key),
+                &crate::ID,
+            );
+            if ticket_info.key() != ticket_address
+                || receipt.key() != receipt_address
+                || owner.key() != ticket.authority
+            {
+                return err!(LotteryError::RefundAccountMismatch);
+            }
+
+            // A ticket refunded with `recover_funds` has a receipt, and is paid out at most once
+            if !receipt.data_is_empty() {
+                continue;
+            }
+
+            let share = lottery
+                .recovery_share(ticket.paid)
+                .ok_or(LotteryError::PotOverflow)?;
+
+            // Stop at the circuit breaker like `claim_refund`; the rest of the batch stays open for later
+            if !master.record_payout(share, now) {
+                emit!(PayoutsPaused {
+                    seq: master.next_seq(),
+                    lottery_id,
+                    amount: share,
+                    window_payouts: master.window_payouts,
+                    window_budget: master.window_budget,
+                });
+                return Ok(CrankStatus::Paused);
+            }
+            **lottery.to_account_info().try_borrow_mut_lamports()? -= share;
+            **owner.try_borrow_mut_lamports()? += share;
+
+            emit!(FundsRecovered {
+                seq: master.next_seq(),
+                lottery_id,
+                ticket_id: ticket.id,
+                owner: owner.key(),
+                amount: share,
+            });
+
+            // Close the ticket account, so the refund can't be repeated
+            ticket.close(owner.clone())?;
+            refunded += 1;
+        }
+
+        // Log the batch
+        msg!("Refunded {} tickets of lottery id {}", refunded, lottery_id);
+
+        Ok(if refunded > 0 {
+            CrankStatus::Done
+        } else {
+            CrankStatus::AlreadyDone
+        })
+    }
+
+    // Function to create the global leaderboard account; anyone can pay for it
+    pub fn init_leaderboard(ctx: Context<InitLeaderboard>) -> Result<()> {
+        ctx.accounts.leaderboard.load_init()?; // Start with empty boards
+        Ok(())
+    }
+
+    // Function for the treasury's accounting to read the protocol fees of periods `first` to `last` via return data
+    // Periods are FEE_PERIOD long and numbered from the unix epoch; only the last FEE_LEDGER_PERIODS are kept, and
+    // periods without claims are left out of the report
+    pub fn get_fee_report(ctx: Context<GetFeeReport>, first: u32, last: u32) -> Result<FeeReport> {
+        if last < first || last - first >= FEE_LEDGER_PERIODS as u32 {
+            return err!(LotteryError::InvalidFeeReportRange);
+        }
+
+        let mut report = FeeReport::default();
+        for period in (first..=last).filter_map(|period| ctx.accounts.fee_ledger.get(period)) {
+            report.lamports = report.lamports.saturating_add(period.lamports);
+            report.claims = report.claims.saturating_add(period.claims);
+            report.token_claims = report.token_claims.saturating_add(period.token_claims);
+            report.periods.push(*period);
+        }
+
+        Ok(report)
+    }
+
+    // Function to read the leaderboards via return data
+    pub fn get_leaderboard(ctx: Context<GetLeaderboard>) -> Result<LeaderboardView> {
+        let leaderboard = ctx.accounts.leaderboard.load()?;
+
+        // Only return the slots that are in use
+        let in_use = |board: &[LeaderboardEntry]| {
+            board
+                .iter()
+                .filter(|entry| entry.value > 0)
+                .map(|entry| LeaderboardRanking {
+                    player: entry.player,
+                    value: entry.value,
+                })
+                .collect()
+        };
+
+        Ok(LeaderboardView {
+            top_winners: in_use(&leaderboard.top_winners),
+            top_buyers: in_use(&leaderboard.top_buyers),
+        })
+    }
+
+    // Function to aggregate a creator's lotteries via return data
+    // The lotteries are passed as a batch of remaining accounts (see `batch`), at most MAX_DASHBOARD_LOTTERIES per call;
+    // `app/dashboard.ts` aggregates every lottery off-chain with the same semantics
+    pub fn get_creator_dashboard<'info>(
+        ctx: Context<'_, '_, 'info, 'info, GetCreatorDashboard>,
+        creator: Pubkey,
+    ) -> Result<CreatorDashboard> {
+        // Counting a lottery twice would inflate the totals, which the batch checks rule out
+        let lotteries =
+            batch::load_batch::<Lottery>(ctx.remaining_accounts, MAX_DASHBOARD_LOTTERIES)?;
+
+        let mut dashboard = CreatorDashboard::default();
+        for lottery in lotteries {
+            if lottery.authority != creator {
+                return err!(LotteryError::LotteryCreatorMismatch);
+            }
+            dashboard.add(&lottery)?;
+        }
+
+        Ok(dashboard)
+    }
+
+    // Function for keepers and dashboards to check many lotteries for lifecycle problems via return data
+    // The lotteries are passed as a batch of remaining accounts (see `batch`), at most MAX_HEALTH_CHECK_LOTTERIES per call;
+    // only lotteries with at least one warning are returned, see the HEALTH_* constants
+    pub fn check_lottery_health<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CheckLotteryHealth<'info>>,
+    ) -> Result<Vec<LotteryHealth>> {
+        let lotteries =
+            batch::load_batch::<Lottery>(ctx.remaining_accounts, MAX_HEALTH_CHECK_LOTTERIES)?;
+
+        let clock = time::clock(&ctx.accounts.master)?;
+        let rent = Rent::get()?;
+        let mut unhealthy = Vec::new();
+        for lottery in lotteries {
+            let info = lottery.to_account_info();
+            let warnings = LotteryHealth::warnings(
+                &lottery,
+                info.lamports()
+                    .saturating_sub(rent.minimum_balance(info.data_len())),
+                &clock,
+                ctx.accounts.master.recovery_period,
+            );
+            if warnings != 0 {
+                unhealthy.push(LotteryHealth {
+                    lottery_id: lottery.id,
+                    warnings,
+                });
+            }
+        }
+
+        Ok(unhealthy)
+    }
+
+    // Function for a player to register (or clear) the key notification services should use to reach them
+    // e.g. the hash of a dialect or other dApp messaging address
+    pub fn set_notification_key(
+        ctx: Context<SetNotificationKey>,
+        notification_key: Option<[u8; 32]>,
+    ) -> Result<()> {
+        let profile = &mut ctx.accounts.profile; // Get a mutable reference to the player's profile
+
+        profile.owner = ctx.accounts.owner.key();
+        profile.notification_key = notification_key;
+
+        Ok(())
+    }
+
+    // Function for a player to record a self-attestation on their profile, e.g. that they are of legal age
+    // `claims` is a bitfield of the ATTEST_* constants and `jurisdiction` an ISO 3166-1 alpha-2 country code;
+    // the player signs the transaction, so the profile keeps an audit trail of what they attested and when
+    // Uses the same accounts as `set_notification_key`
+    pub fn attest(
+        ctx: Context<SetNotificationKey>,
+        claims: u8,
+        jurisdiction: [u8; 2],
+    ) -> Result<()> {
+        let profile = &mut ctx.accounts.profile; // Get a mutable reference to the player's profile
+
+        validation::validate_attestation(claims, jurisdiction)?;
+
+        profile.owner = ctx.accounts.owner.key();
+        profile.attestation = Some(Attestation {
+            claims,
+            jurisdiction,
+            attested_at: time::now(&ctx.accounts.master)?,
+        });
+
+        // Log the attestation
+        msg!(
+            "{} attested {:#b} in {}",
+            profile.owner,
+            claims,
+            String::from_utf8_lossy(&jurisdiction)
+        );
+
+        Ok(())
+    }
+
+    // Legacy name of `claim_prize`, kept working for existing integrators
+    // Emits `DeprecatedInstructionUsed` on every call and fails once the admin turns off FEATURE_LEGACY_INSTRUCTIONS
+    pub fn claim_price(
+        ctx: Context<ClaimPrice>,
+        lottery_id: u32,
+        ticket_id: u32,
+    ) -> Result<ClaimOutcome> {
+        ctx.accounts
+            .master
+            .use_legacy_instruction("claim_price", "claim_prize")?;
+
+        claim_prize(ctx, lottery_id, ticket_id)
+    }
+
+    // Function for the winner to claim the prize
+    // In match mode every matching ticket claims its share this way, see `set_match_bits`. Returns
+    // `ClaimOutcome::Paused` when the payout trips the circuit breaker and nothing was paid
+    pub fn claim_prize(
+        ctx: Context<ClaimPrice>,
+        _lottery_id: u32,
+        _ticket_id: u32,
+    ) -> Result<ClaimOutcome> {
+        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+        let ticket = &mut ctx.accounts.ticket; // Get a mutable reference to the ticket account
+        let winner = &mut ctx.accounts.authority; // Get a mutable reference to the winner's account, which is paid even when a delegate claims
+
+        // Rights are claimed with `claim_right` instead
+        if lottery.prize_kind != PrizeKind::Lamports {
+            return err!(LotteryError::WrongPrizeKind);
+        }
+
+        // Check that the ticket is the unclaimed winner and still within the claim window
+        validation::validate_claim(
+            lottery,
+            ticket,
+            time::now(&ctx.accounts.master)?,
+            ctx.accounts.master.recovery_period,
+        )?;
+
+        // Split the jackpot into the winner's price (or this ticket's share of it) and what rolls over, then the
+        // protocol fee off the price
+        let (price, rollover) = lottery.claim_split().ok_or(LotteryError::PotOverflow)?;
+        let fee_bps = ctx.accounts.config.fee_bps_for(&ctx.accounts.creator);
+        let (fee, price) = math::split_bps(price, fee_bps).ok_or(LotteryError::PotOverflow)?;
+
+        // Hold the payout back if it trips the circuit breaker; the pause has to be stored, so this succeeds without
+        // paying and says so with `ClaimOutcome::Paused`, and the receipt is closed again so the prize can still be
+        // claimed once the admin resumes payouts
+        let master = &mut ctx.accounts.master;
+        if master.claims_paused {
+            return err!(LotteryError::ClaimsPaused);
+        }
+        let now = time::now(master)?;
+        if lottery.holds_lamports() && !master.record_payout(price + fee + rollover, now) {
+            emit!(PayoutsPaused {
+                seq: master.next_seq(),
+                lottery_id: lottery.id,
+                amount: price + fee + rollover,
+                window_payouts: master.window_payouts,
+                window_budget: master.window_budget,
+            });
+            ctx.accounts
+                .receipt
+                .close(ctx.accounts.claimer.to_account_info())?;
+            return Ok(ClaimOutcome::Paused);
+        }
+
+        if lottery.payment_mint.is_some() {
+            // Transfer the price in tokens from the lottery's vault to the winner's token account
+            token::withdraw(
+                lottery,
+                ctx.bumps.lottery,
+                ctx.accounts.vault.as_ref(),
+                ctx.accounts.winner_token_account.as_ref(),
+                &winner.key(),
+                ctx.accounts.token_program.as_ref(),
+                price,
+            )?;
+            // Transfer the fee in tokens to the treasury's token account
+            if fee > 0 {
+                token::withdraw(
+                    lottery,
+                    ctx.bumps.lottery,
+                    ctx.accounts.vault.as_ref(),
+                    ctx.accounts.treasury_token_account.as_ref(),
+                    &ctx.accounts.config.treasury,
+                    ctx.accounts.token_program.as_ref(),
+                    fee,
+                )?;
+            }
+        } else {
+            // Transfer the price amount from the lottery account to the winner's account, and the fee to the treasury
+            **lottery.to_account_info().try_borrow_mut_lamports()? -= price + fee;
+            **winner.to_account_info().try_borrow_mut_lamports()? += price;
+            **ctx.accounts.treasury.try_borrow_mut_lamports()? += fee;
+        }
+
+        // Park the rest with the creator until their next lottery picks it up
+        if rollover > 0 {
+            let creator = &mut ctx.accounts.creator;
+            **lottery.to_account_info().try_borrow_mut_lamports()? -= rollover;
+            **creator.to_account_info().try_borrow_mut_lamports()? += rollover;
+            creator.rollover += rollover;
+        }
+
+        // Mark the price as claimed, once every matching ticket has claimed its share in match mode
+        if lottery.match_count > 0 {
+            lottery.matches_claimed += 1;
+            lottery.claimed = lottery.matches_claimed == lottery.match_count;
+        } else {
+            lottery.claimed = true;
+        }
+
+        // Count the fee towards the current period for the treasury's accounting
+        ctx.accounts
+            .fee_ledger
+            .record(now, fee, lottery.payment_mint.is_some());
+
+        // Season and winner stats are in lamports, so token prizes, counted in their mint's base units, stay out of them
+        if lottery.payment_mint.is_none() {
+            // Count the payout towards the current season
+            let season = &mut ctx.accounts.season;
+            season.payouts = season.payouts.saturating_add(price);
+
+            // Track the winner's stats when they passed their profile, and rank them if the leaderboard was passed too
+            if let Some(profile) = ctx.accounts.profile.as_mut() {
+                profile.total_won = profile.total_won.saturating_add(price);
+                if let Some(leaderboard) = &ctx.accounts.leaderboard {
+                    leaderboard
+                        .load_mut()?
+                        .record_winner(profile.owner, profile.total_won);
+                }
+            }
+        }
+
+        // Record the payout in a receipt; the receipt account can only be created once, so the claim can't be replayed
+        let receipt = &mut ctx.accounts.receipt;
+        receipt.lottery_id = lottery.id;
+        receipt.ticket_id = ticket.id;
+        receipt.winner = winner.key();
+        receipt.amount = price;
+        receipt.claimed_at = time::now(&ctx.accounts.master)?;
+
+        emit!(PrizeClaimed {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id: receipt.lottery_id,
+            ticket_id: receipt.ticket_id,
+            winner: receipt.winner,
+            amount: receipt.amount,
+            rolled_over: rollover,
+            fee,
+        });
+
+        // Log the price claim
+        msg!(
+            "{} claimed {} lamports from lottery id {} with ticket id {}",
+            winner.key(),
+            price,
+            lottery.id,
+            ticket.id
+        );
+
+        Ok(ClaimOutcome::Paid)
+    }
+
+    // Function for the winner of a right lottery to claim their right
+    // Writes the winner into a `PrizeRight` account that other programs (mint allowlists, access gates) can read;
+    // the ticket sales go to the lottery authority with `withdraw_proceeds`
+    pub fn claim_right(ctx: Context<ClaimRight>, _lottery_id: u32, _ticket_id: u32) -> Result<()> {
+        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+        let ticket = &ctx.accounts.ticket; // Get a reference to the ticket account
+        let now = time::now(&ctx.accounts.master)?;
+
+        // Lamport prizes are claimed with `claim_prize` instead
+        if lottery.prize_kind != PrizeKind::Right {
+            return err!(LotteryError::WrongPrizeKind);
+        }
+
+        // Check that the ticket is the unclaimed winner and still within the claim window
+        validation::validate_claim(lottery, ticket, now, ctx.accounts.master.recovery_period)?;
+
+        // Mark the right as claimed
+        lottery.claimed = true;
+
+        // Record the right; its address only depends on the lottery, so readers can derive it
+        let right = &mut ctx.accounts.right;
+        right.lottery = lottery.key();
+        right.lottery_id = lottery.id;
+        right.winner = ticket.authority;
+        right.granted_at = now;
+
+        // Log the grant
+        msg!(
+            "{} was granted the right of lottery id {}",
+            right.winner,
+            lottery.id
+        );
+
+        emit!(RightGranted {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id: lottery.id,
+            winner: right.winner,
+        });
+
+        Ok(())
+    }
+
+    // Function for anyone to deliver a claimed lottery's winner callback, see `set_winner_callback`
+    // The callback runs in a transaction of its own after the claim, so a failing, upgraded or compute-hungry callback
+    // program can't hold the prize back. A delivery is recorded in `winner_notified` and the `WinnerNotified` event;
+    // a failed one reverts only itself, leaves the lottery pending and can be retried. Before the claim, and once
+    // delivered, it succeeds without delivering and returns `CrankStatus::NotDue` or `CrankStatus::AlreadyDone`
+    pub fn notify_winner(ctx: Context<NotifyWinner>, _lottery_id: u32) -> Result<CrankStatus> {
+        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+        let winner = ctx.accounts.winner.to_account_info();
+
+        // The callback follows the claim, and is delivered once
+        if !lottery.claimed {
+            return Ok(CrankStatus::NotDue);
+        }
+        if lottery.winner_notified {
+            return Ok(CrankStatus::AlreadyDone);
+        }
+
+        lottery.winner_notified = true;
+
+        emit!(WinnerNotified {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id: lottery.id,
+            program: ctx.accounts.callback_program.key(),
+            winner: winner.key(),
+        });
+
+        // Let the registered callback program react to the win
+        callback::invoke_winner_callback(
+            lottery,
+            ctx.bumps.lottery,
+            &ctx.accounts.callback_program,
+            &winner,
+        )?;
+
+        Ok(CrankStatus::Done)
+    }
+
+    // Function for the authority of a right lottery to withdraw the ticket sales once the right is claimed
+    // Only lamports above the lottery account's rent exemption are moved, so calling it again is harmless
+    pub fn withdraw_proceeds(ctx: Context<WithdrawProceeds>, _lottery_id: u32) -> Result<()> {
+        let lottery = ctx.accounts.lottery.to_account_info();
+        let reserved = Rent::get()?.minimum_balance(lottery.data_len());
+        let proceeds = lottery.lamports().saturating_sub(reserved);
+
+        **lottery.try_borrow_mut_lamports()? -= proceeds;
+        **ctx.accounts.authority.try_borrow_mut_lamports()? += proceeds;
+
+        // Log the withdrawal
+        msg!("Withdrew {} lamports of proceeds", proceeds);
+
+        Ok(())
+    }
+
+    // Function for a ticket owner to recover their ticket price from a prize that was never claimed
+    // Opens once the winner has had `recovery_period` seconds to claim; every ticket gets back what it paid,
+    // plus an even share of what earlier lotteries rolled over, see `Lottery::recovery_share`. Returns
+    // `ClaimOutcome::Paused` when the refund trips the circuit breaker and nothing was paid
+    pub fn recover_funds(
+        ctx: Context<RecoverFunds>,
+        _lottery_id: u32,
+        _ticket_id: u32,
+    ) -> Result<ClaimOutcome> {
+        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+        let ticket = &ctx.accounts.ticket; // Get a reference to the ticket account
+        let owner = &ctx.accounts.authority; // Get a reference to the ticket owner
+        let now = time::now(&ctx.accounts.master)?;
+
+        if !lottery.recovery_open(now, ctx.accounts.master.recovery_period) {
+            return err!(LotteryError::RecoveryNotOpen);
+        }
+
+        // The pot stays put while the draw is under dispute
+        if lottery.disputed {
+            return err!(LotteryError::DrawDisputed);
+        }
+
+        // Transfer the ticket's share of the jackpot from the lottery account to its owner
+        let share = lottery
+            .recovery_share(ticket.paid)
+            .ok_or(LotteryError::PotOverflow)?;
+
+        // Hold the refund back if it trips the circuit breaker, like `claim_prize` does
+        let master = &mut ctx.accounts.master;
+        if master.claims_paused {
+            return err!(LotteryError::ClaimsPaused);
+        }
+        if lottery.payment_mint.is_none() && !master.record_payout(share, now) {
+            emit!(PayoutsPaused {
+                seq: master.next_seq(),
+                lottery_id: lottery.id,
+                amount: share,
+                window_payouts: master.window_payouts,
+                window_budget: master.window_budget,
+            });
+            ctx.accounts
+                .receipt
+                .close(ctx.accounts.authority.to_account_info())?;
+            return Ok(ClaimOutcome::Paused);
+        }
+        if lottery.payment_mint.is_some() {
+            token::withdraw(
+                lottery,
+                ctx.bumps.lottery,
+                ctx.accounts.vault.as_ref(),
+                ctx.accounts.owner_token_account.as_ref(),
+                &owner.key(),
+                ctx.accounts.token_program.as_ref(),
+                share,
+            )?;
+        } else {
+            **lottery.to_account_info().try_borrow_mut_lamports()? -= share;
+            **owner.to_account_info().try_borrow_mut_lamports()? += share;
+        }
+
+        // Record the refund in a receipt; the receipt account can only be created once per ticket, so it can't be replayed
+        let receipt = &mut ctx.accounts.receipt;
+        receipt.lottery_id = lottery.id;
+        receipt.ticket_id = ticket.id;
+        receipt.winner = owner.key();
+        receipt.amount = share;
+        receipt.claimed_at = now;
+
+        // Log the recovery
+        msg!(
+            "{} recovered {} lamports from lottery id {} with ticket id {}",
+            owner.key(),
+            share,
+            lottery.id,
+            ticket.id
+        );
+
+        emit!(FundsRecovered {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id: lottery.id,
+            ticket_id: ticket.id,
+            owner: owner.key(),
+            amount: share,
+        });
+
+        Ok(ClaimOutcome::Paid)
+    }
+
+    // Function to compare what a lamport lottery owes with what its account actually holds
+    // Anyone can call it; the result is recorded in the lottery's `Discrepancy` account. With `absorb` set,
+    // a surplus (e.g. a direct donation to the lottery account) is added to the jackpot of an unclaimed lottery
+    // Once refunds have started there is nothing left to reconcile, and it returns `CrankStatus::AlreadyDone`
+    pub fn reconcile_lottery(
+        ctx: Context<ReconcileLottery>,
+        _lottery_id: u32,
+        absorb: bool,
+    ) -> Result<CrankStatus> {
+        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
+        let now = time::now(&ctx.accounts.master)?;
+
+        // A token lottery's jackpot isn't held as lamports at all
+        if lottery.payment_mint.is_some() {
+            return err!(LotteryError::CannotReconcile);
+        }
+
+        // Once refunds start, what is owed depends on how many tickets were refunded, which isn't tracked, and the
+        // same goes for the shares matching tickets have claimed
+        if lottery.recovery_open(now, ctx.accounts.master.recovery_period)
+            || (lottery.payout_started() && !lottery.claimed)
+        {
+            return Ok(CrankStatus::AlreadyDone);
+        }
+
+        // Everything above the rent is the vault; an unclaimed lottery owes its jackpot, a claimed one nothing
+        let info = lottery.to_account_info();
+        let actual = info
+            .lamports()
+            .saturating_sub(Rent::get()?.minimum_balance(info.data_len()));
+        let expected = if lottery.claimed {
+            0
+        } else {
+            lottery.jackpot().ok_or(LotteryError::PotOverflow)?
+        };
+
+        let surplus = actual.saturating_sub(expected);
+        let absorbed = if absorb && !lottery.claimed {
+            surplus
+        } else {
+            0
+        };
+        lottery.carried_over += absorbed;
+
+        // An absorbed surplus raises what a drawn lottery owes
+        if absorbed > 0 && lottery.winner_id.is_some() {
+            ctx.accounts.master.record_pot(absorbed, now);
+        }
+
+        let discrepancy = &mut ctx.accounts.discrepancy;
+        discrepancy.lottery = lottery.key();
+        discrepancy.expected = expected;
+        discrepancy.actual = actual;
+        discrepancy.absorbed = absorbed;
+        discrepancy.reconciled_at = now;
+
+        // Log the result
+        msg!(
+            "Lottery id {} expected {} lamports, holds {}, absorbed {}",
+            lottery.id,
+            expected,
+            actual,
+            absorbed
+        );
+
+        emit!(LotteryReconciled {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id: lottery.id,
+            expected,
+            actual,
+            absorbed,
+        });
+
+        Ok(CrankStatus::Done)
+    }
+
+    // Function to archive a finished lottery
+    // Writes a compact summary account and closes the lottery account, returning its rent to the authority
+    pub fn archive_lottery(ctx: Context<ArchiveLottery>, _lottery_id: u32) -> Result<()> {
+        let lottery = &ctx.accounts.lottery; // Get a reference to the lottery account being closed
+        let summary = &mut ctx.accounts.summary; // Get a mutable reference to the summary account
+
+        // Keep only what is needed to answer "who won what, and when"
+        summary.id = lottery.id;
+        summary.authority = lottery.authority;
+        summary.winner_id = lottery.winner_id.unwrap();
+        summary.winner = ctx.accounts.winning_ticket.authority;
+        summary.pot = lottery.pot().ok_or(LotteryError::PotOverflow)?;
+        summary.tickets_sold = lottery.live_tickets();
+        summary.created_at = lottery.created_at;
+        summary.drawn_at = lottery.drawn_at;
+        summary.archived_at = time::now(&ctx.accounts.master)?;
+
+        // Log the archival
+        msg!("Lottery ID {} archived", summary.id);
+
+        emit!(LotteryArchived {
+            seq: ctx.accounts.master.next_seq(),
+            lottery_id: summary.id,
+            winner: summary.winner,
+            pot: summary.pot,
+        });
+
+        // Closing the account hands everything to the authority; report what was left above the rent
+        // (rounding remainders, donations made after the claim) so it doesn't vanish unaccounted
+        let info = lottery.to_account_info();
+        let dust = info
+            .lamports()
+            .saturating_sub(Rent::get()?.minimum_balance(info.data_len()));
+        if dust > 0 {
+            emit!(DustSwept {
+                seq: ctx.accounts.master.next_seq(),
+                lottery_id: summary.id,
+                recipient: lottery.authority,
+                amount: dust,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+// Define the accounts context for the `init_master` function
+// Specifies the accounts that need to be provided to this instruction
+#[derive(Accounts)]
+pub struct InitMaster<'info> {
+    // Define the master account, which is initialized here
+    #[account(
+        init_if_needed, // This attribute initializes the account on the first call and loads it on later calls
+        payer = payer, // The payer is responsible for covering the fees for creating this account
+        space = 8 + 4 + 1 + 32 + 8 + 4 + 8 + 8 + 4 + 8 + 1 + 4 + 8 + 8 + 8 + 8 + 2, // Allocate enough space for the account (total 124 bytes)
+        // 8 +  // Account discriminator
+        // 4 +  // last_id: u32
+        // 1 +  // initialized: bool
+        // 32 + // authority: Pubkey
+        // 8 +  // features: u64
+        // 4 +  // season: u32
+        // 8 +  // recovery_period: i64
+        // 8 +  // seq: u64
+        // 4 +  // max_open_lotteries: u32
+        // 8 +  // clock_offset: i64
+        // 1 +  // claims_paused: bool
+        // 4 +  // payout_limit_bps: u32
+        // 8 +  // owed_pots: u64
+        // 8 +  // window_start: i64
+        // 8 +  // window_budget: u64
+        // 8 +  // window_payouts: u64
+        // 2;   // max_draw_bounty_bps: u16
+        seeds = [MASTER_SEED.as_bytes()], // Use MASTER_SEED as the seed for generating a program-derived address (PDA)
+        bump, // The bump seed used to create a valid PDA; prevents collision
+    )]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the audit log account, created alongside the master account (or on the next call for older deployments)
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + 4 + 8 + AUDIT_LOG_CAPACITY * (32 + 1 + 8 + 8), // Allocate enough space for the account (total 1588 bytes)
+        // 8 +  // Account discriminator
+        // 4 +  // head: u32
+        // 8 +  // total: u64
+        // 32 * (32 + 1 + 8 + 8); // entries: [AuditEntry; 32] (actor, action, value, timestamp)
+        seeds = [AUDIT_LOG_SEED.as_bytes()],
+        bump,
+    )]
+    pub audit_log: Box<Account<'info, AuditLog>>, // Define the audit log account of type `AuditLog`
+
+    // Define the config account, created alongside the master account (or on the next call for older deployments)
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + 32 + 2 + 32, // Allocate enough space for the account (total 74 bytes)
+        // 8 +  // Account discriminator
+        // 32 + // authority: Pubkey
+        // 2 +  // fee_bps: u16
+        // 32;  // treasury: Pubkey
+        seeds = [CONFIG_SEED.as_bytes()],
+        bump,
+    )]
+    pub config: Account<'info, Config>, // Define the config account of type `Config`
+
+    // Define the fee ledger account, created alongside the master account (or on the next call for older deployments)
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + FEE_LEDGER_PERIODS * (4 + 8 + 4 + 4), // Allocate enough space for the account (total 528 bytes)
+        // 8 +  // Account discriminator
+        // 26 * (4 + 8 + 4 + 4); // periods: [FeePeriod; 26] (period, lamports, claims, token_claims)
+        seeds = [FEE_LEDGER_SEED.as_bytes()],
+        bump,
+    )]
+    pub fee_ledger: Box<Account<'info, FeeLedger>>, // Define the fee ledger account of type `FeeLedger`
+
+    // Define the account of the first season, created alongside the master account
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = SEASON_SPACE,
+        seeds = [SEASON_SEED.as_bytes(), &0u32.to_le_bytes()],
+        bump,
+    )]
+    pub season: Account<'info, Season>, // Define the season account of type `Season`
+
+    // Define this program, whose program data account holds the upgrade authority
+    #[account(
+        constraint = program.programdata_address()? == Some(program_data.key()) @ LotteryError::ProgramDataMismatch,
+    )]
+    pub program: Program<'info, crate::program::Lottery>,
+
+    // Define the program data account of this program, read for the upgrade authority the first payer must be
+    pub program_data: Account<'info, ProgramData>,
+
+    // Define the payer account, which will pay the rent for initializing the master account
+    #[account(mut)] // `mut` means this account is mutable (its balance can change)
+    pub payer: Signer<'info>, // The signer is the account that authorizes this transaction
+
+    // Reference to the system program, used to interact with Solana's native features
+    pub system_program: Program<'info, System>,
+}
+
+// Define the data structure that will be stored in the config account
+// The protocol fee settings, with an authority of their own so e.g. a treasury multisig can manage them
+#[account]
+pub struct Config {
+    pub authority: Pubkey, // The public key allowed to change the config (32 bytes)
+    pub fee_bps: u16, // The protocol fee taken from each prize, in basis points (2 bytes for a u16 integer)
+    pub treasury: Pubkey, // The wallet the protocol fee is paid to (32 bytes)
+}
+
+impl Config {
+    // The protocol fee on a prize of one of `creator`'s lotteries: their override if they have one, else the config's
+    pub fn fee_bps_for(&self, creator: &Creator) -> u16 {
+        creator.fee_bps.unwrap_or(self.fee_bps)
+    }
+}
+
+// Define the accounts context for the `update_config` function
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    // Define the config account, which holds the protocol fee settings
+    #[account(
+        mut, // The config account is mutable, as the fee settings will be updated
+        seeds = [CONFIG_SEED.as_bytes()],
+        bump,
+        has_one = authority, // Only the config authority can change the fee
+    )]
+    pub config: Account<'info, Config>, // Define the config account of type `Config`
+
+    // Define the master account, read for the current time and updated with the event sequence number
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the audit log account, which records the change
+    #[account(mut, seeds = [AUDIT_LOG_SEED.as_bytes()], bump)]
+    pub audit_log: Box<Account<'info, AuditLog>>, // Define the audit log account of type `AuditLog`
+
+    // Define the authority account, which must be the config authority
+    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
+}
+
+// Define the accounts context for the `set_creator_fee` function
+#[derive(Accounts)]
+#[instruction(creator: Pubkey)]
+pub struct SetCreatorFee<'info> {
+    // Define the config account, whose authority sets fee overrides
+    #[account(seeds = [CONFIG_SEED.as_bytes()], bump, has_one = authority)]
+    pub config: Account<'info, Config>, // Define the config account of type `Config`
+
+    // Define the creator's account, which holds the override (created here if the creator has no lottery yet)
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = CREATOR_SPACE,
+        seeds = [CREATOR_SEED.as_bytes(), creator.as_ref()],
+        bump,
+    )]
+    pub creator_account: Account<'info, Creator>, // Define the creator account of type `Creator`
+
+    // Define the master account, read for the current time and updated with the event sequence number
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the audit log account, which records the change
+    #[account(mut, seeds = [AUDIT_LOG_SEED.as_bytes()], bump)]
+    pub audit_log: Box<Account<'info, AuditLog>>, // Define the audit log account of type `AuditLog`
+
+    // Define the authority account, which must be the config authority and pays for a new creator account
+    #[account(mut)]
+    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
+
+    // Reference to the system program, used to create the creator account
+    pub system_program: Program<'info, System>,
+}
+
+// Define the data structure that will be stored in the master account
+#[account]
+#[derive(Default)]
+pub struct Master {
+    pub last_id: u32,             // Track the last lottery ID created (4 bytes for a u32 integer)
+    pub initialized: bool,        // Set once the master account has been initialized (1 byte for a boolean)
+    pub authority: Pubkey,        // The public key of the program admin (32 bytes)
+    pub features: u64,            // Bitfield of enabled modules, see the FEATURE_* constants (8 bytes for a u64 integer)
+    pub season: u32,              // The ID of the current season (4 bytes for a u32 integer)
+    pub recovery_period: i64,     // Seconds a drawn prize stays claimable before buyers can recover their funds (8 bytes for an i64 integer)
+    pub seq: u64,                 // The sequence number of the last emitted event (8 bytes for a u64 integer)
+    pub max_open_lotteries: u32,  // The most undrawn lotteries a single creator can have at once (4 bytes for a u32 integer)
+    pub clock_offset: i64,        // Seconds added to the clock in `test-clock` builds, see the `time` module (8 bytes for an i64 integer)
+    pub claims_paused: bool,      // Set when the payout circuit breaker trips, until the admin resumes payouts (1 byte for a boolean)
+    pub payout_limit_bps: u32,    // The most a window may pay out, in basis points of its budget; 0 disables the breaker (4 bytes for a u32 integer)
+    pub owed_pots: u64,           // Lamports drawn lotteries owe and haven't paid out yet (8 bytes for a u64 integer)
+    pub window_start: i64,        // The unix timestamp the current payout window started at (8 bytes for an i64 integer)
+    pub window_budget: u64,       // The pots owed at the window's start plus those drawn since (8 bytes for a u64 integer)
+    pub window_payouts: u64,      // The lamports paid out in the current window (8 bytes for a u64 integer)
+    pub max_draw_bounty_bps: u16, // The most a draw can pay its caller, in basis points of the jackpot (2 bytes for a u16 integer)
+}
+
+impl Master {
+    // Check whether a module is enabled
+    pub fn has_feature(&self, feature: u64) -> bool {
+        self.features & feature == feature
+    }
+
+    // Take the sequence number of the next event; numbers start at 1 and never skip, so indexers can spot gaps
+    pub fn next_seq(&mut self) -> u64 {
+        self.seq += 1;
+        self.seq
+    }
+
+    // Record the jackpot of a drawn lamport lottery as owed, which adds it to the current window's budget
+    pub fn record_pot(&mut self, amount: u64, now: i64) {
+        self.roll_payout_window(now);
+        self.owed_pots = self.owed_pots.saturating_add(amount);
+        self.window_budget = self.window_budget.saturating_add(amount);
+    }
+
+    // Drop a jackpot that will no longer be paid out, e.g. because an upheld dispute voided its draw
+    // Takes it out of the window's budget too, which either `record_pot` or the window restart added it to, so a
+    // repeated draw doesn't count the same jackpot twice
+    pub fn release_pot(&mut self, amount: u64) {
+        self.owed_pots = self.owed_pots.saturating_sub(amount);
+        self.window_budget = self.window_budget.saturating_sub(amount);
+    }
+
+    // Count a payout against the circuit breaker before it is made
+    // Returns false, and pauses payouts, if it would take the window's payouts past `payout_limit_bps` of its budget;
+    // a correct program never pays out more than it owes, so that points to a bug the admin should look at first
+    pub fn record_payout(&mut self, amount: u64, now: i64) -> bool {
+        self.roll_payout_window(now);
+        let payouts = self.window_payouts.saturating_add(amount);
+        if self.payout_limit_bps > 0
+            && math::exceeds_bps(payouts, self.window_budget, self.payout_limit_bps)
+        {
+            self.claims_paused = true;
+            return false;
+        }
+        self.window_payouts = payouts;
+        self.owed_pots = self.owed_pots.saturating_sub(amount);
+        true
+    }
+
+    // Start a new payout window, budgeted with everything owed right now
+    pub fn restart_payout_window(&mut self, now: i64) {
+        self.window_start = now;
+        self.window_budget = self.owed_pots;
+        self.window_payouts = 0;
+    }
+
+    // Start a new payout window once the current one is over
+    fn roll_payout_window(&mut self, now: i64) {
+        if now >= self.window_start + PAYOUT_WINDOW {
+            self.restart_payout_window(now);
+        }
+    }
+
+    // Gate a deprecated instruction behind FEATURE_LEGACY_INSTRUCTIONS and tell indexers it was used,
+    // so integrators can find and migrate their remaining callers before the admin switches it off
+    pub fn use_legacy_instruction(&mut self, instruction: &str, replacement: &str) -> Result<()> {
+        if !self.has_feature(FEATURE_LEGACY_INSTRUCTIONS) {
+            return err!(LotteryError::FeatureDisabled);
+        }
+
+        emit!(DeprecatedInstructionUsed {
+            seq: self.next_seq(),
+            instruction: instruction.to_string(),
+            replacement: replacement.to_string(),
+        });
+
+        Ok(())
+    }
+}
+
+// Define the data structure that will be stored in the audit log account
+// A fixed size ring buffer of the most recent admin actions; `head` is the slot the next entry is written to
+#[account]
+pub struct AuditLog {
+    pub head: u32,                                 // The index the next entry will be written to (4 bytes for a u32 integer)
+    pub total: u64,                                // The number of entries ever written, including overwritten ones (8 bytes for a u64 integer)
+    pub entries: [AuditEntry; AUDIT_LOG_CAPACITY], // The most recent entries (49 bytes each)
+}
+
+impl AuditLog {
+    // Append an entry, overwriting the oldest one once the buffer is full
+    pub fn record(&mut self, actor: Pubkey, action: u8, value: u64, timestamp: i64) {
+        self.entries[self.head as usize] = AuditEntry {
+            actor,
+            action,
+            value,
+            timestamp,
+        };
+        self.head = (self.head + 1) % AUDIT_LOG_CAPACITY as u32;
+        self.total += 1;
+    }
+}
+
+// Define a single admin action recorded in the audit log
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct AuditEntry {
+    pub actor: Pubkey,  // The public key of the admin that performed the action (32 bytes)
+    pub action: u8,     // The kind of action, see the AUDIT_* constants; 0 marks an unused slot (1 byte for a u8 integer)
+    pub value: u64,     // The action's new value, e.g. the new feature bitfield (8 bytes for a u64 integer)
+    pub timestamp: i64, // The unix timestamp of the action (8 bytes for an i64 integer)
+}
+
+// Define the data structure that will be stored in the fee ledger account
+// A fixed size ring buffer of per-period protocol fee counters; a period's slot is reused once it is FEE_LEDGER_PERIODS
+// periods old, so reports only reach that far back
+#[account]
+pub struct FeeLedger {
+    pub periods: [FeePeriod; FEE_LEDGER_PERIODS], // The counters of the most recent periods, indexed by period modulo their number (20 bytes each)
+}
+
+impl FeeLedger {
+    // Count a claim's protocol fee towards the period `now` falls in, resetting the period's slot if it held an older one
+    // Token fees are in their mint's base units, which don't add up across mints, so only their claims are counted
+    pub fn record(&mut self, now: i64, fee: u64, token: bool) {
+        let period = now.div_euclid(FEE_PERIOD) as u32;
+        let slot = &mut self.periods[period as usize % FEE_LEDGER_PERIODS];
+        if slot.period != period {
+            *slot = FeePeriod {
+                period,
+                ..FeePeriod::default()
+            };
+        }
+
+        if token {
+            slot.token_claims = slot.token_claims.saturating_add(1);
+        } else {
+            slot.lamports = slot.lamports.saturating_add(fee);
+            slot.claims = slot.claims.saturating_add(1);
+        }
+    }
+
+    // The counters of `period`, if any claim was counted in it and its slot wasn't reused since
+    pub fn get(&self, period: u32) -> Option<&FeePeriod> {
+        let slot = &self.periods[period as usize % FEE_LEDGER_PERIODS];
+        (slot.period == period && (slot.claims > 0 || slot.token_claims > 0)).then_some(slot)
+    }
+}
+
+// Define the protocol fee counters of a single period
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct FeePeriod {
+    pub period: u32, // The period counted, the unix timestamp divided by FEE_PERIOD (4 bytes for a u32 integer)
+    pub lamports: u64, // The protocol fees paid to the treasury by lamport lotteries, in lamports (8 bytes for a u64 integer)
+    pub claims: u32,   // The prizes claimed from lamport lotteries (4 bytes for a u32 integer)
+    pub token_claims: u32, // The prizes claimed from token lotteries, whose fees aren't summed (4 bytes for a u32 integer)
+}
+
+// Define the accounts context for the `get_version` function, which needs no accounts
+#[derive(Accounts)]
+pub struct GetVersion {}
+
+// Define the accounts context for the `verify_upgrade_authority` function
+#[derive(Accounts)]
+pub struct VerifyUpgradeAuthority<'info> {
+    // Define the master account, read for the admin
+    #[account(seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define this program, whose program data account holds the upgrade authority
+    #[account(
+        constraint = program.programdata_address()? == Some(program_data.key()) @ LotteryError::ProgramDataMismatch,
+    )]
+    pub program: Program<'info, crate::program::Lottery>,
+
+    // Define the program data account of this program
+    pub program_data: Account<'info, ProgramData>,
+
+    /// CHECK: The governance account the caller expects to control upgrades, only compared by key
+    pub governance: Option<UncheckedAccount<'info>>,
+}
+
+// Define who can upgrade the program, as returned by `verify_upgrade_authority`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UpgradeAuthority {
+    Immutable,  // The program can't be upgraded any more
+    Admin,      // The master admin can upgrade the program
+    Governance, // The governance account passed by the caller can upgrade the program
+}
+
+// Define the accounts context for the `set_features` function
+#[derive(Accounts)]
+pub struct SetFeatures<'info> {
+    // Define the master account, which holds the feature bitfield
+    #[account(
+        mut, // The master account is mutable, as the features will be updated
+        seeds = [MASTER_SEED.as_bytes()],
+        bump,
+        has_one = authority, // Only the program admin can change features
+    )]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the audit log account, which records the change
+    #[account(mut, seeds = [AUDIT_LOG_SEED.as_bytes()], bump)]
+    pub audit_log: Box<Account<'info, AuditLog>>, // Define the audit log account of type `AuditLog`
+
+    // Define the authority account, which must be the program admin
+    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
+}
+
+// Define the accounts context for the `close_season` function
+#[derive(Accounts)]
+pub struct CloseSeason<'info> {
+    // Define the master account, which tracks the current season
+    #[account(
+        mut, // The master account is mutable, as the season will be advanced
+        seeds = [MASTER_SEED.as_bytes()],
+        bump,
+        has_one = authority, // Only the program admin can close a season
+    )]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the account of the season being closed
+    #[account(mut, seeds = [SEASON_SEED.as_bytes(), &master.season.to_le_bytes()], bump)]
+    pub season: Account<'info, Season>, // Define the season account of type `Season`
+
+    // Define the account of the season being started, which is initialized here
+    #[account(
+        init,
+        payer = authority,
+        space = SEASON_SPACE,
+        seeds = [SEASON_SEED.as_bytes(), &(master.season + 1).to_le_bytes()],
+        bump,
+    )]
+    pub next_season: Account<'info, Season>, // Define the season account of type `Season`
+
+    // Define the audit log account, which records the change
+    #[account(mut, seeds = [AUDIT_LOG_SEED.as_bytes()], bump)]
+    pub audit_log: Box<Account<'info, AuditLog>>, // Define the audit log account of type `AuditLog`
+
+    // Define the authority account, which must be the program admin and pays for the next season's account
+    #[account(mut)]
+    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
+
+    // Reference to the system program, used to interact with Solana's native features
+    pub system_program: Program<'info, System>,
+}
+
+// Space of a season account (total 105 bytes)
+// 8 +  // Account discriminator
+// 4 +  // id: u32
+// 8 +  // started_at: i64
+// 8 +  // closed_at: i64
+// 4 +  // lotteries: u32
+// 8 +  // tickets: u64
+// 8 +  // volume: u64
+// 8 +  // payouts: u64
+// 1 + 32 + // reward_root: Option<[u8; 32]> (1 byte for option tag + 32 bytes for the root)
+// 8 +  // rewards_total: u64
+// 8;   // rewards_claimed: u64
+const SEASON_SPACE: usize = 8 + 4 + 8 + 8 + 4 + 8 + 8 + 8 + 1 + 32 + 8 + 8;
+
+// Define the data structure that will be stored in each season account
+// One account per season, so the stats of past seasons stay readable for reward programs built on top
+#[account]
+pub struct Season {
+    pub id: u32,                       // The ID of the season (4 bytes for a u32 integer)
+    pub started_at: i64,               // The unix timestamp the season started at (8 bytes for an i64 integer)
+    pub closed_at: i64,                // The unix timestamp the season was closed at, 0 while it is open (8 bytes for an i64 integer)
+    pub lotteries: u32,                // The number of lotteries created during the season (4 bytes for a u32 integer)
+    pub tickets: u64,                  // The number of tickets sold during the season (8 bytes for a u64 integer)
+    pub volume: u64,                   // The lamports spent on tickets during the season (8 bytes for a u64 integer)
+    pub payouts: u64,                  // The lamports paid out to winners during the season (8 bytes for a u64 integer)
+    pub reward_root: Option<[u8; 32]>, // The Merkle root of the season's reward allocations, once committed
+    pub rewards_total: u64,            // The lamports funded for the season's rewards (8 bytes for a u64 integer)
+    pub rewards_claimed: u64,          // The lamports of rewards claimed so far (8 bytes for a u64 integer)
+}
+
+impl Season {
+    // Count a ticket sold at the given price
+    pub fn record_sale(&mut self, price: u64) {
+        self.tickets += 1;
+        self.volume += price;
+    }
+
+    // Record a block of `tickets` sold at once for `volume` lamports in total
+    pub fn record_sales(&mut self, tickets: u32, volume: u64) {
+        self.tickets += u64::from(tickets);
+        self.volume += volume;
+    }
+}
+
+// Define the accounts context for the `commit_season_rewards` function
+#[derive(Accounts)]
+#[instruction(season_id: u32)]
+pub struct CommitSeasonRewards<'info> {
+    // Define the master account, read to check the admin and updated with the event sequence number
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump, has_one = authority)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the season account, which receives the root and the reward funds
+    #[account(
+        mut,
+        seeds = [SEASON_SEED.as_bytes(), &season_id.to_le_bytes()],
+        bump,
+        constraint = season.closed_at > 0 @ LotteryError::SeasonNotClosed, // Allocations are computed from final stats
+        constraint = season.reward_root.is_none() @ LotteryError::SeasonRewardsAlreadyCommitted, // The root can't be swapped after players start claiming
+    )]
+    pub season: Account<'info, Season>, // Define the season account of type `Season`
+
+    // Define the audit log account, which records the commitment
+    #[account(mut, seeds = [AUDIT_LOG_SEED.as_bytes()], bump)]
+    pub audit_log: Box<Account<'info, AuditLog>>, // Define the audit log account of type `AuditLog`
+
+    // Define the authority account, which must be the program admin and funds the rewards
+    #[account(mut)]
+    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
+
+    // Reference to the system program, used to interact with Solana's native features
+    pub system_program: Program<'info, System>,
+}
+
+// Define the accounts context for the `claim_season_reward` function
+#[derive(Accounts)]
+#[instruction(season_id: u32)]
+pub struct ClaimSeasonReward<'info> {
+    // Define the season account, which pays out the reward
+    #[account(mut, seeds = [SEASON_SEED.as_bytes(), &season_id.to_le_bytes()], bump)]
+    pub season: Account<'info, Season>, // Define the season account of type `Season`
+
+    // Define the reward receipt account, which is initialized here and can only exist once per player and season
+    #[account(
+        init,
+        payer = claimer,
+        space = 8 + 4 + 32 + 8 + 8, // Allocate enough space for the account (total 60 bytes)
+        // 8 +  // Account discriminator
+        // 4 +  // season: u32
+        // 32 + // claimer: Pubkey
+        // 8 +  // amount: u64
+        // 8;   // claimed_at: i64
+        seeds = [SEASON_REWARD_SEED.as_bytes(), season.key().as_ref(), claimer.key().as_ref()],
+        bump,
+    )]
+    pub receipt: Account<'info, SeasonRewardReceipt>, // Define the receipt account of type `SeasonRewardReceipt`
+
+    // Define the claimer account, which receives the reward
+    #[account(mut)]
+    pub claimer: Signer<'info>, // The signer is the account that authorizes this transaction
+
+    // Reference to the system program, used to interact with Solana's native features
+    pub system_program: Program<'info, System>,
+
+    // Define the master account, which numbers the emitted event
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+}
+
+// Define the data structure that will be stored in the receipt account of each claimed season reward
+#[account]
+pub struct SeasonRewardReceipt {
+    pub season: u32,     // The ID of the season the reward was paid from (4 bytes for a u32 integer)
+    pub claimer: Pubkey, // The public key of the player that claimed the reward (32 bytes)
+    pub amount: u64,     // The amount of lamports paid out (8 bytes for a u64 integer)
+    pub claimed_at: i64, // The unix timestamp of the claim (8 bytes for an i64 integer)
+}
+
+// Define the accounts context for the `create_lottery` function
+// Specifies the accounts that need to be provided to this instruction
+#[derive(Accounts)]
+pub struct CreateLottery<'info> {
+    // Define the lottery account, which is initialized here
+    #[account(
+        init, // This attribute indicates that this account is being initialized
+        payer = authority, // The authority is responsible for covering the fees for creating this account
+        space = LOTTERY_SPACE, // Allocate enough space for the account (total 498 bytes)
+        seeds = [LOTTERY_SEED.as_bytes(), &master.last_id.to_le_bytes()], // Use LOTTERY_SEED and current last_id as seeds for generating a PDA
+        bump, // The bump seed used to create a valid PDA; prevents collision
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the master account, which keeps track of the lottery ID
+    #[account(
+        mut, // The master account is mutable, as the lottery ID will be updated
+        seeds = [MASTER_SEED.as_bytes()], // Use MASTER_SEED as the seed for generating the PDA
+        bump, // The bump seed used to create a valid PDA
+    )]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the authority account, which will be responsible for managing the lottery
+    #[account(mut)] // The authority account is mutable (e.g., its balance can change)
+    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
+
+    // Reference to the system program, used to interact with Solana's native features
+    pub system_program: Program<'info, System>,
+
+    // Define the current season account, which accumulates the season's stats
+    #[account(mut, seeds = [SEASON_SEED.as_bytes(), &master.season.to_le_bytes()], bump)]
+    pub season: Account<'info, Season>, // Define the season account of type `Season`
+
+    // Define the creator account, which counts the authority's open lotteries (created on their first lottery)
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = CREATOR_SPACE, // Allocate enough space for the account (total 55 bytes)
+        seeds = [CREATOR_SEED.as_bytes(), authority.key().as_ref()],
+        bump,
+    )]
+    pub creator: Account<'info, Creator>, // Define the creator account of type `Creator`
+}
+
+// Define the accounts context for the `create_named_lottery` function
+// Specifies the accounts that need to be provided to this instruction; the same as `CreateLottery`, plus the name
+#[derive(Accounts)]
+#[instruction(name: String, lottery_id: u32)]
+pub struct CreateNamedLottery<'info> {
+    // Define the lottery account, which is initialized here at the name's lottery ID
+    #[account(
+        init, // This attribute indicates that this account is being initialized
+        payer = authority, // The authority is responsible for covering the fees for creating this account
+        space = LOTTERY_SPACE, // Allocate enough space for the account (total 498 bytes)
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()], // Use LOTTERY_SEED and lottery_id as seeds for generating the PDA
+        bump, // The bump seed used to create a valid PDA
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the lottery name account, which reserves the name for the authority
+    // Its `init` fails if the authority already used the name, so a name always means the same lottery
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 4 + 32 + 4 + MAX_LOTTERY_NAME_LEN, // Allocate enough space for the account (total 80 bytes)
+        // 8 +  // Account discriminator
+        // 4 +  // lottery_id: u32
+        // 32 + // authority: Pubkey
+        // 4 + 32; // name: String (4 bytes for the length + up to MAX_LOTTERY_NAME_LEN bytes)
+        seeds = [LOTTERY_SEED.as_bytes(), authority.key().as_ref(), name.as_bytes()],
+        bump,
+    )]
+    pub lottery_name: Account<'info, LotteryName>, // Define the lottery name account of type `LotteryName`
+
+    // Define the master account, which counts the events the lottery emits
+    #[account(
+        mut, // The master account is mutable, as its event sequence number will be updated
+        seeds = [MASTER_SEED.as_bytes()], // Use MASTER_SEED as the seed for generating the PDA
+        bump, // The bump seed used to create a valid PDA
+    )]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the authority account, which will be responsible for managing the lottery
+    #[account(mut)] // The authority account is mutable (e.g., its balance can change)
+    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
+
+    // Reference to the system program, used to interact with Solana's native features
+    pub system_program: Program<'info, System>,
+
+    // Define the current season account, which accumulates the season's stats
+    #[account(mut, seeds = [SEASON_SEED.as_bytes(), &master.season.to_le_bytes()], bump)]
+    pub season: Account<'info, Season>, // Define the season account of type `Season`
+
+    // Define the creator account, which counts the authority's open lotteries (created on their first lottery)
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = CREATOR_SPACE, // Allocate enough space for the account (total 55 bytes)
+        seeds = [CREATOR_SEED.as_bytes(), authority.key().as_ref()],
+        bump,
+    )]
+    pub creator: Account<'info, Creator>, // Define the creator account of type `Creator`
+}
+
+// Define the data structure that will be stored in a lottery name account
+// One per name a creator used, pointing at the named lottery; kept after the lottery is archived
+#[account]
+pub struct LotteryName {
+    pub lottery_id: u32,   // The ID of the named lottery (4 bytes for a u32 integer)
+    pub authority: Pubkey, // The creator the name belongs to (32 bytes)
+    pub name: String, // The name, up to MAX_LOTTERY_NAME_LEN bytes (4 bytes for the length + the name)
+}
+
+// What a new lottery is set up with, the parameters `create_lottery` and `create_named_lottery` share
+struct NewLottery {
+    ticket_price: u64,
+    draw_epoch: Option<u64>,
+    end_time: i64,
+    no_self_entry: bool,
+    prize_kind: PrizeKind,
+    currency: [u8; 3],
+    locale: String,
+}
+
+// Set up a freshly created lottery account with ID `id`, for `create_lottery` and `create_named_lottery`
+// Validates the parameters, counts the lottery towards its creator and the season, and announces it
+fn open_lottery(
+    lottery: &mut Account<Lottery>,
+    master: &mut Account<Master>,
+    creator: &mut Account<Creator>,
+    season: &mut Account<Season>,
+    authority: Pubkey,
+    id: u32,
+    new: NewLottery,
+) -> Result<()> {
+    let NewLottery {
+        ticket_price,
+        draw_epoch,
+        end_time,
+        no_self_entry,
+        prize_kind,
+        currency,
+        locale,
+    } = new;
+    let clock = time::clock(master)?; // Retrieve the current clock data, see the `time` module
+
+    // Epoch-locked lotteries are an optional module
+    if draw_epoch.is_some() && !master.has_feature(FEATURE_EPOCH_DRAWS) {
+        return err!(LotteryError::FeatureDisabled);
+    }
+
+    // Reject parameters that would leave an unusable lottery behind
+    validation::validate_ticket_price(ticket_price)?;
+    validation::validate_draw_epoch(draw_epoch, clock.epoch)?;
+    validation::validate_end_time(end_time, clock.unix_timestamp)?;
+    validation::validate_display_hints(currency, &locale)?;
+
+    // Keep a single creator from flooding discovery with open rounds
+    if creator.open_lotteries >= master.max_open_lotteries {
+        return err!(LotteryError::TooManyOpenLotteries);
+    }
+    creator.authority = authority;
+    creator.open_lotteries += 1;
+
+    // Set up the lottery account with relevant details
+    lottery.id = id; // Assign the new lottery ID
+    lottery.authority = authority; // Set the authority for the lottery
+    lottery.ticket_price = ticket_price; // Set the price for lottery tickets
+    lottery.draw_epoch = draw_epoch; // Set the epoch the draw is locked to, if any
+    lottery.end_time = end_time; // Set when sales end and the draw opens, if ever
+    lottery.created_at = clock.unix_timestamp; // Record when the lottery was created
+    lottery.version = LOTTERY_VERSION; // Mark the lottery as using 0-based winner selection
+    lottery.season = master.season; // Group the lottery into the current season
+    lottery.no_self_entry = no_self_entry; // Bar the authority from entering, if requested
+    lottery.prize_kind = prize_kind; // Set what the winner receives
+    lottery.winner_share_bps = MAX_WINNER_SHARE_BPS; // Pay the whole jackpot unless the creator sets a share
+    lottery.currency = currency; // Set the currency frontends display prices in
+    lottery.locale = locale; // Set the locale frontends format prices with
+
+    // Seed the jackpot with what the creator's earlier lotteries rolled over
+    if prize_kind == PrizeKind::Lamports && creator.rollover > 0 {
+        **creator.to_account_info().try_borrow_mut_lamports()? -= creator.rollover;
+        **lottery.to_account_info().try_borrow_mut_lamports()? += creator.rollover;
+        lottery.carried_over = creator.rollover;
+        creator.rollover = 0;
+    }
+
+    // Count the lottery towards the current season
+    season.lotteries += 1;
+
+    emit!(LotteryCreated {
+        seq: master.next_seq(),
+        lottery_id: lottery.id,
+        authority: lottery.authority,
+        ticket_price: lottery.ticket_price,
+        draw_epoch: lottery.draw_epoch,
+    });
+
+    // Publish the display hints, so indexers can render the lottery from events alone
+    emit!(DisplayHintsSet {
+        seq: master.next_seq(),
+        lottery_id: lottery.id,
+        currency: lottery.currency,
+        locale: lottery.locale.clone(),
+    });
+
+    // Log information about the newly created lottery
+    msg!("Lottery with ID : {}", lottery.id);
+    msg!("Authority: {}", lottery.authority);
+    msg!("Lottery ticket price: {}", lottery.ticket_price);
+    if let Some(draw_epoch) = lottery.draw_epoch {
+        msg!("Draw locked to the end of epoch: {}", draw_epoch);
+    }
+    if lottery.end_time != 0 {
+        msg!("Sales end at: {}", lottery.end_time);
+    }
+
+    Ok(())
+}
+
+// Space of a creator account (total 55 bytes)
+// 8 +  // Account discriminator
+// 32 + // authority: Pubkey
+// 4 +  // open_lotteries: u32
+// 8 +  // rollover: u64
+// 1 + 2; // fee_bps: Option<u16> (1 byte for option tag + 2 bytes for u16)
+const CREATOR_SPACE: usize = 8 + 32 + 4 + 8 + 1 + 2;
+
+// Define the data structure that will be stored in the creator account
+// One per lottery authority, tracking how many of their lotteries are still waiting for a draw
+#[account]
+pub struct Creator {
+    pub authority: Pubkey,    // The public key of the lottery creator (32 bytes)
+    pub open_lotteries: u32,  // The number of the creator's lotteries without a winner yet (4 bytes for a u32 integer)
+    pub rollover: u64,        // Lamports held here for the creator's next lamport lottery (8 bytes for a u64 integer)
+    pub fee_bps: Option<u16>, // The protocol fee on this creator's prizes in place of the config's, see `set_creator_fee`
+}
+
+// Space of a lottery account (total 528 bytes)
+// 8 +  // Account discriminator
+// 4 +  // id: u32
+// 32 + // authority: Pubkey
+// 8 +  // ticket_price: u64
+// 4 +  // last_ticket_id: u32
+// 1 + 4 + // winner_id: Option<u32> (1 byte for option tag + 4 bytes for u32)
+// 1 +  // claimed: bool
+// 1 + 8 + // draw_epoch: Option<u64> (1 byte for option tag + 8 bytes for u64)
+// 8 +  // created_at: i64
+// 8 +  // drawn_at: i64
+// 1 +  // version: u8
+// 4 +  // season: u32
+// 1 +  // no_self_entry: bool
+// 1 +  // prize_kind: PrizeKind
+// 1 + 32 + 8 + // callback: Option<WinnerCallback> (1 byte for option tag + 32 bytes for the program + 8 bytes for the discriminator)
+// 32 + // terms_hash: [u8; 32]
+// 1 +  // attestation_policy: u8
+// 1 +  // disputed: bool
+// 2 +  // winner_share_bps: u16
+// 8 +  // carried_over: u64
+// 1 +  // extra_version: u8
+// 4 + 128 + // extra: Vec<u8> (4 bytes for the length + up to MAX_EXTRA_LEN bytes)
+// 3 +  // currency: [u8; 3]
+// 4 + 16 + // locale: String (4 bytes for the length + up to MAX_LOCALE_LEN bytes)
+// 8 +  // last_sale_slot: u64
+// 8 +  // claim_cooldown: i64
+// 1 + 8 + // draw_bounty: DrawBounty (1 byte for the variant + 8 bytes for the largest payload)
+// 8 +  // bounty_paid: u64
+// 1 +  // cancelled: bool
+// 8 +  // force_resolve_at: i64
+// 1 + 32 + // commitment: Option<[u8; 32]> (1 byte for option tag + 32 bytes for the hash)
+// 8 +  // commit_slot: u64
+// 1 + 32 + // payment_mint: Option<Pubkey> (1 byte for option tag + 32 bytes for the mint)
+// 8 +  // end_time: i64
+// 1 +  // winner_notified: bool
+// 1 +  // sales_closed: bool
+// 1 +  // committee: bool
+// 4 +  // min_tickets: u32
+// 32 + // draw_seed: [u8; 32]
+// 8 +  // refund_grace_slots: u64
+// 4 +  // refunded_tickets: u32
+// 1 +  // redraws: u8
+// 1 +  // match_bits: u8
+// 4 +  // match_cursor: u32
+// 4 +  // match_count: u32
+// 4 +  // matches_claimed: u32
+// 4;   // unredeemed_codes: u32
+const LOTTERY_SPACE: usize = 8
+    + 4
+    + 32
+    + 8
+    + 4
+    + 1
+    + 4
+    + 1
+    + 1
+    + 8
+    + 8
+    + 8
+    + 1
+    + 4
+    + 1
+    + 1
+    + 1
+    + 32
+    + 8
+    + 32
+    + 1
+    + 1
+    + 2
+    + 8
+    + 1
+    + 4
+    + MAX_EXTRA_LEN
+    + 3
+    + 4
+    + MAX_LOCALE_LEN
+    + 8
+    + 8
+    + 1
+    + 8
+    + 8
+    + 1
+    + 8
+    + 1
+    + 32
+    + 8
+    + 1
+    + 32
+    + 8
+    + 1
+    + 1
+    + 1
+    + 4
+    + 32
+    + 8
+    + 4
+    + 1
+    + 1
+    + 4
+    + 4
+    + 4
+    + 4;
+
+// Define the data structure that will be stored in the lottery account
+#[account]
+#[derive(Default)]
+pub struct Lottery {
+    pub id: u32,                          // The ID of the lottery (4 bytes for a u32 integer)
+    pub authority: Pubkey,                // The public key of the authority managing the lottery (32 bytes)
+    pub ticket_price: u64,                // The price of a lottery ticket (8 bytes for a u64 integer)
+    pub last_ticket_id: u32,              // The ID of the last issued ticket (4 bytes for a u32 integer)
+    pub winner_id: Option<u32>,           // The ID of the winning ticket, if any (wrapped in Option)
+    pub claimed: bool,                    // Indicates whether the price has been claimed (1 byte for a boolean)
+    pub draw_epoch: Option<u64>,          // The epoch the draw is locked to, if any (wrapped in Option)
+    pub created_at: i64,                  // The unix timestamp the lottery was created at (8 bytes for an i64 integer)
+    pub drawn_at: i64,                    // The unix timestamp the winner was drawn at, 0 until then (8 bytes for an i64 integer)
+    pub version: u8,                      // The lottery layout version, see LOTTERY_VERSION (1 byte for a u8 integer)
+    pub season: u32,                      // The ID of the season the lottery was created in (4 bytes for a u32 integer)
+    pub no_self_entry: bool,              // Bars the authority from holding tickets in this lottery (1 byte for a boolean)
+    pub prize_kind: PrizeKind,            // What the winner receives (1 byte for the enum variant)
+    pub callback: Option<WinnerCallback>, // The program `notify_winner` invokes once the prize is claimed, if any
+    pub terms_hash: [u8; 32],             // The hash of the lottery's terms buyers acknowledge, all zeros until set (32 bytes)
+    pub attestation_policy: u8,           // The ATTEST_* claims buyers must have attested on their profile (1 byte for a u8 integer)
+    pub disputed: bool,                   // Set while a challenge of the draw is pending, which freezes claims (1 byte for a boolean)
+    pub winner_share_bps: u16,            // The share of the jackpot paid to the winner, in basis points (2 bytes for a u16 integer)
+    pub carried_over: u64,                // Lamports rolled over from the creator's earlier lotteries, on top of the pot (8 bytes for a u64 integer)
+    pub extra_version: u8,                // The format of `extra`, chosen by the creator's frontend (1 byte for a u8 integer)
+    pub extra: Vec<u8>,                   // Opaque frontend-specific data, at most MAX_EXTRA_LEN bytes
+    pub currency: [u8; 3],                // The ISO 4217 code frontends display prices in, e.g. "USD"; all zeros if unset (3 bytes)
+    pub locale: String,                   // The BCP 47 tag frontends format prices with, e.g. "en-US"; at most MAX_LOCALE_LEN bytes
+    pub last_sale_slot: u64,              // The slot the last ticket was sold in; the draw must happen in a later slot (8 bytes for a u64 integer)
+    pub claim_cooldown: i64,              // Seconds after the draw before the prize can be claimed, 0 for none (8 bytes for an i64 integer)
+    pub draw_bounty: DrawBounty,          // What the draw pays whoever calls `pick_winner`, see `DrawBounty`
+    pub bounty_paid: u64,                 // The lamports the draw paid its caller out of the jackpot (8 bytes for a u64 integer)
+    pub cancelled: bool,                  // Whether the lottery was cancelled; ticket owners recover their funds right away (1 byte for a boolean)
+    pub force_resolve_at: i64,            // Unix timestamp from which the admin can force-resolve the lottery, 0 if not scheduled (8 bytes for an i64 integer)
+    pub commitment: Option<[u8; 32]>,     // The hash of the authority's secret, see `commit_randomness`; sales are closed once set
+    pub commit_slot: u64,                 // The slot the commitment was made in (8 bytes for a u64 integer)
+    pub payment_mint: Option<Pubkey>,     // The SPL token tickets are paid in, None for lamports; see `set_payment_mint`
+    pub end_time: i64,                    // Unix timestamp when sales end and the draw opens, 0 for none (8 bytes for an i64 integer)
+    pub winner_notified: bool,            // Whether the winner callback was delivered with `notify_winner` (1 byte for a boolean)
+    pub sales_closed: bool,               // Whether sales closed for good because the lottery was drawn (1 byte for a boolean)
+    pub committee: bool,                  // Whether the lottery is drawn by an entropy committee, see `set_committee`
+    pub min_tickets: u32,                 // The ticket count sales can end early at, see `close_sales_early` (4 bytes for a u32 integer)
+    pub draw_seed: [u8; 32],              // The seed the winner was drawn from, so anyone can recompute it with `draw::uniform_index`
+    pub refund_grace_slots: u64,          // Slots after a purchase the buyer can still take it back, 0 for none; see `refund_ticket`
+    pub refunded_tickets: u32,            // The tickets refunded within their grace period, which stay in the ID range (4 bytes for a u32 integer)
+    pub redraws: u8,                      // How often the winner was redrawn because the drawn ticket was refunded (1 byte for a u8 integer)
+    pub match_bits: u8,                   // The draw seed prefix, in bits, tickets must match to split the prize; 0 for a single winner
+    pub match_cursor: u32,                // The next ticket ID `count_matches` checks (4 bytes for a u32 integer)
+    pub match_count: u32,                 // The matching tickets found so far, which split the prize (4 bytes for a u32 integer)
+    pub matches_claimed: u32,             // The matching tickets that claimed their share (4 bytes for a u32 integer)
+    pub unredeemed_codes: u32,            // The code tickets whose codes weren't redeemed yet (4 bytes for a u32 integer)
+}
+
+// Define a program a lottery calls once its prize is claimed, see `notify_winner`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct WinnerCallback {
+    pub program: Pubkey,        // The program to invoke (32 bytes)
+    pub discriminator: [u8; 8], // The instruction discriminator the call starts with (8 bytes)
+}
+
+// Define what the winner of a lottery receives
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PrizeKind {
+    #[default]
+    Lamports, // The pot is paid to the winner with `claim_price`
+    Right, // The winner is written into a `PrizeRight` account with `claim_right`; the pot goes to the authority
+}
+
+// Define what a lottery's draw pays the account that calls `pick_winner`, out of the jackpot
+// Both kinds are capped at the master account's `max_draw_bounty_bps` of the jackpot when the draw pays out
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DrawBounty {
+    #[default]
+    None,
+    Flat(u64), // A fixed amount of lamports
+    Bps(u16),  // A share of the jackpot, in basis points
+}
+
+impl Lottery {
+    // Whether the lottery is still open, i.e. selling tickets and waiting for its draw
+    // Instructions that need an open lottery check this in their accounts constraints
+    pub fn is_open(&self) -> bool {
+        self.winner_id.is_none()
+            && !self.cancelled
+            && self.commitment.is_none()
+            && !self.sales_closed
+    }
+
+    // Whether anyone may draw the lottery: epoch-locked and committee lotteries draw from entropy fixed before the
+    // draw time, so their authority can't hold the draw back and a caller can't retry until it likes the result.
+    // Other lotteries draw from recent slot hashes, which the caller sees, so only their authority draws them
+    pub fn anyone_can_draw(&self) -> bool {
+        self.draw_epoch.is_some() || self.committee
+    }
+
+    // Whether the jackpot is lamports held by the lottery account, which the circuit breaker and draw bounties count in
+    pub fn holds_lamports(&self) -> bool {
+        self.prize_kind == PrizeKind::Lamports && self.payment_mint.is_none()
+    }
+
+    // The tickets that can still win: every ticket sold, less those refunded within their grace period
+    // Refunded tickets keep their IDs, so the draw still picks from 0..last_ticket_id; see `redraw_void_winner`
+    pub fn live_tickets(&self) -> u32 {
+        self.last_ticket_id - self.refunded_tickets
+    }
+
+    // The total prize: every live ticket at the ticket price, or None if it doesn't fit in a u64
+    pub fn pot(&self) -> Option<u64> {
+        self.ticket_price.checked_mul(self.live_tickets().into())
+    }
+
+    // The pot plus what earlier lotteries of the creator rolled over into this one, less the draw bounty paid out of it
+    pub fn jackpot(&self) -> Option<u64> {
+        self.pot()?
+            .checked_add(self.carried_over)?
+            .checked_sub(self.bounty_paid)
+    }
+
+    // The bounty the draw pays its caller: the lottery's `draw_bounty`, capped at `max_bps` of the jackpot
+    // Shares of the jackpot are rounded down, see `math`
+    pub fn draw_bounty_amount(&self, max_bps: u16) -> Option<u64> {
+        let jackpot = self.jackpot()?;
+        let bounty = match self.draw_bounty {
+            DrawBounty::None => 0,
+            DrawBounty::Flat(amount) => amount,
+            DrawBounty::Bps(bps) => math::bps_floor(jackpot, bps)?,
+        };
+        Some(bounty.min(math::bps_floor(jackpot, max_bps)?))
+    }
+
+    // Split the jackpot into the winner's `winner_share_bps` and the remainder that rolls over
+    // The winner's share is rounded down, so the rounding dust rolls over with the remainder
+    pub fn split_jackpot(&self) -> Option<(u64, u64)> {
+        math::split_bps(self.jackpot()?, self.winner_share_bps)
+    }
+
+    // What a ticket that paid `paid` lamports recovers from an unclaimed prize: its own payment back, plus an even
+    // share of the carried over jackpot, less an even share (rounded up) of the draw bounty. The shares add up to
+    // at most the jackpot, as tickets paid exactly the pot. A ticket refunded within its grace period paid nothing
+    // and gets nothing
+    pub fn recovery_share(&self, paid: u64) -> Option<u64> {
+        if paid == 0 {
+            return Some(0);
+        }
+        let tickets = u64::from(self.live_tickets());
+        paid.checked_add(math::pro_rata_floor(self.carried_over, tickets)?)?
+            .checked_sub(math::pro_rata_ceil(self.bounty_paid, tickets)?)
+    }
+
+    // Whether any of the prize was paid out: the winner claimed it, or a matching ticket claimed its share
+    // The rest of a match mode prize stays claimable for good once the first share is paid
+    pub fn payout_started(&self) -> bool {
+        self.claimed || self.matches_claimed > 0
+    }
+
+    // Whether `count_matches` has checked every ticket of a match mode lottery; a single winner lottery never counts
+    pub fn matches_counted(&self) -> bool {
+        self.match_bits > 0 && self.winner_id.is_some() && self.match_cursor == self.last_ticket_id
+    }
+
+    // Whether code tickets can still be redeemed once the lottery is drawn
+    // Not in a no_self_entry lottery, where the authority holding an unredeemed ticket that won could redeem it to a
+    // wallet of its own, nor in match mode, where redeeming derives a new art seed after the tickets were counted
+    pub fn redeems_after_draw(&self) -> bool {
+        !self.no_self_entry && self.match_bits == 0
+    }
+
+    // Whether `ticket` can win at all: it wasn't refunded, and if it is an unredeemed code ticket, its code can still
+    // be redeemed. Tickets that can't win still take part in the draw, which is redone if it picks one
+    pub fn can_win(&self, ticket: &Ticket) -> bool {
+        !ticket.refunded && (ticket.code_hash.is_none() || self.redeems_after_draw())
+    }
+
+    // The tickets that can win once the lottery is drawn: the live ones, less the unredeemed code tickets if their
+    // codes can't be redeemed after the draw. The draw waits until there is at least one, so redraws always end
+    pub fn eligible_tickets(&self) -> u32 {
+        if self.redeems_after_draw() {
+            self.live_tickets()
+        } else {
+            self.live_tickets().saturating_sub(self.unredeemed_codes)
+        }
+    }
+
+    // Whether the drawn ticket can't win and the lottery has to be redrawn with `redraw_void_winner`
+    // A match mode lottery only falls back to its drawn ticket, and so only redraws, once it counted no matches
+    pub fn needs_redraw(&self, winning_ticket: &Ticket) -> bool {
+        self.winner_id == Some(winning_ticket.id)
+            && !self.can_win(winning_ticket)
+            && (self.match_bits == 0 || (self.matches_counted() && self.match_count == 0))
+    }
+
+    // Whether `ticket` won the prize, or a share of it: the drawn ticket if it can win, or in match mode every ticket
+    // that can win and whose art seed shares the draw seed's first `match_bits` bits. A match mode lottery only knows
+    // its winners once they're counted, and falls back to the drawn ticket if none matched
+    pub fn wins(&self, ticket: &Ticket) -> bool {
+        let Some(winner_id) = self.winner_id else {
+            return false;
+        };
+        if !self.can_win(ticket) {
+            return false;
+        }
+        if self.match_bits == 0 || (self.matches_counted() && self.match_count == 0) {
+            return winner_id == ticket.id;
+        }
+        self.matches_counted()
+            && draw::prefix_matches(&ticket.art_seed, &self.draw_seed, self.match_bits)
+    }
+
+    // What the next claim pays, as `split_jackpot` splits it: a single winner gets the whole winner's share and the
+    // remainder rolls over. Matching tickets each get an even share of the winner's share, rounded down, with the
+    // dust going to the last one to claim; the first one to claim rolls the remainder over
+    pub fn claim_split(&self) -> Option<(u64, u64)> {
+        let (price, rollover) = self.split_jackpot()?;
+        if self.match_count == 0 {
+            return Some((price, rollover));
+        }
+        let share = math::pro_rata_floor(price, self.match_count.into())?;
+        let share = if self.matches_claimed + 1 == self.match_count {
+            price.checked_sub(share.checked_mul((self.match_count - 1).into())?)?
+        } else {
+            share
+        };
+        Some((
+            share,
+            if self.matches_claimed == 0 {
+                rollover
+            } else {
+                0
+            },
+        ))
+    }
+
+    // Whether ticket owners can recover their funds: the lottery was cancelled, or the prize went unclaimed for long enough
+    pub fn recovery_open(&self, now: i64, recovery_period: i64) -> bool {
+        !self.payout_started()
+            && (self.cancelled
+                || (self.winner_id.is_some()
+                    && now >= self.drawn_at.saturating_add(recovery_period)))
+    }
+
+    // Whether `player` may hold a ticket in this lottery
+    pub fn accepts_player(&self, player: &Pubkey) -> bool {
+        !(self.no_self_entry && *player == self.authority)
+    }
+
+    // Whether a player's profile carries every self-attestation the lottery's policy requires
+    pub fn attestation_met(&self, profile: Option<&Profile>) -> bool {
+        let claims = profile
+            .and_then(|profile| profile.attestation)
+            .map_or(0, |attestation| attestation.claims);
+        claims & self.attestation_policy == self.attestation_policy
+    }
+}
+
+// Define the accounts context for the `set_winner_callback` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct SetWinnerCallback<'info> {
+    // Define the lottery account, which receives the callback
+    #[account(
+        mut, // The lottery account is mutable, as its callback will change
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        has_one = authority, // Only the lottery authority registers callbacks
+        constraint = lottery.last_ticket_id == 0 @ LotteryError::CallbackLocked, // Buyers must know what a win triggers
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    /// CHECK: The program being registered, checked to be executable and not this program
+    pub callback_program: Option<UncheckedAccount<'info>>,
+
+    // Define the authority account, which must be the lottery authority
+    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
+}
+
+// Define the accounts context for the `set_display_hints` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct SetDisplayHints<'info> {
+    // Define the lottery account, whose display hints will change
+    #[account(
+        mut, // The lottery account is mutable, as its display hints will change
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        has_one = authority, // Only the lottery authority sets the display hints
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the master account, which numbers the emitted event
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the authority account, which must be the lottery authority
+    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
+}
+
+// Define the accounts context for the `set_terms` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct SetTerms<'info> {
+    // Define the lottery account, whose terms hash will change
+    #[account(
+        mut, // The lottery account is mutable, as its terms hash will change
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        has_one = authority, // Only the lottery authority sets the terms
+        constraint = lottery.last_ticket_id == 0 @ LotteryError::TermsLocked, // Tickets already sold were bought under the current terms
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the authority account, which must be the lottery authority
+    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
+}
+
+// Define the accounts context for the `set_payment_mint` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct SetPaymentMint<'info> {
+    // Define the lottery account, which records the payment mint
+    #[account(
+        mut, // The lottery account is mutable, as the payment mint will be set
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        has_one = authority, // Only the lottery authority sets the payment mint
+        constraint = lottery.last_ticket_id == 0 @ LotteryError::TermsLocked, // Tickets already sold were paid in lamports
+        constraint = lottery.prize_kind == PrizeKind::Lamports @ LotteryError::WrongPrizeKind, // A right lottery's pot goes to the authority
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the mint tickets will be paid in
+    pub mint: Account<'info, Mint>,
+
+    // Define the lottery's vault token account, which is initialized here and owned by the lottery PDA
+    // Its address only depends on the lottery, so a lottery has one payment mint for good
+    #[account(
+        init,
+        payer = authority,
+        seeds = [VAULT_SEED.as_bytes(), lottery.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = lottery,
+    )]
+    pub vault: Account<'info, TokenAccount>,
+
+    // Define the creator account, which takes back lamports rolled over into the lottery
+    #[account(mut, seeds = [CREATOR_SEED.as_bytes(), lottery.authority.as_ref()], bump)]
+    pub creator: Account<'info, Creator>, // Define the creator account of type `Creator`
+
+    // Define the authority account, which must be the lottery authority and pays for the vault
+    #[account(mut)]
+    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
+
+    // Reference to the token program, which owns the vault
+    pub token_program: Program<'info, Token>,
+
+    // Reference to the system program, used to create the vault
+    pub system_program: Program<'info, System>,
+}
+
+// Define the accounts context for the `buy_ticket` function
+// Specifies the accounts that need to be provided to this instruction
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct BuyTicket<'info> {
+    // Define the lottery account, which the ticket will be associated with
+    #[account(
+        mut, // The lottery account is mutable, as the last_ticket_id will be updated
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()], // Use LOTTERY_SEED and lottery_id as seeds for generating the PDA
+        bump, // The bump seed used to create a valid PDA
+        constraint = lottery.id == lottery_id @ LotteryError::LotteryIdMismatch, // Don't trust the lottery_id argument on its own
+        constraint = lottery.is_open() @ LotteryError::SalesClosed, // No tickets once the winner is drawn
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the ticket account, which is initialized here
+    #[account(
+        init, // This attribute indicates that this account is being initialized
+        payer = buyer, // The buyer is responsible for covering the fees for creating this account
+        space = TICKET_SPACE, // Allocate enough space for the account (total 154 bytes)
+        seeds = [
+            TICKET_SEED.as_bytes(), // Use TICKET_SEED as part of the seed for generating a PDA
+            lottery.key().as_ref(), // Include the lottery key as part of the seed
+            &lottery.last_ticket_id.to_le_bytes(), // Include the current last_ticket_id as part of the seed
+        ],
+        bump, // The bump seed used to create a valid PDA
+    )]
+    pub ticket: Account<'info, Ticket>, // Define the ticket account of type `Ticket`
+
+    // Define the buyer account, which will purchase the ticket
+    #[account(
+        mut, // The buyer account is mutable (e.g., its balance will be deducted)
+        constraint = lottery.accepts_player(&buyer.key()) @ LotteryError::SelfEntryForbidden, // Enforce self-exclusion
+    )]
+    pub buyer: Signer<'info>, // The signer is the account that authorizes this transaction
+
+    // Reference to the system program, used to interact with Solana's native features
+    pub system_program: Program<'info, System>,
+
+    // Define the master account, read for the current season and updated with the event sequence number
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the current season account, which accumulates the season's stats
+    #[account(mut, seeds = [SEASON_SEED.as_bytes(), &master.season.to_le_bytes()], bump)]
+    pub season: Account<'info, Season>, // Define the season account of type `Season`
+
+    /// CHECK: Optional Solana Pay reference key, only included read-only so the purchase can be located by it
+    pub reference: Option<UncheckedAccount<'info>>,
+
+    // Define the buyer's profile account, passed to track their stats
+    #[account(mut, seeds = [PROFILE_SEED.as_bytes(), buyer.key().as_ref()], bump)]
+    pub profile: Option<Account<'info, Profile>>, // Define the profile account of type `Profile`
+
+    // Define the leaderboard account, passed along with the profile to rank the buyer
+    #[account(mut, seeds = [LEADERBOARD_SEED.as_bytes()], bump)]
+    pub leaderboard: Option<AccountLoader<'info, Leaderboard>>, // Define the leaderboard account of type `Leaderboard`
+
+    // Define the lottery's rent pool account, passed to have the ticket rent refunded
+    #[account(mut, seeds = [RENT_POOL_SEED.as_bytes(), lottery.key().as_ref()], bump)]
+    pub rent_pool: Option<Account<'info, RentPool>>, // Define the rent pool account of type `RentPool`
+
+    // Define the buyer's voucher account, passed to pay with voucher credit instead of lamports
+    #[account(mut, seeds = [VOUCHER_SEED.as_bytes(), buyer.key().as_ref()], bump)]
+    pub voucher: Option<Account<'info, Voucher>>, // Define the voucher account of type `Voucher`
+
+    // Define the buyer's token account, which pays for the ticket in a token lottery
+    #[account(mut)]
+    pub buyer_token_account: Option<Account<'info, TokenAccount>>,
+
+    // Define the lottery's vault token account, which receives the payment in a token lottery
+    #[account(mut, seeds = [VAULT_SEED.as_bytes(), lottery.key().as_ref()], bump)]
+    pub vault: Option<Account<'info, TokenAccount>>,
+
+    // Reference to the token program, used for token payments
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+// Define the accounts context for the `buy_tickets` function
+// The ticket accounts are passed as remaining accounts, see `buy_tickets`
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct BuyTickets<'info> {
+    // Define the lottery account, which the tickets will be associated with
+    #[account(
+        mut, // The lottery account is mutable, as the last_ticket_id will be updated
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()], // Use LOTTERY_SEED and lottery_id as seeds for generating the PDA
+        bump, // The bump seed used to create a valid PDA
+        constraint = lottery.id == lottery_id @ LotteryError::LotteryIdMismatch, // Don't trust the lottery_id argument on its own
+        constraint = lottery.is_open() @ LotteryError::SalesClosed, // No tickets once the winner is drawn
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the buyer account, which will purchase the tickets
+    #[account(
+        mut, // The buyer account is mutable (e.g., its balance will be deducted)
+        constraint = lottery.accepts_player(&buyer.key()) @ LotteryError::SelfEntryForbidden, // Enforce self-exclusion
+    )]
+    pub buyer: Signer<'info>, // The signer is the account that authorizes this transaction
+
+    // Reference to the system program, used to interact with Solana's native features
+    pub system_program: Program<'info, System>,
+
+    // Define the master account, read for the current season and updated with the event sequence number
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the current season account, which accumulates the season's stats
+    #[account(mut, seeds = [SEASON_SEED.as_bytes(), &master.season.to_le_bytes()], bump)]
+    pub season: Account<'info, Season>, // Define the season account of type `Season`
+
+    // Define the buyer's profile account, passed to track their stats
+    #[account(mut, seeds = [PROFILE_SEED.as_bytes(), buyer.key().as_ref()], bump)]
+    pub profile: Option<Account<'info, Profile>>, // Define the profile account of type `Profile`
+
+    // Define the leaderboard account, passed along with the profile to rank the buyer
+    #[account(mut, seeds = [LEADERBOARD_SEED.as_bytes()], bump)]
+    pub leaderboard: Option<AccountLoader<'info, Leaderboard>>, // Define the leaderboard account of type `Leaderboard`
+
+    // Define the buyer's token account, which pays for the tickets in a token lottery
+    #[account(mut)]
+    pub buyer_token_account: Option<Account<'info, TokenAccount>>,
+
+    // Define the lottery's vault token account, which receives the payment in a token lottery
+    #[account(mut, seeds = [VAULT_SEED.as_bytes(), lottery.key().as_ref()], bump)]
+    pub vault: Option<Account<'info, TokenAccount>>,
+
+    // Reference to the token program, used for token payments
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+// Define the accounts context for the `buy_voucher` function
+#[derive(Accounts)]
+#[instruction(recipient: Pubkey)]
+pub struct BuyVoucher<'info> {
+    // Define the recipient's voucher account, created on the first purchase
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + 32 + 8, // Allocate enough space for the account (total 48 bytes)
+        // 8 +  // Account discriminator
+        // 32 + // owner: Pubkey
+        // 8;   // balance: u64
+        seeds = [VOUCHER_SEED.as_bytes(), recipient.as_ref()],
+        bump,
+    )]
+    pub voucher: Account<'info, Voucher>, // Define the voucher account of type `Voucher`
+
+    // Define the payer account, which buys the credit and pays the rent of a new voucher
+    #[account(mut)]
+    pub payer: Signer<'info>, // The signer is the account that authorizes this transaction
+
+    // Reference to the system program, used to interact with Solana's native features
+    pub system_program: Program<'info, System>,
+
+    // Define the master account, which numbers the emitted event
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+}
+
+// Define the data structure that will be stored in each voucher account
+// The account holds the credit as lamports on top of its rent exemption; `balance` tracks how much of it is credit
+#[account]
+pub struct Voucher {
+    pub owner: Pubkey, // The public key of the wallet that can spend the credit (32 bytes)
+    pub balance: u64,  // The credit left, in lamports (8 bytes for a u64 integer)
+}
+
+impl Voucher {
+    // Spend `amount` lamports of credit, paying them to `to`
+    pub fn spend(voucher: &mut Account<Voucher>, to: &AccountInfo, amount: u64) -> Result<()> {
+        voucher.balance = voucher
+            .balance
+            .checked_sub(amount)
+            .ok_or(LotteryError::InsufficientVoucherBalance)?;
+
+        **voucher.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **to.try_borrow_mut_lamports()? += amount;
+        Ok(())
+    }
+}
+
+// Define the accounts context for the `fund_rent_pool` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct FundRentPool<'info> {
+    // Define the lottery account the pool belongs to
+    #[account(
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        has_one = authority, // Only the lottery authority funds its rent pool
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the rent pool account, created on the first call
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + 32 + 8, // Allocate enough space for the account (total 48 bytes)
+        // 8 +  // Account discriminator
+        // 32 + // lottery: Pubkey
+        // 8;   // reimbursed: u64
+        seeds = [RENT_POOL_SEED.as_bytes(), lottery.key().as_ref()],
+        bump,
+    )]
+    pub rent_pool: Account<'info, RentPool>, // Define the rent pool account of type `RentPool`
+
+    // Define the authority account, which funds the pool
+    #[account(mut)]
+    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
+
+    // Reference to the system program, used to interact with Solana's native features
+    pub system_program: Program<'info, System>,
+}
+
+// Define the accounts context for the `close_rent_pool` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct CloseRentPool<'info> {
+    // Define the lottery account the pool belongs to
+    #[account(
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        has_one = authority, // Only the lottery authority gets the unused rent back
+        constraint = lottery.winner_id.is_some() @ LotteryError::WinnerNotChosen, // No more tickets can be sold
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the rent pool account, which is closed here
+    #[account(
+        mut,
+        seeds = [RENT_POOL_SEED.as_bytes(), lottery.key().as_ref()],
+        bump,
+        close = authority, // Return the remaining lamports to the authority
+    )]
+    pub rent_pool: Account<'info, RentPool>, // Define the rent pool account of type `RentPool`
+
+    // Define the authority account, which receives the unused funds
+    #[account(mut)]
+    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
+}
+
+// Define the data structure that will be stored in each lottery's rent pool account
+// The pool's lamports above its own rent exemption are available to refund ticket rent
+#[account]
+pub struct RentPool {
+    pub lottery: Pubkey, // The public key of the lottery the pool belongs to (32 bytes)
+    pub reimbursed: u64, // The lamports refunded to buyers so far (8 bytes for a u64 integer)
+}
+
+impl RentPool {
+    // Refund `amount` lamports of rent to the buyer, if the pool can still cover it
+    // An empty pool isn't an error; the buyer just keeps paying the rent themselves
+    pub fn reimburse(pool: &mut Account<RentPool>, buyer: &AccountInfo, amount: u64) -> Result<()> {
+        let info = pool.to_account_info();
+        let reserved = Rent::get()?.minimum_balance(info.data_len());
+        if info.lamports().saturating_sub(reserved) < amount {
+            return Ok(());
+        }
+
+        **info.try_borrow_mut_lamports()? -= amount;
+        **buyer.try_borrow_mut_lamports()? += amount;
+        pool.reimbursed += amount;
+        Ok(())
+    }
+}
+
+// Define the accounts context for the `simulate_buy` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct SimulateBuy<'info> {
+    // Define the lottery account the purchase would be made in
+    #[account(
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        constraint = lottery.id == lottery_id @ LotteryError::LotteryIdMismatch, // Don't trust the lottery_id argument on its own
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the master account, read for the current time
+    #[account(seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the would-be buyer, only read for its balance
+    pub buyer: SystemAccount<'info>,
+
+    // Define the buyer's profile account, to be passed like to `buy_ticket` when the lottery asks for self-attestations
+    #[account(seeds = [PROFILE_SEED.as_bytes(), buyer.key().as_ref()], bump)]
+    pub profile: Option<Account<'info, Profile>>, // Define the profile account of type `Profile`
+}
+
+// Define the data returned by `simulate_buy`
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SimulatedBuy {
+    pub error_code: Option<u32>, // The error code the purchase would fail with, None if it would succeed
+    pub total_cost: u64,         // What the tickets would cost, in lamports or the payment mint's base units, excluding account rent
+}
+
+// Define the accounts context for the `get_ticket_status` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32, ticket_id: u32)]
+pub struct GetTicketStatus<'info> {
+    // Define the lottery account the ticket belongs to
+    #[account(
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        constraint = lottery.id == lottery_id @ LotteryError::LotteryIdMismatch, // Don't trust the lottery_id argument on its own
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the ticket account whose status is read
+    #[account(
+        seeds = [TICKET_SEED.as_bytes(), lottery.key().as_ref(), &ticket_id.to_le_bytes()],
+        bump,
+        constraint = ticket.id == ticket_id @ LotteryError::TicketIdMismatch, // Don't trust the ticket_id argument on its own
+    )]
+    pub ticket: Account<'info, Ticket>, // Define the ticket account of type `Ticket`
+
+    /// CHECK: The ticket's receipt, which only exists once the ticket was paid out; only its existence is read
+    #[account(seeds = [RECEIPT_SEED.as_bytes(), lottery.key().as_ref(), ticket.key().as_ref()], bump)]
+    pub receipt: UncheckedAccount<'info>,
+
+    // Define the master account, read for the current time and the recovery period
+    #[account(seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the config account, read for the protocol fee
+    #[account(seeds = [CONFIG_SEED.as_bytes()], bump)]
+    pub config: Account<'info, Config>, // Define the config account of type `Config`
+
+    // Define the lottery creator's account, read for their protocol fee override
+    #[account(seeds = [CREATOR_SEED.as_bytes(), lottery.authority.as_ref()], bump)]
+    pub creator: Account<'info, Creator>, // Define the creator account of type `Creator`
+}
+
+// Define the data returned by `get_ticket_status`
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct TicketStatus {
+    pub is_winner: bool, // Whether the ticket was drawn as the winner of a lottery that wasn't cancelled
+    pub tier: u8, // The prize tier the ticket won, WINNING_TIER for the winner and 0 for every other ticket
+    pub gross: u64, // The lamports the ticket is owed before fees
+    pub fees: u64, // The ticket's part of the draw bounty plus the protocol fee on a prize, in lamports
+    pub net: u64,  // The lamports a claim or recovery pays out right now
+    pub claimed: bool, // Whether the ticket has been paid out
+}
+
+// Space of a ticket account (total 163 bytes)
+// 8 +  // Account discriminator
+// 4 +  // id: u32
+// 32 + // authority: Pubkey
+// 4 +  // lottery_id: u32
+// 1 + 32 + // code_hash: Option<[u8; 32]> (1 byte for option tag + 32 bytes for the hash)
+// 1 + 32 + // delegate: Option<Pubkey> (1 byte for option tag + 32 bytes for the key)
+// 32 + // art_seed: [u8; 32]
+// 8 +  // paid: u64
+// 8 +  // bought_slot: u64
+// 1;   // refunded: bool
+const TICKET_SPACE: usize = 8 + 4 + 32 + 4 + 1 + 32 + 1 + 32 + 32 + 8 + 8 + 1;
+
+// Define the data structure that will be stored in the ticket account
+#[account]
+pub struct Ticket {
+    pub id: u32,                     // The ID of the ticket (4 bytes for a u32 integer)
+    pub authority: Pubkey,           // The public key of the ticket owner (32 bytes)
+    pub lottery_id: u32,             // The ID of the lottery that this ticket belongs to (4 bytes for a u32 integer)
+    pub code_hash: Option<[u8; 32]>, // The hash of the redemption code while the ticket is unredeemed
+    pub delegate: Option<Pubkey>,    // A wallet the owner allowed to claim the prize on their behalf, if any
+    pub art_seed: [u8; 32],          // Seed frontends render the ticket's generative art from, see `Ticket::art_seed` (32 bytes)
+    pub paid: u64,                   // The lamports actually paid for the ticket, which refunds are based on (8 bytes for a u64 integer)
+    pub bought_slot: u64,            // The slot the ticket was bought in, 0 if it has no refund grace period (8 bytes for a u64 integer)
+    pub refunded: bool,              // Whether the ticket was refunded within its grace period; it keeps its ID but can't win
+}
+
+impl Ticket {
+    // Derive the art seed of a ticket: sha256(lottery key ++ ticket id ++ owner)
+    // Anyone can recompute it, so frontends can check the art they render belongs to the ticket
+    pub fn art_seed(lottery: &Pubkey, id: u32, owner: &Pubkey) -> [u8; 32] {
+        hashv(&[lottery.as_ref(), &id.to_le_bytes(), owner.as_ref()]).to_bytes()
+    }
+
+    // Whether `signer` may claim this ticket's prize: the owner, or the delegate they authorized
+    pub fn may_claim(&self, signer: &Pubkey) -> bool {
+        *signer == self.authority || self.delegate == Some(*signer)
+    }
+}
+
+// Define the accounts context for the `set_ticket_delegate` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32, ticket_id: u32)]
+pub struct SetTicketDelegate<'info> {
+    // Define the lottery account the ticket belongs to
+    #[account(seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()], bump)]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the ticket account, which receives the delegate
+    #[account(
+        mut, // The ticket account is mutable, as its delegate will change
+        seeds = [
+            TICKET_SEED.as_bytes(),
+            lottery.key().as_ref(),
+            &ticket_id.to_le_bytes()
+        ],
+        bump,
+        has_one = authority, // Only the ticket owner can choose a delegate
+    )]
+    pub ticket: Account<'info, Ticket>, // Define the ticket account of type `Ticket`
+
+    // Define the authority account, which must be the ticket owner
+    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
+
+    // Define the master account, which numbers the emitted event
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+}
+
+// Define the accounts context for the `refund_ticket` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32, ticket_id: u32)]
+pub struct RefundTicket<'info> {
+    // Define the lottery account, which pays the refund out of its pot
+    #[account(
+        mut, // The lottery account is mutable, as it pays the refund
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        constraint = lottery.id == lottery_id @ LotteryError::LotteryIdMismatch, // Don't trust the lottery_id argument on its own
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the ticket account being refunded, which stays open as a refunded ticket
+    #[account(
+        mut, // The ticket account is mutable, as it is marked refunded
+        seeds = [TICKET_SEED.as_bytes(), lottery.key().as_ref(), &ticket_id.to_le_bytes()],
+        bump,
+        has_one = authority, // Only the ticket owner takes their ticket back
+        constraint = ticket.id == ticket_id @ LotteryError::TicketIdMismatch, // Don't trust the ticket_id argument on its own
+    )]
+    pub ticket: Account<'info, Ticket>, // Define the ticket account of type `Ticket`
+
+    // Define the authority account, which must be the ticket owner and receives the refund
+    #[account(mut)]
+    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
+
+    // Define the master account, read for the current time and numbering the emitted event
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+}
+
+// Define the accounts context for the `create_code_ticket` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct CreateCodeTicket<'info> {
+    // Define the lottery account, which the ticket will be associated with
+    #[account(
+        mut, // The lottery account is mutable, as the last_ticket_id will be updated
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        has_one = authority, // Only the lottery authority can mint code tickets
+        constraint = lottery.is_open() @ LotteryError::SalesClosed, // No tickets once the winner is drawn
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the master account, read to check that code tickets are enabled
+    #[account(
+        mut, // The master account is mutable, as the event sequence number will be updated
+        seeds = [MASTER_SEED.as_bytes()],
+        bump,
+        constraint = master.has_feature(FEATURE_CODE_TICKETS) @ LotteryError::FeatureDisabled,
+    )]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the ticket account, which is initialized here with the same layout as a bought ticket
+    #[account(
+        init,
+        payer = authority, // The authority pays for the ticket account until the code is redeemed
+        space = TICKET_SPACE, // Same space as a bought ticket (total 154 bytes)
+        seeds = [
+            TICKET_SEED.as_bytes(),
+            lottery.key().as_ref(),
+            &lottery.last_ticket_id.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub ticket: Account<'info, Ticket>, // Define the ticket account of type `Ticket`
+
+    // Define the authority account, which pays for the ticket
+    #[account(mut)] // The authority account is mutable (e.g., its balance will be deducted)
+    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
+
+    // Reference to the system program, used to interact with Solana's native features
+    pub system_program: Program<'info, System>,
+
+    // Define the current season account, which accumulates the season's stats
+    #[account(mut, seeds = [SEASON_SEED.as_bytes(), &master.season.to_le_bytes()], bump)]
+    pub season: Account<'info, Season>, // Define the season account of type `Season`
+}
+
+// Define the accounts context for the `redeem_code_ticket` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32, ticket_id: u32)]
+pub struct RedeemCodeTicket<'info> {
+    // Define the lottery account the ticket belongs to
+    #[account(
+        mut, // The lottery account is mutable, as its unredeemed code tickets will be counted down
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the ticket account being redeemed
+    #[account(
+        mut, // The ticket account is mutable, as its authority will change
+        seeds = [
+            TICKET_SEED.as_bytes(),
+            lottery.key().as_ref(),
+            &ticket_id.to_le_bytes()
+        ],
+        bump,
+    )]
+    pub ticket: Account<'info, Ticket>, // Define the ticket account of type `Ticket`
+
+    // Define the wallet that will own the ticket once the code is redeemed
+    #[account(
+        constraint = lottery.accepts_player(&owner.key()) @ LotteryError::SelfEntryForbidden, // Enforce self-exclusion
+    )]
+    pub owner: Signer<'info>, // The signer is the account that authorizes this transaction
+
+    // Define the master account, which numbers the emitted event
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the redeemer's profile account, required when the lottery asks for self-attestations
+    #[account(seeds = [PROFILE_SEED.as_bytes(), owner.key().as_ref()], bump)]
+    pub profile: Option<Account<'info, Profile>>, // Define the profile account of type `Profile`
+}
+
+// Define the accounts context for the `reserve_tickets` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct ReserveTickets<'info> {
+    // Define the lottery account the block of tickets is reserved in
+    #[account(
+        mut, // The lottery account is mutable, as the last_ticket_id will be updated
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        has_one = authority, // Only the lottery authority can reserve tickets
+        constraint = lottery.is_open() @ LotteryError::SalesClosed, // No tickets once the winner is drawn
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the reservation account, one per partner and lottery
+    #[account(
+        init,
+        payer = partner, // The partner pays for the reservation along with its tickets
+        space = 8 + 4 + 32 + 4 + 4 + 4 + 8, // Allocate enough space for the account (total 64 bytes)
+        // 8 +  // Account discriminator
+        // 4 +  // lottery_id: u32
+        // 32 + // partner: Pubkey
+        // 4 +  // first_ticket_id: u32
+        // 4 +  // count: u32
+        // 4 +  // assigned: u32
+        // 8;   // price: u64
+        seeds = [RESERVATION_SEED.as_bytes(), lottery.key().as_ref(), partner.key().as_ref()],
+        bump,
+    )]
+    pub reservation: Account<'info, Reservation>, // Define the reservation account of type `Reservation`
+
+    // Define the authority account, which agrees to the deal
+    pub authority: Signer<'info>, // The signer is the lottery authority
+
+    // Define the partner account, which pays for the block
+    #[account(mut)] // The partner account is mutable (e.g., its balance will be deducted)
+    pub partner: Signer<'info>, // The signer is the partner the block is reserved for
+
+    // Define the master account, which numbers the emitted event
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Reference to the system program, used to interact with Solana's native features
+    pub system_program: Program<'info, System>,
+
+    // Define the current season account, which accumulates the season's stats
+    #[account(mut, seeds = [SEASON_SEED.as_bytes(), &master.season.to_le_bytes()], bump)]
+    pub season: Account<'info, Season>, // Define the season account of type `Season`
+}
+
+// Define the accounts context for the `assign_reserved_ticket` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct AssignReservedTicket<'info> {
+    // Define the lottery account the ticket belongs to
+    #[account(
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the partner's reservation account
+    #[account(
+        mut, // The reservation account is mutable, as the number of assigned tickets will be updated
+        seeds = [RESERVATION_SEED.as_bytes(), lottery.key().as_ref(), partner.key().as_ref()],
+        bump,
+        constraint = reservation.assigned < reservation.count @ LotteryError::ReservationExhausted, // Every ticket of the block was handed out
+    )]
+    pub reservation: Account<'info, Reservation>, // Define the reservation account of type `Reservation`
+
+    // Define the ticket account, which is initialized here with the same layout as a bought ticket
+    #[account(
+        init,
+        payer = partner, // The partner pays for the ticket account
+        space = TICKET_SPACE, // Same space as a bought ticket (total 154 bytes)
+        seeds = [
+            TICKET_SEED.as_bytes(),
+            lottery.key().as_ref(),
+            &(reservation.first_ticket_id + reservation.assigned).to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub ticket: Account<'info, Ticket>, // Define the ticket account of type `Ticket`
+
+    // Define the partner account, which hands out the ticket
+    #[account(mut)] // The partner account is mutable (e.g., its balance will be deducted)
+    pub partner: Signer<'info>, // The signer is the partner the block is reserved for
+
+    /// CHECK: The end user receiving the ticket, only stored as the ticket owner
+    #[account(
+        constraint = lottery.accepts_player(&owner.key()) @ LotteryError::SelfEntryForbidden, // Enforce self-exclusion
+    )]
+    pub owner: UncheckedAccount<'info>,
+
+    // Define the end user's profile account, required when the lottery asks for self-attestations
+    #[account(seeds = [PROFILE_SEED.as_bytes(), owner.key().as_ref()], bump)]
+    pub profile: Option<Account<'info, Profile>>, // Define the profile account of type `Profile`
+
+    // Define the master account, which numbers the emitted event
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Reference to the system program, used to interact with Solana's native features
+    pub system_program: Program<'info, System>,
+}
+
+// Define the data structure that will be stored in each reservation account
+// Ticket IDs `first_ticket_id..first_ticket_id + count` belong to the partner; the first `assigned` of them have been
+// handed out and have ticket accounts, the rest are held by the partner
+#[account]
+pub struct Reservation {
+    pub lottery_id: u32, // The ID of the lottery the block belongs to (4 bytes for a u32 integer)
+    pub partner: Pubkey, // The public key of the partner the block is reserved for (32 bytes)
+    pub first_ticket_id: u32, // The first ticket ID of the block (4 bytes for a u32 integer)
+    pub count: u32,      // The number of tickets in the block (4 bytes for a u32 integer)
+    pub assigned: u32,   // The number of tickets handed out so far (4 bytes for a u32 integer)
+    pub price: u64, // The lamports paid for each ticket of the block (8 bytes for a u64 integer)
+}
+
+// Define the accounts context for the `stock_inventory` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32, block_id: u32)]
+pub struct StockInventory<'info> {
+    // Define the lottery account the block is stocked for
+    #[account(
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        has_one = authority, // Only the lottery authority stocks house tickets
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the inventory account, which is initialized here; block IDs are chosen by the authority
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 32 + 4 + 32 + 2 + 2 + 32, // Allocate enough space for the account (total 112 bytes)
+        // 8 +  // Account discriminator
+        // 32 + // lottery: Pubkey
+        // 4 +  // block_id: u32
+        // 32 + // code_root: [u8; 32]
+        // 2 +  // count: u16
+        // 2 +  // sold: u16
+        // 32;  // sold_map: [u8; 32] (one bit per ticket of a MAX_INVENTORY_BLOCK block)
+        seeds = [INVENTORY_SEED.as_bytes(), lottery.key().as_ref(), &block_id.to_le_bytes()],
+        bump,
+    )]
+    pub inventory: Account<'info, Inventory>, // Define the inventory account of type `Inventory`
+
+    // Define the master account, which numbers the emitted event
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the authority account, which must be the lottery authority and pays for the inventory account
+    #[account(mut)]
+    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
+
+    // Reference to the system program, used to create the inventory account
+    pub system_program: Program<'info, System>,
+}
+
+// Define the accounts context for the `buy_from_inventory` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32, block_id: u32)]
+pub struct BuyFromInventory<'info> {
+    // Define the lottery account, which the ticket will be associated with
+    #[account(
+        mut, // The lottery account is mutable, as the last_ticket_id will be updated
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the inventory account the ticket is sold from
+    #[account(
+        mut, // The inventory account is mutable, as the ticket will be marked sold
+        seeds = [INVENTORY_SEED.as_bytes(), lottery.key().as_ref(), &block_id.to_le_bytes()],
+        bump,
+    )]
+    pub inventory: Account<'info, Inventory>, // Define the inventory account of type `Inventory`
+
+    // Define the ticket account, which is initialized here with the same layout as a bought ticket
+    #[account(
+        init,
+        payer = buyer, // The buyer pays for the ticket account
+        space = TICKET_SPACE, // Same space as a bought ticket (total 154 bytes)
+        seeds = [
+            TICKET_SEED.as_bytes(),
+            lottery.key().as_ref(),
+            &lottery.last_ticket_id.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub ticket: Account<'info, Ticket>, // Define the ticket account of type `Ticket`
+
+    // Define the buyer account, which presents the code and pays for the ticket
+    #[account(
+        mut, // The buyer account is mutable (e.g., its balance will be deducted)
+        constraint = lottery.accepts_player(&buyer.key()) @ LotteryError::SelfEntryForbidden, // Enforce self-exclusion
+    )]
+    pub buyer: Signer<'info>, // The signer is the account that authorizes this transaction
+
+    // Define the buyer's profile account, required when the lottery asks for self-attestations
+    #[account(seeds = [PROFILE_SEED.as_bytes(), buyer.key().as_ref()], bump)]
+    pub profile: Option<Account<'info, Profile>>, // Define the profile account of type `Profile`
+
+    // Define the master account, which numbers the emitted events
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the current season account, which accumulates the season's stats
+    #[account(mut, seeds = [SEASON_SEED.as_bytes(), &master.season.to_le_bytes()], bump)]
+    pub season: Account<'info, Season>, // Define the season account of type `Season`
+
+    // Reference to the system program, used to interact with Solana's native features
+    pub system_program: Program<'info, System>,
+}
+
+// Define the accounts context for the `void_inventory` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32, block_id: u32)]
+pub struct VoidInventory<'info> {
+    // Define the lottery account the block was stocked for
+    #[account(
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        has_one = authority, // Only the lottery authority voids house tickets
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the inventory account, which is closed here once sales are over
+    #[account(
+        mut,
+        seeds = [INVENTORY_SEED.as_bytes(), lottery.key().as_ref(), &block_id.to_le_bytes()],
+        bump,
+    )]
+    pub inventory: Account<'info, Inventory>, // Define the inventory account of type `Inventory`
+
+    // Define the master account, which numbers the emitted event
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the authority account, which must be the lottery authority and receives the rent
+    #[account(mut)]
+    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
+}
+
+// Define the accounts context for the `close_sales_early` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct CloseSalesEarly<'info> {
+    // Define the lottery account whose sales end
+    #[account(
+        mut, // The lottery account is mutable, as its end time will change
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        has_one = authority, // Only the lottery authority ends sales early
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the master account, which numbers the emitted event
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the authority account, which must be the lottery authority
+    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
+}
+
+// Define the data structure that will be stored in each inventory account
+// A block of house tickets waiting to be sold; `sold_map` has one bit per ticket of the block, set once it is sold
+#[account]
+pub struct Inventory {
+    pub lottery: Pubkey,     // The lottery the block belongs to (32 bytes)
+    pub block_id: u32,       // The ID the authority gave the block (4 bytes for a u32 integer)
+    pub code_root: [u8; 32], // The Merkle root of the block's redemption codes, see `merkle::code_leaf`
+    pub count: u16,          // The number of tickets in the block (2 bytes for a u16 integer)
+    pub sold: u16,           // The number of tickets sold so far (2 bytes for a u16 integer)
+    pub sold_map: [u8; 32],  // Which tickets of the block were sold, one bit each
+}
+
+impl Inventory {
+    // Whether the ticket at `index` of the block was sold
+    pub fn is_sold(&self, index: u16) -> bool {
+        self.sold_map[usize::from(index / 8)] & (1 << (index % 8)) != 0
+    }
+
+    // Take the ticket at `index` out of stock
+    pub fn mark_sold(&mut self, index: u16) {
+        self.sold_map[usize::from(index / 8)] |= 1 << (index % 8);
+        self.sold += 1;
+    }
+}
+
+// Define the accounts context for the `challenge_draw` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32, ticket_id: u32)]
+pub struct ChallengeDraw<'info> {
+    // Define the lottery account whose draw is challenged
+    #[account(
+        mut, // The lottery account is mutable, as it will be marked disputed
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        constraint = lottery.winner_id.is_some() @ LotteryError::WinnerNotChosen, // Only a draw that happened can be challenged
+        constraint = !lottery.cancelled @ LotteryError::LotteryCancelled, // A cancelled lottery has no draw left to challenge
+        constraint = !lottery.payout_started() @ LotteryError::AlreadyClaimed, // The prize is gone once claimed
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define a ticket of the challenger, proving they took part in the lottery
+    #[account(
+        seeds = [
+            TICKET_SEED.as_bytes(),
+            lottery.key().as_ref(),
+            &ticket_id.to_le_bytes()
+        ],
+        bump,
+        constraint = ticket.authority == challenger.key() @ LotteryError::NotAParticipant,
+    )]
+    pub ticket: Account<'info, Ticket>, // Define the ticket account of type `Ticket`
+
+    // Define the dispute account, which is initialized here and escrows the bond; only one can be open per lottery
+    #[account(
+        init,
+        payer = challenger,
+        space = 8 + 32 + 32 + 8 + 8, // Allocate enough space for the account (total 88 bytes)
+        // 8 +  // Account discriminator
+        // 32 + // lottery: Pubkey
+        // 32 + // challenger: Pubkey
+        // 8 +  // bond: u64
+        // 8;   // opened_at: i64
+        seeds = [DISPUTE_SEED.as_bytes(), lottery.key().as_ref()],
+        bump,
+    )]
+    pub dispute: Account<'info, Dispute>, // Define the dispute account of type `Dispute`
+
+    // Define the challenger account, which posts the bond
+    #[account(mut)] // The challenger account is mutable, as the bond and rent will be deducted
+    pub challenger: Signer<'info>, // The signer is the account that authorizes this transaction
+
+    // Define the master account, which numbers the emitted event
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Reference to the system program, used to interact with Solana's native features
+    pub system_program: Program<'info, System>,
+}
+
+// Define the accounts context for the `resolve_dispute` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct ResolveDispute<'info> {
+    // Define the master account, read to check the admin and updated with the event sequence number
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump, has_one = authority)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the lottery account whose draw was challenged
+    #[account(
+        mut, // The lottery account is mutable, as the ruling unfreezes it and may void the draw
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the dispute account, which is closed here with its rent (and the bond, unless slashed) going to the challenger
+    #[account(
+        mut,
+        seeds = [DISPUTE_SEED.as_bytes(), lottery.key().as_ref()],
+        bump,
+        has_one = challenger,
+        close = challenger,
+    )]
+    pub dispute: Account<'info, Dispute>, // Define the dispute account of type `Dispute`
+
+    // Define the challenger account, which gets the dispute account's lamports back
+    #[account(mut)]
+    pub challenger: SystemAccount<'info>,
+
+    // Define the creator account, whose open lottery count goes back up when the draw is voided
+    #[account(mut, seeds = [CREATOR_SEED.as_bytes(), lottery.authority.as_ref()], bump)]
+    pub creator: Account<'info, Creator>, // Define the creator account of type `Creator`
+
+    // Define the audit log account, which records the ruling
+    #[account(mut, seeds = [AUDIT_LOG_SEED.as_bytes()], bump)]
+    pub audit_log: Box<Account<'info, AuditLog>>, // Define the audit log account of type `AuditLog`
+
+    // Define the authority account, which must be the program admin and receives slashed bonds
+    #[account(mut)]
+    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
+}
+
+// Define the accounts context for the `schedule_force_resolve` and `force_resolve` functions
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct ForceResolve<'info> {
+    // Define the master account, read to check the admin and updated with the pots owed and the event sequence number
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump, has_one = authority)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the stuck lottery account
+    #[account(
+        mut, // The lottery account is mutable, as it is scheduled and then cancelled
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        constraint = !lottery.cancelled @ LotteryError::LotteryCancelled, // A lottery is only cancelled once
+        constraint = !lottery.payout_started() @ LotteryError::AlreadyClaimed, // A paid out lottery isn't stuck
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the creator account, whose open lottery count goes down when an undrawn lottery is cancelled
+    #[account(mut, seeds = [CREATOR_SEED.as_bytes(), lottery.authority.as_ref()], bump)]
+    pub creator: Account<'info, Creator>, // Define the creator account of type `Creator`
+
+    // Define the audit log account, which records the admin action
+    #[account(mut, seeds = [AUDIT_LOG_SEED.as_bytes()], bump)]
+    pub audit_log: Box<Account<'info, AuditLog>>, // Define the audit log account of type `AuditLog`
+
+    // Define the authority account, which must be the program admin
+    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
+}
+
+// Define the accounts context for the `cancel_lottery` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct CancelLottery<'info> {
+    // Define the lottery account, which is cancelled here
+    #[account(
+        mut, // The lottery account is mutable, as it will be marked as cancelled
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        has_one = authority, // Only the lottery authority can cancel it
+        constraint = lottery.id == lottery_id @ LotteryError::LotteryIdMismatch, // Don't trust the lottery_id argument on its own
+        constraint = !lottery.cancelled @ LotteryError::LotteryCancelled, // A lottery is only cancelled once
+        constraint = lottery.winner_id.is_none() @ LotteryError::WinnerAlreadyExists, // Only before the draw
+        constraint = lottery.commitment.is_none() @ LotteryError::DrawCommitted, // Nor once the draw is committed
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the creator account, whose open lottery count goes down
+    #[account(mut, seeds = [CREATOR_SEED.as_bytes(), lottery.authority.as_ref()], bump)]
+    pub creator: Account<'info, Creator>, // Define the creator account of type `Creator`
+
+    // Define the master account, updated with the pots owed and the event sequence number
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the authority account, which must be the lottery authority
+    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
+}
+
+// Define the accounts context for the `claim_refund` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32, ticket_id: u32)]
+pub struct ClaimRefund<'info> {
+    // Define the cancelled lottery account the refund is paid from
+    #[account(
+        mut, // The lottery account is mutable, as lamports will be deducted
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        constraint = lottery.id == lottery_id @ LotteryError::LotteryIdMismatch, // Don't trust the lottery_id argument on its own
+        constraint = lottery.cancelled @ LotteryError::LotteryNotCancelled, // Refunds are only owed by cancelled lotteries
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the master account, checked for paused payouts and updated with the event sequence number
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the ticket account that is refunded, and closed once it is
+    #[account(
+        mut, // The ticket account is mutable, as it will be closed
+        seeds = [
+            TICKET_SEED.as_bytes(),
+            lottery.key().as_ref(),
+            &ticket_id.to_le_bytes()
+        ],
+        bump,
+        has_one = authority, // Only the ticket owner can get its refund
+        constraint = ticket.id == ticket_id @ LotteryError::TicketIdMismatch, // Don't trust the ticket_id argument on its own
+    )]
+    pub ticket: Account<'info, Ticket>, // Define the ticket account of type `Ticket`
+
+    /// CHECK: The ticket's receipt, which only exists if the ticket was paid out by `recover_funds`; only its existence is read
+    #[account(seeds = [RECEIPT_SEED.as_bytes(), lottery.key().as_ref(), ticket.key().as_ref()], bump)]
+    pub receipt: UncheckedAccount<'info>,
+
+    // Define the authority account, which must be the ticket owner
+    #[account(mut)] // The authority account is mutable (e.g., its balance will increase)
+    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
+
+    // Define the lottery's vault token account, which pays the refund in a token lottery
+    #[account(mut, seeds = [VAULT_SEED.as_bytes(), lottery.key().as_ref()], bump)]
+    pub vault: Option<Account<'info, TokenAccount>>,
+
+    // Define the owner's token account, which receives the refund in a token lottery
+    #[account(mut)]
+    pub owner_token_account: Option<Account<'info, TokenAccount>>,
+
+    // Reference to the token program, used for token refunds
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+// Define the accounts context for the `refund_batch` function
+// The tickets, their receipts and their owners are passed as remaining accounts
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct RefundBatch<'info> {
+    // Define the cancelled lottery account the refunds are paid from
+    #[account(
+        mut, // The lottery account is mutable, as lamports will be deducted
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        constraint = lottery.id == lottery_id @ LotteryError::LotteryIdMismatch, // Don't trust the lottery_id argument on its own
+        constraint = lottery.cancelled @ LotteryError::LotteryNotCancelled, // Refunds are only owed by cancelled lotteries
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the master account, checked for paused payouts and updated with the event sequence numbers
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+}
+
+// Define the data structure that will be stored in a dispute account
+// Holds the challenger's bond on top of its rent until the admin's ruling
+#[account]
+pub struct Dispute {
+    pub lottery: Pubkey,    // The lottery whose draw is challenged (32 bytes)
+    pub challenger: Pubkey, // The ticket owner who posted the bond (32 bytes)
+    pub bond: u64,          // The escrowed bond, in lamports (8 bytes for a u64 integer)
+    pub opened_at: i64,     // The unix timestamp of the challenge (8 bytes for an i64 integer)
+}
+
+// Define the accounts context for the `announce_winner` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct AnnounceWinner<'info> {
+    // Define the lottery account, which must already have a winner
+    #[account(
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        constraint = lottery.winner_id.is_some() @ LotteryError::WinnerNotChosen,
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the winning ticket account
+    #[account(
+        seeds = [
+            TICKET_SEED.as_bytes(),
+            lottery.key().as_ref(),
+            &lottery.winner_id.unwrap_or_default().to_le_bytes()
+        ],
+        bump,
+        constraint = !winning_ticket.refunded @ LotteryError::TicketRefunded, // A refunded winner is redrawn instead
+        constraint = lottery.wins(&winning_ticket) @ LotteryError::InvalidWinner, // In match mode, only if nothing matched
+    )]
+    pub winning_ticket: Account<'info, Ticket>, // Define the ticket account of type `Ticket`
+
+    // Define the winner's profile account, which only exists if they ever registered one
+    #[account(
+        seeds = [PROFILE_SEED.as_bytes(), winning_ticket.authority.as_ref()],
+        bump,
+    )]
+    pub profile: Option<Account<'info, Profile>>, // Define the profile account of type `Profile`
+
+    // Define the master account, which numbers the emitted event
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+}
+
+// Define the accounts context for the `redraw_void_winner` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct RedrawVoidWinner<'info> {
+    // Define the lottery account, which must have drawn a refunded ticket
+    #[account(
+        mut, // The lottery account is mutable, as its winner will change
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        constraint = lottery.id == lottery_id @ LotteryError::LotteryIdMismatch, // Don't trust the lottery_id argument on its own
+        constraint = lottery.winner_id.is_some() @ LotteryError::WinnerNotChosen,
+        constraint = !lottery.disputed @ LotteryError::DrawDisputed, // The admin rules on a challenged draw first
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the winning ticket account, which must have been refunded
+    #[account(
+        seeds = [
+            TICKET_SEED.as_bytes(),
+            lottery.key().as_ref(),
+            &lottery.winner_id.unwrap_or_default().to_le_bytes()
+        ],
+        bump,
+        constraint = lottery.needs_redraw(&winning_ticket) @ LotteryError::WinnerNotVoid,
+    )]
+    pub winning_ticket: Account<'info, Ticket>, // Define the ticket account of type `Ticket`
+
+    // Define the master account, read for the current time and numbering the emitted event
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+}
+
+// Define the accounts context for the `count_matches` function
+// The ticket accounts to count are passed as remaining accounts
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct CountMatches<'info> {
+    // Define the lottery account whose matching tickets are counted
+    #[account(
+        mut, // The lottery account is mutable, as the count is recorded on it
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        constraint = lottery.id == lottery_id @ LotteryError::LotteryIdMismatch, // Don't trust the lottery_id argument on its own
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the master account, read for the current time and the recovery period, and numbering the emitted event
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+}
+
+// Define the accounts context for the `set_notification_key` function
+#[derive(Accounts)]
+pub struct SetNotificationKey<'info> {
+    // Define the player's profile account, created on first use
+    #[account(
+        init_if_needed,
+        payer = owner, // The player pays for their own profile
+        space = 8 + 32 + 1 + 32 + 8 + 8 + 1 + 1 + 2 + 8, // Allocate enough space for the account (total 101 bytes)
+        // 8 +  // Account discriminator
+        // 32 + // owner: Pubkey
+        // 1 + 32 + // notification_key: Option<[u8; 32]> (1 byte for option tag + 32 bytes for the key)
+        // 8 +  // tickets_bought: u64
+        // 8 +  // total_won: u64
+        // 1 + 1 + 2 + 8; // attestation: Option<Attestation> (1 byte for option tag + claims, jurisdiction and attested_at)
+        seeds = [PROFILE_SEED.as_bytes(), owner.key().as_ref()],
+        bump,
+    )]
+    pub profile: Account<'info, Profile>, // Define the profile account of type `Profile`
+
+    // Define the master account, read for the current time
+    #[account(seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the owner account, the player the profile belongs to
+    #[account(mut)] // The owner account is mutable (e.g., its balance will be deducted for rent)
+    pub owner: Signer<'info>, // The signer is the account that authorizes this transaction
+
+    // Reference to the system program, used to interact with Solana's native features
+    pub system_program: Program<'info, System>,
+}
+
+// Define the data structure that will be stored in a player's profile account
+#[account]
+pub struct Profile {
+    pub owner: Pubkey,                      // The public key of the player (32 bytes)
+    pub notification_key: Option<[u8; 32]>, // The key notification services use to reach the player, if registered
+    pub tickets_bought: u64,                // The number of tickets bought while passing this profile (8 bytes for a u64 integer)
+    pub total_won: u64,                     // The lamports won while passing this profile (8 bytes for a u64 integer)
+    pub attestation: Option<Attestation>,   // The player's latest self-attestation, if any
+}
+
+// Define a self-attestation a player recorded with `attest`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct Attestation {
+    pub claims: u8,            // Bitfield of the attested ATTEST_* claims (1 byte for a u8 integer)
+    pub jurisdiction: [u8; 2], // The ISO 3166-1 alpha-2 code of the player's declared jurisdiction (2 bytes)
+    pub attested_at: i64,      // The unix timestamp of the attestation (8 bytes for an i64 integer)
+}
+
+// Define the accounts context for the `init_leaderboard` function
+#[derive(Accounts)]
+pub struct InitLeaderboard<'info> {
+    // Define the leaderboard account, which is initialized here
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + 2 * LEADERBOARD_SIZE * (32 + 8), // Allocate enough space for the account (total 808 bytes)
+        // 8 +  // Account discriminator
+        // 10 * (32 + 8) + // top_winners: [LeaderboardEntry; 10]
+        // 10 * (32 + 8);  // top_buyers: [LeaderboardEntry; 10]
+        seeds = [LEADERBOARD_SEED.as_bytes()],
+        bump,
+    )]
+    pub leaderboard: AccountLoader<'info, Leaderboard>, // Define the leaderboard account of type `Leaderboard`
+
+    // Define the payer account, which pays the rent for the leaderboard
+    #[account(mut)]
+    pub payer: Signer<'info>, // The signer is the account that authorizes this transaction
+
+    // Reference to the system program, used to interact with Solana's native features
+    pub system_program: Program<'info, System>,
+}
+
+// Define the accounts context for the `check_lottery_health` function
+// The lotteries to check are passed as remaining accounts
+#[derive(Accounts)]
+pub struct CheckLotteryHealth<'info> {
+    // Define the master account, read for the claim window
+    #[account(seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+}
+
+// Define the data returned for each unhealthy lottery by `check_lottery_health`
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LotteryHealth {
+    pub lottery_id: u32, // The ID of the lottery
+    pub warnings: u8,    // Bitfield of the HEALTH_* warnings that apply
+}
+
+impl LotteryHealth {
+    // Collect the warnings for a lottery holding `balance` lamports on top of its rent
+    pub fn warnings(lottery: &Lottery, balance: u64, clock: &Clock, recovery_period: i64) -> u8 {
+        let mut warnings = 0;
+
+        match lottery.winner_id {
+            // Sales ended with the draw epoch or the end time, but nobody drew the winner and the lottery wasn't cancelled
+            None => {
+                let sales_ended = lottery
+                    .draw_epoch
+                    .is_some_and(|draw_epoch| clock.epoch > draw_epoch)
+                    || (lottery.end_time != 0 && clock.unix_timestamp >= lottery.end_time);
+                if !lottery.cancelled && sales_ended {
+                    warnings |= HEALTH_DRAW_OVERDUE;
+                }
+            }
+            Some(_) if !lottery.claimed => {
+                if lottery.recovery_open(clock.unix_timestamp, recovery_period) {
+                    warnings |= HEALTH_CLAIM_EXPIRED;
+                } else if clock.unix_timestamp + CLAIM_EXPIRY_WARNING
+                    >= lottery.drawn_at + recovery_period
+                {
+                    warnings |= HEALTH_CLAIM_EXPIRING;
+                }
+            }
+            Some(_) => {}
+        }
+
+        // Until the prize is paid (or refunds start), the lottery account must hold the whole jackpot
+        let holds_jackpot = lottery.holds_lamports()
+            && !lottery.payout_started()
+            && !lottery.recovery_open(clock.unix_timestamp, recovery_period);
+        if holds_jackpot && lottery.jackpot().is_none_or(|jackpot| balance < jackpot) {
+            warnings |= HEALTH_VAULT_SHORT;
+        }
+
+        if lottery.disputed {
+            warnings |= HEALTH_DISPUTED;
+        }
+
+        warnings
+    }
+}
+
+// Define the accounts context for the `get_creator_dashboard` function
+// The creator's lotteries are passed as remaining accounts
+#[derive(Accounts)]
+pub struct GetCreatorDashboard {}
+
+// Define the data returned by `get_creator_dashboard`
+#[derive(AnchorSerialize, AnchorDeserialize, Default)]
+pub struct CreatorDashboard {
+    pub lotteries: u32,    // The number of lotteries aggregated
+    pub open: u32,         // Lotteries still selling tickets, without a winner
+    pub drawn: u32,        // Lotteries with a winner that hasn't claimed yet
+    pub claimed: u32,      // Lotteries whose prize has been claimed
+    pub tickets_sold: u64, // Tickets sold across all lotteries
+    pub volume: u64,       // Lamports spent on tickets across all lamport lotteries
+}
+
+impl CreatorDashboard {
+    // Count a lottery into the totals
+    pub fn add(&mut self, lottery: &Lottery) -> Result<()> {
+        self.lotteries += 1;
+        match (lottery.winner_id, lottery.claimed) {
+            (None, _) => self.open += 1,
+            (Some(_), false) => self.drawn += 1,
+            (Some(_), true) => self.claimed += 1,
+        }
+        self.tickets_sold += u64::from(lottery.live_tickets());
+        if lottery.payment_mint.is_none() {
+            self.volume = self
+                .volume
+                .checked_add(lottery.pot().ok_or(LotteryError::PotOverflow)?)
+                .ok_or(LotteryError::PotOverflow)?;
+        }
+        Ok(())
+    }
+}
+
+// Define the accounts context for the `get_leaderboard` function
+#[derive(Accounts)]
+pub struct GetLeaderboard<'info> {
+    // Define the leaderboard account, which is only read
+    #[account(seeds = [LEADERBOARD_SEED.as_bytes()], bump)]
+    pub leaderboard: AccountLoader<'info, Leaderboard>, // Define the leaderboard account of type `Leaderboard`
+}
+
+// Define the accounts context for the `get_fee_report` function
+#[derive(Accounts)]
+pub struct GetFeeReport<'info> {
+    // Define the fee ledger account, which is only read
+    #[account(seeds = [FEE_LEDGER_SEED.as_bytes()], bump)]
+    pub fee_ledger: Box<Account<'info, FeeLedger>>, // Define the fee ledger account of type `FeeLedger`
+}
+
+// Define the data returned by `get_fee_report`
+#[derive(AnchorSerialize, AnchorDeserialize, Default)]
+pub struct FeeReport {
+    pub periods: Vec<FeePeriod>, // The counters of each reported period with claims, oldest first
+    pub lamports: u64,           // The protocol fees of all reported periods, in lamports
+    pub claims: u32, // The prizes claimed from lamport lotteries in all reported periods
+    pub token_claims: u32, // The prizes claimed from token lotteries in all reported periods
+}
+
+// Define the data structure that will be stored in the leaderboard account
+// Zero-copy so updating it on every purchase doesn't deserialize both boards; each board is sorted highest first
+#[account(zero_copy)]
+pub struct Leaderboard {
+    pub top_winners: [LeaderboardEntry; LEADERBOARD_SIZE], // Players with the highest total winnings
+    pub top_buyers: [LeaderboardEntry; LEADERBOARD_SIZE],  // Players with the most tickets bought
+}
+
+impl Leaderboard {
+    // Record a player's new total winnings
+    pub fn record_winner(&mut self, player: Pubkey, total_won: u64) {
+        Self::record(&mut self.top_winners, player, total_won);
+    }
+
+    // Record a player's new total of tickets bought
+    pub fn record_buyer(&mut self, player: Pubkey, tickets_bought: u64) {
+        Self::record(&mut self.top_buyers, player, tickets_bought);
+    }
+
+    // Update the player's entry if they are already ranked, otherwise replace the lowest entry if they beat it
+    fn record(board: &mut [LeaderboardEntry; LEADERBOARD_SIZE], player: Pubkey, value: u64) {
+        let lowest = LEADERBOARD_SIZE - 1;
+        let slot = board
+            .iter()
+            .position(|entry| entry.player == player)
+            .or_else(|| (value > board[lowest].value).then_some(lowest));
+
+        if let Some(slot) = slot {
+            board[slot] = LeaderboardEntry { player, value };
+            board.sort_by_key(|entry| std::cmp::Reverse(entry.value));
+        }
+    }
+}
+
+// Define a single leaderboard ranking
+#[zero_copy]
+pub struct LeaderboardEntry {
+    pub player: Pubkey, // The public key of the ranked player (32 bytes)
+    pub value: u64,     // The ranked total, lamports won or tickets bought (8 bytes for a u64 integer)
+}
+
+// Define the data returned by `get_leaderboard`
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LeaderboardView {
+    pub top_winners: Vec<LeaderboardRanking>, // Players with the highest total winnings, highest first
+    pub top_buyers: Vec<LeaderboardRanking>,  // Players with the most tickets bought, highest first
+}
+
+// Define a single ranking returned by `get_leaderboard`
+// A borsh copy of `LeaderboardEntry`, since zero-copy types can't also derive the borsh traits under idl-build
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LeaderboardRanking {
+    pub player: Pubkey, // The public key of the ranked player
+    pub value: u64,     // The ranked total, lamports won or tickets bought
+}
+
+// Define the accounts context for the `pick_winner` function
+// Specifies the accounts that need to be provided to this instruction
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct PickWinner<'info> {
+    // Define the lottery account, which will have its winner_id updated
+    #[account(
+        mut, // The lottery account is mutable, as the winner_id will be set
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()], // Use LOTTERY_SEED and lottery_id as seeds for generating the PDA
+        bump, // The bump seed used to create a valid PDA
+        // The authority draws lotteries seeded from recent slot hashes; anyone may draw the others once they're due
+        constraint = lottery.anyone_can_draw() || lottery.authority == caller.key() @ LotteryError::NotLotteryAuthority,
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the caller account, which draws the lottery and receives its draw bounty, if any
+    #[account(mut)] // The caller account is mutable (e.g., its balance can increase)
+    pub caller: Signer<'info>, // The signer is the account that authorizes this transaction
+
+    /// CHECK: The SlotHashes sysvar, read manually since it is too large to deserialize
+    #[account(address = SlotHashes::id())]
+    pub slot_hashes: UncheckedAccount<'info>,
+
+    // Define the master account, which numbers the emitted event
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the creator account, whose open lottery count drops once the winner is drawn
+    #[account(mut, seeds = [CREATOR_SEED.as_bytes(), lottery.authority.as_ref()], bump)]
+    pub creator: Account<'info, Creator>, // Define the creator account of type `Creator`
+}
+
+// The parts of a draw shared by `pick_winner` and `reveal_and_pick`, which only differ in where the seed comes from
+impl PickWinner<'_> {
+    // Check that the lottery can be drawn at all
+    fn check_drawable(&self, clock: &Clock) -> Result<()> {
+        match self.not_drawable(clock) {
+            Some((error, _)) => Err(error.into()),
+            None => Ok(()),
+        }
+    }
+
+    // Why the lottery can't be drawn now, if it can't: the error its authority gets, and the status a keeper
+    // drawing a permissionless lottery gets instead
+    fn not_drawable(&self, clock: &Clock) -> Option<(LotteryError, CrankStatus)> {
+        let lottery = &self.lottery;
+
+        // A cancelled lottery is never drawn, and any lottery only once
+        if lottery.cancelled {
+            return Some((LotteryError::LotteryCancelled, CrankStatus::AlreadyDone));
+        }
+        if lottery.winner_id.is_some() {
+            return Some((LotteryError::WinnerAlreadyExists, CrankStatus::AlreadyDone));
+        }
+
+        // Check if there are any tickets purchased that can win
+        if lottery.eligible_tickets() == 0 {
+            return Some((LotteryError::NoTickets, CrankStatus::NotDue));
+        }
+
+        // Never draw in the slot of the last sale, so a single transaction (or bundle) can't buy and draw
+        // against entropy it already knows
+        if clock.slot <= lottery.last_sale_slot {
+            return Some((LotteryError::DrawInSaleSlot, CrankStatus::NotDue));
+        }
+
+        // A lottery with an end time sells tickets until then, however early its authority wants to draw
+        if clock.unix_timestamp < lottery.end_time {
+            return Some((LotteryError::DrawBeforeEndTime, CrankStatus::NotDue));
+        }
+
+        None
+    }
+
+    // Draw the winner from `seed`, pay the caller's bounty and record the jackpot as owed
+    fn draw(&mut self, seed: [u8; 32], clock: &Clock) -> Result<()> {
+        let lottery = &mut self.lottery;
+
+        // Calculate the winner ticket ID
+        // Tickets are numbered from 0, so the winner_id is within the range of ticket IDs (0 to last_ticket_id - 1)
+        let winner_id = draw::uniform_index(seed, lottery.last_ticket_id);
+
+        // Set the winner_id in the lottery account, with the seed it was drawn from
+        lottery.winner_id = Some(winner_id);
+        lottery.draw_seed = seed;
+        lottery.drawn_at = clock.unix_timestamp; // Record when the winner was drawn
+        lottery.sales_closed = true; // For good, even if an upheld dispute voids this draw
+
+        // Pay the caller's bounty out of the jackpot; a draw repeated after an upheld dispute doesn't pay it again
+        if lottery.holds_lamports() && lottery.bounty_paid == 0 {
+            let bounty = lottery
+                .draw_bounty_amount(self.master.max_draw_bounty_bps)
+                .ok_or(LotteryError::PotOverflow)?;
+            if bounty > 0 {
+                **lottery.to_account_info().try_borrow_mut_lamports()? -= bounty;
+                **self.caller.to_account_info().try_borrow_mut_lamports()? += bounty;
+                lottery.bounty_paid = bounty;
+
+                emit!(DrawBountyPaid {
+                    seq: self.master.next_seq(),
+                    lottery_id: lottery.id,
+                    caller: self.caller.key(),
+                    amount: bounty,
+                });
+            }
+        }
+
+        // The jackpot is owed from now on, which lets the circuit breaker budget its payout
+        if lottery.holds_lamports() {
+            let jackpot = lottery.jackpot().ok_or(LotteryError::PotOverflow)?;
+            self.master.record_pot(jackpot, clock.unix_timestamp);
+        }
+
+        // A drawn lottery no longer counts towards its creator's open lotteries
+        self.creator.open_lotteries = self.creator.open_lotteries.saturating_sub(1);
+
+        // Log the winner ID
+        msg!("Winner id: {}", winner_id);
+
+        emit!(WinnerPicked {
+            seq: self.master.next_seq(),
+            lottery_id: lottery.id,
+            winner_id,
+            seed,
+        });
+
+        Ok(())
+    }
+}
+
+// Define what a maintenance instruction did, returned via return data
+// Keepers run these on a schedule without knowing whether anything is due; having nothing to do succeeds with a
+// status rather than failing, so only real failures reach their alerting
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CrankStatus {
+    Done,        // The instruction did its work
+    NotDue,      // Nothing to do yet; calling again later may do something
+    AlreadyDone, // Nothing to do any more, e.g. the lottery was already drawn; it can be dropped from the schedule
+    Paused, // Payouts are paused, see `Master::record_payout`; calling again once they resume continues
+}
+
+// Define what a claim paid, returned via return data by `claim_prize`, `claim_refund` and `recover_funds`
+// A claim that trips the circuit breaker has to store the pause, so it can't fail; it succeeds without paying instead,
+// and this tells the caller apart from a claim that paid
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ClaimOutcome {
+    Paid,   // The claim was paid out
+    Paused, // Payouts are paused, see `Master::record_payout`; nothing was paid, the claim can be sent again later
+}
+
+// Define the accounts context for the `commit_randomness` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct CommitRandomness<'info> {
+    // Define the lottery account, which records the commitment
+    #[account(
+        mut, // The lottery account is mutable, as the commitment will be stored
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        has_one = authority, // Only the lottery's authority can commit
+        constraint = lottery.commitment.is_none() @ LotteryError::DrawCommitted, // A lottery commits once
+        constraint = lottery.winner_id.is_none() && !lottery.cancelled @ LotteryError::WinnerAlreadyExists, // Only an undrawn lottery commits, including one whose draw was voided
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the master account, which numbers the emitted event
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the authority account, which must be the lottery's authority
+    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
+}
+
+// Define the accounts context for the `set_committee` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct SetCommittee<'info> {
+    // Define the lottery account, which will be marked as drawn by its committee
+    #[account(
+        mut, // The lottery account is mutable, as its committee flag will be set
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        has_one = authority, // Only the lottery authority sets the committee
+        constraint = lottery.last_ticket_id == 0 @ LotteryError::TermsLocked, // Tickets already sold were bought under the current draw
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the committee account, which is initialized here; a lottery has one committee at most
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 32 + 1 + 4 + 32 * MAX_COMMITTEE_MEMBERS + 4 + 33 * MAX_COMMITTEE_MEMBERS + 4 + 33 * MAX_COMMITTEE_MEMBERS + 8, // Allocate enough space for the account (total 845 bytes)
+        // 8 +  // Account discriminator
+        // 32 + // lottery: Pubkey
+        // 1 +  // threshold: u8
+        // 4 + 32 * 8 + // members: Vec<Pubkey> (4 bytes for the length + up to MAX_COMMITTEE_MEMBERS keys)
+        // 4 + 33 * 8 + // commitments: Vec<Option<[u8; 32]>> (4 bytes for the length + 1 + 32 bytes per member)
+        // 4 + 33 * 8 + // shares: Vec<Option<[u8; 32]>> (4 bytes for the length + 1 + 32 bytes per member)
+        // 8;   // reveal_slot: u64
+        seeds = [COMMITTEE_SEED.as_bytes(), lottery.key().as_ref()],
+        bump,
+    )]
+    pub committee: Account<'info, Committee>, // Define the committee account of type `Committee`
+
+    // Define the master account, which numbers the emitted event
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
 
-        // Generate a pseudo-random number based on the current timestamp and slot
-        // Note: This method is deterministic and predictable, and should be replaced by a secure random number generator (e.g., an oracle)
-        let pseudo_random_number = ((u64::from_le_bytes(
-            <[u8; 8]>::try_from(&hash(&clock.unix_timestamp.to_be_bytes()).to_bytes()[..8])
-                .unwrap(),
-        ) * clock.slot)
-            % u32::MAX as u64) as u32;
+    // Define the authority account, which must be the lottery authority and pays for the committee account
+    #[account(mut)]
+    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
 
-        // Calculate the winner ticket ID
-        // The '+1' ensures the winner_id is within the range of ticket IDs (1 to last_ticket_id)
-        let winner_id = (pseudo_random_number % lottery.last_ticket_id) + 1;
+    // Reference to the system program, used to create the committee account
+    pub system_program: Program<'info, System>,
+}
 
-        // Set the winner_id in the lottery account
-        lottery.winner_id = Some(winner_id);
+// Define the accounts context for the `commit_share` and `reveal_share` functions
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct SubmitShare<'info> {
+    // Define the lottery account the committee draws
+    #[account(
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        constraint = !lottery.cancelled @ LotteryError::LotteryCancelled, // A cancelled lottery is never drawn
+        constraint = lottery.winner_id.is_none() @ LotteryError::WinnerAlreadyExists, // Shares are only taken before the draw
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
 
-        // Log the winner ID
-        msg!("Winner id: {}", winner_id);
-        Ok(())
-    }
+    // Define the committee account, which records the member's commitment or share
+    #[account(mut, seeds = [COMMITTEE_SEED.as_bytes(), lottery.key().as_ref()], bump)]
+    pub committee: Account<'info, Committee>, // Define the committee account of type `Committee`
 
-    // Function for the winner to claim the price
-    pub fn claim_price(ctx: Context<ClaimPrice>, _lottery_id: u32, _ticket_id: u32) -> Result<()> {
-        let lottery = &mut ctx.accounts.lottery; // Get a mutable reference to the lottery account
-        let ticket = &mut ctx.accounts.ticket; // Get a mutable reference to the ticket account
-        let winner = &mut ctx.accounts.authority; // Get a mutable reference to the winner's account
+    // Define the master account, which numbers the emitted event
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
 
-        // Check if the price has already been claimed
-        if lottery.claimed {
-            return err!(LotteryError::AlreadyClaimed);
-        }
+    // Define the member account, which must be part of the committee
+    pub member: Signer<'info>, // The signer is the account that authorizes this transaction
+}
 
-        // Check if the ticket ID matches the winner ID
-        match lottery.winner_id {
-            Some(winner_id) => {
-                if winner_id != ticket.id {
-                    return err!(LotteryError::InvalidWinner);
-                }
-            }
-            None => return err!(LotteryError::WinnerNotChosen),
-        }
+// Define the accounts context for the `finalize_draw` function
+// The draw itself uses the same accounts as `pick_winner`, plus the committee whose shares it mixes
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct FinalizeDraw<'info> {
+    pub draw: PickWinner<'info>,
 
-        // Calculate the total price amount
-        let price = lottery
-            .ticket_price
-            .checked_mul(lottery.last_ticket_id.into())
-            .unwrap();
+    // Define the committee account, whose revealed shares seed the draw
+    #[account(seeds = [COMMITTEE_SEED.as_bytes(), draw.lottery.key().as_ref()], bump)]
+    pub committee: Account<'info, Committee>, // Define the committee account of type `Committee`
+}
 
-        // Transfer the price amount from the lottery account to the winner's account
-        **lottery.to_account_info().try_borrow_mut_lamports()? -= price;
-        **winner.to_account_info().try_borrow_mut_lamports()? += price;
+// Define the data structure that will be stored in a committee account
+// One per committee lottery, see `set_committee`; commitments and shares are kept in member order
+#[account]
+pub struct Committee {
+    pub lottery: Pubkey,      // The lottery the committee draws (32 bytes)
+    pub threshold: u8,        // The revealed shares the draw needs (1 byte for a u8 integer)
+    pub members: Vec<Pubkey>, // The entropy providers, up to MAX_COMMITTEE_MEMBERS
+    pub commitments: Vec<Option<[u8; 32]>>, // The SHA-256 hash of each member's share, once committed
+    pub shares: Vec<Option<[u8; 32]>>,      // Each member's share, once revealed
+    pub reveal_slot: u64, // The slot of the first revealed share, 0 before (8 bytes for a u64 integer)
+}
 
-        // Mark the price as claimed
-        lottery.claimed = true;
+impl Committee {
+    // The position of `member` in the committee, if it is one of its members
+    pub fn member_index(&self, member: &Pubkey) -> Option<usize> {
+        self.members.iter().position(|key| key == member)
+    }
 
-        // Log the price claim
-        msg!(
-            "{} claimed {} lamports from lottery id {} with ticket id {}",
-            winner.key(),
-            price,
-            lottery.id,
-            ticket.id
-        );
-        Ok(())
+    // The slot whose hash `finalize_draw` mixes in: the first after the reveal window, which the first share opens
+    pub fn entropy_slot(&self) -> u64 {
+        self.reveal_slot + COMMITTEE_REVEAL_SLOTS + 1
     }
 }
 
-// Define the accounts context for the `init_master` function
+// Define the accounts context for the `claim_price` function
 // Specifies the accounts that need to be provided to this instruction
 #[derive(Accounts)]
-pub struct InitMaster<'info> {
-    // Define the master account, which is initialized here
+#[instruction(lottery_id: u32, ticket_id: u32)]
+pub struct ClaimPrice<'info> {
+    // Define the lottery account from which the price will be claimed
     #[account(
-        init, // This attribute indicates that this account is being initialized
-        payer = payer, // The payer is responsible for covering the fees for creating this account
-        space = 4 + 8, // Allocate enough space for the account (4 bytes for u32 + 8 bytes for extra space)
-        seeds = [MASTER_SEED.as_bytes()], // Use MASTER_SEED as the seed for generating a program-derived address (PDA)
-        bump, // The bump seed used to create a valid PDA; prevents collision
+        mut, // The lottery account is mutable, as lamports will be deducted
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        constraint = lottery.id == lottery_id @ LotteryError::LotteryIdMismatch, // Don't trust the lottery_id argument on its own
     )]
-    pub master: Account<'info, Master>, // Define the master account of type `Master`
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
 
-    // Define the payer account, which will pay the rent for initializing the master account
-    #[account(mut)] // `mut` means this account is mutable (its balance can change)
-    pub payer: Signer<'info>, // The signer is the account that authorizes this transaction
+    // Define the ticket account that must match the winning ticket
+    #[account(
+        seeds = [
+            TICKET_SEED.as_bytes(),
+            lottery.key().as_ref(),
+            &ticket_id.to_le_bytes()
+        ],
+        bump,
+        has_one = authority, // Ensure that the authority is the owner of this ticket
+        constraint = ticket.id == ticket_id @ LotteryError::TicketIdMismatch, // Don't trust the ticket_id argument on its own
+        constraint = ticket.lottery_id == lottery.id @ LotteryError::LotteryIdMismatch, // The ticket must belong to this lottery
+    )]
+    pub ticket: Account<'info, Ticket>, // Define the ticket account of type `Ticket`
+
+    // Define the claim receipt account, which is initialized here and can only exist once per winning ticket
+    #[account(
+        init,
+        payer = claimer, // The claimer pays for the receipt
+        space = 8 + 4 + 4 + 32 + 8 + 8, // Allocate enough space for the account (total 64 bytes)
+        // 8 +  // Account discriminator
+        // 4 +  // lottery_id: u32
+        // 4 +  // ticket_id: u32
+        // 32 + // winner: Pubkey
+        // 8 +  // amount: u64
+        // 8;   // claimed_at: i64
+        seeds = [RECEIPT_SEED.as_bytes(), lottery.key().as_ref(), ticket.key().as_ref()],
+        bump,
+    )]
+    pub receipt: Account<'info, ClaimReceipt>, // Define the receipt account of type `ClaimReceipt`
+
+    // Define the authority account, which must be the owner of the winning ticket and receives the prize
+    #[account(mut)] // The authority account is mutable (e.g., its balance will increase)
+    pub authority: SystemAccount<'info>,
+
+    // Define the claimer account, which must be the ticket owner or their delegate
+    #[account(
+        mut, // The claimer account is mutable, as it pays for the receipt
+        constraint = ticket.may_claim(&claimer.key()) @ LotteryError::NotAuthorizedToClaim,
+    )]
+    pub claimer: Signer<'info>, // The signer is the account that authorizes this transaction
 
     // Reference to the system program, used to interact with Solana's native features
     pub system_program: Program<'info, System>,
+
+    // Define the master account, read for the current season and updated with the event sequence number
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the current season account, which accumulates the season's stats
+    #[account(mut, seeds = [SEASON_SEED.as_bytes(), &master.season.to_le_bytes()], bump)]
+    pub season: Account<'info, Season>, // Define the season account of type `Season`
+
+    // Define the winner's profile account, passed to track their stats
+    #[account(mut, seeds = [PROFILE_SEED.as_bytes(), authority.key().as_ref()], bump)]
+    pub profile: Option<Account<'info, Profile>>, // Define the profile account of type `Profile`
+
+    // Define the leaderboard account, passed along with the profile to rank the winner
+    #[account(mut, seeds = [LEADERBOARD_SEED.as_bytes()], bump)]
+    pub leaderboard: Option<AccountLoader<'info, Leaderboard>>, // Define the leaderboard account of type `Leaderboard`
+
+    // Define the lottery creator's account, which holds the part of the jackpot that rolls over
+    #[account(mut, seeds = [CREATOR_SEED.as_bytes(), lottery.authority.as_ref()], bump)]
+    pub creator: Account<'info, Creator>, // Define the creator account of type `Creator`
+
+    // Define the lottery's vault token account, which pays the prize in a token lottery
+    #[account(mut, seeds = [VAULT_SEED.as_bytes(), lottery.key().as_ref()], bump)]
+    pub vault: Option<Account<'info, TokenAccount>>,
+
+    // Define the winner's token account, which receives the prize in a token lottery
+    #[account(mut)]
+    pub winner_token_account: Option<Account<'info, TokenAccount>>,
+
+    // Define the config account, read for the protocol fee and treasury
+    #[account(seeds = [CONFIG_SEED.as_bytes()], bump, has_one = treasury)]
+    pub config: Box<Account<'info, Config>>, // Define the config account of type `Config`
+
+    /// CHECK: The treasury wallet, which receives the protocol fee in a lamport lottery; checked against `config.treasury`
+    #[account(mut)]
+    pub treasury: UncheckedAccount<'info>,
+
+    // Define the treasury's token account, which receives the protocol fee in a token lottery
+    #[account(mut)]
+    pub treasury_token_account: Option<Account<'info, TokenAccount>>,
+
+    // Define the fee ledger account, which counts the protocol fee towards the current period
+    #[account(mut, seeds = [FEE_LEDGER_SEED.as_bytes()], bump)]
+    pub fee_ledger: Box<Account<'info, FeeLedger>>, // Define the fee ledger account of type `FeeLedger`
+
+    // Reference to the token program, used for token payouts
+    pub token_program: Option<Program<'info, Token>>,
 }
 
-// Define the data structure that will be stored in the master account
+// Define the data structure that will be stored in the receipt account of each paid out claim
 #[account]
-pub struct Master {
-    pub last_id: u32, // Track the last lottery ID created (4 bytes for a u32 integer)
+pub struct ClaimReceipt {
+    pub lottery_id: u32, // The ID of the lottery the prize was paid from (4 bytes for a u32 integer)
+    pub ticket_id: u32,  // The ID of the winning ticket (4 bytes for a u32 integer)
+    pub winner: Pubkey,  // The public key of the wallet that was paid (32 bytes)
+    pub amount: u64,     // The amount paid out, in lamports (8 bytes for a u64 integer)
+    pub claimed_at: i64, // The unix timestamp of the claim (8 bytes for an i64 integer)
 }
 
-// Define the accounts context for the `create_lottery` function
-// Specifies the accounts that need to be provided to this instruction
+// Define the accounts context for the `claim_right` function
 #[derive(Accounts)]
-pub struct CreateLottery<'info> {
-    // Define the lottery account, which is initialized here
+#[instruction(lottery_id: u32, ticket_id: u32)]
+pub struct ClaimRight<'info> {
+    // Define the lottery account whose right is claimed
     #[account(
-        init, // This attribute indicates that this account is being initialized
-        payer = authority, // The authority is responsible for covering the fees for creating this account
-        space = 8 + 4 + 32 + 8 + 4 + 1 + 4 + 1, // Allocate enough space for the account (total 62 bytes)
-        // 8 +  // Account discriminator
-        // 4 +  // id: u32
-        // 32 + // authority: Pubkey
-        // 8 +  // ticket_price: u64
-        // 4 +  // last_ticket_id: u32
-        // 1 + 4 + // winner_id: Option<u32> (1 byte for option tag + 4 bytes for u32)
-        // 1;   // claimed: bool
-        seeds = [LOTTERY_SEED.as_bytes(), &master.last_id.to_le_bytes()], // Use LOTTERY_SEED and current last_id as seeds for generating a PDA
-        bump, // The bump seed used to create a valid PDA; prevents collision
+        mut, // The lottery account is mutable, as it will be marked claimed
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        constraint = lottery.id == lottery_id @ LotteryError::LotteryIdMismatch, // Don't trust the lottery_id argument on its own
     )]
     pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
 
-    // Define the master account, which keeps track of the lottery ID
+    // Define the master account, read for the recovery period and updated with the event sequence number
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the ticket account that must match the winning ticket
     #[account(
-        mut, // The master account is mutable, as the lottery ID will be updated
-        seeds = [MASTER_SEED.as_bytes()], // Use MASTER_SEED as the seed for generating the PDA
-        bump, // The bump seed used to create a valid PDA
+        seeds = [
+            TICKET_SEED.as_bytes(),
+            lottery.key().as_ref(),
+            &ticket_id.to_le_bytes()
+        ],
+        bump,
+        constraint = ticket.id == ticket_id @ LotteryError::TicketIdMismatch, // Don't trust the ticket_id argument on its own
+        constraint = ticket.may_claim(&claimer.key()) @ LotteryError::NotAuthorizedToClaim, // The owner or their delegate
     )]
-    pub master: Account<'info, Master>, // Define the master account of type `Master`
+    pub ticket: Account<'info, Ticket>, // Define the ticket account of type `Ticket`
 
-    // Define the authority account, which will be responsible for managing the lottery
-    #[account(mut)] // The authority account is mutable (e.g., its balance can change)
-    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
+    // Define the right account, which is initialized here and can only exist once per lottery
+    #[account(
+        init,
+        payer = claimer,
+        space = 8 + 32 + 4 + 32 + 8, // Allocate enough space for the account (total 84 bytes)
+        // 8 +  // Account discriminator
+        // 32 + // lottery: Pubkey
+        // 4 +  // lottery_id: u32
+        // 32 + // winner: Pubkey
+        // 8;   // granted_at: i64
+        seeds = [PRIZE_RIGHT_SEED.as_bytes(), lottery.key().as_ref()],
+        bump,
+    )]
+    pub right: Account<'info, PrizeRight>, // Define the right account of type `PrizeRight`
+
+    // Define the claimer account, which must be the ticket owner or their delegate
+    #[account(mut)] // The claimer account is mutable, as it pays for the right account
+    pub claimer: Signer<'info>, // The signer is the account that authorizes this transaction
 
     // Reference to the system program, used to interact with Solana's native features
     pub system_program: Program<'info, System>,
 }
 
-// Define the data structure that will be stored in the lottery account
-#[account]
-pub struct Lottery {
-    pub id: u32,                // The ID of the lottery (4 bytes for a u32 integer)
-    pub authority: Pubkey,      // The public key of the authority managing the lottery (32 bytes)
-    pub ticket_price: u64,      // The price of a lottery ticket (8 bytes for a u64 integer)
-    pub last_ticket_id: u32,    // The ID of the last issued ticket (4 bytes for a u32 integer)
-    pub winner_id: Option<u32>, // The ID of the winning ticket, if any (wrapped in Option)
-    pub claimed: bool,          // Indicates whether the price has been claimed (1 byte for a boolean)
-}
-
-// Define the accounts context for the `buy_ticket` function
-// Specifies the accounts that need to be provided to this instruction
+// Define the accounts context for the `notify_winner` function
 #[derive(Accounts)]
 #[instruction(lottery_id: u32)]
-pub struct BuyTicket<'info> {
-    // Define the lottery account, which the ticket will be associated with
+pub struct NotifyWinner<'info> {
+    // Define the lottery account whose winner callback is delivered
     #[account(
-        mut, // The lottery account is mutable, as the last_ticket_id will be updated
-        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()], // Use LOTTERY_SEED and lottery_id as seeds for generating the PDA
-        bump, // The bump seed used to create a valid PDA
+        mut, // The lottery account is mutable, as the delivery is recorded on it
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        constraint = lottery.id == lottery_id @ LotteryError::LotteryIdMismatch, // Don't trust the lottery_id argument on its own
     )]
     pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
 
-    // Define the ticket account, which is initialized here
+    // Define the winning ticket account, whose owner is the winner
     #[account(
-        init, // This attribute indicates that this account is being initialized
-        payer = buyer, // The buyer is responsible for covering the fees for creating this account
-        space = 8 + 4 + 32 + 4, // Allocate enough space for the account (total 48 bytes)
-        // 8 +  // Account discriminator
-        // 4 +  // id: u32
-        // 32 + // authority: Pubkey
-        // 4;   // lottery_id: u32
         seeds = [
-            TICKET_SEED.as_bytes(), // Use TICKET_SEED as part of the seed for generating a PDA
-            lottery.key().as_ref(), // Include the lottery key as part of the seed
-            &lottery.last_ticket_id.to_le_bytes(), // Include the current last_ticket_id as part of the seed
+            TICKET_SEED.as_bytes(),
+            lottery.key().as_ref(),
+            &lottery.winner_id.unwrap_or_default().to_le_bytes()
         ],
-        bump, // The bump seed used to create a valid PDA
+        bump,
     )]
     pub ticket: Account<'info, Ticket>, // Define the ticket account of type `Ticket`
 
-    // Define the buyer account, which will purchase the ticket
-    #[account(mut)] // The buyer account is mutable (e.g., its balance will be deducted)
-    pub buyer: Signer<'info>, // The signer is the account that authorizes this transaction
+    // Define the ticket owner account, passed to the callback program as the winner
+    #[account(address = ticket.authority)]
+    pub winner: SystemAccount<'info>,
 
-    // Reference to the system program, used to interact with Solana's native features
-    pub system_program: Program<'info, System>,
+    /// CHECK: The lottery's callback program, checked against `lottery.callback`
+    pub callback_program: UncheckedAccount<'info>,
+
+    // Define the master account, which numbers the emitted event
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
 }
 
-// Define the data structure that will be stored in the ticket account
+// Define the data structure that will be stored in the right account of each claimed right lottery
+// External programs check a wallet's right by deriving this account from the lottery and comparing `winner`
 #[account]
-pub struct Ticket {
-    pub id: u32,           // The ID of the ticket (4 bytes for a u32 integer)
-    pub authority: Pubkey, // The public key of the ticket owner (32 bytes)
-    pub lottery_id: u32,   // The ID of the lottery that this ticket belongs to (4 bytes for a u32 integer)
+pub struct PrizeRight {
+    pub lottery: Pubkey, // The public key of the lottery the right was won in (32 bytes)
+    pub lottery_id: u32, // The ID of the lottery (4 bytes for a u32 integer)
+    pub winner: Pubkey,  // The public key of the wallet holding the right (32 bytes)
+    pub granted_at: i64, // The unix timestamp the right was claimed at (8 bytes for an i64 integer)
 }
 
-// Define the accounts context for the `pick_winner` function
-// Specifies the accounts that need to be provided to this instruction
+// Define the accounts context for the `withdraw_proceeds` function
 #[derive(Accounts)]
 #[instruction(lottery_id: u32)]
-pub struct PickWinner<'info> {
-    // Define the lottery account, which will have its winner_id updated
+pub struct WithdrawProceeds<'info> {
+    // Define the lottery account the proceeds are withdrawn from
     #[account(
-        mut, // The lottery account is mutable, as the winner_id will be set
-        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()], // Use LOTTERY_SEED and lottery_id as seeds for generating the PDA
-        bump, // The bump seed used to create a valid PDA
-        has_one = authority // Ensure that the authority is the same as the lottery's authority
+        mut, // The lottery account is mutable, as lamports will be deducted
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        has_one = authority, // Only the lottery authority gets the proceeds
+        constraint = lottery.prize_kind == PrizeKind::Right @ LotteryError::WrongPrizeKind, // Lamport pots belong to the winner
+        constraint = lottery.claimed @ LotteryError::LotteryNotFinished, // Keep the funds until the right is claimed
     )]
     pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
 
-    // Define the authority account, which must sign the transaction
+    // Define the authority account, which receives the proceeds
+    #[account(mut)]
     pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
 }
 
-// Define the accounts context for the `claim_price` function
-// Specifies the accounts that need to be provided to this instruction
+// Define the accounts context for the `recover_funds` function
 #[derive(Accounts)]
 #[instruction(lottery_id: u32, ticket_id: u32)]
-pub struct ClaimPrice<'info> {
-    // Define the lottery account from which the price will be claimed
+pub struct RecoverFunds<'info> {
+    // Define the lottery account the funds are recovered from
     #[account(
         mut, // The lottery account is mutable, as lamports will be deducted
         seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
         bump,
+        constraint = lottery.id == lottery_id @ LotteryError::LotteryIdMismatch, // Don't trust the lottery_id argument on its own
     )]
     pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
 
-    // Define the ticket account that must match the winning ticket
+    // Define the master account, read for the recovery period and updated with the event sequence number
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the ticket account whose price is recovered
     #[account(
         seeds = [
             TICKET_SEED.as_bytes(),
@@ -330,14 +6568,168 @@ pub struct ClaimPrice<'info> {
             &ticket_id.to_le_bytes()
         ],
         bump,
-        has_one = authority, // Ensure that the authority is the owner of this ticket
+        has_one = authority, // Only the ticket owner can recover its funds
+        constraint = ticket.id == ticket_id @ LotteryError::TicketIdMismatch, // Don't trust the ticket_id argument on its own
     )]
     pub ticket: Account<'info, Ticket>, // Define the ticket account of type `Ticket`
 
-    // Define the authority account, which must be the winner
+    // Define the receipt account, shared with prize claims so each ticket is paid out at most once
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 4 + 4 + 32 + 8 + 8, // Same space as a claim receipt (total 64 bytes)
+        seeds = [RECEIPT_SEED.as_bytes(), lottery.key().as_ref(), ticket.key().as_ref()],
+        bump,
+    )]
+    pub receipt: Account<'info, ClaimReceipt>, // Define the receipt account of type `ClaimReceipt`
+
+    // Define the authority account, which must be the ticket owner
     #[account(mut)] // The authority account is mutable (e.g., its balance will increase)
     pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
 
     // Reference to the system program, used to interact with Solana's native features
     pub system_program: Program<'info, System>,
+
+    // Define the lottery's vault token account, which pays the refund in a token lottery
+    #[account(mut, seeds = [VAULT_SEED.as_bytes(), lottery.key().as_ref()], bump)]
+    pub vault: Option<Account<'info, TokenAccount>>,
+
+    // Define the owner's token account, which receives the refund in a token lottery
+    #[account(mut)]
+    pub owner_token_account: Option<Account<'info, TokenAccount>>,
+
+    // Reference to the token program, used for token refunds
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+// Define the accounts context for the `archive_lottery` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct ArchiveLottery<'info> {
+    // Define the lottery account, which is closed here
+    #[account(
+        mut, // The lottery account is mutable, as it will be closed
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        has_one = authority, // Only the lottery authority can archive it
+        constraint = lottery.claimed @ LotteryError::LotteryNotFinished, // The prize must have been paid out first
+        constraint = lottery.callback.is_none() || lottery.winner_notified @ LotteryError::LotteryNotFinished, // And the winner callback delivered
+        // Closing a token lottery would strand its vault, which only the lottery PDA can close, and the summary's pot is in lamports
+        constraint = lottery.payment_mint.is_none() @ LotteryError::UnsupportedForTokenLottery,
+        close = authority, // Return the remaining lamports to the authority
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the master account, read to check that archival is enabled
+    #[account(
+        mut, // The master account is mutable, as the event sequence number will be updated
+        seeds = [MASTER_SEED.as_bytes()],
+        bump,
+        constraint = master.has_feature(FEATURE_ARCHIVAL) @ LotteryError::FeatureDisabled,
+    )]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the winning ticket account, read to record the winner in the summary
+    #[account(
+        seeds = [
+            TICKET_SEED.as_bytes(),
+            lottery.key().as_ref(),
+            &lottery.winner_id.unwrap_or_default().to_le_bytes()
+        ],
+        bump,
+    )]
+    pub winning_ticket: Account<'info, Ticket>, // Define the ticket account of type `Ticket`
+
+    // Define the summary account, which is initialized here
+    #[account(
+        init,
+        payer = authority, // The authority pays for the summary out of the reclaimed lottery rent
+        space = 8 + 4 + 32 + 4 + 32 + 8 + 4 + 8 + 8 + 8, // Allocate enough space for the account (total 116 bytes)
+        // 8 +  // Account discriminator
+        // 4 +  // id: u32
+        // 32 + // authority: Pubkey
+        // 4 +  // winner_id: u32
+        // 32 + // winner: Pubkey
+        // 8 +  // pot: u64
+        // 4 +  // tickets_sold: u32
+        // 8 +  // created_at: i64
+        // 8 +  // drawn_at: i64
+        // 8;   // archived_at: i64
+        seeds = [SUMMARY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+    )]
+    pub summary: Account<'info, LotterySummary>, // Define the summary account of type `LotterySummary`
+
+    // Define the authority account, which receives the lottery rent and pays for the summary
+    #[account(mut)]
+    pub authority: Signer<'info>, // The signer is the account that authorizes this transaction
+
+    // Reference to the system program, used to interact with Solana's native features
+    pub system_program: Program<'info, System>,
+}
+
+// Define the accounts context for the `reconcile_lottery` function
+#[derive(Accounts)]
+#[instruction(lottery_id: u32)]
+pub struct ReconcileLottery<'info> {
+    // Define the lottery account being reconciled
+    #[account(
+        mut, // The lottery account is mutable, as an absorbed surplus is added to its jackpot
+        seeds = [LOTTERY_SEED.as_bytes(), &lottery_id.to_le_bytes()],
+        bump,
+        constraint = lottery.prize_kind == PrizeKind::Lamports @ LotteryError::WrongPrizeKind, // Right lotteries owe their proceeds to the creator instead
+    )]
+    pub lottery: Account<'info, Lottery>, // Define the lottery account of type `Lottery`
+
+    // Define the discrepancy account, which is created on the first reconciliation and overwritten on later ones
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + 32 + 8 + 8 + 8 + 8, // Allocate enough space for the account (total 72 bytes)
+        // 8 +  // Account discriminator
+        // 32 + // lottery: Pubkey
+        // 8 +  // expected: u64
+        // 8 +  // actual: u64
+        // 8 +  // absorbed: u64
+        // 8;   // reconciled_at: i64
+        seeds = [DISCREPANCY_SEED.as_bytes(), lottery.key().as_ref()],
+        bump,
+    )]
+    pub discrepancy: Account<'info, Discrepancy>, // Define the discrepancy account of type `Discrepancy`
+
+    // Define the master account, read for the claim window and updated with the event sequence number
+    #[account(mut, seeds = [MASTER_SEED.as_bytes()], bump)]
+    pub master: Account<'info, Master>, // Define the master account of type `Master`
+
+    // Define the payer account, which pays for the discrepancy account
+    #[account(mut)]
+    pub payer: Signer<'info>, // The signer is the account that authorizes this transaction
+
+    // Reference to the system program, used to interact with Solana's native features
+    pub system_program: Program<'info, System>,
+}
+
+// Define the data structure that will be stored in a discrepancy account
+// The result of the latest `reconcile_lottery` call for a lottery; `actual` differs from `expected` when balances drifted
+#[account]
+pub struct Discrepancy {
+    pub lottery: Pubkey,    // The reconciled lottery (32 bytes)
+    pub expected: u64,      // The lamports the lottery owed, above its rent (8 bytes for a u64 integer)
+    pub actual: u64,        // The lamports the lottery held, above its rent (8 bytes for a u64 integer)
+    pub absorbed: u64,      // The part of the surplus added to the jackpot (8 bytes for a u64 integer)
+    pub reconciled_at: i64, // The unix timestamp of the reconciliation (8 bytes for an i64 integer)
+}
+
+// Define the data structure that will be stored in the summary account of an archived lottery
+#[account]
+pub struct LotterySummary {
+    pub id: u32,           // The ID of the archived lottery (4 bytes for a u32 integer)
+    pub authority: Pubkey, // The public key of the authority that managed the lottery (32 bytes)
+    pub winner_id: u32,    // The ID of the winning ticket (4 bytes for a u32 integer)
+    pub winner: Pubkey,    // The public key of the wallet that claimed the prize (32 bytes)
+    pub pot: u64,          // The prize paid out, in lamports (8 bytes for a u64 integer)
+    pub tickets_sold: u32, // The number of tickets sold (4 bytes for a u32 integer)
+    pub created_at: i64,   // The unix timestamp the lottery was created at (8 bytes for an i64 integer)
+    pub drawn_at: i64,     // The unix timestamp the winner was drawn at (8 bytes for an i64 integer)
+    pub archived_at: i64,  // The unix timestamp the lottery was archived at (8 bytes for an i64 integer)
 }
@@ -0,0 +1,136 @@
+// Every split of lamports goes through this module, so rounding is decided in one place
+// The rounding policy: amounts paid out to an account are rounded down, amounts charged to a group are rounded up,
+// and what rounding leaves over stays with the side that holds the lamports, i.e. rolls over with the jackpot.
+// Nothing here can pay out more than the amount it splits, which is what keeps the lottery account solvent.
+
+// Basis points in a whole, the denominator of every share expressed in basis points
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+// `bps` basis points of `amount`, rounded down; None if `bps` is more than the whole
+pub fn bps_floor(amount: u64, bps: u16) -> Option<u64> {
+    if u64::from(bps) > BPS_DENOMINATOR {
+        return None;
+    }
+    // At most `amount`, so it always fits back in a u64
+    Some((u128::from(amount) * u128::from(bps) / u128::from(BPS_DENOMINATOR)) as u64)
+}
+
+// Split `amount` into `bps` basis points of it, rounded down, and the remainder
+// The remainder gets the rounding dust, so the two parts always add up to `amount` exactly
+pub fn split_bps(amount: u64, bps: u16) -> Option<(u64, u64)> {
+    let share = bps_floor(amount, bps)?;
+    Some((share, amount - share))
+}
+
+// Whether `value` is more than `bps` basis points of `whole`, compared exactly without rounding either side
+// `bps` may be more than the whole here, e.g. a limit that allows paying out more than a budget
+pub fn exceeds_bps(value: u64, whole: u64, bps: u32) -> bool {
+    u128::from(value) * u128::from(BPS_DENOMINATOR) > u128::from(whole) * u128::from(bps)
+}
+
+// Each of `parts` even shares of `amount` paid out, rounded down; None if there are no parts
+// The dust, less than `parts` lamports, stays with the payer
+pub fn pro_rata_floor(amount: u64, parts: u64) -> Option<u64> {
+    amount.checked_div(parts)
+}
+
+// Each of `parts` even shares of `amount` charged, rounded up; None if there are no parts
+// The shares together cover `amount`, overcharging by less than `parts` lamports
+pub fn pro_rata_ceil(amount: u64, parts: u64) -> Option<u64> {
+    if parts == 0 {
+        return None;
+    }
+    Some(amount.div_ceil(parts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Amounts around the edges rounding can go wrong at, plus every small amount
+    fn amounts() -> impl Iterator<Item = u64> {
+        (0..=1_000).chain([
+            9_999,
+            10_000,
+            10_001,
+            u64::MAX / 10_000,
+            u64::MAX - 1,
+            u64::MAX,
+        ])
+    }
+
+    #[test]
+    fn bps_floor_rounds_down() {
+        for amount in amounts() {
+            for bps in 0..=10_000u16 {
+                let share = u128::from(bps_floor(amount, bps).unwrap());
+                let exact = u128::from(amount) * u128::from(bps);
+                assert!(share * 10_000 <= exact);
+                assert!((share + 1) * 10_000 > exact);
+            }
+        }
+    }
+
+    #[test]
+    fn bps_floor_takes_the_whole_at_most() {
+        assert_eq!(bps_floor(u64::MAX, 10_000), Some(u64::MAX));
+        assert_eq!(bps_floor(1_000, 10_001), None);
+        assert_eq!(bps_floor(0, u16::MAX), None);
+    }
+
+    #[test]
+    fn split_parts_add_up() {
+        for amount in amounts() {
+            for bps in 0..=10_000u16 {
+                let (share, rest) = split_bps(amount, bps).unwrap();
+                assert_eq!(u128::from(share) + u128::from(rest), u128::from(amount));
+            }
+        }
+    }
+
+    #[test]
+    fn split_gives_the_dust_to_the_remainder() {
+        // 1 bps of 9_999 lamports is 0.9999 lamports: the share gets nothing, the remainder everything
+        assert_eq!(split_bps(9_999, 1), Some((0, 9_999)));
+        assert_eq!(split_bps(10_001, 5_000), Some((5_000, 5_001)));
+        assert_eq!(split_bps(7, 10_000), Some((7, 0)));
+    }
+
+    #[test]
+    fn exceeds_bps_is_exact() {
+        for value in 0..=200u64 {
+            for whole in 0..=200u64 {
+                for bps in [0, 1, 5_000, 9_999, 10_000, 10_001, 20_000, u32::MAX] {
+                    let exact = u128::from(whole) * u128::from(bps);
+                    assert_eq!(
+                        exceeds_bps(value, whole, bps),
+                        u128::from(value) * 10_000 > exact
+                    );
+                }
+            }
+        }
+        assert!(!exceeds_bps(u64::MAX, u64::MAX, 10_000));
+        assert!(exceeds_bps(u64::MAX, u64::MAX - 1, 10_000));
+    }
+
+    #[test]
+    fn pro_rata_shares_bracket_the_amount() {
+        for amount in amounts() {
+            for parts in 1..=100u64 {
+                let floor = u128::from(pro_rata_floor(amount, parts).unwrap());
+                let ceil = u128::from(pro_rata_ceil(amount, parts).unwrap());
+                let parts = u128::from(parts);
+                let amount = u128::from(amount);
+                assert!(floor * parts <= amount && amount - floor * parts < parts);
+                assert!(ceil * parts >= amount && ceil * parts - amount < parts);
+            }
+        }
+    }
+
+    #[test]
+    fn pro_rata_needs_parts() {
+        assert_eq!(pro_rata_floor(100, 0), None);
+        assert_eq!(pro_rata_ceil(100, 0), None);
+        assert_eq!(pro_rata_ceil(u64::MAX, 1), Some(u64::MAX));
+    }
+}
@@ -0,0 +1,93 @@
+use anchor_lang::prelude::*;
+use solana_sha256_hasher::hashv;
+
+// Prefixes keeping leaves and inner nodes apart, so an inner node can't be passed off as a leaf
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+// Hash a reward allocation into a leaf of the season's reward tree
+pub fn leaf(claimer: &Pubkey, amount: u64) -> [u8; 32] {
+    hashv(&[LEAF_PREFIX, claimer.as_ref(), &amount.to_le_bytes()]).to_bytes()
+}
+
+// Hash a house ticket's redemption code, at `index` of its inventory block, into a leaf of the block's code tree
+pub fn code_leaf(index: u16, code_hash: [u8; 32]) -> [u8; 32] {
+    hashv(&[LEAF_PREFIX, &index.to_le_bytes(), &code_hash]).to_bytes()
+}
+
+// Hash two sibling nodes into their parent
+// Siblings are sorted first, so proofs don't need to say which side each hash is on
+pub fn parent(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[NODE_PREFIX, &left, &right]).to_bytes()
+}
+
+// Check that `leaf` is part of the tree with the given root
+pub fn verify(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -> bool {
+    proof
+        .iter()
+        .fold(leaf, |node, sibling| parent(node, *sibling))
+        == root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Proof = Vec<[u8; 32]>;
+
+    // Four allocations and the root and proofs of the tree built from them
+    fn tree() -> (Vec<[u8; 32]>, [u8; 32], Vec<Proof>) {
+        let leaves: Vec<[u8; 32]> = (0..4u64)
+            .map(|i| leaf(&Pubkey::new_from_array([i as u8; 32]), i * 100))
+            .collect();
+        let left = parent(leaves[0], leaves[1]);
+        let right = parent(leaves[2], leaves[3]);
+        let root = parent(left, right);
+        let proofs = vec![
+            vec![leaves[1], right],
+            vec![leaves[0], right],
+            vec![leaves[3], left],
+            vec![leaves[2], left],
+        ];
+        (leaves, root, proofs)
+    }
+
+    #[test]
+    fn every_allocation_verifies() {
+        let (leaves, root, proofs) = tree();
+        for (leaf, proof) in leaves.iter().zip(&proofs) {
+            assert!(verify(proof, root, *leaf));
+        }
+    }
+
+    #[test]
+    fn tampered_allocation_is_rejected() {
+        let (_, root, proofs) = tree();
+        let inflated = leaf(&Pubkey::new_from_array([1; 32]), 1_000);
+        assert!(!verify(&proofs[1], root, inflated));
+    }
+
+    #[test]
+    fn single_allocation_needs_no_proof() {
+        let only = leaf(&Pubkey::new_from_array([7; 32]), 500);
+        assert!(verify(&[], only, only));
+    }
+
+    #[test]
+    fn code_is_bound_to_its_index() {
+        let codes = [[1; 32], [2; 32]];
+        let root = parent(code_leaf(0, codes[0]), code_leaf(1, codes[1]));
+        assert!(verify(
+            &[code_leaf(1, codes[1])],
+            root,
+            code_leaf(0, codes[0])
+        ));
+        // The same code can't be presented for another ticket of the block
+        assert!(!verify(
+            &[code_leaf(1, codes[1])],
+            root,
+            code_leaf(1, codes[0])
+        ));
+    }
+}
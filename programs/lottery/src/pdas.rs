@@ -0,0 +1,290 @@
+// Program-derived addresses of the program's accounts, in one place for the program and its Rust clients
+// Account constraints must spell their seeds out for Anchor (and the IDL), so they use the same constants
+// directly; everything else derives addresses through these functions so seeds can't drift apart.
+// The `find_*` helpers search for the bump, which is too expensive on-chain; they are only compiled for
+// off-chain users of the crate, with the `client` feature.
+
+use anchor_lang::prelude::*;
+use solana_sha256_hasher::hashv;
+
+use crate::constants::*;
+
+// Seeds of the master account
+pub fn master_seeds() -> [&'static [u8]; 1] {
+    [MASTER_SEED.as_bytes()]
+}
+
+// Seeds of the config account
+pub fn config_seeds() -> [&'static [u8]; 1] {
+    [CONFIG_SEED.as_bytes()]
+}
+
+// Seeds of the fee ledger account, which counts protocol fees per period
+pub fn fee_ledger_seeds() -> [&'static [u8]; 1] {
+    [FEE_LEDGER_SEED.as_bytes()]
+}
+
+// Seeds of a lottery account, `id` being the lottery ID in little-endian bytes
+pub fn lottery_seeds(id: &[u8; 4]) -> [&[u8]; 2] {
+    [LOTTERY_SEED.as_bytes(), id]
+}
+
+// Seeds of a lottery name account, which reserves `name` for its creator, see `create_named_lottery`
+pub fn lottery_name_seeds<'a>(creator: &'a Pubkey, name: &'a str) -> [&'a [u8]; 3] {
+    [LOTTERY_SEED.as_bytes(), creator.as_ref(), name.as_bytes()]
+}
+
+// The ID of a creator's named lottery: the first bytes of a hash of the creator and the name, moved into the upper
+// half of the ID range, which the master account's counter never reaches
+pub fn named_lottery_id(creator: &Pubkey, name: &str) -> u32 {
+    let hash = hashv(&[creator.as_ref(), name.as_bytes()]).to_bytes();
+    u32::from_le_bytes([hash[0], hash[1], hash[2], hash[3]]) | NAMED_LOTTERY_ID_BASE
+}
+
+// Seeds of a ticket account, `id` being the ticket ID in little-endian bytes
+pub fn ticket_seeds<'a>(lottery: &'a Pubkey, id: &'a [u8; 4]) -> [&'a [u8]; 3] {
+    [TICKET_SEED.as_bytes(), lottery.as_ref(), id]
+}
+
+// Seeds of a token lottery's vault token account, which holds its ticket sales, see `set_payment_mint`
+pub fn vault_seeds(lottery: &Pubkey) -> [&[u8]; 2] {
+    [VAULT_SEED.as_bytes(), lottery.as_ref()]
+}
+
+// Seeds of a ticket's claim receipt, which exists once the ticket was paid out
+pub fn receipt_seeds<'a>(lottery: &'a Pubkey, ticket: &'a Pubkey) -> [&'a [u8]; 3] {
+    [RECEIPT_SEED.as_bytes(), lottery.as_ref(), ticket.as_ref()]
+}
+
+// Seeds of a partner's block of reserved tickets, see `reserve_tickets`
+pub fn reservation_seeds<'a>(lottery: &'a Pubkey, partner: &'a Pubkey) -> [&'a [u8]; 3] {
+    [
+        RESERVATION_SEED.as_bytes(),
+        lottery.as_ref(),
+        partner.as_ref(),
+    ]
+}
+
+// Seeds of a player's voucher account
+pub fn voucher_seeds(owner: &Pubkey) -> [&[u8]; 2] {
+    [VOUCHER_SEED.as_bytes(), owner.as_ref()]
+}
+
+// Seeds of a lottery's rent pool account
+pub fn rent_pool_seeds(lottery: &Pubkey) -> [&[u8]; 2] {
+    [RENT_POOL_SEED.as_bytes(), lottery.as_ref()]
+}
+
+// Seeds of the dispute account challenging a lottery's draw
+pub fn dispute_seeds(lottery: &Pubkey) -> [&[u8]; 2] {
+    [DISPUTE_SEED.as_bytes(), lottery.as_ref()]
+}
+
+// Seeds of a lottery's entropy committee account, see `set_committee`
+pub fn committee_seeds(lottery: &Pubkey) -> [&[u8]; 2] {
+    [COMMITTEE_SEED.as_bytes(), lottery.as_ref()]
+}
+
+// Seeds of a block of house tickets, `block_id` being the block ID in little-endian bytes, see `stock_inventory`
+pub fn inventory_seeds<'a>(lottery: &'a Pubkey, block_id: &'a [u8; 4]) -> [&'a [u8]; 3] {
+    [INVENTORY_SEED.as_bytes(), lottery.as_ref(), block_id]
+}
+
+// Seeds of a season account, `id` being the season ID in little-endian bytes
+pub fn season_seeds(id: &[u8; 4]) -> [&[u8]; 2] {
+    [SEASON_SEED.as_bytes(), id]
+}
+
+// Seeds of a player's profile account
+pub fn profile_seeds(owner: &Pubkey) -> [&[u8]; 2] {
+    [PROFILE_SEED.as_bytes(), owner.as_ref()]
+}
+
+// Seeds of a lottery creator's account
+pub fn creator_seeds(authority: &Pubkey) -> [&[u8]; 2] {
+    [CREATOR_SEED.as_bytes(), authority.as_ref()]
+}
+
+#[cfg(any(feature = "client", test))]
+pub fn master_pda() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&master_seeds(), &crate::ID)
+}
+
+#[cfg(any(feature = "client", test))]
+pub fn config_pda() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&config_seeds(), &crate::ID)
+}
+
+#[cfg(any(feature = "client", test))]
+pub fn fee_ledger_pda() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&fee_ledger_seeds(), &crate::ID)
+}
+
+// A lamport lottery's account also holds its ticket sales; a token lottery keeps them in its vault, see `vault_pda`
+#[cfg(any(feature = "client", test))]
+pub fn lottery_pda(id: u32) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&lottery_seeds(&id.to_le_bytes()), &crate::ID)
+}
+
+#[cfg(any(feature = "client", test))]
+pub fn lottery_name_pda(creator: &Pubkey, name: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&lottery_name_seeds(creator, name), &crate::ID)
+}
+
+// A named lottery's account, derived from its creator and name without reading the master account
+#[cfg(any(feature = "client", test))]
+pub fn named_lottery_pda(creator: &Pubkey, name: &str) -> (Pubkey, u8) {
+    lottery_pda(named_lottery_id(creator, name))
+}
+
+#[cfg(any(feature = "client", test))]
+pub fn ticket_pda(lottery: &Pubkey, id: u32) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&ticket_seeds(lottery, &id.to_le_bytes()), &crate::ID)
+}
+
+#[cfg(any(feature = "client", test))]
+pub fn vault_pda(lottery: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&vault_seeds(lottery), &crate::ID)
+}
+
+#[cfg(any(feature = "client", test))]
+pub fn receipt_pda(lottery: &Pubkey, ticket: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&receipt_seeds(lottery, ticket), &crate::ID)
+}
+
+#[cfg(any(feature = "client", test))]
+pub fn reservation_pda(lottery: &Pubkey, partner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&reservation_seeds(lottery, partner), &crate::ID)
+}
+
+#[cfg(any(feature = "client", test))]
+pub fn voucher_pda(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&voucher_seeds(owner), &crate::ID)
+}
+
+#[cfg(any(feature = "client", test))]
+pub fn rent_pool_pda(lottery: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&rent_pool_seeds(lottery), &crate::ID)
+}
+
+#[cfg(any(feature = "client", test))]
+pub fn dispute_pda(lottery: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&dispute_seeds(lottery), &crate::ID)
+}
+
+#[cfg(any(feature = "client", test))]
+pub fn committee_pda(lottery: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&committee_seeds(lottery), &crate::ID)
+}
+
+#[cfg(any(feature = "client", test))]
+pub fn inventory_pda(lottery: &Pubkey, block_id: u32) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &inventory_seeds(lottery, &block_id.to_le_bytes()),
+        &crate::ID,
+    )
+}
+
+#[cfg(any(feature = "client", test))]
+pub fn season_pda(id: u32) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&season_seeds(&id.to_le_bytes()), &crate::ID)
+}
+
+#[cfg(any(feature = "client", test))]
+pub fn profile_pda(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&profile_seeds(owner), &crate::ID)
+}
+
+#[cfg(any(feature = "client", test))]
+pub fn creator_pda(authority: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&creator_seeds(authority), &crate::ID)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The bump each finder returns recreates the same address from the seeds, as the program's signer seeds do
+    #[test]
+    fn bumps_recreate_the_addresses() {
+        let owner = Pubkey::new_unique();
+        let (lottery, lottery_bump) = lottery_pda(7);
+        let id = 7u32.to_le_bytes();
+        let ticket_id = 3u32.to_le_bytes();
+
+        let [prefix, seed] = lottery_seeds(&id);
+        assert_eq!(
+            Pubkey::create_program_address(&[prefix, seed, &[lottery_bump]], &crate::ID),
+            Ok(lottery)
+        );
+
+        let (ticket, ticket_bump) = ticket_pda(&lottery, 3);
+        let [prefix, lottery_key, seed] = ticket_seeds(&lottery, &ticket_id);
+        assert_eq!(
+            Pubkey::create_program_address(
+                &[prefix, lottery_key, seed, &[ticket_bump]],
+                &crate::ID
+            ),
+            Ok(ticket)
+        );
+
+        let (profile, profile_bump) = profile_pda(&owner);
+        let [prefix, seed] = profile_seeds(&owner);
+        assert_eq!(
+            Pubkey::create_program_address(&[prefix, seed, &[profile_bump]], &crate::ID),
+            Ok(profile)
+        );
+
+        let (vault, vault_bump) = vault_pda(&lottery);
+        let [prefix, seed] = vault_seeds(&lottery);
+        assert_eq!(
+            Pubkey::create_program_address(&[prefix, seed, &[vault_bump]], &crate::ID),
+            Ok(vault)
+        );
+    }
+
+    // Every kind of account lives at its own address, even for the same ID
+    #[test]
+    fn kinds_do_not_collide() {
+        let authority = Pubkey::new_unique();
+        let lottery = lottery_pda(0).0;
+        let ticket = ticket_pda(&lottery, 0).0;
+        let addresses = [
+            master_pda().0,
+            config_pda().0,
+            fee_ledger_pda().0,
+            lottery_pda(0).0,
+            season_pda(0).0,
+            profile_pda(&authority).0,
+            creator_pda(&authority).0,
+            lottery_name_pda(&authority, "weekly").0,
+            named_lottery_pda(&authority, "weekly").0,
+            vault_pda(&lottery).0,
+            receipt_pda(&lottery, &ticket).0,
+            reservation_pda(&lottery, &authority).0,
+            voucher_pda(&authority).0,
+            rent_pool_pda(&lottery).0,
+            dispute_pda(&lottery).0,
+            committee_pda(&lottery).0,
+            inventory_pda(&lottery, 0).0,
+            ticket,
+        ];
+        for (i, address) in addresses.iter().enumerate() {
+            assert!(!addresses[..i].contains(address));
+        }
+    }
+
+    // Named lottery IDs depend on both the creator and the name, and never fall in the counter's range
+    #[test]
+    fn named_ids_are_reserved_per_creator() {
+        let creator = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let id = named_lottery_id(&creator, "weekly");
+
+        assert!(id >= NAMED_LOTTERY_ID_BASE);
+        assert_eq!(id, named_lottery_id(&creator, "weekly"));
+        assert_ne!(id, named_lottery_id(&creator, "daily"));
+        assert_ne!(id, named_lottery_id(&other, "weekly"));
+        assert_eq!(named_lottery_pda(&creator, "weekly"), lottery_pda(id));
+    }
+}
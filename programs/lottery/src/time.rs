@@ -0,0 +1,26 @@
+// Every read of the current time goes through this module instead of `Clock::get()`
+// Builds with the `test-clock` feature add the master account's `clock_offset` to the unix timestamp, which the
+// admin moves with `warp_clock`, so deadlines, claim windows and recovery periods can be tested end to end
+// without sleeping through real time. Other builds have no `warp_clock` and ignore the offset.
+
+use anchor_lang::prelude::*;
+
+use crate::Master;
+
+// The cluster clock, with the test offset applied in `test-clock` builds
+// Only the unix timestamp moves; slots and epochs stay real because draws read slot hashes for them
+pub fn clock(master: &Master) -> Result<Clock> {
+    let clock = Clock::get()?;
+    if cfg!(feature = "test-clock") {
+        return Ok(Clock {
+            unix_timestamp: clock.unix_timestamp + master.clock_offset,
+            ..clock
+        });
+    }
+    Ok(clock)
+}
+
+// The current unix timestamp, see `clock`
+pub fn now(master: &Master) -> Result<i64> {
+    Ok(clock(master)?.unix_timestamp)
+}
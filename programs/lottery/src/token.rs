@@ -0,0 +1,70 @@
+// SPL token payments for lotteries with a payment mint, see `set_payment_mint`
+// A token lottery keeps its ticket sales in a vault token account owned by the lottery PDA instead of as lamports on
+// the lottery account. Instructions take the token accounts as optional accounts, as lamport lotteries don't need
+// them; these helpers fail if a token lottery's accounts are missing.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+
+use crate::{error::LotteryError, pdas, Lottery};
+
+// Move `amount` tokens from `from`, signed by its owner, into the lottery's vault
+// The token program checks that both accounts hold the same mint and that `owner` may spend from `from`
+pub fn deposit<'info>(
+    from: Option<&Account<'info, TokenAccount>>,
+    vault: Option<&Account<'info, TokenAccount>>,
+    owner: &AccountInfo<'info>,
+    token_program: Option<&Program<'info, Token>>,
+    amount: u64,
+) -> Result<()> {
+    let (Some(from), Some(vault), Some(token_program)) = (from, vault, token_program) else {
+        return err!(LotteryError::TokenAccountsMissing);
+    };
+
+    token::transfer(
+        CpiContext::new(
+            token_program.to_account_info(),
+            Transfer {
+                from: from.to_account_info(),
+                to: vault.to_account_info(),
+                authority: owner.clone(),
+            },
+        ),
+        amount,
+    )
+}
+
+// Pay `amount` tokens out of the lottery's vault into `to`, which must belong to `recipient`
+// The lottery PDA owns the vault, so it signs the transfer with its seeds
+pub fn withdraw<'info>(
+    lottery: &Account<'info, Lottery>,
+    bump: u8,
+    vault: Option<&Account<'info, TokenAccount>>,
+    to: Option<&Account<'info, TokenAccount>>,
+    recipient: &Pubkey,
+    token_program: Option<&Program<'info, Token>>,
+    amount: u64,
+) -> Result<()> {
+    let (Some(vault), Some(to), Some(token_program)) = (vault, to, token_program) else {
+        return err!(LotteryError::TokenAccountsMissing);
+    };
+    // Whoever sends the claim picks the destination, so it must be the ticket owner's
+    if to.owner != *recipient {
+        return err!(LotteryError::TokenAccountOwnerMismatch);
+    }
+
+    let id = lottery.id.to_le_bytes();
+    let [prefix, id] = pdas::lottery_seeds(&id);
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            Transfer {
+                from: vault.to_account_info(),
+                to: to.to_account_info(),
+                authority: lottery.to_account_info(),
+            },
+            &[&[prefix, id, &[bump]]],
+        ),
+        amount,
+    )
+}
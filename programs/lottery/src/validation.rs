@@ -0,0 +1,191 @@
+use anchor_lang::prelude::*;
+
+use crate::{constants::*, error::LotteryError, Lottery, Ticket, UpgradeAuthority};
+
+// Check that a ticket price is within the allowed range
+// A zero price would let anyone create tickets for free and leave a lottery with nothing to win
+pub fn validate_ticket_price(ticket_price: u64) -> Result<()> {
+    if ticket_price < MIN_TICKET_PRICE {
+        return err!(LotteryError::TicketPriceTooLow);
+    }
+    if ticket_price > MAX_TICKET_PRICE {
+        return err!(LotteryError::TicketPriceTooHigh);
+    }
+    Ok(())
+}
+
+// Check that an epoch-locked draw is neither in the past nor unreasonably far in the future
+pub fn validate_draw_epoch(draw_epoch: Option<u64>, current_epoch: u64) -> Result<()> {
+    if let Some(draw_epoch) = draw_epoch {
+        if draw_epoch < current_epoch {
+            return err!(LotteryError::InvalidDrawEpoch);
+        }
+        if draw_epoch - current_epoch > MAX_DRAW_EPOCHS_AHEAD {
+            return err!(LotteryError::DrawEpochTooFar);
+        }
+    }
+    Ok(())
+}
+
+// Check that an end time, if set (non-zero), is in the future and at most MAX_END_TIME_AHEAD away
+pub fn validate_end_time(end_time: i64, now: i64) -> Result<()> {
+    if end_time != 0 && (end_time <= now || end_time - now > MAX_END_TIME_AHEAD) {
+        return err!(LotteryError::InvalidEndTime);
+    }
+    Ok(())
+}
+
+// Check that an attestation only claims known ATTEST_* bits, and names a jurisdiction when it claims one
+// The jurisdiction must look like an ISO 3166-1 alpha-2 code (two uppercase ASCII letters)
+pub fn validate_attestation(claims: u8, jurisdiction: [u8; 2]) -> Result<()> {
+    if claims & !ATTEST_ALL != 0 {
+        return err!(LotteryError::InvalidAttestation);
+    }
+    if claims & ATTEST_JURISDICTION != 0 && !jurisdiction.iter().all(u8::is_ascii_uppercase) {
+        return err!(LotteryError::InvalidAttestation);
+    }
+    Ok(())
+}
+
+// Check a lottery's display hints, which the program stores but never interprets
+// The currency must be all zeros (unset) or look like an ISO 4217 code (three uppercase ASCII letters), and the
+// locale must be empty or a short BCP 47 tag (ASCII letters, digits and dashes)
+pub fn validate_display_hints(currency: [u8; 3], locale: &str) -> Result<()> {
+    if currency != [0; 3] && !currency.iter().all(u8::is_ascii_uppercase) {
+        return err!(LotteryError::InvalidDisplayHints);
+    }
+    if locale.len() > MAX_LOCALE_LEN
+        || !locale
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-')
+    {
+        return err!(LotteryError::InvalidDisplayHints);
+    }
+    Ok(())
+}
+
+// Check the name of a named lottery, which is part of its name account's seeds
+// It must be 1 to MAX_LOTTERY_NAME_LEN bytes of printable ASCII, so integrators can type and derive it reliably
+pub fn validate_lottery_name(name: &str) -> Result<()> {
+    if name.is_empty()
+        || name.len() > MAX_LOTTERY_NAME_LEN
+        || !name
+            .bytes()
+            .all(|byte| byte.is_ascii_graphic() || byte == b' ')
+    {
+        return err!(LotteryError::InvalidLotteryName);
+    }
+    Ok(())
+}
+
+// Check a lottery's entropy committee: 1 to MAX_COMMITTEE_MEMBERS distinct members, and a threshold of shares
+// between 1 and the number of members, so the members can always reach it
+pub fn validate_committee(members: &[Pubkey], threshold: u8) -> Result<()> {
+    if members.is_empty()
+        || members.len() > MAX_COMMITTEE_MEMBERS
+        || threshold == 0
+        || usize::from(threshold) > members.len()
+        || (1..members.len()).any(|i| members[..i].contains(&members[i]))
+    {
+        return err!(LotteryError::InvalidCommittee);
+    }
+    Ok(())
+}
+
+// Check who can upgrade the program: no one, the master admin, or the expected governance account
+// Any other upgrade authority could replace the program's code, and with it every rule players rely on
+pub fn validate_upgrade_authority(
+    upgrade_authority: Option<Pubkey>,
+    admin: &Pubkey,
+    governance: Option<&Pubkey>,
+) -> Result<UpgradeAuthority> {
+    match upgrade_authority {
+        None => Ok(UpgradeAuthority::Immutable),
+        Some(key) if key == *admin => Ok(UpgradeAuthority::Admin),
+        Some(key) if Some(&key) == governance => Ok(UpgradeAuthority::Governance),
+        Some(_) => err!(LotteryError::UnexpectedUpgradeAuthority),
+    }
+}
+
+// Check that a lottery is still selling tickets
+// Sales stop once a winner is drawn, for epoch-locked lotteries once the draw epoch is over, and for lotteries
+// with an end time once it is reached
+// Sale instructions also check the first part in their accounts constraints; `simulate_buy` relies on this one
+pub fn validate_sale_open(lottery: &Lottery, clock: &Clock) -> Result<()> {
+    if !lottery.is_open() {
+        return err!(LotteryError::SalesClosed);
+    }
+    if let Some(draw_epoch) = lottery.draw_epoch {
+        if clock.epoch > draw_epoch {
+            return err!(LotteryError::DrawEpochPassed);
+        }
+    }
+    if lottery.end_time != 0 && clock.unix_timestamp >= lottery.end_time {
+        return err!(LotteryError::SalesEnded);
+    }
+    Ok(())
+}
+
+// Check that a claim cooldown is non-negative and leaves the winner time to claim before recovery opens
+pub fn validate_claim_cooldown(claim_cooldown: i64) -> Result<()> {
+    if !(0..=MAX_CLAIM_COOLDOWN).contains(&claim_cooldown) {
+        return err!(LotteryError::InvalidClaimCooldown);
+    }
+    Ok(())
+}
+
+// Check that a ticket can claim its lottery's prize right now
+// It must be the drawn winner (or match the draw seed, see `Lottery::wins`), not refunded and redeemed if it is a code
+// ticket, and the prize must be unclaimed, past the claim cooldown and within the claim window
+pub fn validate_claim(
+    lottery: &Lottery,
+    ticket: &Ticket,
+    now: i64,
+    recovery_period: i64,
+) -> Result<()> {
+    // Check if the price has already been claimed
+    if lottery.claimed {
+        return err!(LotteryError::AlreadyClaimed);
+    }
+
+    // A challenged draw can't pay out until the admin rules on it
+    if lottery.disputed {
+        return err!(LotteryError::DrawDisputed);
+    }
+
+    // A cancelled lottery only pays refunds
+    if lottery.cancelled {
+        return err!(LotteryError::LotteryCancelled);
+    }
+
+    // Once buyers can recover their funds the prize is no longer whole, so it can't be claimed
+    if lottery.recovery_open(now, recovery_period) {
+        return err!(LotteryError::ClaimWindowExpired);
+    }
+
+    // The operator may hold claims back for a while after the draw
+    if lottery.winner_id.is_some() && now < lottery.drawn_at.saturating_add(lottery.claim_cooldown)
+    {
+        return err!(LotteryError::ClaimCooldown);
+    }
+
+    // A code ticket can only be claimed once its code has been redeemed
+    if ticket.code_hash.is_some() {
+        return err!(LotteryError::TicketNotRedeemed);
+    }
+
+    // A refunded ticket can't win; a draw that picked one is redone with `redraw_void_winner`
+    if ticket.refunded {
+        return err!(LotteryError::TicketRefunded);
+    }
+
+    // Check that the ticket won: it is the drawn winner, or one of the matching tickets of a match mode lottery
+    match lottery.winner_id {
+        None => err!(LotteryError::WinnerNotChosen),
+        Some(_) if lottery.match_bits > 0 && !lottery.matches_counted() => {
+            err!(LotteryError::MatchesNotCounted)
+        }
+        Some(_) if lottery.wins(ticket) => Ok(()),
+        Some(_) => err!(LotteryError::InvalidWinner),
+    }
+}
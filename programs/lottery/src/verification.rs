@@ -0,0 +1,611 @@
+// Machine-checked invariants for the draw and payout math
+// The kani proofs run with `cargo kani`; the model tests check the same properties with `cargo test`, and also run
+// random instruction sequences through the checks the handlers use
+
+#[cfg(kani)]
+mod proofs {
+    use crate::{
+        constants::MAX_WINNER_SHARE_BPS,
+        draw::{index_from_value, last_accepted_value},
+        Lottery, Master,
+    };
+
+    // The winner index is always a ticket that exists
+    #[kani::proof]
+    fn winner_index_is_in_range() {
+        let value: u64 = kani::any();
+        let count: u32 = kani::any();
+        kani::assume(count > 0);
+
+        if let Some(index) = index_from_value(value, count) {
+            assert!(index < count);
+        }
+    }
+
+    // Accepted values form whole blocks of `count`, so every index has the same number of preimages
+    #[kani::proof]
+    fn accepted_values_are_unbiased() {
+        let count: u64 = kani::any();
+        kani::assume(count > 0 && count <= u64::from(u32::MAX));
+
+        assert_eq!(
+            (u128::from(last_accepted_value(count)) + 1) % u128::from(count),
+            0
+        );
+        assert!(u128::from(u64::MAX) - u128::from(last_accepted_value(count)) < u128::from(count));
+    }
+
+    // Splitting the jackpot neither creates nor loses lamports, and the winner gets at most their share
+    #[kani::proof]
+    fn jackpot_split_is_exact() {
+        let lottery = Lottery {
+            ticket_price: kani::any(),
+            last_ticket_id: kani::any(),
+            carried_over: kani::any(),
+            winner_share_bps: kani::any(),
+            ..Default::default()
+        };
+        kani::assume(lottery.winner_share_bps <= MAX_WINNER_SHARE_BPS);
+
+        if let (Some(jackpot), Some((price, rollover))) =
+            (lottery.jackpot(), lottery.split_jackpot())
+        {
+            assert_eq!(
+                u128::from(price) + u128::from(rollover),
+                u128::from(jackpot)
+            );
+            assert!(
+                u128::from(price) * 10_000
+                    <= u128::from(jackpot) * u128::from(lottery.winner_share_bps)
+            );
+        }
+    }
+
+    // A payout the circuit breaker lets through never takes the window past its limit, and one it holds back pauses payouts
+    #[kani::proof]
+    fn breaker_caps_window_payouts() {
+        let mut master = Master {
+            payout_limit_bps: kani::any(),
+            window_start: 0,
+            window_budget: kani::any(),
+            window_payouts: kani::any(),
+            ..Default::default()
+        };
+        kani::assume(master.payout_limit_bps > 0);
+        let amount: u64 = kani::any();
+
+        if master.record_payout(amount, 0) {
+            assert!(
+                u128::from(master.window_payouts) * 10_000
+                    <= u128::from(master.window_budget) * u128::from(master.payout_limit_bps)
+            );
+        } else {
+            assert!(master.claims_paused);
+        }
+    }
+
+    // The payout never exceeds what the tickets paid in, and is never silently truncated
+    #[kani::proof]
+    fn payout_equals_ticket_sales() {
+        let lottery = Lottery {
+            ticket_price: kani::any(),
+            last_ticket_id: kani::any(),
+            ..Default::default()
+        };
+        let sales = u128::from(lottery.ticket_price) * u128::from(lottery.last_ticket_id);
+
+        match lottery.pot() {
+            Some(pot) => assert_eq!(u128::from(pot), sales),
+            None => assert!(sales > u128::from(u64::MAX)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod model_tests {
+    use anchor_lang::prelude::*;
+    use solana_sha256_hasher::hash;
+
+    use crate::{
+        constants::{DEFAULT_RECOVERY_PERIOD, MAX_WINNER_SHARE_BPS, PAYOUT_WINDOW},
+        draw::{index_from_value, last_accepted_value, redraw_seed, uniform_index},
+        validation::{validate_claim, validate_sale_open},
+        DrawBounty, Lottery, Master, Ticket,
+    };
+
+    // Ticket counts around the interesting boundaries: small, powers of two and their neighbours, and the maximum
+    fn counts() -> Vec<u32> {
+        let mut counts: Vec<u32> = (1..=300).collect();
+        for shift in 9..32 {
+            counts.extend([(1 << shift) - 1, 1 << shift, (1 << shift) + 1]);
+        }
+        counts.push(u32::MAX);
+        counts
+    }
+
+    #[test]
+    fn winner_index_is_in_range() {
+        for count in counts() {
+            let last_accepted = last_accepted_value(count.into());
+            for value in [
+                0,
+                1,
+                u64::from(count),
+                last_accepted - 1,
+                last_accepted,
+                u64::MAX,
+            ] {
+                if let Some(index) = index_from_value(value, count) {
+                    assert!(
+                        index < count,
+                        "index {index} out of range for {count} tickets"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn accepted_values_are_unbiased() {
+        for count in counts() {
+            let count = u64::from(count);
+            let last_accepted = last_accepted_value(count);
+
+            // Whole blocks only, and less than one block is thrown away
+            assert_eq!((u128::from(last_accepted) + 1) % u128::from(count), 0);
+            assert!(u128::from(u64::MAX) - u128::from(last_accepted) < u128::from(count));
+
+            // The last accepted value maps to the last index, the first rejected one isn't used
+            assert_eq!(
+                index_from_value(last_accepted, count as u32),
+                Some((count - 1) as u32)
+            );
+            if last_accepted < u64::MAX {
+                assert_eq!(index_from_value(last_accepted + 1, count as u32), None);
+            }
+        }
+    }
+
+    #[test]
+    fn payout_equals_ticket_sales() {
+        let prices = [
+            0,
+            1,
+            1_000_000,
+            1_000_000_000,
+            u64::MAX / u64::from(u32::MAX),
+            u64::MAX,
+        ];
+        let tickets = [0, 1, 2, 1_000, u32::MAX];
+
+        for ticket_price in prices {
+            for last_ticket_id in tickets {
+                let lottery = Lottery {
+                    ticket_price,
+                    last_ticket_id,
+                    ..Default::default()
+                };
+                let sales = u128::from(ticket_price) * u128::from(last_ticket_id);
+
+                match lottery.pot() {
+                    Some(pot) => assert_eq!(u128::from(pot), sales),
+                    None => assert!(sales > u128::from(u64::MAX)),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn recovery_shares_fit_in_the_jackpot() {
+        for ticket_price in [1_000, 1_000_000_000] {
+            for last_ticket_id in [1, 7, 1_000] {
+                for carried_over in [0, 1, 999_999_999] {
+                    for bounty_paid in [0, 1, 999] {
+                        let lottery = Lottery {
+                            ticket_price,
+                            last_ticket_id,
+                            carried_over,
+                            bounty_paid,
+                            ..Default::default()
+                        };
+                        let share = lottery.recovery_share(ticket_price).unwrap();
+
+                        // Without a bounty every ticket gets its price back, and all shares together never exceed the jackpot
+                        assert!(bounty_paid > 0 || share >= ticket_price);
+                        assert!(
+                            u128::from(share) * u128::from(last_ticket_id)
+                                <= u128::from(lottery.jackpot().unwrap())
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn draw_bounty_is_capped() {
+        let lottery = |draw_bounty| Lottery {
+            ticket_price: 1_000,
+            last_ticket_id: 10,
+            draw_bounty,
+            ..Default::default()
+        };
+
+        assert_eq!(lottery(DrawBounty::None).draw_bounty_amount(100), Some(0));
+        assert_eq!(
+            lottery(DrawBounty::Flat(50)).draw_bounty_amount(100),
+            Some(50)
+        );
+        assert_eq!(
+            lottery(DrawBounty::Bps(50)).draw_bounty_amount(100),
+            Some(50)
+        );
+
+        // 1% of the 10,000 lamport jackpot at most, whatever the lottery asks for
+        assert_eq!(
+            lottery(DrawBounty::Flat(u64::MAX)).draw_bounty_amount(100),
+            Some(100)
+        );
+        assert_eq!(
+            lottery(DrawBounty::Bps(10_000)).draw_bounty_amount(100),
+            Some(100)
+        );
+        assert_eq!(
+            lottery(DrawBounty::Bps(10_000)).draw_bounty_amount(0),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn jackpot_split_is_exact() {
+        for ticket_price in [1, 3, 1_000_000_000] {
+            for last_ticket_id in [1, 7, 1_000] {
+                for carried_over in [0, 1, 999_999_999] {
+                    for winner_share_bps in [1, 3_333, 5_000, 9_999, MAX_WINNER_SHARE_BPS] {
+                        let lottery = Lottery {
+                            ticket_price,
+                            last_ticket_id,
+                            carried_over,
+                            winner_share_bps,
+                            ..Default::default()
+                        };
+                        let jackpot = lottery.jackpot().unwrap();
+                        let (price, rollover) = lottery.split_jackpot().unwrap();
+
+                        assert_eq!(price + rollover, jackpot);
+                        assert!(
+                            u128::from(price) * 10_000
+                                <= u128::from(jackpot) * u128::from(winner_share_bps)
+                        );
+                        if winner_share_bps == MAX_WINNER_SHARE_BPS {
+                            assert_eq!(rollover, 0);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn match_shares_add_up_to_the_split() {
+        for match_count in [1, 2, 7, 1_000] {
+            for winner_share_bps in [3_333, MAX_WINNER_SHARE_BPS] {
+                let mut lottery = Lottery {
+                    ticket_price: 1_000_003,
+                    last_ticket_id: 1_000,
+                    carried_over: 999,
+                    winner_share_bps,
+                    match_count,
+                    ..Default::default()
+                };
+                let (price, rollover) = lottery.split_jackpot().unwrap();
+
+                // Every matching ticket claims in turn; together they pay out exactly what a single winner would
+                let (mut paid, mut rolled_over) = (0, 0);
+                while lottery.matches_claimed < match_count {
+                    let (share, rollover) = lottery.claim_split().unwrap();
+                    paid += share;
+                    rolled_over += rollover;
+                    lottery.matches_claimed += 1;
+                }
+                assert_eq!((paid, rolled_over), (price, rollover));
+            }
+        }
+    }
+
+    #[test]
+    fn unredeemed_codes_win_only_if_redeemable_after_the_draw() {
+        let code_ticket = Ticket {
+            id: 0,
+            authority: Pubkey::default(),
+            lottery_id: 0,
+            code_hash: Some([1; 32]),
+            delegate: None,
+            art_seed: [0; 32],
+            paid: 1,
+            bought_slot: 0,
+            refunded: false,
+        };
+        let redeemed = Ticket {
+            id: 1,
+            code_hash: None,
+            ..code_ticket.clone()
+        };
+
+        for (no_self_entry, match_bits) in [(false, 0), (true, 0), (false, 8), (true, 8)] {
+            let mut lottery = Lottery {
+                last_ticket_id: 2,
+                unredeemed_codes: 1,
+                winner_id: Some(0),
+                no_self_entry,
+                match_bits,
+                match_cursor: 2,
+                ..Default::default()
+            };
+
+            // Where the authority could redeem a winning code to itself, the unredeemed ticket doesn't count and
+            // drawing it means drawing again
+            let redeemable = !no_self_entry && match_bits == 0;
+            assert_eq!(lottery.can_win(&code_ticket), redeemable);
+            assert_eq!(lottery.wins(&code_ticket), redeemable);
+            assert_eq!(lottery.needs_redraw(&code_ticket), !redeemable);
+            assert_eq!(lottery.eligible_tickets(), if redeemable { 2 } else { 1 });
+
+            // A redeemed ticket that is drawn wins wherever nothing matched
+            lottery.winner_id = Some(1);
+            assert!(lottery.wins(&redeemed));
+            assert!(!lottery.needs_redraw(&redeemed));
+        }
+    }
+
+    #[test]
+    fn breaker_caps_window_payouts() {
+        let mut master = Master {
+            payout_limit_bps: 10_000,
+            ..Default::default()
+        };
+        master.record_pot(1_000, 0);
+
+        // Paying out what is owed is fine, a single lamport more trips the breaker
+        assert!(master.record_payout(600, 1));
+        assert!(master.record_payout(400, 2));
+        assert!(!master.record_payout(1, 3));
+        assert!(master.claims_paused);
+        assert_eq!((master.window_payouts, master.owed_pots), (1_000, 0));
+
+        // The next window is budgeted with what is owed then
+        master.claims_paused = false;
+        master.record_pot(500, PAYOUT_WINDOW);
+        assert_eq!((master.window_budget, master.window_payouts), (500, 0));
+        assert!(master.record_payout(500, PAYOUT_WINDOW + 1));
+
+        // A limit of 0 disables the breaker
+        master.payout_limit_bps = 0;
+        assert!(master.record_payout(u64::MAX, PAYOUT_WINDOW + 2));
+    }
+
+    // A deterministic stream of pseudo-random numbers, so the sequence test can't flake
+    struct Rng([u8; 32]);
+
+    impl Rng {
+        fn below(&mut self, n: u64) -> u64 {
+            self.0 = hash(&self.0).to_bytes();
+            u64::from_le_bytes(self.0[..8].try_into().unwrap()) % n
+        }
+    }
+
+    // A lottery driven through the checks and math its instructions use, with its lamports tracked on the side
+    struct Model {
+        lottery: Lottery,
+        master: Master,
+        clock: Clock,
+        balance: u64, // Lamports the lottery account holds on top of its rent
+        tickets: Vec<Option<Ticket>>, // None once a ticket's funds are recovered
+    }
+
+    impl Model {
+        fn new(rng: &mut Rng) -> Self {
+            let carried_over = rng.below(1_000);
+            let lottery = Lottery {
+                ticket_price: 1 + rng.below(1_000_000),
+                end_time: [0, 50][rng.below(2) as usize],
+                carried_over,
+                winner_share_bps: [5_000, MAX_WINNER_SHARE_BPS][rng.below(2) as usize],
+                draw_bounty: [DrawBounty::None, DrawBounty::Bps(100)][rng.below(2) as usize],
+                refund_grace_slots: [0, 5][rng.below(2) as usize],
+                ..Default::default()
+            };
+            let master = Master {
+                payout_limit_bps: 10_000,
+                ..Default::default()
+            };
+            Model {
+                lottery,
+                master,
+                clock: Clock {
+                    slot: 1, // Slot 0 would mark tickets as having no grace period
+                    ..Default::default()
+                },
+                balance: carried_over,
+                tickets: vec![],
+            }
+        }
+
+        // Pay `amount` out of the lottery account; a correct program never owes more than the account holds, and the
+        // circuit breaker, budgeted with exactly what is owed, never trips
+        fn pay(&mut self, amount: u64) {
+            assert!(
+                self.master.record_payout(amount, self.clock.unix_timestamp),
+                "payout tripped the breaker"
+            );
+            self.balance = self
+                .balance
+                .checked_sub(amount)
+                .expect("lottery account went negative");
+        }
+
+        // `buy_ticket`
+        fn buy(&mut self) {
+            let sale = validate_sale_open(&self.lottery, &self.clock);
+            if self.lottery.winner_id.is_some() || self.lottery.cancelled {
+                assert!(sale.is_err(), "ticket sold after the lottery closed");
+            }
+            if sale.is_err() {
+                return;
+            }
+            self.balance += self.lottery.ticket_price;
+            self.tickets.push(Some(Ticket {
+                id: self.lottery.last_ticket_id,
+                authority: Pubkey::default(),
+                lottery_id: 0,
+                code_hash: None,
+                delegate: None,
+                art_seed: [0; 32],
+                paid: self.lottery.ticket_price,
+                bought_slot: self.clock.slot,
+                refunded: false,
+            }));
+            self.lottery.last_ticket_id += 1;
+            self.lottery.last_sale_slot = self.clock.slot;
+        }
+
+        // `pick_winner`: the accounts constraints, `check_drawable` and `draw`
+        fn draw(&mut self, seed: [u8; 32]) {
+            let lottery = &self.lottery;
+            if lottery.cancelled
+                || lottery.winner_id.is_some()
+                || lottery.eligible_tickets() == 0
+                || self.clock.slot <= lottery.last_sale_slot
+                || self.clock.unix_timestamp < lottery.end_time
+            {
+                return;
+            }
+
+            let bounty = lottery.draw_bounty_amount(100).unwrap();
+            self.balance = self.balance.checked_sub(bounty).unwrap();
+            self.lottery.bounty_paid = bounty;
+            self.lottery.winner_id = Some(uniform_index(seed, self.lottery.last_ticket_id));
+            self.lottery.draw_seed = seed;
+            self.lottery.drawn_at = self.clock.unix_timestamp;
+            self.lottery.sales_closed = true;
+            let jackpot = self.lottery.jackpot().unwrap();
+            self.master.record_pot(jackpot, self.clock.unix_timestamp);
+        }
+
+        // `refund_ticket`
+        fn grace_refund(&mut self, index: usize) {
+            let Some(Some(ticket)) = self.tickets.get_mut(index) else {
+                return;
+            };
+            let lottery = &mut self.lottery;
+            if validate_sale_open(lottery, &self.clock).is_err()
+                || ticket.refunded
+                || lottery.refund_grace_slots == 0
+                || self.clock.slot > ticket.bought_slot + lottery.refund_grace_slots
+            {
+                return;
+            }
+            self.balance = self
+                .balance
+                .checked_sub(ticket.paid)
+                .expect("lottery account went negative");
+            ticket.paid = 0;
+            ticket.refunded = true;
+            lottery.refunded_tickets += 1;
+        }
+
+        // `redraw_void_winner`
+        fn redraw(&mut self) {
+            let Some(winner_id) = self.lottery.winner_id else {
+                return;
+            };
+            let Some(ticket) = &self.tickets[winner_id as usize] else {
+                return;
+            };
+            if !ticket.refunded || self.lottery.disputed {
+                return;
+            }
+            let seed = redraw_seed(self.lottery.draw_seed);
+            self.lottery.winner_id = Some(uniform_index(seed, self.lottery.last_ticket_id));
+            self.lottery.draw_seed = seed;
+            self.lottery.drawn_at = self.clock.unix_timestamp;
+        }
+
+        // `cancel_lottery`
+        fn cancel(&mut self) {
+            if self.lottery.cancelled || self.lottery.winner_id.is_some() {
+                return;
+            }
+            self.lottery.cancelled = true;
+            let jackpot = self.lottery.jackpot().unwrap();
+            self.master.record_pot(jackpot, self.clock.unix_timestamp);
+        }
+
+        // `claim_prize`, without the protocol fee, which is paid out of the same amount
+        fn claim(&mut self, index: usize) {
+            let Some(Some(ticket)) = self.tickets.get(index) else {
+                return;
+            };
+            let now = self.clock.unix_timestamp;
+            if validate_claim(&self.lottery, ticket, now, DEFAULT_RECOVERY_PERIOD).is_err() {
+                return;
+            }
+            assert_eq!(
+                self.lottery.winner_id,
+                Some(ticket.id),
+                "claim without a winner"
+            );
+            assert!(!self.lottery.cancelled, "claim from a cancelled lottery");
+            assert!(!ticket.refunded, "claim by a refunded ticket");
+
+            let (price, rollover) = self.lottery.split_jackpot().unwrap();
+            self.pay(price + rollover);
+            self.lottery.claimed = true;
+        }
+
+        // `claim_refund` and `recover_funds`
+        fn refund(&mut self, index: usize) {
+            let Some(Some(ticket)) = self.tickets.get(index) else {
+                return;
+            };
+            if !self
+                .lottery
+                .recovery_open(self.clock.unix_timestamp, DEFAULT_RECOVERY_PERIOD)
+            {
+                return;
+            }
+            let share = self.lottery.recovery_share(ticket.paid).unwrap();
+            self.pay(share);
+            self.tickets[index] = None;
+        }
+    }
+
+    // Random instruction sequences against the model: no ticket is sold once the lottery is drawn or cancelled, only
+    // the drawn ticket of a lottery that wasn't cancelled and wasn't refunded is paid the prize, and the lottery account
+    // never pays out more than it holds, whichever order buys, grace refunds, draws, redraws, cancellations, claims,
+    // refunds and the passing of time come in
+    #[test]
+    fn instruction_sequences_keep_the_invariants() {
+        let mut rng = Rng([0; 32]);
+        for _ in 0..2_000 {
+            let mut model = Model::new(&mut rng);
+            for _ in 0..60 {
+                let index = rng.below(model.tickets.len() as u64 + 1) as usize;
+                match rng.below(10) {
+                    0..=2 => model.buy(),
+                    3 => model.draw(rng.0),
+                    4 => model.cancel(),
+                    5 => model.claim(index),
+                    6 => model.refund(index),
+                    7 => model.grace_refund(index),
+                    8 => model.redraw(),
+                    _ => {
+                        model.clock.slot += 1 + rng.below(3);
+                        model.clock.unix_timestamp +=
+                            [1, 30, DEFAULT_RECOVERY_PERIOD][rng.below(3) as usize];
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,16 +1,2519 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
+import { expect } from "chai";
+import { createHash, randomBytes } from "crypto";
 import { Lottery } from "../target/types/lottery";
+import { verifyTicketArtSeed } from "../app/ticket-art";
+import { lotteryBatch } from "../app/batch";
+import { checkUpgradeAuthority } from "../app/version";
+import { buildCreateRoundsTransaction } from "../app/rounds";
+import { exportWinnerBundle, verifyWinnerBundle } from "../app/winners";
+import { MAX_REFUNDS_PER_BATCH, refundCancelledLottery } from "../app/refunds";
+import { buildFairnessReport, verifyFairnessReport } from "../app/fairness";
+
+const { PublicKey, LAMPORTS_PER_SOL } = anchor.web3;
 
 describe("lottery", () => {
   // Configure the client to use the local cluster.
-  anchor.setProvider(anchor.AnchorProvider.env());
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
 
   const program = anchor.workspace.Lottery as Program<Lottery>;
+  const authority = provider.wallet.publicKey;
+  const ticketPrice = new anchor.BN(LAMPORTS_PER_SOL / 100);
+
+  // PDA helpers mirroring the seeds used by the program
+  const u32Seed = (value: number) =>
+    new anchor.BN(value).toArrayLike(Buffer, "le", 4);
+  const masterPda = () =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("master")],
+      program.programId
+    )[0];
+  const auditLogPda = () =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("audit_log")],
+      program.programId
+    )[0];
+  const feeLedgerPda = () =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("fee_ledger")],
+      program.programId
+    )[0];
+  const seasonPda = (id: number) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("season"), u32Seed(id)],
+      program.programId
+    )[0];
+  const creatorPda = (creator: anchor.web3.PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("creator"), creator.toBuffer()],
+      program.programId
+    )[0];
+  const lotteryPda = (id: number) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("lottery"), u32Seed(id)],
+      program.programId
+    )[0];
+  const programDataPda = () =>
+    PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      anchor.web3.BPF_LOADER_UPGRADEABLE_PROGRAM_ID
+    )[0];
+  const ticketPda = (lottery: anchor.web3.PublicKey, id: number) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("ticket"), lottery.toBuffer(), u32Seed(id)],
+      program.programId
+    )[0];
+
+  // Await a transaction that must fail, and check it failed with one of the given error codes
+  const expectError = async (tx: Promise<unknown>, codes: string[]) => {
+    try {
+      await tx;
+    } catch (err) {
+      expect(codes).to.include(
+        (err as anchor.AnchorError).error.errorCode.code
+      );
+      return;
+    }
+    expect.fail(`expected one of ${codes.join(", ")}`);
+  };
+
+  // The `CrankStatus` a maintenance instruction returns, read from a simulation of it, which commits nothing
+  // Mirrors the order of its variants in the program
+  const returnedVariant = async (method: {
+    simulate: () => Promise<{ raw: readonly string[] }>;
+  }) => {
+    const { raw } = await method.simulate();
+    const prefix = `Program return: ${program.programId} `;
+    const data = raw
+      .find((log) => log.startsWith(prefix))
+      .slice(prefix.length);
+    return Buffer.from(data, "base64")[0];
+  };
+  const crankStatuses = ["done", "notDue", "alreadyDone", "paused"];
+  const crankStatus = async (
+    method: Parameters<typeof returnedVariant>[0]
+  ) => crankStatuses[await returnedVariant(method)];
+  const claimOutcomes = ["paid", "paused"];
+  const claimOutcome = async (
+    method: Parameters<typeof returnedVariant>[0]
+  ) => claimOutcomes[await returnedVariant(method)];
+
+  // Await a transaction that must fail for any reason
+  const expectFailure = async (tx: Promise<unknown>) => {
+    let succeeded = false;
+    try {
+      await tx;
+      succeeded = true;
+    } catch (err) {
+      // expected
+    }
+    expect(succeeded, "expected the transaction to fail").to.be.false;
+  };
+
+  const createLottery = async (noSelfEntry = false, endTime = 0) => {
+    const id = (await program.account.master.fetch(masterPda())).lastId;
+    await program.methods
+      .createLottery(
+        ticketPrice,
+        null,
+        new anchor.BN(endTime),
+        noSelfEntry,
+        { lamports: {} },
+        [...Buffer.from("USD")],
+        "en-US"
+      )
+      .accountsPartial({
+        lottery: lotteryPda(id),
+        master: masterPda(),
+        authority,
+      })
+      .rpc();
+    return id;
+  };
+
+  // Deadline passed to buy_ticket, far enough ahead for any test transaction to land
+  const validUntil = () => new anchor.BN(Math.floor(Date.now() / 1000) + 60);
+
+  // Terms hash of lotteries that never called set_terms
+  const noTerms = Array(32).fill(0);
+
+  const buyTicket = async (lotteryId: number, termsHash = noTerms) => {
+    const lottery = lotteryPda(lotteryId);
+    const ticketId = (await program.account.lottery.fetch(lottery))
+      .lastTicketId;
+    await program.methods
+      .buyTicket(lotteryId, validUntil(), termsHash)
+      .accountsPartial({
+        lottery,
+        ticket: ticketPda(lottery, ticketId),
+        buyer: authority,
+        reference: null,
+      })
+      .rpc();
+    await nextSlot();
+    return ticketId;
+  };
+
+  // Wait until the cluster is past the current slot, since a lottery can't be drawn in the slot of its last sale
+  const nextSlot = async () => {
+    const slot = await provider.connection.getSlot();
+    while ((await provider.connection.getSlot()) <= slot) {
+      await new Promise((resolve) => setTimeout(resolve, 100));
+    }
+  };
+
+  // Wait until the cluster clock has reached `unixTime`, e.g. a lottery's end time
+  const waitUntil = async (unixTime: number) => {
+    const connection = provider.connection;
+    while (
+      ((await connection.getBlockTime(await connection.getSlot())) ?? 0) <
+      unixTime
+    ) {
+      await nextSlot();
+    }
+  };
+
+  // A lottery with an end time drawn by a one member committee, which anyone may draw once it's due
+  // The member commits to its share right away, while sales are open
+  const createCommitteeLottery = async (endTime: number) => {
+    const lotteryId = await createLottery(false, endTime);
+    const lottery = lotteryPda(lotteryId);
+    const committee = PublicKey.findProgramAddressSync(
+      [Buffer.from("committee"), lottery.toBuffer()],
+      program.programId
+    )[0];
+    const member = anchor.web3.Keypair.generate();
+    const share = randomBytes(32);
+    await program.methods
+      .setCommittee(lotteryId, [member.publicKey], 1)
+      .accountsPartial({ lottery, committee, authority })
+      .rpc();
+    const commitment = createHash("sha256").update(share).digest();
+    await program.methods
+      .commitShare(lotteryId, [...commitment])
+      .accountsPartial({ lottery, committee, member: member.publicKey })
+      .signers([member])
+      .rpc();
+
+    // Reveal the share after the end time, wait out the reveal window and draw as `caller`
+    const finalize = async (caller: anchor.web3.Keypair) => {
+      await waitUntil(endTime);
+      await program.methods
+        .revealShare(lotteryId, [...share])
+        .accountsPartial({ lottery, committee, member: member.publicKey })
+        .signers([member])
+        .rpc();
+      const { revealSlot } = await program.account.committee.fetch(committee);
+      while (
+        (await provider.connection.getSlot()) <=
+        revealSlot.toNumber() + 151
+      ) {
+        await nextSlot();
+      }
+      await program.methods
+        .finalizeDraw(lotteryId)
+        .accountsPartial({
+          draw: { lottery, caller: caller.publicKey },
+          committee,
+        })
+        .signers([caller])
+        .rpc();
+    };
+    return { lotteryId, finalize };
+  };
+
+  before(async () => {
+    await program.methods
+      .initMaster()
+      .accountsPartial({
+        master: masterPda(),
+        auditLog: auditLogPda(),
+        season: seasonPda(0),
+        programData: programDataPda(),
+        payer: authority,
+      })
+      .rpc();
+  });
+
+  it("can call init_master again without resetting the master account", async () => {
+    const before = await program.account.master.fetch(masterPda());
+    await program.methods
+      .initMaster()
+      .accountsPartial({
+        master: masterPda(),
+        auditLog: auditLogPda(),
+        season: seasonPda(0),
+        programData: programDataPda(),
+        payer: authority,
+      })
+      .rpc();
+    const after = await program.account.master.fetch(masterPda());
+    expect(after.lastId).to.equal(before.lastId);
+    expect(after.initialized).to.be.true;
+  });
+
+  it("reports who can upgrade the program", async () => {
+    // Local deployments are upgradeable by the provider wallet, which is also the master admin
+    expect(await checkUpgradeAuthority(program, { strict: true })).to.equal(
+      "admin"
+    );
+    expect(
+      await checkUpgradeAuthority(program, {
+        governance: anchor.web3.Keypair.generate().publicKey,
+        strict: true,
+      })
+    ).to.equal("admin");
+  });
+
+  it("creates a lottery and sells tickets", async () => {
+    const lotteryId = await createLottery();
+    const ticketId = await buyTicket(lotteryId);
+
+    const lottery = await program.account.lottery.fetch(lotteryPda(lotteryId));
+    expect(lottery.id).to.equal(lotteryId);
+    expect(lottery.ticketPrice.eq(ticketPrice)).to.be.true;
+    expect(lottery.lastTicketId).to.equal(1);
+
+    const ticket = await program.account.ticket.fetch(
+      ticketPda(lotteryPda(lotteryId), ticketId)
+    );
+    expect(ticket.id).to.equal(ticketId);
+    expect(ticket.authority.equals(authority)).to.be.true;
+    expect(ticket.paid.eq(ticketPrice)).to.be.true;
+
+    // The art seed is sha256(lottery ++ ticket id ++ owner)
+    expect(
+      verifyTicketArtSeed(
+        ticket.artSeed,
+        lotteryPda(lotteryId),
+        ticketId,
+        authority
+      )
+    ).to.be.true;
+  });
+
+  it("buys a block of tickets with contiguous IDs in one transaction", async () => {
+    const lotteryId = await createLottery();
+    await buyTicket(lotteryId);
+    const lottery = lotteryPda(lotteryId);
+    const buyTickets = (ids: number[]) =>
+      program.methods
+        .buyTickets(lotteryId, ids.length, validUntil(), noTerms)
+        .accountsPartial({ lottery, buyer: authority })
+        .remainingAccounts(
+          ids.map((id) => ({
+            pubkey: ticketPda(lottery, id),
+            isSigner: false,
+            isWritable: true,
+          }))
+        )
+        .rpc();
+
+    // The accounts must be the next tickets' in ID order
+    await expectError(buyTickets([2, 1]), ["TicketAccountMismatch"]);
+
+    // Lamports sent to a ticket address ahead of time don't block the purchase
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: authority,
+          toPubkey: ticketPda(lottery, 2),
+          lamports: 1_000,
+        })
+      )
+    );
+
+    const before = await provider.connection.getBalance(lottery);
+    await buyTickets([1, 2, 3]);
+    expect(await provider.connection.getBalance(lottery)).to.equal(
+      before + 3 * ticketPrice.toNumber()
+    );
+    expect((await program.account.lottery.fetch(lottery)).lastTicketId).to.equal(
+      4
+    );
+    for (const id of [1, 2, 3]) {
+      const ticket = await program.account.ticket.fetch(ticketPda(lottery, id));
+      expect(ticket.id).to.equal(id);
+      expect(ticket.authority.equals(authority)).to.be.true;
+      expect(ticket.paid.eq(ticketPrice)).to.be.true;
+    }
+  });
+
+  it("draws a winner among the sold tickets and pays out the prize", async () => {
+    const lotteryId = await createLottery();
+    for (let i = 0; i < 3; i++) {
+      await buyTicket(lotteryId);
+    }
+
+    const lottery = lotteryPda(lotteryId);
+    await program.methods
+      .pickWinner(lotteryId)
+      .accountsPartial({ lottery, caller: authority })
+      .rpc();
+
+    // Tickets are numbered from 0, so the winner must be one of 0, 1 or 2
+    const { winnerId } = await program.account.lottery.fetch(lottery);
+    expect(winnerId).to.be.lessThan(3);
+
+    await program.methods
+      .claimPrize(lotteryId, winnerId)
+      .accountsPartial({
+        lottery,
+        ticket: ticketPda(lottery, winnerId),
+        authority,
+        claimer: authority,
+      })
+      .rpc();
+    expect((await program.account.lottery.fetch(lottery)).claimed).to.be.true;
+
+    // The claim leaves a receipt behind, and can't be repeated
+    const [receipt] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("receipt"),
+        lottery.toBuffer(),
+        ticketPda(lottery, winnerId).toBuffer(),
+      ],
+      program.programId
+    );
+    const { amount } = await program.account.claimReceipt.fetch(receipt);
+    expect(amount.eq(ticketPrice.muln(3))).to.be.true;
+
+    // The receipt account already exists, so the system program rejects the replay before the handler runs
+    await expectFailure(
+      program.methods
+        .claimPrize(lotteryId, winnerId)
+        .accountsPartial({
+          lottery,
+          ticket: ticketPda(lottery, winnerId),
+          authority,
+          claimer: authority,
+        })
+        .rpc()
+    );
+
+    // The published result checks out, a tampered one doesn't
+    const bundle = await exportWinnerBundle(program, lotteryId);
+    expect(bundle.winners[0].claimed).to.be.true;
+    expect(verifyWinnerBundle(program, bundle)).to.be.true;
+    bundle.winners[0].prize = "1";
+    expect(verifyWinnerBundle(program, bundle)).to.be.false;
+
+    // So does the signed fairness report, recomputed from the stored draw seed
+    const operator = anchor.web3.Keypair.generate();
+    const report = await buildFairnessReport(program, lotteryId, operator);
+    expect(report.draw.recomputedWinnerId).to.equal(winnerId);
+    expect(Object.values(report.checks).every((check) => check)).to.be.true;
+    expect(verifyFairnessReport(report)).to.be.true;
+    report.draw.winnerId = (winnerId + 1) % 3;
+    expect(verifyFairnessReport(report)).to.be.false;
+  });
+
+  it("pays the protocol fee out of the prize to the treasury", async () => {
+    const treasury = anchor.web3.Keypair.generate().publicKey;
+    const updateConfig = (feeBps: number, to: PublicKey) =>
+      program.methods
+        .updateConfig(feeBps, to)
+        .accountsPartial({ master: masterPda(), authority })
+        .rpc();
+
+    // The fee can't be set above the hard cap
+    await expectError(updateConfig(1_001, treasury), ["InvalidFee"]);
+
+    // Fund the treasury so it can take a fee below the rent-exempt minimum
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: authority,
+          toPubkey: treasury,
+          lamports: LAMPORTS_PER_SOL,
+        })
+      )
+    );
+    await updateConfig(500, treasury);
+
+    const lotteryId = await createLottery();
+    const ticketId = await buyTicket(lotteryId);
+    const lottery = lotteryPda(lotteryId);
+    await program.methods
+      .pickWinner(lotteryId)
+      .accountsPartial({ lottery, caller: authority })
+      .rpc();
+    const ledgerBefore = await program.account.feeLedger.fetch(feeLedgerPda());
+    await program.methods
+      .claimPrize(lotteryId, ticketId)
+      .accountsPartial({
+        lottery,
+        ticket: ticketPda(lottery, ticketId),
+        authority,
+        claimer: authority,
+      })
+      .rpc();
+
+    // 5% of the prize goes to the treasury, the winner's receipt records the rest
+    const fee = ticketPrice.muln(500).divn(10_000);
+    expect(await provider.connection.getBalance(treasury)).to.equal(
+      LAMPORTS_PER_SOL + fee.toNumber()
+    );
+    const [receipt] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("receipt"),
+        lottery.toBuffer(),
+        ticketPda(lottery, ticketId).toBuffer(),
+      ],
+      program.programId
+    );
+    const { amount, claimedAt } = await program.account.claimReceipt.fetch(
+      receipt
+    );
+    expect(amount.eq(ticketPrice.sub(fee))).to.be.true;
+
+    // The fee is counted towards the claim's period in the fee report; periods
+    // are FEE_PERIOD seconds, and the ledger keeps FEE_LEDGER_PERIODS of them
+    const feePeriod = 7 * 24 * 60 * 60;
+    const feeLedgerPeriods = 26;
+    const period = claimedAt.divn(feePeriod).toNumber();
+    const before = ledgerBefore.periods[period % feeLedgerPeriods];
+    const counted =
+      before.period === period ? before.lamports : new anchor.BN(0);
+    const report = await program.methods
+      .getFeeReport(period, period)
+      .view();
+    expect(report.lamports.eq(counted.add(fee))).to.be.true;
+    expect(report.periods.map((entry) => entry.period)).to.deep.equal([
+      period,
+    ]);
+    await expectError(
+      program.methods
+        .getFeeReport(period, period + feeLedgerPeriods)
+        .view(),
+      ["InvalidFeeReportRange"]
+    );
+
+    // Later tests expect no fee
+    await updateConfig(0, authority);
+  });
+
+  it("charges a creator's protocol fee override instead of the config's", async () => {
+    const treasury = anchor.web3.Keypair.generate().publicKey;
+    const setCreatorFee = (feeBps: number | null) =>
+      program.methods
+        .setCreatorFee(authority, feeBps)
+        .accountsPartial({
+          creatorAccount: creatorPda(authority),
+          master: masterPda(),
+          authority,
+        })
+        .rpc();
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: authority,
+          toPubkey: treasury,
+          lamports: LAMPORTS_PER_SOL,
+        })
+      )
+    );
+    await program.methods
+      .updateConfig(500, treasury)
+      .accountsPartial({ master: masterPda(), authority })
+      .rpc();
+
+    // Overrides are capped like the config's fee
+    await expectError(setCreatorFee(1_001), ["InvalidFee"]);
+    await setCreatorFee(100);
+    expect(
+      (await program.account.creator.fetch(creatorPda(authority))).feeBps
+    ).to.equal(100);
+
+    const lotteryId = await createLottery();
+    const ticketId = await buyTicket(lotteryId);
+    const lottery = lotteryPda(lotteryId);
+    await program.methods
+      .pickWinner(lotteryId)
+      .accountsPartial({ lottery, caller: authority })
+      .rpc();
+    await program.methods
+      .claimPrize(lotteryId, ticketId)
+      .accountsPartial({
+        lottery,
+        ticket: ticketPda(lottery, ticketId),
+        authority,
+        claimer: authority,
+      })
+      .rpc();
+
+    // 1% of the prize goes to the treasury rather than the config's 5%
+    expect(await provider.connection.getBalance(treasury)).to.equal(
+      LAMPORTS_PER_SOL + ticketPrice.muln(100).divn(10_000).toNumber()
+    );
+
+    // Later tests expect no fee
+    await setCreatorFee(null);
+    expect(
+      (await program.account.creator.fetch(creatorPda(authority))).feeBps
+    ).to.be.null;
+    await program.methods
+      .updateConfig(0, authority)
+      .accountsPartial({ master: masterPda(), authority })
+      .rpc();
+  });
+
+  it("delivers winner callbacks separately from the claim", async () => {
+    const lotteryId = await createLottery();
+    const ticketId = await buyTicket(lotteryId);
+    const lottery = lotteryPda(lotteryId);
+    const ticket = ticketPda(lottery, ticketId);
+    const notifyWinner = () =>
+      program.methods.notifyWinner(lotteryId).accountsPartial({
+        lottery,
+        ticket,
+        winner: authority,
+        callbackProgram: program.programId,
+      });
+
+    // Before the claim there is nothing to deliver yet
+    await program.methods
+      .pickWinner(lotteryId)
+      .accountsPartial({ lottery, caller: authority })
+      .rpc();
+    expect(await crankStatus(notifyWinner())).to.equal("notDue");
+
+    await program.methods
+      .claimPrize(lotteryId, ticketId)
+      .accountsPartial({ lottery, ticket, authority, claimer: authority })
+      .rpc();
+
+    // Without a registered callback there is nothing to deliver
+    await expectError(notifyWinner().rpc(), ["NoWinnerCallback"]);
+    expect((await program.account.lottery.fetch(lottery)).winnerNotified).to.be
+      .false;
+  });
+
+  it("keeps the legacy claim_price working until the admin disables it", async () => {
+    const lotteryId = await createLottery();
+    const ticketId = await buyTicket(lotteryId);
+    const lottery = lotteryPda(lotteryId);
+    await program.methods
+      .pickWinner(lotteryId)
+      .accountsPartial({ lottery, caller: authority })
+      .rpc();
+
+    const { features } = await program.account.master.fetch(masterPda());
+    const legacyInstructions = new anchor.BN(1 << 3);
+    const setFeatures = (value: anchor.BN) =>
+      program.methods
+        .setFeatures(value)
+        .accountsPartial({
+          master: masterPda(),
+          auditLog: auditLogPda(),
+          authority,
+        })
+        .rpc();
+    const claimPrice = () =>
+      program.methods
+        .claimPrice(lotteryId, ticketId)
+        .accountsPartial({
+          lottery,
+          ticket: ticketPda(lottery, ticketId),
+          authority,
+          claimer: authority,
+        })
+        .rpc();
+
+    await setFeatures(features.and(legacyInstructions.notn(64)));
+    await expectError(claimPrice(), ["FeatureDisabled"]);
+
+    await setFeatures(features);
+    await claimPrice();
+    expect((await program.account.lottery.fetch(lottery)).claimed).to.be.true;
+  });
+
+  it("doesn't draw in the slot of the last sale", async () => {
+    const lotteryId = await createLottery();
+    const lottery = lotteryPda(lotteryId);
+    const buy = await program.methods
+      .buyTicket(lotteryId, validUntil(), noTerms)
+      .accountsPartial({
+        lottery,
+        ticket: ticketPda(lottery, 0),
+        buyer: authority,
+        reference: null,
+      })
+      .instruction();
+
+    // Buying and drawing in one transaction always shares a slot
+    await expectError(
+      program.methods
+        .pickWinner(lotteryId)
+        .accountsPartial({ lottery, caller: authority })
+        .preInstructions([buy])
+        .rpc(),
+      ["DrawInSaleSlot"]
+    );
+  });
+
+  it("draws with a committed secret once the reveal delay has passed", async () => {
+    const lotteryId = await createLottery();
+    await buyTicket(lotteryId);
+    const lottery = lotteryPda(lotteryId);
+    const secret = randomBytes(32);
+    const commitment = createHash("sha256").update(secret).digest();
+
+    await program.methods
+      .commitRandomness(lotteryId, [...commitment])
+      .accountsPartial({ lottery, authority })
+      .rpc();
+
+    // Committing closes sales, and the lottery can only be drawn by revealing
+    await expectError(buyTicket(lotteryId), ["SalesClosed"]);
+    await expectError(
+      program.methods
+        .pickWinner(lotteryId)
+        .accountsPartial({ lottery, caller: authority })
+        .rpc(),
+      ["DrawCommitted"]
+    );
+
+    // Nor can the authority cancel it anymore, once the reveal slot's hash would tell them the winner
+    await expectError(
+      program.methods
+        .cancelLottery(lotteryId)
+        .accountsPartial({ lottery, master: masterPda(), authority })
+        .rpc(),
+      ["DrawCommitted"]
+    );
+
+    const reveal = (revealed: Buffer) =>
+      program.methods
+        .revealAndPick(lotteryId, [...revealed])
+        .accountsPartial({ lottery, caller: authority })
+        .rpc();
+    await expectError(reveal(secret), ["RevealTooEarly"]);
+
+    // Wait out COMMIT_REVEAL_DELAY_SLOTS
+    const { commitSlot } = await program.account.lottery.fetch(lottery);
+    const revealSlot = commitSlot.toNumber() + 10;
+    while ((await provider.connection.getSlot()) <= revealSlot) {
+      await nextSlot();
+    }
+    await expectError(reveal(randomBytes(32)), ["RevealMismatch"]);
+    await reveal(secret);
+    expect((await program.account.lottery.fetch(lottery)).winnerId).to.equal(0);
+  });
+
+  it("sells tickets until the end time and only draws after it", async () => {
+    const createWithEndTime = async (endTime: number) => {
+      const id = (await program.account.master.fetch(masterPda())).lastId;
+      await program.methods
+        .createLottery(
+          ticketPrice,
+          null,
+          new anchor.BN(endTime),
+          false,
+          { lamports: {} },
+          [...Buffer.from("USD")],
+          "en-US"
+        )
+        .accountsPartial({ lottery: lotteryPda(id), master: masterPda() })
+        .rpc();
+      return id;
+    };
+    const now = Math.floor(Date.now() / 1000);
+
+    await expectError(createWithEndTime(now - 60), ["InvalidEndTime"]);
+
+    // Sales are open until the end time, and the authority can't draw early
+    const lotteryId = await createWithEndTime(now + 3600);
+    await buyTicket(lotteryId);
+    await expectError(
+      program.methods
+        .pickWinner(lotteryId)
+        .accountsPartial({ lottery: lotteryPda(lotteryId), caller: authority })
+        .rpc(),
+      ["DrawBeforeEndTime"]
+    );
+  });
+
+  it("draws a committee lottery from its members' revealed shares", async () => {
+    const endTime = Math.floor(Date.now() / 1000) + 15;
+    const lotteryId = await createLottery(false, endTime);
+    const lottery = lotteryPda(lotteryId);
+    const committee = PublicKey.findProgramAddressSync(
+      [Buffer.from("committee"), lottery.toBuffer()],
+      program.programId
+    )[0];
+    const members = [0, 1, 2].map(() => anchor.web3.Keypair.generate());
+    const shares = members.map(() => randomBytes(32));
+    const setCommittee = (threshold: number) =>
+      program.methods
+        .setCommittee(
+          lotteryId,
+          members.map((member) => member.publicKey),
+          threshold
+        )
+        .accountsPartial({ lottery, committee, authority })
+        .rpc();
+    const submit = (
+      method: "commitShare" | "revealShare",
+      member: anchor.web3.Keypair,
+      value: Buffer
+    ) =>
+      program.methods[method](lotteryId, [...value])
+        .accountsPartial({ lottery, committee, member: member.publicKey })
+        .signers([member])
+        .rpc();
+    const commit = (member: anchor.web3.Keypair, share: Buffer) =>
+      submit(
+        "commitShare",
+        member,
+        createHash("sha256").update(share).digest()
+      );
+    const finalize = () =>
+      program.methods.finalizeDraw(lotteryId).accountsPartial({
+        draw: { lottery, caller: authority },
+        committee,
+      });
+
+    // Two of the three members must reveal
+    await expectError(setCommittee(0), ["InvalidCommittee"]);
+    await setCommittee(2);
+    await buyTicket(lotteryId);
+    await buyTicket(lotteryId);
+
+    // Shares are committed while tickets are sold, and only by members
+    for (const [i, member] of members.entries()) {
+      await commit(member, shares[i]);
+    }
+    await expectError(commit(members[0], shares[0]), [
+      "ShareAlreadyCommitted",
+    ]);
+    await expectError(commit(anchor.web3.Keypair.generate(), shares[0]), [
+      "NotCommitteeMember",
+    ]);
+    await expectError(submit("revealShare", members[0], shares[0]), [
+      "DrawBeforeEndTime",
+    ]);
+
+    // After the end time, commitments are closed and shares are revealed; the third member withholds theirs
+    await waitUntil(endTime);
+    await expectError(commit(members[0], shares[0]), ["SalesEnded"]);
+    await expectError(submit("revealShare", members[0], shares[1]), [
+      "RevealMismatch",
+    ]);
+    await submit("revealShare", members[0], shares[0]);
+    await expectError(finalize().rpc(), ["CommitteeThresholdNotMet"]);
+    await submit("revealShare", members[1], shares[1]);
+    expect(await crankStatus(finalize())).to.equal("notDue");
+
+    // With shares public, the authority can no longer cancel a draw it expects to dislike
+    await expectError(
+      program.methods
+        .cancelLottery(lotteryId)
+        .accountsPartial({ lottery, master: masterPda(), authority })
+        .rpc(),
+      ["CommitteeRevealStarted"]
+    );
+    await expectError(
+      program.methods
+        .pickWinner(lotteryId)
+        .accountsPartial({ lottery, caller: authority })
+        .rpc(),
+      ["CommitteeDraw"]
+    );
+
+    // Wait out the reveal window, COMMITTEE_REVEAL_SLOTS after the first share
+    const { revealSlot } = await program.account.committee.fetch(committee);
+    while (
+      (await provider.connection.getSlot()) <=
+      revealSlot.toNumber() + 151
+    ) {
+      await nextSlot();
+    }
+    await expectError(submit("revealShare", members[2], shares[2]), [
+      "RevealWindowClosed",
+    ]);
+    await finalize().rpc();
+    expect(
+      (await program.account.lottery.fetch(lottery)).winnerId
+    ).to.be.below(2);
+
+    // Keepers can keep calling it; a drawn lottery is reported as done
+    expect(await crankStatus(finalize())).to.equal("alreadyDone");
+  });
+
+  it("creates lotteries addressed by their creator and name", async () => {
+    // Mirrors `pdas::named_lottery_id`
+    const namedLotteryId = (name: string) =>
+      (createHash("sha256")
+        .update(authority.toBuffer())
+        .update(name)
+        .digest()
+        .readUInt32LE(0) |
+        0x8000_0000) >>>
+      0;
+    const createNamed = (name: string, lotteryId = namedLotteryId(name)) =>
+      program.methods
+        .createNamedLottery(
+          name,
+          lotteryId,
+          ticketPrice,
+          null,
+          new anchor.BN(0),
+          false,
+          { lamports: {} },
+          [...Buffer.from("USD")],
+          "en-US"
+        )
+        .accountsPartial({
+          lottery: lotteryPda(lotteryId),
+          lotteryName: PublicKey.findProgramAddressSync(
+            [Buffer.from("lottery"), authority.toBuffer(), Buffer.from(name)],
+            program.programId
+          )[0],
+          master: masterPda(),
+          authority,
+        })
+        .rpc();
+    const { lastId } = await program.account.master.fetch(masterPda());
+
+    await expectError(createNamed(""), ["InvalidLotteryName"]);
+    await expectError(createNamed("weekly", 0x8000_0000), [
+      "LotteryNameMismatch",
+    ]);
+
+    // The lottery lives at the ID derived from the name, outside the counter
+    const lotteryId = namedLotteryId("weekly");
+    await createNamed("weekly");
+    const lottery = await program.account.lottery.fetch(lotteryPda(lotteryId));
+    expect(lottery.id).to.equal(lotteryId);
+    expect((await program.account.master.fetch(masterPda())).lastId).to.equal(
+      lastId
+    );
+    await buyTicket(lotteryId);
+
+    // A name can only be used once
+    await expectFailure(createNamed("weekly"));
+  });
+
+  it("pays the draw's caller a capped bounty out of the jackpot", async () => {
+    const setBounty = (lotteryId: number) =>
+      program.methods
+        .setDrawBounty(lotteryId, {
+          flat: { 0: new anchor.BN(LAMPORTS_PER_SOL) },
+        })
+        .accountsPartial({ lottery: lotteryPda(lotteryId), authority })
+        .rpc();
+    const pickWinner = (lotteryId: number, caller: anchor.web3.Keypair) =>
+      program.methods
+        .pickWinner(lotteryId)
+        .accountsPartial({
+          lottery: lotteryPda(lotteryId),
+          caller: caller.publicKey,
+        })
+        .signers([caller])
+        .rpc();
+    const stranger = anchor.web3.Keypair.generate();
+
+    // Only the authority may draw a lottery seeded from recent slot hashes, end time or not, so it takes no bounty
+    for (const endTime of [0, Math.floor(Date.now() / 1000) + 5]) {
+      const byHand = await createLottery(false, endTime);
+      await expectError(setBounty(byHand), ["DrawNotPermissionless"]);
+      await buyTicket(byHand);
+      await expectError(pickWinner(byHand, stranger), [
+        "NotLotteryAuthority",
+      ]);
+    }
+
+    // Anyone may draw a committee lottery once it's due
+    const endTime = Math.floor(Date.now() / 1000) + 5;
+    const { lotteryId, finalize } = await createCommitteeLottery(endTime);
+    const lottery = lotteryPda(lotteryId);
+    await setBounty(lotteryId);
+    await buyTicket(lotteryId);
+    await buyTicket(lotteryId);
+
+    // The provider pays the fee; fund the stranger so the bounty leaves it rent-exempt
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(
+        stranger.publicKey,
+        LAMPORTS_PER_SOL
+      )
+    );
+    const before = await provider.connection.getBalance(stranger.publicKey);
+    await finalize(stranger);
+
+    // The 1 SOL bounty is capped at 1% of the two ticket jackpot, which shrinks by as much
+    const { maxDrawBountyBps } = await program.account.master.fetch(
+      masterPda()
+    );
+    const bounty = ticketPrice.muln(2).muln(maxDrawBountyBps).divn(10_000);
+    const { bountyPaid } = await program.account.lottery.fetch(lottery);
+    expect(bountyPaid.eq(bounty)).to.be.true;
+    expect(
+      (await provider.connection.getBalance(stranger.publicKey)) - before
+    ).to.equal(bounty.toNumber());
+  });
+
+  it("reports what a ticket is owed, net of its part of the draw bounty", async () => {
+    const endTime = Math.floor(Date.now() / 1000) + 5;
+    const { lotteryId, finalize } = await createCommitteeLottery(endTime);
+    const lottery = lotteryPda(lotteryId);
+    await program.methods
+      .setDrawBounty(lotteryId, { bps: { 0: 50 } })
+      .accountsPartial({ lottery, authority })
+      .rpc();
+    await buyTicket(lotteryId);
+    await buyTicket(lotteryId);
+    await finalize((provider.wallet as anchor.Wallet).payer);
+
+    const { winnerId, bountyPaid } = await program.account.lottery.fetch(
+      lottery
+    );
+    const status = (ticketId: number) =>
+      program.methods
+        .getTicketStatus(lotteryId, ticketId)
+        .accountsPartial({ lottery, ticket: ticketPda(lottery, ticketId) })
+        .view();
+
+    const winner = await status(winnerId);
+    expect(winner.isWinner).to.be.true;
+    expect(winner.tier).to.equal(1);
+    expect(winner.gross.eq(ticketPrice.muln(2))).to.be.true;
+    expect(winner.fees.eq(bountyPaid)).to.be.true;
+    expect(winner.net.eq(winner.gross.sub(bountyPaid))).to.be.true;
+    expect(winner.claimed).to.be.false;
+
+    const loser = await status(1 - winnerId);
+    expect(loser.isWinner).to.be.false;
+    expect(loser.net.toNumber()).to.equal(0);
+
+    await program.methods
+      .claimPrize(lotteryId, winnerId)
+      .accountsPartial({
+        lottery,
+        ticket: ticketPda(lottery, winnerId),
+        authority,
+        claimer: authority,
+      })
+      .rpc();
+    expect((await status(winnerId)).claimed).to.be.true;
+  });
+
+  it("holds claims back for the lottery's claim cooldown", async () => {
+    const lotteryId = await createLottery();
+    const lottery = lotteryPda(lotteryId);
+    const setClaimCooldown = (seconds: number) =>
+      program.methods
+        .setClaimCooldown(lotteryId, new anchor.BN(seconds))
+        .accountsPartial({ lottery, authority })
+        .rpc();
+
+    await expectError(setClaimCooldown(-1), ["InvalidClaimCooldown"]);
+    await setClaimCooldown(60 * 60);
+
+    const ticketId = await buyTicket(lotteryId);
+    await program.methods
+      .pickWinner(lotteryId)
+      .accountsPartial({ lottery, caller: authority })
+      .rpc();
+    await expectError(
+      program.methods
+        .claimPrize(lotteryId, ticketId)
+        .accountsPartial({
+          lottery,
+          ticket: ticketPda(lottery, ticketId),
+          authority,
+          claimer: authority,
+        })
+        .rpc(),
+      ["ClaimCooldown"]
+    );
+  });
+
+  it("freezes claims while a challenge of the draw is pending", async () => {
+    const lotteryId = await createLottery();
+    const ticketId = await buyTicket(lotteryId);
+    const lottery = lotteryPda(lotteryId);
+    const ticket = ticketPda(lottery, ticketId);
+    await program.methods
+      .pickWinner(lotteryId)
+      .accountsPartial({ lottery, caller: authority })
+      .rpc();
+
+    await program.methods
+      .challengeDraw(lotteryId, ticketId)
+      .accountsPartial({ lottery, ticket, challenger: authority })
+      .rpc();
+
+    const claimPrize = () =>
+      program.methods
+        .claimPrize(lotteryId, ticketId)
+        .accountsPartial({ lottery, ticket, authority, claimer: authority })
+        .rpc();
+    await expectError(claimPrize(), ["DrawDisputed"]);
+
+    // Health checks flag the pending dispute
+    const health = await program.methods
+      .checkLotteryHealth()
+      .accountsPartial({ master: masterPda() })
+      .remainingAccounts(lotteryBatch(program.programId, [lotteryId], 50))
+      .view();
+    expect(health).to.deep.equal([{ lotteryId, warnings: 1 << 4 }]);
+
+    // Rejecting the challenge unfreezes the lottery and closes the dispute
+    const [dispute] = PublicKey.findProgramAddressSync(
+      [Buffer.from("dispute"), lottery.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .resolveDispute(lotteryId, false)
+      .accountsPartial({
+        master: masterPda(),
+        lottery,
+        dispute,
+        challenger: authority,
+        auditLog: auditLogPda(),
+        authority,
+      })
+      .rpc();
+    expect(await provider.connection.getAccountInfo(dispute)).to.be.null;
+
+    await claimPrize();
+  });
+
+  it("keeps sales closed and the payout budget intact when a challenge is upheld", async () => {
+    const lotteryId = await createLottery();
+    const ticketId = await buyTicket(lotteryId);
+    const lottery = lotteryPda(lotteryId);
+    const ticket = ticketPda(lottery, ticketId);
+    const pickWinner = () =>
+      program.methods
+        .pickWinner(lotteryId)
+        .accountsPartial({ lottery, caller: authority })
+        .rpc();
+    await pickWinner();
+    const { windowBudget } = await program.account.master.fetch(masterPda());
+
+    await program.methods
+      .challengeDraw(lotteryId, ticketId)
+      .accountsPartial({ lottery, ticket, challenger: authority })
+      .rpc();
+    const [dispute] = PublicKey.findProgramAddressSync(
+      [Buffer.from("dispute"), lottery.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .resolveDispute(lotteryId, true)
+      .accountsPartial({
+        master: masterPda(),
+        lottery,
+        dispute,
+        challenger: authority,
+        auditLog: auditLogPda(),
+        authority,
+      })
+      .rpc();
+
+    // The voided jackpot leaves the breaker's budget, so the redraw doesn't count it twice
+    expect(
+      (await program.account.master.fetch(masterPda())).windowBudget.eq(
+        windowBudget.sub(ticketPrice)
+      )
+    ).to.be.true;
+
+    // The outcome is known, so nobody can buy in before the redraw
+    await expectError(buyTicket(lotteryId), ["SalesClosed"]);
+    await nextSlot();
+    await pickWinner();
+    expect((await program.account.lottery.fetch(lottery)).winnerId).to.equal(
+      ticketId
+    );
+  });
+
+  it("only force-resolves a stuck lottery once its timelock has passed", async () => {
+    const lotteryId = await createLottery();
+    await buyTicket(lotteryId);
+    const lottery = lotteryPda(lotteryId);
+    const admin = {
+      master: masterPda(),
+      lottery,
+      auditLog: auditLogPda(),
+      authority,
+    };
+
+    await program.methods
+      .scheduleForceResolve(lotteryId)
+      .accountsPartial(admin)
+      .rpc();
+    const { forceResolveAt } = await program.account.lottery.fetch(lottery);
+    expect(forceResolveAt.toNumber()).to.be.greaterThan(0);
+
+    // The timelock hasn't passed, so the lottery stays open
+    await expectError(
+      program.methods.forceResolve(lotteryId).accountsPartial(admin).rpc(),
+      ["ForceResolveNotReady"]
+    );
+    expect((await program.account.lottery.fetch(lottery)).cancelled).to.be
+      .false;
+    await buyTicket(lotteryId);
+  });
+
+  it("refunds the tickets of a lottery its authority cancelled", async () => {
+    const lotteryId = await createLottery();
+    const ticketId = await buyTicket(lotteryId);
+    const lottery = lotteryPda(lotteryId);
+    const ticket = ticketPda(lottery, ticketId);
+    const claimRefund = () =>
+      program.methods
+        .claimRefund(lotteryId, ticketId)
+        .accountsPartial({ lottery, ticket, authority })
+        .rpc();
+
+    // Only a cancelled lottery owes refunds
+    await expectError(claimRefund(), ["LotteryNotCancelled"]);
+
+    await program.methods
+      .cancelLottery(lotteryId)
+      .accountsPartial({ lottery, master: masterPda(), authority })
+      .rpc();
+    expect((await program.account.lottery.fetch(lottery)).cancelled).to.be
+      .true;
+
+    // The lottery neither sells tickets nor draws anymore
+    await expectError(buyTicket(lotteryId), ["SalesClosed"]);
+    await expectError(
+      program.methods
+        .pickWinner(lotteryId)
+        .accountsPartial({ lottery, caller: authority })
+        .rpc(),
+      ["SalesClosed", "LotteryCancelled"]
+    );
+
+    // The owner gets the ticket price back, and the ticket account is closed
+    const before = await provider.connection.getBalance(lottery);
+    await claimRefund();
+    expect(await provider.connection.getBalance(lottery)).to.equal(
+      before - ticketPrice.toNumber()
+    );
+    expect(await provider.connection.getAccountInfo(ticket)).to.be.null;
+    await expectFailure(claimRefund());
+  });
+
+  it("refunds a cancelled lottery's tickets in batches", async () => {
+    const lotteryId = await createLottery();
+    const lottery = lotteryPda(lotteryId);
+    const tickets = [];
+    for (let i = 0; i < 3; i++) {
+      tickets.push(ticketPda(lottery, await buyTicket(lotteryId)));
+    }
+    await program.methods
+      .cancelLottery(lotteryId)
+      .accountsPartial({ lottery, master: masterPda(), authority })
+      .rpc();
+
+    // Each ticket has to come with its receipt address and its owner
+    const receipt = PublicKey.findProgramAddressSync(
+      [Buffer.from("receipt"), lottery.toBuffer(), tickets[0].toBuffer()],
+      program.programId
+    )[0];
+    await expectError(
+      program.methods
+        .refundBatch(lotteryId)
+        .accountsPartial({ lottery })
+        .remainingAccounts([
+          { pubkey: tickets[0], isSigner: false, isWritable: true },
+          { pubkey: receipt, isSigner: false, isWritable: false },
+          {
+            pubkey: anchor.web3.Keypair.generate().publicKey,
+            isSigner: false,
+            isWritable: true,
+          },
+        ])
+        .rpc(),
+      ["RefundAccountMismatch"]
+    );
+
+    // Every ticket is refunded and closed
+    const before = await provider.connection.getBalance(lottery);
+    await refundCancelledLottery(program, lotteryId);
+    expect(await provider.connection.getBalance(lottery)).to.equal(
+      before - 3 * ticketPrice.toNumber()
+    );
+    for (const ticket of tickets) {
+      expect(await provider.connection.getAccountInfo(ticket)).to.be.null;
+    }
+
+    // Sending the batch again refunds nothing, and says so instead of failing
+    const resend = program.methods
+      .refundBatch(lotteryId)
+      .accountsPartial({ lottery })
+      .remainingAccounts(
+        [...tickets]
+          .sort((a, b) => Buffer.compare(a.toBuffer(), b.toBuffer()))
+          .flatMap((ticket) => [
+            { pubkey: ticket, isSigner: false, isWritable: true },
+            {
+              pubkey: PublicKey.findProgramAddressSync(
+                [Buffer.from("receipt"), lottery.toBuffer(), ticket.toBuffer()],
+                program.programId
+              )[0],
+              isSigner: false,
+              isWritable: false,
+            },
+            { pubkey: authority, isSigner: false, isWritable: true },
+          ])
+      );
+    expect(await crankStatus(resend)).to.equal("alreadyDone");
+    await resend.rpc();
+  });
+
+  it("rolls the part of the jackpot the winner doesn't get into the next lottery", async () => {
+    const lotteryId = await createLottery();
+    const lottery = lotteryPda(lotteryId);
+    await program.methods
+      .setWinnerShare(lotteryId, 5_000)
+      .accountsPartial({ lottery, authority })
+      .rpc();
+    await buyTicket(lotteryId);
+    await buyTicket(lotteryId);
+    await program.methods
+      .pickWinner(lotteryId)
+      .accountsPartial({ lottery, caller: authority })
+      .rpc();
+
+    const { winnerId } = await program.account.lottery.fetch(lottery);
+    await program.methods
+      .claimPrize(lotteryId, winnerId)
+      .accountsPartial({
+        lottery,
+        ticket: ticketPda(lottery, winnerId),
+        authority,
+        claimer: authority,
+      })
+      .rpc();
+
+    // Half of the two tickets is kept for the creator's next lottery
+    const { rollover } = await program.account.creator.fetch(
+      creatorPda(authority)
+    );
+    expect(rollover.eq(ticketPrice)).to.be.true;
+
+    const next = await createLottery();
+    const { carriedOver } = await program.account.lottery.fetch(
+      lotteryPda(next)
+    );
+    expect(carriedOver.eq(ticketPrice)).to.be.true;
+  });
+
+  it("absorbs a donation to the lottery account into the jackpot", async () => {
+    const lotteryId = await createLottery();
+    const lottery = lotteryPda(lotteryId);
+    await buyTicket(lotteryId);
+
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: authority,
+          toPubkey: lottery,
+          lamports: 1_000,
+        })
+      )
+    );
+
+    await program.methods
+      .reconcileLottery(lotteryId, true)
+      .accountsPartial({ lottery, master: masterPda(), payer: authority })
+      .rpc();
+
+    const [discrepancy] = PublicKey.findProgramAddressSync(
+      [Buffer.from("discrepancy"), lottery.toBuffer()],
+      program.programId
+    );
+    const { expected, actual, absorbed } =
+      await program.account.discrepancy.fetch(discrepancy);
+    expect(expected.eq(ticketPrice)).to.be.true;
+    expect(actual.eq(ticketPrice.addn(1_000))).to.be.true;
+    expect(absorbed.toNumber()).to.equal(1_000);
+
+    const { carriedOver } = await program.account.lottery.fetch(lottery);
+    expect(carriedOver.toNumber()).to.equal(1_000);
+  });
+
+  it("rolls season stats over when the admin closes a season", async () => {
+    const { season } = await program.account.master.fetch(masterPda());
+    const lotteryId = await createLottery();
+    await buyTicket(lotteryId);
+    const stats = await program.account.season.fetch(seasonPda(season));
+    expect(stats.tickets.toNumber()).to.be.greaterThan(0);
+
+    await program.methods
+      .closeSeason()
+      .accountsPartial({
+        master: masterPda(),
+        season: seasonPda(season),
+        nextSeason: seasonPda(season + 1),
+        auditLog: auditLogPda(),
+        authority,
+      })
+      .rpc();
+
+    // The closed season keeps its stats, and new sales are counted in the next one
+    const closed = await program.account.season.fetch(seasonPda(season));
+    expect(closed.closedAt.toNumber()).to.be.greaterThan(0);
+    await buyTicket(lotteryId);
+    const next = await program.account.season.fetch(seasonPda(season + 1));
+    expect(next.tickets.toNumber()).to.equal(1);
+    expect(next.volume.eq(ticketPrice)).to.be.true;
+  });
+
+  it("pays for tickets with voucher credit", async () => {
+    const [voucher] = PublicKey.findProgramAddressSync(
+      [Buffer.from("voucher"), authority.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .buyVoucher(authority, ticketPrice)
+      .accountsPartial({ voucher, payer: authority })
+      .rpc();
+
+    const lotteryId = await createLottery();
+    const lottery = lotteryPda(lotteryId);
+    await program.methods
+      .buyTicket(lotteryId, validUntil(), noTerms)
+      .accountsPartial({
+        lottery,
+        ticket: ticketPda(lottery, 0),
+        buyer: authority,
+        voucher,
+      })
+      .rpc();
+    const { balance } = await program.account.voucher.fetch(voucher);
+    expect(balance.toNumber()).to.equal(0);
+  });
+
+  it("makes buyers acknowledge the current terms", async () => {
+    const lotteryId = await createLottery();
+    const lottery = lotteryPda(lotteryId);
+    const terms = Array(32).fill(7);
+    const setTerms = () =>
+      program.methods
+        .setTerms(lotteryId, terms)
+        .accountsPartial({ lottery, authority })
+        .rpc();
+
+    // A client that still shows the old terms can't buy
+    await setTerms();
+    await expectError(buyTicket(lotteryId), ["TermsMismatch"]);
+    await buyTicket(lotteryId, terms);
+
+    // The terms are fixed once tickets are sold
+    await expectError(setTerms(), ["TermsLocked"]);
+  });
+
+  it("stores size-checked frontend data on a lottery", async () => {
+    const lotteryId = await createLottery();
+    const lottery = lotteryPda(lotteryId);
+    const setExtra = (extra: Buffer) =>
+      program.methods
+        .setExtra(lotteryId, 1, extra)
+        .accountsPartial({ lottery, authority })
+        .rpc();
+
+    await expectError(setExtra(Buffer.alloc(129)), ["ExtraTooLong"]);
+    await setExtra(Buffer.from('{"theme":"dark"}'));
+
+    const { extraVersion, extra } = await program.account.lottery.fetch(
+      lottery
+    );
+    expect(extraVersion).to.equal(1);
+    expect(extra.toString()).to.equal('{"theme":"dark"}');
+  });
+
+  it("stores validated display hints on a lottery", async () => {
+    const lotteryId = await createLottery();
+    const lottery = lotteryPda(lotteryId);
+    const setDisplayHints = (currency: number[], locale: string) =>
+      program.methods
+        .setDisplayHints(lotteryId, currency, locale)
+        .accountsPartial({ lottery, authority })
+        .rpc();
+
+    let hints = await program.account.lottery.fetch(lottery);
+    expect(Buffer.from(hints.currency).toString()).to.equal("USD");
+    expect(hints.locale).to.equal("en-US");
+
+    // Hints are display-only, so they can still change after a sale
+    await buyTicket(lotteryId);
+    await setDisplayHints([...Buffer.from("EUR")], "de-DE");
+    hints = await program.account.lottery.fetch(lottery);
+    expect(Buffer.from(hints.currency).toString()).to.equal("EUR");
+    expect(hints.locale).to.equal("de-DE");
+
+    await expectError(setDisplayHints([...Buffer.from("eur")], "de-DE"), [
+      "InvalidDisplayHints",
+    ]);
+    await expectError(setDisplayHints([0, 0, 0], "x".repeat(17)), [
+      "InvalidDisplayHints",
+    ]);
+  });
+
+  it("lets a partner hand out a reserved block of tickets", async () => {
+    const lotteryId = await createLottery();
+    await buyTicket(lotteryId);
+    const lottery = lotteryPda(lotteryId);
+
+    // The creator is their own partner here; the block takes the next two IDs
+    await program.methods
+      .reserveTickets(lotteryId, 2)
+      .accountsPartial({ lottery, authority, partner: authority })
+      .rpc();
+    expect((await program.account.lottery.fetch(lottery)).lastTicketId).to.equal(
+      3
+    );
+
+    const owners = [
+      anchor.web3.Keypair.generate().publicKey,
+      anchor.web3.Keypair.generate().publicKey,
+    ];
+    const assign = (owner: anchor.web3.PublicKey, ticketId: number) =>
+      program.methods
+        .assignReservedTicket(lotteryId)
+        .accountsPartial({
+          lottery,
+          ticket: ticketPda(lottery, ticketId),
+          partner: authority,
+          owner,
+          profile: null,
+        })
+        .rpc();
+    await assign(owners[0], 1);
+    await assign(owners[1], 2);
+
+    const ticket = await program.account.ticket.fetch(ticketPda(lottery, 2));
+    expect(ticket.authority.equals(owners[1])).to.be.true;
+    expect(ticket.paid.eq(ticketPrice)).to.be.true;
+
+    await expectFailure(assign(owners[0], 3));
+  });
+
+  it("sells house inventory by redemption code until sales close", async () => {
+    const endTime = Math.floor(Date.now() / 1000) + 8;
+    const lotteryId = await createLottery(false, endTime);
+    const lottery = lotteryPda(lotteryId);
+    const inventory = PublicKey.findProgramAddressSync(
+      [Buffer.from("inventory"), lottery.toBuffer(), u32Seed(0)],
+      program.programId
+    )[0];
+
+    // Mirrors `merkle::code_leaf` and `merkle::parent` for a block of two codes
+    const sha256 = (...parts: Buffer[]) =>
+      createHash("sha256").update(Buffer.concat(parts)).digest();
+    const codes = [Buffer.from("CARD-0001"), Buffer.from("CARD-0002")];
+    const leaves = codes.map((code, index) => {
+      const indexSeed = Buffer.alloc(2);
+      indexSeed.writeUInt16LE(index);
+      return sha256(Buffer.from([0]), indexSeed, sha256(code));
+    });
+    const root = sha256(Buffer.from([1]), ...[...leaves].sort(Buffer.compare));
+
+    await program.methods
+      .stockInventory(lotteryId, 0, [...root], 2)
+      .accountsPartial({ lottery, inventory, authority })
+      .rpc();
+
+    const buy = async (index: number, code: Buffer) => {
+      const ticketId = (await program.account.lottery.fetch(lottery))
+        .lastTicketId;
+      return program.methods
+        .buyFromInventory(lotteryId, 0, index, code, [[...leaves[1 - index]]])
+        .accountsPartial({
+          lottery,
+          inventory,
+          ticket: ticketPda(lottery, ticketId),
+          buyer: authority,
+          profile: null,
+        })
+        .rpc();
+    };
+    const voidInventory = () =>
+      program.methods
+        .voidInventory(lotteryId, 0)
+        .accountsPartial({ lottery, inventory, authority });
+
+    // A sold ticket takes the next ticket ID, and each code sells once
+    await buy(0, codes[0]);
+    const ticket = await program.account.ticket.fetch(ticketPda(lottery, 0));
+    expect(ticket.authority.equals(authority)).to.be.true;
+    expect(ticket.paid.eq(ticketPrice)).to.be.true;
+    await expectError(buy(0, codes[0]), ["InventoryTicketSold"]);
+    await expectError(buy(1, codes[0]), ["InvalidRedemptionCode"]);
+
+    // While sales are open the block is left alone
+    expect(await crankStatus(voidInventory())).to.equal("notDue");
+    await voidInventory().rpc();
+    expect(await provider.connection.getAccountInfo(inventory)).not.to.be.null;
+
+    // The unsold ticket is voided once sales close at the end time
+    await waitUntil(endTime);
+    await expectError(buy(1, codes[1]), ["SalesEnded"]);
+    await voidInventory().rpc();
+    expect(await provider.connection.getAccountInfo(inventory)).to.be.null;
+  });
+
+  it("only closes sales early once the minimum pot is sold", async () => {
+    // Without an end time the creator can draw whenever it likes anyway
+    const openEnded = await createLottery();
+    await expectError(
+      program.methods
+        .setMinTickets(openEnded, 3)
+        .accountsPartial({ lottery: lotteryPda(openEnded), authority })
+        .rpc(),
+      ["MinTicketsNeedEndTime"]
+    );
+
+    const endTime = Math.floor(Date.now() / 1000) + 3600;
+    const lotteryId = await createLottery(false, endTime);
+    const lottery = lotteryPda(lotteryId);
+    await program.methods
+      .setMinTickets(lotteryId, 3)
+      .accountsPartial({ lottery, authority })
+      .rpc();
+    await buyTicket(lotteryId);
+
+    const closeSalesEarly = program.methods
+      .closeSalesEarly(lotteryId)
+      .accountsPartial({ lottery, authority });
+    await expectError(closeSalesEarly.rpc(), ["MinTicketsNotSold"]);
+
+    // The creator buys the two missing tickets in the same transaction
+    const buyback = await program.methods
+      .reserveTickets(lotteryId, 2)
+      .accountsPartial({ lottery, authority, partner: authority })
+      .instruction();
+    await closeSalesEarly.preInstructions([buyback]).rpc();
+
+    const { lastTicketId, endTime: closedAt } =
+      await program.account.lottery.fetch(lottery);
+    expect(lastTicketId).to.equal(3);
+    expect(closedAt.toNumber()).to.be.lessThan(endTime);
+    await expectError(buyTicket(lotteryId), ["SalesEnded"]);
+
+    // The draw opens right away
+    await nextSlot();
+    await program.methods
+      .pickWinner(lotteryId)
+      .accountsPartial({ lottery, caller: authority })
+      .rpc();
+  });
+
+  it("refunds tickets within the grace period and redraws a refunded winner", async () => {
+    const lotteryId = await createLottery();
+    const lottery = lotteryPda(lotteryId);
+    await program.methods
+      .setRefundGrace(lotteryId, new anchor.BN(1_000))
+      .accountsPartial({ lottery, authority })
+      .rpc();
+    for (let i = 0; i < 3; i++) {
+      await buyTicket(lotteryId);
+    }
+
+    // Take tickets 1 and 2 back; they keep their IDs but leave the pot
+    const refundTicket = (ticketId: number) =>
+      program.methods
+        .refundTicket(lotteryId, ticketId)
+        .accountsPartial({
+          lottery,
+          ticket: ticketPda(lottery, ticketId),
+          authority,
+        })
+        .rpc();
+    const before = await provider.connection.getBalance(lottery);
+    await refundTicket(1);
+    await refundTicket(2);
+    expect(before - (await provider.connection.getBalance(lottery))).to.equal(
+      ticketPrice.muln(2).toNumber()
+    );
+    await expectError(refundTicket(1), ["TicketRefunded"]);
+    const refunded = await program.account.ticket.fetch(ticketPda(lottery, 1));
+    expect(refunded.refunded).to.be.true;
+    expect(refunded.paid.isZero()).to.be.true;
+    expect(
+      (await program.account.lottery.fetch(lottery)).refundedTickets
+    ).to.equal(2);
+
+    await program.methods
+      .pickWinner(lotteryId)
+      .accountsPartial({ lottery, caller: authority })
+      .rpc();
+
+    // Only ticket 0 is live, so however the draw falls, redrawing ends on it
+    const redraw = (winnerId: number) =>
+      program.methods
+        .redrawVoidWinner(lotteryId)
+        .accountsPartial({
+          lottery,
+          winningTicket: ticketPda(lottery, winnerId),
+        })
+        .rpc();
+    let { winnerId } = await program.account.lottery.fetch(lottery);
+    while (winnerId !== 0) {
+      await expectError(
+        program.methods
+          .claimPrize(lotteryId, winnerId)
+          .accountsPartial({
+            lottery,
+            ticket: ticketPda(lottery, winnerId),
+            authority,
+            claimer: authority,
+          })
+          .rpc(),
+        ["TicketRefunded"]
+      );
+      await redraw(winnerId);
+      ({ winnerId } = await program.account.lottery.fetch(lottery));
+    }
+    await expectError(redraw(0), ["WinnerNotVoid"]);
+
+    // The winner gets the pot of the one ticket left, and the fairness report nets out the refunds
+    await program.methods
+      .claimPrize(lotteryId, 0)
+      .accountsPartial({
+        lottery,
+        ticket: ticketPda(lottery, 0),
+        authority,
+        claimer: authority,
+      })
+      .rpc();
+    const [receipt] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("receipt"),
+        lottery.toBuffer(),
+        ticketPda(lottery, 0).toBuffer(),
+      ],
+      program.programId
+    );
+    const { amount } = await program.account.claimReceipt.fetch(receipt);
+    expect(amount.eq(ticketPrice)).to.be.true;
+    const report = await buildFairnessReport(
+      program,
+      lotteryId,
+      anchor.web3.Keypair.generate()
+    );
+    expect(Object.values(report.checks).every((check) => check)).to.be.true;
+
+    // Once the grace period is over, the ticket is final
+    const shortGrace = await createLottery();
+    await program.methods
+      .setRefundGrace(shortGrace, new anchor.BN(1))
+      .accountsPartial({ lottery: lotteryPda(shortGrace), authority })
+      .rpc();
+    const ticketId = await buyTicket(shortGrace);
+    await nextSlot();
+    await expectError(
+      program.methods
+        .refundTicket(shortGrace, ticketId)
+        .accountsPartial({
+          lottery: lotteryPda(shortGrace),
+          ticket: ticketPda(lotteryPda(shortGrace), ticketId),
+          authority,
+        })
+        .rpc(),
+      ["RefundGraceOver"]
+    );
+  });
+
+  it("splits the prize among the tickets matching the draw seed", async () => {
+    const lotteryId = await createLottery();
+    const lottery = lotteryPda(lotteryId);
+    await expectError(
+      program.methods
+        .setMatchBits(lotteryId, 25)
+        .accountsPartial({ lottery, authority })
+        .rpc(),
+      ["InvalidMatchBits"]
+    );
+    // With one bit, about half the tickets match
+    await program.methods
+      .setMatchBits(lotteryId, 1)
+      .accountsPartial({ lottery, authority })
+      .rpc();
+    for (let i = 0; i < 4; i++) {
+      await buyTicket(lotteryId);
+    }
+    await program.methods
+      .pickWinner(lotteryId)
+      .accountsPartial({ lottery, caller: authority })
+      .rpc();
+
+    const claim = (ticketId: number) =>
+      program.methods
+        .claimPrize(lotteryId, ticketId)
+        .accountsPartial({
+          lottery,
+          ticket: ticketPda(lottery, ticketId),
+          authority,
+          claimer: authority,
+        })
+        .rpc();
+    const { winnerId, drawSeed } = await program.account.lottery.fetch(
+      lottery
+    );
+    await expectError(claim(winnerId), ["MatchesNotCounted"]);
+
+    // Count every ticket in one batch; a second count has nothing left to do
+    const countMatches = () =>
+      program.methods
+        .countMatches(lotteryId)
+        .accountsPartial({ lottery })
+        .remainingAccounts(
+          [0, 1, 2, 3].map((ticketId) => ({
+            pubkey: ticketPda(lottery, ticketId),
+            isSigner: false,
+            isWritable: false,
+          }))
+        );
+    await countMatches().rpc();
+    expect(await crankStatus(countMatches())).to.equal("alreadyDone");
+
+    // The matching tickets are the ones whose art seed starts with the draw seed's first bit
+    const matching: number[] = [];
+    for (let ticketId = 0; ticketId < 4; ticketId++) {
+      const { artSeed } = await program.account.ticket.fetch(
+        ticketPda(lottery, ticketId)
+      );
+      if ((artSeed[0] ^ drawSeed[0]) >> 7 === 0) matching.push(ticketId);
+    }
+    const { matchCount } = await program.account.lottery.fetch(lottery);
+    expect(matchCount).to.equal(matching.length);
+
+    // Every matching ticket claims a share, or the drawn ticket takes it all if none matched
+    const winners = matching.length > 0 ? matching : [winnerId];
+    for (let ticketId = 0; ticketId < 4; ticketId++) {
+      if (!winners.includes(ticketId)) {
+        await expectError(claim(ticketId), ["InvalidWinner"]);
+      }
+    }
+    for (const ticketId of winners) {
+      expect((await program.account.lottery.fetch(lottery)).claimed).to.be
+        .false;
+      await claim(ticketId);
+    }
+    expect((await program.account.lottery.fetch(lottery)).claimed).to.be.true;
+  });
+
+  it("requires the self-attestations a lottery's policy asks for", async () => {
+    const lotteryId = await createLottery();
+    const lottery = lotteryPda(lotteryId);
+    const legalAge = 1 << 0;
+    const [profile] = PublicKey.findProgramAddressSync(
+      [Buffer.from("profile"), authority.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .setAttestationPolicy(lotteryId, legalAge)
+      .accountsPartial({ lottery, authority })
+      .rpc();
+
+    await expectError(buyTicket(lotteryId), ["AttestationRequired"]);
+
+    await program.methods
+      .attest(legalAge, [...Buffer.from("US")])
+      .accountsPartial({ profile, owner: authority })
+      .rpc();
+    await program.methods
+      .buyTicket(lotteryId, validUntil(), noTerms)
+      .accountsPartial({
+        lottery,
+        ticket: ticketPda(lottery, 0),
+        buyer: authority,
+        profile,
+      })
+      .rpc();
+  });
+
+  it("keeps the authority out of a no_self_entry lottery", async () => {
+    const lotteryId = await createLottery(true);
+    await expectError(buyTicket(lotteryId), ["SelfEntryForbidden"]);
+  });
+
+  it("redraws a no_self_entry lottery's unredeemed code tickets", async () => {
+    const lotteryId = await createLottery(true);
+    const lottery = lotteryPda(lotteryId);
+    const player = anchor.web3.Keypair.generate();
+    const codes = [0, 1, 2].map((i) => Buffer.from(`CODE-000${i}`));
+    for (const [ticketId, code] of codes.entries()) {
+      await program.methods
+        .createCodeTicket(lotteryId, [
+          ...createHash("sha256").update(code).digest(),
+        ])
+        .accountsPartial({
+          lottery,
+          master: masterPda(),
+          ticket: ticketPda(lottery, ticketId),
+          authority,
+        })
+        .rpc();
+    }
+    const redeem = (ticketId: number) =>
+      program.methods
+        .redeemCodeTicket(lotteryId, ticketId, codes[ticketId])
+        .accountsPartial({
+          lottery,
+          ticket: ticketPda(lottery, ticketId),
+          owner: player.publicKey,
+          master: masterPda(),
+          profile: null,
+        })
+        .signers([player])
+        .rpc();
+    const pickWinner = () =>
+      program.methods
+        .pickWinner(lotteryId)
+        .accountsPartial({ lottery, caller: authority })
+        .rpc();
+
+    // Tickets the authority still holds can't win, so there is nothing to draw until a code is redeemed
+    await nextSlot();
+    await expectError(pickWinner(), ["NoTickets"]);
+    await redeem(2);
+    await nextSlot();
+    await pickWinner();
+
+    // Codes left unredeemed at the draw stay with the authority, and a draw that picks one is redone
+    await expectError(redeem(0), ["RedemptionClosed"]);
+    const redraw = (winnerId: number) =>
+      program.methods
+        .redrawVoidWinner(lotteryId)
+        .accountsPartial({
+          lottery,
+          winningTicket: ticketPda(lottery, winnerId),
+        })
+        .rpc();
+    let { winnerId } = await program.account.lottery.fetch(lottery);
+    while (winnerId !== 2) {
+      await redraw(winnerId);
+      ({ winnerId } = await program.account.lottery.fetch(lottery));
+    }
+    await expectError(redraw(2), ["WinnerNotVoid"]);
+    const ticket = await program.account.ticket.fetch(ticketPda(lottery, 2));
+    expect(ticket.authority.equals(player.publicKey)).to.be.true;
+  });
+
+  it("lets the admin disable modules", async () => {
+    const { features } = await program.account.master.fetch(masterPda());
+    const codeTickets = new anchor.BN(1 << 1);
+
+    await program.methods
+      .setFeatures(features.and(codeTickets.notn(64)))
+      .accountsPartial({
+        master: masterPda(),
+        auditLog: auditLogPda(),
+        authority,
+      })
+      .rpc();
+
+    const lotteryId = await createLottery();
+    const lottery = lotteryPda(lotteryId);
+    await expectError(
+      program.methods
+        .createCodeTicket(lotteryId, Array(32).fill(0))
+        .accountsPartial({
+          lottery,
+          master: masterPda(),
+          ticket: ticketPda(lottery, 0),
+          authority,
+        })
+        .rpc(),
+      ["FeatureDisabled"]
+    );
+
+    await program.methods
+      .setFeatures(features)
+      .accountsPartial({
+        master: masterPda(),
+        auditLog: auditLogPda(),
+        authority,
+      })
+      .rpc();
+
+    // Both changes were recorded in the audit log, most recent last
+    const auditLog = await program.account.auditLog.fetch(auditLogPda());
+    const last = auditLog.entries[(auditLog.head + 31) % 32];
+    expect(last.actor.equals(authority)).to.be.true;
+    expect(last.value.eq(features)).to.be.true;
+  });
+
+  it("caps the number of open lotteries per creator", async () => {
+    const { maxOpenLotteries } = await program.account.master.fetch(
+      masterPda()
+    );
+    const { openLotteries } = await program.account.creator.fetch(
+      creatorPda(authority)
+    );
+    const setMaxOpenLotteries = (value: number) =>
+      program.methods
+        .setMaxOpenLotteries(value)
+        .accountsPartial({
+          master: masterPda(),
+          auditLog: auditLogPda(),
+          authority,
+        })
+        .rpc();
+
+    await setMaxOpenLotteries(openLotteries);
+    await expectError(createLottery(), ["TooManyOpenLotteries"]);
+
+    // Drawing one of the open lotteries frees a slot
+    await setMaxOpenLotteries(openLotteries + 1);
+    const lotteryId = await createLottery();
+    await expectError(createLottery(), ["TooManyOpenLotteries"]);
+    await buyTicket(lotteryId);
+    await program.methods
+      .pickWinner(lotteryId)
+      .accountsPartial({ lottery: lotteryPda(lotteryId), caller: authority })
+      .rpc();
+    await createLottery();
+
+    await setMaxOpenLotteries(maxOpenLotteries);
+  });
+
+  it("pauses payouts when a window pays out more than is owed", async () => {
+    const lotteryId = await createLottery();
+    const ticketId = await buyTicket(lotteryId);
+    const lottery = lotteryPda(lotteryId);
+    await program.methods
+      .pickWinner(lotteryId)
+      .accountsPartial({ lottery, caller: authority })
+      .rpc();
+
+    const admin = { master: masterPda(), auditLog: auditLogPda(), authority };
+    const setPayoutLimit = (value: number) =>
+      program.methods.setPayoutLimit(value).accountsPartial(admin).rpc();
+    const claimPrize = () =>
+      program.methods.claimPrize(lotteryId, ticketId).accountsPartial({
+        lottery,
+        ticket: ticketPda(lottery, ticketId),
+        authority,
+        claimer: authority,
+      });
+
+    // A limit of 1 bps trips on this claim: it succeeds, but only to store the pause, and says it paid nothing
+    await setPayoutLimit(1);
+    expect(await claimOutcome(claimPrize())).to.equal("paused");
+    await claimPrize().rpc();
+    expect((await program.account.lottery.fetch(lottery)).claimed).to.be.false;
+    expect((await program.account.master.fetch(masterPda())).claimsPaused).to
+      .be.true;
+    await expectError(claimPrize().rpc(), ["ClaimsPaused"]);
+
+    // Once the admin resumes payouts, the prize can be claimed
+    await program.methods.resumeClaims().accountsPartial(admin).rpc();
+    await setPayoutLimit(10_000);
+    expect(await claimOutcome(claimPrize())).to.equal("paid");
+    await claimPrize().rpc();
+    expect((await program.account.lottery.fetch(lottery)).claimed).to.be.true;
+  });
+
+  it("publishes a schedule of rounds in one transaction", async () => {
+    const { epoch } = await provider.connection.getEpochInfo();
+    const { transaction, lotteryIds } = await buildCreateRoundsTransaction(
+      program,
+      authority,
+      { count: 3, ticketPrice, firstDrawEpoch: epoch + 1, epochsPerRound: 2 }
+    );
+    await provider.sendAndConfirm(transaction);
+
+    const drawEpochs = await Promise.all(
+      lotteryIds.map(
+        async (id) =>
+          (await program.account.lottery.fetch(lotteryPda(id))).drawEpoch
+      )
+    );
+    expect(drawEpochs.map((drawEpoch) => drawEpoch.toNumber())).to.deep.equal(
+      [epoch + 1, epoch + 3, epoch + 5]
+    );
+
+    // Anyone can draw a round, and a keeper calling it early is told it isn't due
+    // rather than failing
+    const keeper = anchor.web3.Keypair.generate();
+    await buyTicket(lotteryIds[0]);
+    const draw = program.methods
+      .pickWinner(lotteryIds[0])
+      .accountsPartial({
+        lottery: lotteryPda(lotteryIds[0]),
+        caller: keeper.publicKey,
+      })
+      .signers([keeper]);
+    expect(await crankStatus(draw)).to.equal("notDue");
+  });
+
+  it("sells tickets and pays the prize in an SPL token", async () => {
+    // Build the token accounts with raw token program instructions, so the tests need no extra dependency
+    const tokenProgram = new PublicKey(
+      "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"
+    );
+    const { SystemProgram, Keypair, Transaction, TransactionInstruction } =
+      anchor.web3;
+    const createTokenAccount = async (
+      space: number,
+      init: (
+        account: anchor.web3.PublicKey
+      ) => anchor.web3.TransactionInstruction
+    ) => {
+      const account = Keypair.generate();
+      const lamports =
+        await provider.connection.getMinimumBalanceForRentExemption(space);
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.createAccount({
+            fromPubkey: authority,
+            newAccountPubkey: account.publicKey,
+            lamports,
+            space,
+            programId: tokenProgram,
+          }),
+          init(account.publicKey)
+        ),
+        [account]
+      );
+      return account.publicKey;
+    };
+    const tokenInstruction = (
+      keys: anchor.web3.PublicKey[],
+      data: Buffer,
+      signer?: anchor.web3.PublicKey
+    ) =>
+      new TransactionInstruction({
+        programId: tokenProgram,
+        keys: [
+          ...keys.map((pubkey) => ({
+            pubkey,
+            isSigner: false,
+            isWritable: true,
+          })),
+          ...(signer
+            ? [{ pubkey: signer, isSigner: true, isWritable: false }]
+            : []),
+        ],
+        data,
+      });
+
+    // InitializeMint2 with 6 decimals and no freeze authority
+    const mint = await createTokenAccount(82, (account) =>
+      tokenInstruction(
+        [account],
+        Buffer.concat([
+          Buffer.from([20, 6]),
+          authority.toBuffer(),
+          Buffer.from([0]),
+        ])
+      )
+    );
+    // InitializeAccount3 owned by the provider wallet, then MintTo it
+    const wallet = await createTokenAccount(165, (account) =>
+      tokenInstruction(
+        [account, mint],
+        Buffer.concat([Buffer.from([18]), authority.toBuffer()])
+      )
+    );
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        tokenInstruction(
+          [mint, wallet],
+          Buffer.concat([
+            Buffer.from([7]),
+            ticketPrice.toArrayLike(Buffer, "le", 8),
+          ]),
+          authority
+        )
+      )
+    );
+    const balance = async (account: anchor.web3.PublicKey) =>
+      (await provider.connection.getTokenAccountBalance(account)).value.amount;
+
+    const lotteryId = await createLottery();
+    const lottery = lotteryPda(lotteryId);
+    const [vault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), lottery.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .setPaymentMint(lotteryId)
+      .accountsPartial({ lottery, mint, vault, authority, tokenProgram })
+      .rpc();
+
+    // Lamport-only purchases are refused, and the ticket price moves in tokens
+    await expectError(buyTicket(lotteryId), ["TokenAccountsMissing"]);
+    await program.methods
+      .buyTicket(lotteryId, validUntil(), noTerms)
+      .accountsPartial({
+        lottery,
+        ticket: ticketPda(lottery, 0),
+        buyer: authority,
+        reference: null,
+        buyerTokenAccount: wallet,
+        vault,
+        tokenProgram,
+      })
+      .rpc();
+    expect(await balance(vault)).to.equal(ticketPrice.toString());
+    expect(await balance(wallet)).to.equal("0");
+
+    await nextSlot();
+    await program.methods
+      .pickWinner(lotteryId)
+      .accountsPartial({ lottery, caller: authority })
+      .rpc();
+    await program.methods
+      .claimPrize(lotteryId, 0)
+      .accountsPartial({
+        lottery,
+        ticket: ticketPda(lottery, 0),
+        authority,
+        claimer: authority,
+        vault,
+        winnerTokenAccount: wallet,
+        tokenProgram,
+      })
+      .rpc();
+    expect(await balance(vault)).to.equal("0");
+    expect(await balance(wallet)).to.equal(ticketPrice.toString());
+
+    // The vault belongs to the lottery account, so closing the lottery would strand it
+    await expectError(
+      program.methods
+        .archiveLottery(lotteryId)
+        .accountsPartial({
+          lottery,
+          winningTicket: ticketPda(lottery, 0),
+          authority,
+        })
+        .rpc(),
+      ["UnsupportedForTokenLottery"]
+    );
+  });
+
+  it("only accepts batches of distinct accounts in address order", async () => {
+    const ids = [await createLottery(), await createLottery()];
+    const [first, second] = lotteryBatch(program.programId, ids, 20);
+    const dashboard = (remainingAccounts: anchor.web3.AccountMeta[]) =>
+      program.methods
+        .getCreatorDashboard(authority)
+        .remainingAccounts(remainingAccounts)
+        .view();
+
+    expect((await dashboard([first, second])).lotteries).to.equal(2);
+    await expectError(dashboard([second, first]), ["AccountsOutOfOrder"]);
+    await expectError(dashboard([first, first]), ["DuplicateAccount"]);
+  });
+
+  describe("argument validation", () => {
+    let lotteryId: number;
+    let otherLotteryId: number;
+
+    before(async () => {
+      lotteryId = await createLottery();
+      otherLotteryId = await createLottery();
+      await buyTicket(lotteryId);
+      await buyTicket(lotteryId);
+    });
+
+    it("rejects a lottery with a zero ticket price", async () => {
+      const id = (await program.account.master.fetch(masterPda())).lastId;
+
+      await expectError(
+        program.methods
+          .createLottery(
+            new anchor.BN(0),
+            null,
+            new anchor.BN(0),
+            false,
+            { lamports: {} },
+            [0, 0, 0],
+            ""
+          )
+          .accountsPartial({
+            lottery: lotteryPda(id),
+            master: masterPda(),
+            authority,
+          })
+          .rpc(),
+        ["TicketPriceTooLow"]
+      );
+    });
+
+    it("reports why a purchase would fail without buying", async () => {
+      const simulate = (quantity: number) =>
+        program.methods
+          .simulateBuy(lotteryId, quantity, noTerms)
+          .accountsPartial({ lottery: lotteryPda(lotteryId), buyer: authority })
+          .view();
+      const code = (name: string) =>
+        program.idl.errors.find((error) => error.name === name).code;
+
+      const ok = await simulate(2);
+      expect(ok.errorCode).to.be.null;
+      expect(ok.totalCost.eq(ticketPrice.muln(2))).to.be.true;
+      expect((await simulate(0)).errorCode).to.equal(code("InvalidQuantity"));
+
+      // Self-attestations are checked on the profile passed, like buy_ticket does; the provider wallet attested
+      // its legal age earlier
+      const attestedId = await createLottery();
+      const attested = lotteryPda(attestedId);
+      await program.methods
+        .setAttestationPolicy(attestedId, 1 << 0)
+        .accountsPartial({ lottery: attested, authority })
+        .rpc();
+      const simulateWith = (profile: anchor.web3.PublicKey | null) =>
+        program.methods
+          .simulateBuy(attestedId, 1, noTerms)
+          .accountsPartial({ lottery: attested, buyer: authority, profile })
+          .view();
+      const [profile] = PublicKey.findProgramAddressSync(
+        [Buffer.from("profile"), authority.toBuffer()],
+        program.programId
+      );
+      expect((await simulateWith(null)).errorCode).to.equal(
+        code("AttestationRequired")
+      );
+      expect((await simulateWith(profile)).errorCode).to.be.null;
+    });
+
+    it("rejects buy_ticket after its deadline", async () => {
+      const lottery = lotteryPda(lotteryId);
+      const ticketId = (await program.account.lottery.fetch(lottery))
+        .lastTicketId;
+
+      await expectError(
+        program.methods
+          .buyTicket(lotteryId, new anchor.BN(0), noTerms)
+          .accountsPartial({
+            lottery,
+            ticket: ticketPda(lottery, ticketId),
+            buyer: authority,
+            reference: null,
+          })
+          .rpc(),
+        ["TransactionExpired"]
+      );
+    });
+
+    it("rejects sales and draws of a lottery that has drawn its winner", async () => {
+      const drawnId = await createLottery();
+      const lottery = lotteryPda(drawnId);
+      await buyTicket(drawnId);
+      const draw = () =>
+        program.methods
+          .pickWinner(drawnId)
+          .accountsPartial({ lottery, caller: authority })
+          .rpc();
+      await draw();
+
+      // The creator draws this lottery by hand, so a second draw fails rather than returning a status
+      await expectError(buyTicket(drawnId), ["SalesClosed"]);
+      await expectError(draw(), ["WinnerAlreadyExists"]);
+
+      const { errorCode } = await program.methods
+        .simulateBuy(drawnId, 1, noTerms)
+        .accountsPartial({ lottery, buyer: authority })
+        .view();
+      expect(errorCode).to.equal(
+        program.idl.errors.find((error) => error.name === "SalesClosed").code
+      );
+    });
+
+    it("rejects buy_ticket with a lottery_id that doesn't match the lottery account", async () => {
+      const lottery = lotteryPda(lotteryId);
+      const ticketId = (await program.account.lottery.fetch(lottery))
+        .lastTicketId;
+
+      await expectError(
+        program.methods
+          .buyTicket(otherLotteryId, validUntil(), noTerms)
+          .accountsPartial({
+            lottery,
+            ticket: ticketPda(lottery, ticketId),
+            buyer: authority,
+            reference: null,
+          })
+          .rpc(),
+        ["ConstraintSeeds", "LotteryIdMismatch"]
+      );
+    });
+
+    it("rejects claim_prize with a ticket_id that doesn't match the ticket account", async () => {
+      const lottery = lotteryPda(lotteryId);
+
+      await expectError(
+        program.methods
+          .claimPrize(lotteryId, 1)
+          .accountsPartial({
+            lottery,
+            ticket: ticketPda(lottery, 0),
+            authority,
+            claimer: authority,
+          })
+          .rpc(),
+        ["ConstraintSeeds", "TicketIdMismatch"]
+      );
+    });
+
+    it("rejects claim_prize with a lottery_id that doesn't match the lottery account", async () => {
+      const lottery = lotteryPda(lotteryId);
+
+      await expectError(
+        program.methods
+          .claimPrize(otherLotteryId, 0)
+          .accountsPartial({
+            lottery,
+            ticket: ticketPda(lottery, 0),
+            authority,
+            claimer: authority,
+          })
+          .rpc(),
+        ["ConstraintSeeds", "LotteryIdMismatch"]
+      );
+    });
+  });
+
+  // Ceilings that new features must stay under; raise one only on purpose. Measured values are logged for CI
+  describe("compute and size budgets", () => {
+    // The most a transaction can serialize to, see PACKET_DATA_SIZE
+    const maxTransactionSize = 1232;
+
+    const budgets = {
+      buyTicket: { units: 80_000, size: 700 },
+      pickWinner: { units: 80_000, size: 700 },
+      claimPrize: { units: 80_000, size: 700 },
+      // A full batch has to fit the default per-instruction compute limit
+      refundBatch: { units: 200_000, size: maxTransactionSize },
+    };
+
+    const serializedSize = (tx: anchor.web3.Transaction) =>
+      tx.serialize({ requireAllSignatures: false, verifySignatures: false })
+        .length;
+
+    // Simulate a transaction holding `instruction` without committing it, and check it against its budget
+    const expectWithinBudget = async (
+      name: keyof typeof budgets,
+      instruction: anchor.web3.TransactionInstruction
+    ) => {
+      const tx = new anchor.web3.Transaction().add(instruction);
+      tx.feePayer = authority;
+      tx.recentBlockhash = (
+        await provider.connection.getLatestBlockhash()
+      ).blockhash;
+      const { value } = await provider.connection.simulateTransaction(tx);
+      expect(value.err, JSON.stringify(value.logs)).to.be.null;
+
+      const size = serializedSize(tx);
+      console.log(`      ${name}: ${value.unitsConsumed} CU, ${size} bytes`);
+      expect(value.unitsConsumed).to.be.at.most(budgets[name].units);
+      expect(size).to.be.at.most(budgets[name].size);
+    };
+
+    it("keeps buy_ticket, pick_winner and claim_prize within budget", async () => {
+      const lotteryId = await createLottery();
+      const lottery = lotteryPda(lotteryId);
+      await buyTicket(lotteryId);
+
+      await expectWithinBudget(
+        "buyTicket",
+        await program.methods
+          .buyTicket(lotteryId, validUntil(), noTerms)
+          .accountsPartial({
+            lottery,
+            ticket: ticketPda(lottery, 1),
+            buyer: authority,
+            reference: null,
+          })
+          .instruction()
+      );
+
+      const pickWinner = program.methods
+        .pickWinner(lotteryId)
+        .accountsPartial({ lottery, caller: authority });
+      await expectWithinBudget("pickWinner", await pickWinner.instruction());
+      await pickWinner.rpc();
+
+      const { winnerId } = await program.account.lottery.fetch(lottery);
+      await expectWithinBudget(
+        "claimPrize",
+        await program.methods
+          .claimPrize(lotteryId, winnerId)
+          .accountsPartial({
+            lottery,
+            ticket: ticketPda(lottery, winnerId),
+            authority,
+            claimer: authority,
+          })
+          .instruction()
+      );
+    });
+
+    it("fits a full refund batch in one transaction", async () => {
+      const lotteryId = await createLottery();
+      const lottery = lotteryPda(lotteryId);
+      const ids = [...Array(MAX_REFUNDS_PER_BATCH).keys()];
+      await program.methods
+        .buyTickets(lotteryId, ids.length, validUntil(), noTerms)
+        .accountsPartial({ lottery, buyer: authority })
+        .remainingAccounts(
+          ids.map((id) => ({
+            pubkey: ticketPda(lottery, id),
+            isSigner: false,
+            isWritable: true,
+          }))
+        )
+        .rpc();
+      await program.methods
+        .cancelLottery(lotteryId)
+        .accountsPartial({ lottery, master: masterPda(), authority })
+        .rpc();
+
+      const refundBatch = (owners: PublicKey[]) =>
+        program.methods
+          .refundBatch(lotteryId)
+          .accountsPartial({ lottery })
+          .remainingAccounts(
+            ids
+              .map((id) => ticketPda(lottery, id))
+              .sort((a, b) => Buffer.compare(a.toBuffer(), b.toBuffer()))
+              .flatMap((ticket, i) => [
+                { pubkey: ticket, isSigner: false, isWritable: true },
+                {
+                  pubkey: PublicKey.findProgramAddressSync(
+                    [
+                      Buffer.from("receipt"),
+                      lottery.toBuffer(),
+                      ticket.toBuffer(),
+                    ],
+                    program.programId
+                  )[0],
+                  isSigner: false,
+                  isWritable: false,
+                },
+                { pubkey: owners[i], isSigner: false, isWritable: true },
+              ])
+          )
+          .instruction();
+
+      // Every ticket is the authority's here, which the compute doesn't depend on
+      await expectWithinBudget(
+        "refundBatch",
+        await refundBatch(ids.map(() => authority))
+      );
 
-  it("Is initialized!", async () => {
-    // Add your test here.
-    const tx = await program.methods.initialize().rpc();
-    console.log("Your transaction signature", tx);
+      // Distinct owners are the largest a batch gets
+      const owners = ids.map(() => anchor.web3.Keypair.generate().publicKey);
+      const tx = new anchor.web3.Transaction().add(await refundBatch(owners));
+      tx.feePayer = authority;
+      tx.recentBlockhash = (
+        await provider.connection.getLatestBlockhash()
+      ).blockhash;
+      expect(serializedSize(tx)).to.be.at.most(maxTransactionSize);
+    });
   });
 });